@@ -0,0 +1,7 @@
+pub fn main() {
+    // the header test compiles a C program for the same target
+    println!(
+        "cargo::rustc-env=TARGET={}",
+        std::env::var("TARGET").unwrap()
+    );
+}
@@ -0,0 +1,6 @@
+//! C ABI of the `trusted-proxies` crate, see its `capi` module
+//!
+//! This crate builds `libtrusted_proxies_capi.so` and `libtrusted_proxies_capi.a`, the matching
+//! declarations are available in `include/trusted_proxies.h`.
+
+pub use trusted_proxies::capi::*;
@@ -0,0 +1,28 @@
+#include <string.h>
+
+#include "trusted_proxies.h"
+
+int main(void) {
+    TpConfig *config = tp_config_new();
+    TpHeader headers[] = {
+        {"Host", "example.com"},
+        {"X-Forwarded-For", "8.8.8.8, 1.2.3.4"},
+    };
+    TpTrusted *trusted;
+    int failed;
+
+    if (tp_config_add_ip(config, "1.2.3.0/24") != 0 || tp_config_add_ip(config, "invalid") != -1) {
+        return 1;
+    }
+
+    trusted = tp_extract(config, "127.0.0.1", "https", headers, 2);
+
+    failed = trusted == NULL || strcmp(tp_trusted_ip(trusted), "8.8.8.8") != 0 ||
+             strcmp(tp_trusted_host(trusted), "example.com") != 0 ||
+             strcmp(tp_trusted_scheme(trusted), "https") != 0 || tp_trusted_by(trusted) != NULL;
+
+    tp_trusted_free(trusted);
+    tp_config_free(config);
+
+    return failed;
+}
@@ -0,0 +1,41 @@
+use std::path::Path;
+use std::process::Command;
+
+/// Compile a C program against `include/trusted_proxies.h`, link it with the static library and
+/// run it
+#[test]
+fn header() {
+    let manifest = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let out = Path::new(env!("CARGO_TARGET_TMPDIR"));
+    // the test binary is in `deps`, next to the libraries of the profile
+    let exe = std::env::current_exe().unwrap();
+    let profile = exe.parent().unwrap().parent().unwrap();
+    let target = env!("TARGET");
+
+    let compiler = cc::Build::new()
+        .cargo_metadata(false)
+        .cargo_warnings(false)
+        .target(target)
+        .host(target)
+        .opt_level(0)
+        .get_compiler();
+    let program = out.join("trusted_proxies_header");
+
+    let status = compiler
+        .to_command()
+        .arg("-Wall")
+        .arg("-Werror")
+        .arg("-I")
+        .arg(manifest.join("../include"))
+        .arg(manifest.join("tests/header.c"))
+        .arg(profile.join("libtrusted_proxies_capi.a"))
+        .args(["-lpthread", "-lm", "-ldl"])
+        .arg("-o")
+        .arg(&program)
+        .status()
+        .unwrap();
+    assert!(status.success(), "cannot compile tests/header.c");
+
+    let status = Command::new(&program).status().unwrap();
+    assert!(status.success(), "tests/header.c failed: {status}");
+}
@@ -0,0 +1,17 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use trusted_proxies::fuzz::FuzzRequest;
+use trusted_proxies::{Config, Trusted};
+
+fuzz_target!(|input: (Config, FuzzRequest)| {
+    let (config, request) = input;
+    let trusted = Trusted::from(request.peer, &request, &config);
+
+    // the resolved address is never a trusted proxy, unless no untrusted address was found
+    assert!(!config.is_ip_trusted(&trusted.ip()) || trusted.ip() == request.peer);
+
+    let _ = trusted.host();
+    let _ = trusted.port();
+    let _ = trusted.into_owned();
+});
@@ -0,0 +1,34 @@
+#ifndef TRUSTED_PROXIES_H
+#define TRUSTED_PROXIES_H
+
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct TpConfig TpConfig;
+typedef struct TpTrusted TpTrusted;
+
+typedef struct TpHeader {
+    const char *name;
+    const char *value;
+} TpHeader;
+
+TpConfig *tp_config_new(void);
+int tp_config_add_ip(TpConfig *config, const char *ip);
+void tp_config_free(TpConfig *config);
+
+TpTrusted *tp_extract(const TpConfig *config, const char *peer, const char *scheme,
+                      const TpHeader *headers, size_t headers_len);
+const char *tp_trusted_ip(const TpTrusted *trusted);
+const char *tp_trusted_host(const TpTrusted *trusted);
+const char *tp_trusted_scheme(const TpTrusted *trusted);
+const char *tp_trusted_by(const TpTrusted *trusted);
+void tp_trusted_free(TpTrusted *trusted);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
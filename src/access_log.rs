@@ -0,0 +1,514 @@
+//! Access log lines using the trusted client information
+//!
+//! [`CombinedLog`] renders a line in the Apache / nginx combined log format, with the trusted
+//! client address instead of the proxy address, and optionally prefixed by the trusted virtual host
+//! like the Apache `vhost_combined` format.
+//!
+//! [`AccessLogLine`] parses recorded lines back and re-runs the trust evaluation, to correct logs
+//! that recorded a load balancer as the client.
+//!
+//! # Example
+//! ```
+//! use std::time::{Duration, UNIX_EPOCH};
+//! use trusted_proxies::access_log::CombinedLog;
+//! use trusted_proxies::{Config, Trusted};
+//!
+//! let config = Config::new_local();
+//! let mut request = http::Request::get("/index.html").body(()).unwrap();
+//! request.headers_mut().insert(http::header::FORWARDED, "for=1.2.3.4; proto=https; host=example.com".parse().unwrap());
+//! request.headers_mut().insert(http::header::USER_AGENT, "curl/8.0".parse().unwrap());
+//!
+//! let trusted = Trusted::from(core::net::IpAddr::from([127, 0, 0, 1]), &request, &config);
+//! let line = CombinedLog::from_request(&trusted, &request)
+//!     .status(200)
+//!     .bytes(512)
+//!     .time(UNIX_EPOCH + Duration::from_secs(1_700_000_000))
+//!     .vhost()
+//!     .to_string();
+//!
+//! assert_eq!(
+//!     line,
+//!     "example.com:443 1.2.3.4 - - [14/Nov/2023:22:13:20 +0000] \"GET /index.html HTTP/1.1\" 200 512 \"-\" \"curl/8.0\""
+//! );
+//! ```
+
+use crate::{RequestInformation, TrustPolicy, Trusted};
+use core::fmt;
+use core::net::IpAddr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// A combined log format line, rendered with its `Display` implementation
+#[derive(Debug, Clone)]
+pub struct CombinedLog<'a> {
+    trusted: &'a Trusted<'a>,
+    method: &'a str,
+    target: &'a str,
+    protocol: &'a str,
+    status: u16,
+    bytes: Option<u64>,
+    user: Option<&'a str>,
+    referer: Option<&'a str>,
+    user_agent: Option<&'a str>,
+    time: Option<SystemTime>,
+    vhost: bool,
+}
+
+impl<'a> CombinedLog<'a> {
+    /// Create a log line for a request, other fields can be set with the builder methods
+    pub fn new(trusted: &'a Trusted<'a>, method: &'a str, target: &'a str) -> Self {
+        Self {
+            trusted,
+            method,
+            target,
+            protocol: "HTTP/1.1",
+            status: 200,
+            bytes: None,
+            user: None,
+            referer: None,
+            user_agent: None,
+            time: None,
+            vhost: false,
+        }
+    }
+
+    /// Create a log line from a `http` request, filling the request line, the referer and the
+    /// user agent
+    #[cfg(feature = "http")]
+    pub fn from_request<T>(trusted: &'a Trusted<'a>, request: &'a http::Request<T>) -> Self {
+        let header = |name| {
+            request
+                .headers()
+                .get(name)
+                .and_then(|value: &http::HeaderValue| value.to_str().ok())
+        };
+
+        let mut log = Self::new(
+            trusted,
+            request.method().as_str(),
+            request
+                .uri()
+                .path_and_query()
+                .map_or("/", |path| path.as_str()),
+        );
+
+        log.protocol = match request.version() {
+            http::Version::HTTP_09 => "HTTP/0.9",
+            http::Version::HTTP_10 => "HTTP/1.0",
+            http::Version::HTTP_2 => "HTTP/2.0",
+            http::Version::HTTP_3 => "HTTP/3.0",
+            _ => "HTTP/1.1",
+        };
+        log.referer = header(http::header::REFERER);
+        log.user_agent = header(http::header::USER_AGENT);
+
+        log
+    }
+
+    /// Set the protocol of the request line, `HTTP/1.1` by default
+    pub fn protocol(mut self, protocol: &'a str) -> Self {
+        self.protocol = protocol;
+        self
+    }
+
+    /// Set the status code of the response, 200 by default
+    pub fn status(mut self, status: u16) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// Set the size of the response body, `-` is logged when unknown
+    pub fn bytes(mut self, bytes: u64) -> Self {
+        self.bytes = Some(bytes);
+        self
+    }
+
+    /// Set the authenticated user
+    pub fn user(mut self, user: &'a str) -> Self {
+        self.user = Some(user);
+        self
+    }
+
+    /// Set the `Referer` header of the request
+    pub fn referer(mut self, referer: &'a str) -> Self {
+        self.referer = Some(referer);
+        self
+    }
+
+    /// Set the `User-Agent` header of the request
+    pub fn user_agent(mut self, user_agent: &'a str) -> Self {
+        self.user_agent = Some(user_agent);
+        self
+    }
+
+    /// Set the time of the request, the current time is used by default
+    pub fn time(mut self, time: SystemTime) -> Self {
+        self.time = Some(time);
+        self
+    }
+
+    /// Prefix the line with the trusted host and port, the port defaulting to the one of the
+    /// trusted scheme
+    pub fn vhost(mut self) -> Self {
+        self.vhost = true;
+        self
+    }
+}
+
+impl fmt::Display for CombinedLog<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.vhost {
+            let port = self.trusted.port().or(match self.trusted.scheme() {
+                Some("https") | Some("wss") => Some(443),
+                Some("http") | Some("ws") => Some(80),
+                _ => None,
+            });
+
+            // the host comes from the forwarding headers, it must not shift the other fields
+            match self.trusted.host().filter(|host| !host.is_empty()) {
+                Some(host) => {
+                    write_escaped_bare(f, host)?;
+
+                    if let Some(port) = port {
+                        write!(f, ":{port}")?;
+                    }
+                }
+                None => f.write_str("-")?,
+            }
+
+            f.write_str(" ")?;
+        }
+
+        write!(f, "{} - ", self.trusted.ip())?;
+        write_escaped_bare(f, self.user.filter(|user| !user.is_empty()).unwrap_or("-"))?;
+        f.write_str(" [")?;
+        write_time(f, self.time.unwrap_or_else(SystemTime::now))?;
+        f.write_str("] \"")?;
+        write_escaped(f, self.method)?;
+        f.write_str(" ")?;
+        write_escaped(f, self.target)?;
+        f.write_str(" ")?;
+        write_escaped(f, self.protocol)?;
+        write!(f, "\" {} ", self.status)?;
+
+        match self.bytes {
+            Some(bytes) => write!(f, "{bytes}")?,
+            None => f.write_str("-")?,
+        }
+
+        f.write_str(" \"")?;
+        write_escaped(f, self.referer.unwrap_or("-"))?;
+        f.write_str("\" \"")?;
+        write_escaped(f, self.user_agent.unwrap_or("-"))?;
+        f.write_str("\"")
+    }
+}
+
+/// Escape quotes, backslashes and non printable characters like nginx does
+fn write_escaped(f: &mut fmt::Formatter<'_>, value: &str) -> fmt::Result {
+    for byte in value.bytes() {
+        match byte {
+            b'"' | b'\\' => write!(f, "\\x{byte:02X}")?,
+            0x20..=0x7e => write!(f, "{}", byte as char)?,
+            _ => write!(f, "\\x{byte:02X}")?,
+        }
+    }
+
+    Ok(())
+}
+
+/// Escape a field that is not quoted, spaces separating the fields are escaped too
+fn write_escaped_bare(f: &mut fmt::Formatter<'_>, value: &str) -> fmt::Result {
+    for (index, part) in value.split(' ').enumerate() {
+        if index > 0 {
+            f.write_str("\\x20")?;
+        }
+
+        write_escaped(f, part)?;
+    }
+
+    Ok(())
+}
+
+/// Write a time in UTC with the common log format, e.g. `10/Oct/2000:13:55:36 +0000`
+fn write_time(f: &mut fmt::Formatter<'_>, time: SystemTime) -> fmt::Result {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    let (year, month, day) = civil_from_days((secs / 86400) as i64);
+    let secs_of_day = secs % 86400;
+
+    write!(
+        f,
+        "{day:02}/{}/{year}:{:02}:{:02}:{:02} +0000",
+        MONTHS[month as usize - 1],
+        secs_of_day / 3600,
+        secs_of_day / 60 % 60,
+        secs_of_day % 60
+    )
+}
+
+/// Convert a number of days since the unix epoch to a (year, month, day) date
+///
+/// See <http://howardhinnant.github.io/date_algorithms.html#civil_from_days>
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    (year, month, day)
+}
+
+/// A format of access log lines understood by [`AccessLogLine::parse`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum LogFormat {
+    /// The Apache / nginx combined log format
+    Combined,
+    /// The Apache `vhost_combined` format, the combined format prefixed by the virtual host
+    VhostCombined,
+    /// The default nginx `main` format, the combined format followed by the
+    /// `X-Forwarded-For` header
+    NginxMain,
+}
+
+/// A parsed access log line, used to re-run the trust evaluation on recorded requests
+///
+/// The line implements [`RequestInformation`], the recorded client address is the peer of the
+/// request.
+///
+/// # Example
+/// ```
+/// use trusted_proxies::access_log::{AccessLogLine, LogFormat};
+/// use trusted_proxies::Config;
+///
+/// let line = "10.0.0.1 - - [14/Nov/2023:22:13:20 +0000] \"GET / HTTP/1.1\" 200 512 \"-\" \"curl/8.0\" \"1.2.3.4\"";
+/// let line = AccessLogLine::parse(line, LogFormat::NginxMain).unwrap();
+///
+/// assert_eq!(line.peer, core::net::IpAddr::from([10, 0, 0, 1]));
+/// assert_eq!(line.attribute(&Config::new_local()).ip(), core::net::IpAddr::from([1, 2, 3, 4]));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccessLogLine<'a> {
+    pub vhost: Option<&'a str>,
+    pub peer: IpAddr,
+    pub user: Option<&'a str>,
+    pub time: &'a str,
+    pub request: &'a str,
+    pub status: u16,
+    pub bytes: Option<u64>,
+    pub referer: Option<&'a str>,
+    pub user_agent: Option<&'a str>,
+    pub x_forwarded_for: Option<&'a str>,
+}
+
+impl<'a> AccessLogLine<'a> {
+    /// Parse a line with the given format, `None` is returned when the line does not match
+    pub fn parse(line: &'a str, format: LogFormat) -> Option<Self> {
+        let mut fields = Fields(line.trim_end());
+
+        let vhost = match format {
+            LogFormat::VhostCombined => Some(fields.next()?),
+            _ => None,
+        };
+        let peer = fields.next()?.parse().ok()?;
+        let _ident = fields.next()?;
+        let user = fields.next()?;
+        let time = fields.next()?;
+        let request = fields.next()?;
+        let status = fields.next()?.parse().ok()?;
+        let bytes = fields.next()?;
+        let referer = fields.next()?;
+        let user_agent = fields.next()?;
+        let x_forwarded_for = match format {
+            LogFormat::NginxMain => fields.next()?,
+            _ => "-",
+        };
+
+        let value = |field: &'a str| Some(field).filter(|field| *field != "-");
+
+        Some(Self {
+            vhost: vhost.and_then(value),
+            peer,
+            user: value(user),
+            time,
+            request,
+            status,
+            bytes: value(bytes).and_then(|bytes| bytes.parse().ok()),
+            referer: value(referer),
+            user_agent: value(user_agent),
+            x_forwarded_for: value(x_forwarded_for),
+        })
+    }
+
+    /// Extract the trusted information of the recorded request
+    pub fn attribute<P: TrustPolicy + ?Sized>(&'a self, policy: &P) -> Trusted<'a> {
+        Trusted::from(self.peer, self, policy)
+    }
+}
+
+impl RequestInformation for AccessLogLine<'_> {
+    fn is_host_header_allowed(&self) -> bool {
+        true
+    }
+
+    fn host_header(&self) -> Option<&str> {
+        self.vhost
+    }
+
+    fn authority(&self) -> Option<&str> {
+        None
+    }
+
+    fn forwarded(&self) -> impl DoubleEndedIterator<Item = &str> {
+        core::iter::empty()
+    }
+
+    fn x_forwarded_for(&self) -> impl DoubleEndedIterator<Item = &str> {
+        self.x_forwarded_for.into_iter()
+    }
+
+    fn x_forwarded_host(&self) -> impl DoubleEndedIterator<Item = &str> {
+        core::iter::empty()
+    }
+
+    fn x_forwarded_proto(&self) -> impl DoubleEndedIterator<Item = &str> {
+        core::iter::empty()
+    }
+
+    fn x_forwarded_by(&self) -> impl DoubleEndedIterator<Item = &str> {
+        core::iter::empty()
+    }
+
+    fn default_scheme(&self) -> Option<&str> {
+        None
+    }
+}
+
+/// Iterator over the fields of a log line: bare words, `[bracketed]` and `"quoted"` values
+///
+/// Quotes are escaped as `\x22` by nginx and Apache, so a quoted value ends at the next quote.
+struct Fields<'a>(&'a str);
+
+impl<'a> Iterator for Fields<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        let rest = self.0.trim_start_matches(' ');
+
+        let (field, rest) = match rest.chars().next()? {
+            '"' => rest[1..].split_once('"')?,
+            '[' => rest[1..].split_once(']')?,
+            _ => rest.split_once(' ').unwrap_or((rest, "")),
+        };
+
+        self.0 = rest;
+
+        Some(field)
+    }
+}
+
+#[cfg(all(test, feature = "http"))]
+mod tests {
+    use super::*;
+    use crate::Config;
+
+    #[test]
+    fn round_trip() {
+        let config = Config::new_local();
+        let mut request = http::Request::get("/index.html").body(()).unwrap();
+        request.headers_mut().insert(
+            http::header::FORWARDED,
+            "for=1.2.3.4; host=example.com".parse().unwrap(),
+        );
+
+        let trusted = Trusted::from(IpAddr::from([127, 0, 0, 1]), &request, &config);
+        let line = CombinedLog::from_request(&trusted, &request)
+            .status(404)
+            .user_agent("agent \"quoted\"")
+            .vhost()
+            .to_string();
+        let parsed = AccessLogLine::parse(&line, LogFormat::VhostCombined).unwrap();
+
+        assert_eq!(parsed.vhost, Some("example.com"));
+        assert_eq!(parsed.peer, IpAddr::from([1, 2, 3, 4]));
+        assert_eq!(parsed.status, 404);
+        assert_eq!(parsed.bytes, None);
+        assert_eq!(parsed.user_agent, Some("agent \\x22quoted\\x22"));
+        assert!(AccessLogLine::parse(&line, LogFormat::NginxMain).is_none());
+    }
+
+    #[test]
+    fn combined() {
+        let config = Config::new_local();
+        let mut request = http::Request::get("/").body(()).unwrap();
+        request
+            .headers_mut()
+            .insert("x-forwarded-for", "1.2.3.4".parse().unwrap());
+
+        let trusted = Trusted::from(IpAddr::from([127, 0, 0, 1]), &request, &config);
+        let log = CombinedLog::new(&trusted, "GET", "/a\"b")
+            .user("bob smith")
+            .referer("https://example.com/\\")
+            .time(UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000));
+
+        assert_eq!(
+            log.to_string(),
+            r#"1.2.3.4 - bob\x20smith [14/Nov/2023:22:13:20 +0000] "GET /a\x22b HTTP/1.1" 200 - "https://example.com/\x5C" "-""#
+        );
+        assert!(log
+            .clone()
+            .user("")
+            .to_string()
+            .starts_with("1.2.3.4 - - ["));
+
+        let line = log.to_string();
+        let parsed = AccessLogLine::parse(&line, LogFormat::Combined).unwrap();
+        assert_eq!(parsed.user, Some("bob\\x20smith"));
+        assert_eq!(parsed.status, 200);
+    }
+
+    #[test]
+    fn vhost_combined() {
+        let config = Config::new_local();
+        let mut request = http::Request::get("/").body(()).unwrap();
+        request.headers_mut().insert(
+            http::header::FORWARDED,
+            "for=1.2.3.4; proto=https; host=\"a 6.6.6.6 x\""
+                .parse()
+                .unwrap(),
+        );
+
+        let trusted = Trusted::from(IpAddr::from([127, 0, 0, 1]), &request, &config);
+        let line = CombinedLog::from_request(&trusted, &request)
+            .bytes(512)
+            .time(UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000))
+            .vhost()
+            .to_string();
+
+        assert_eq!(
+            line,
+            r#"a\x206.6.6.6\x20x:443 1.2.3.4 - - [14/Nov/2023:22:13:20 +0000] "GET / HTTP/1.1" 200 512 "-" "-""#
+        );
+
+        let parsed = AccessLogLine::parse(&line, LogFormat::VhostCombined).unwrap();
+        assert_eq!(parsed.peer, IpAddr::from([1, 2, 3, 4]));
+
+        let request = http::Request::get("/").body(()).unwrap();
+        let trusted = Trusted::from(IpAddr::from([127, 0, 0, 1]), &request, &config);
+        let line = CombinedLog::from_request(&trusted, &request)
+            .vhost()
+            .to_string();
+
+        assert!(line.starts_with("- 127.0.0.1 - - ["));
+    }
+}
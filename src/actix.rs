@@ -0,0 +1,158 @@
+//! [`actix-web`](https://docs.rs/actix-web) middleware recording the trusted client on the
+//! request span
+//!
+//! [`ClientSpan`] extracts the trusted information of every request and records it on the
+//! current span, usually the root span created by `tracing_actix_web::TracingLogger`, which must
+//! wrap this middleware. Fields are only recorded when the span declares them, e.g. with
+//! `tracing::field::Empty`:
+//!
+//!  * `client.address`: the client address
+//!  * `url.scheme`: the scheme, when known
+//!  * `server.address`: the host, when known
+//!
+//! The trusted information is also inserted in the request extensions as a `Trusted<'static>`.
+//! [`RequestInformation`](crate::RequestInformation) is implemented for
+//! [`HttpRequest`](actix_web::HttpRequest) to extract it in handlers.
+//!
+//! # Example
+//! ```
+//! use std::sync::Arc;
+//! use trusted_proxies::actix::ClientSpan;
+//! use trusted_proxies::Config;
+//!
+//! let app = actix_web::App::new().wrap(ClientSpan::new(Arc::new(Config::new_local())));
+//! ```
+
+use crate::{telemetry, Config, RequestInformation, Trusted};
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::{Error, HttpMessage, HttpRequest};
+use core::future::{ready, Ready};
+use std::sync::Arc;
+
+/// A middleware recording the trusted client on the request span, see the [module](self)
+/// documentation
+#[derive(Debug, Clone)]
+pub struct ClientSpan {
+    config: Arc<Config>,
+}
+
+impl ClientSpan {
+    /// Create a middleware extracting the trusted information with the given configuration
+    pub fn new(config: Arc<Config>) -> Self {
+        Self { config }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for ClientSpan
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = ClientSpanMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(ClientSpanMiddleware {
+            service,
+            config: self.config.clone(),
+        }))
+    }
+}
+
+/// The service created by [`ClientSpan`]
+#[derive(Debug)]
+pub struct ClientSpanMiddleware<S> {
+    service: S,
+    config: Arc<Config>,
+}
+
+impl<S, B> Service<ServiceRequest> for ClientSpanMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = S::Future;
+
+    forward_ready!(service);
+
+    fn call(&self, request: ServiceRequest) -> Self::Future {
+        if let Some(peer) = request.peer_addr() {
+            let trusted =
+                Trusted::from(peer.ip(), request.request(), self.config.as_ref()).into_owned();
+            telemetry::record_client(&trusted);
+            request.extensions_mut().insert(trusted);
+        }
+
+        self.service.call(request)
+    }
+}
+
+impl RequestInformation for HttpRequest {
+    fn is_host_header_allowed(&self) -> bool {
+        self.version() < actix_web::http::Version::HTTP_2
+    }
+
+    fn host_header(&self) -> Option<&str> {
+        self.headers()
+            .get("host")
+            .and_then(|value| value.to_str().ok())
+    }
+
+    fn authority(&self) -> Option<&str> {
+        self.uri().authority().map(|auth| auth.as_str())
+    }
+
+    fn forwarded(&self) -> impl DoubleEndedIterator<Item = &str> {
+        self.header("forwarded")
+    }
+
+    fn x_forwarded_for(&self) -> impl DoubleEndedIterator<Item = &str> {
+        self.header("x-forwarded-for")
+    }
+
+    fn x_forwarded_host(&self) -> impl DoubleEndedIterator<Item = &str> {
+        self.header("x-forwarded-host")
+    }
+
+    fn x_forwarded_proto(&self) -> impl DoubleEndedIterator<Item = &str> {
+        self.header("x-forwarded-proto")
+    }
+
+    fn x_forwarded_by(&self) -> impl DoubleEndedIterator<Item = &str> {
+        self.header("x-forwarded-by")
+    }
+
+    fn header(&self, name: &str) -> impl DoubleEndedIterator<Item = &str> {
+        self.headers()
+            .get_all(name)
+            .filter_map(|value| value.to_str().ok())
+    }
+
+    fn default_scheme(&self) -> Option<&str> {
+        self.uri().scheme_str()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+    use core::net::IpAddr;
+
+    #[test]
+    fn http_request() {
+        let request = TestRequest::get()
+            .uri("/")
+            .insert_header(("host", "example.com"))
+            .insert_header(("x-forwarded-for", "1.2.3.4"))
+            .peer_addr("127.0.0.1:50000".parse().unwrap())
+            .to_http_request();
+
+        let trusted = Trusted::from(IpAddr::from([127, 0, 0, 1]), &request, &Config::new_local());
+        assert_eq!(trusted.ip(), IpAddr::from([1, 2, 3, 4]));
+        assert_eq!(trusted.host(), Some("example.com"));
+    }
+}
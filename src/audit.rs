@@ -0,0 +1,55 @@
+/// A forwarding header present on the request but ignored during the extraction
+///
+/// See [`crate::Trusted::ignored_headers`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IgnoredHeader {
+    /// Lowercase name of the header
+    pub name: &'static str,
+    /// Why the header has been ignored
+    pub reason: IgnoreReason,
+}
+
+/// Where the client address returned by [`crate::Trusted::ip`] comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum IpSource {
+    /// The address of the peer, when it is not trusted or when no forwarding header was usable
+    Peer,
+    /// A `for` parameter of the `Forwarded` header
+    Forwarded,
+    /// An element of the `X-Forwarded-For` header
+    XForwardedFor,
+    /// A client address signed by an edge proxy, see `Config::trust_signed_client_ip`
+    Signed,
+    /// An [`crate::IpExtractor`], see [`crate::Config::add_ip_extractor`]
+    Extractor,
+    /// A single address header, see [`crate::Config::set_client_ip_header`]
+    ClientIpHeader,
+}
+
+impl IpSource {
+    /// Get a short lowercase name of the source, suitable for logs and headers
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Peer => "peer",
+            Self::Forwarded => "forwarded",
+            Self::XForwardedFor => "x-forwarded-for",
+            Self::Signed => "signed",
+            Self::Extractor => "extractor",
+            Self::ClientIpHeader => "client-ip-header",
+        }
+    }
+}
+
+/// The reason why a forwarding header has been ignored
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum IgnoreReason {
+    /// The peer that sent the request is not trusted
+    UntrustedPeer,
+    /// The header is not trusted in the configuration
+    NotTrusted,
+    /// A forwarding header is longer than the maximum chain length, see
+    /// [`crate::Config::set_max_chain_length`]
+    ChainTooLong,
+}
@@ -0,0 +1,233 @@
+//! Presets for services behind AWS CloudFront and Application Load Balancers
+//!
+//! The AWS ranges are published at <https://ip-ranges.amazonaws.com/ip-ranges.json>. No snapshot
+//! of the document is embedded in the crate yet, so the presets cannot be built in a single call:
+//! the downloaded document is read with [`AwsRanges::parse`] and given to the presets. Its
+//! `createDate` is the version of the dataset, see [`AwsRanges::create_date`].
+//!
+//! An Application Load Balancer connects to the targets from addresses of the VPC, so the VPC
+//! CIDR must be trusted along with the CloudFront ranges for the walk to reach the client.
+//!
+//! # Example
+//! ```
+//! use trusted_proxies::aws::AwsRanges;
+//! use trusted_proxies::Config;
+//!
+//! let ranges = AwsRanges::parse(
+//!     r#"{"createDate": "2024-01-01-00-00-00", "prefixes": [{"ip_prefix": "13.32.0.0/15", "region": "GLOBAL", "service": "CLOUDFRONT"}]}"#,
+//! )
+//! .unwrap();
+//! let config = Config::new_cloudfront_alb(&ranges, "10.0.0.0/16").unwrap();
+//!
+//! assert!(config.is_ip_trusted(&core::net::IpAddr::from([10, 0, 1, 12])));
+//! assert!(config.is_ip_trusted(&core::net::IpAddr::from([13, 32, 0, 1])));
+//! ```
+
+use crate::{Config, ConfigError};
+use core::fmt;
+use ipnet::IpNet;
+
+/// An error raised while parsing an `ip-ranges.json` document
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum AwsRangesError {
+    /// The document is not valid JSON
+    Json(serde_json::Error),
+    /// The document has neither a `prefixes` nor an `ipv6_prefixes` list
+    Layout,
+    /// The document contains invalid prefixes
+    Ranges(ConfigError),
+}
+
+impl fmt::Display for AwsRangesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Json(err) => write!(f, "invalid AWS ip-ranges document: {err}"),
+            Self::Layout => write!(
+                f,
+                "invalid AWS ip-ranges document: no `prefixes` nor `ipv6_prefixes` list"
+            ),
+            Self::Ranges(err) => write!(f, "invalid prefixes in AWS ip-ranges document: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for AwsRangesError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Json(err) => Some(err),
+            Self::Layout => None,
+            Self::Ranges(err) => Some(err),
+        }
+    }
+}
+
+/// A prefix of `ip-ranges.json`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AwsPrefix {
+    /// The network
+    pub network: IpNet,
+    /// The region, `GLOBAL` for the edge services
+    pub region: String,
+    /// The service, e.g. `CLOUDFRONT`, `EC2` or `AMAZON`
+    pub service: String,
+}
+
+/// The parsed content of an `ip-ranges.json` document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AwsRanges {
+    create_date: String,
+    prefixes: Vec<AwsPrefix>,
+}
+
+impl AwsRanges {
+    /// Parse an `ip-ranges.json` document
+    pub fn parse(content: &str) -> Result<Self, AwsRangesError> {
+        let document =
+            serde_json::from_str::<serde_json::Value>(content).map_err(AwsRangesError::Json)?;
+        let lists = ["prefixes", "ipv6_prefixes"]
+            .iter()
+            .filter_map(|list| document.get(list)?.as_array())
+            .collect::<Vec<_>>();
+
+        if lists.is_empty() {
+            return Err(AwsRangesError::Layout);
+        }
+
+        let entries = lists.into_iter().flatten().collect::<Vec<_>>();
+
+        let networks =
+            crate::config::parse_networks(entries.iter().enumerate().map(|(index, entry)| {
+                let prefix = entry
+                    .get("ip_prefix")
+                    .or_else(|| entry.get("ipv6_prefix"))
+                    .and_then(|prefix| prefix.as_str());
+
+                (index + 1, prefix.unwrap_or(""))
+            }))
+            .map_err(AwsRangesError::Ranges)?;
+
+        let field = |entry: &serde_json::Value, name: &str| {
+            entry
+                .get(name)
+                .and_then(|value| value.as_str())
+                .unwrap_or("")
+                .to_string()
+        };
+
+        Ok(Self {
+            create_date: field(&document, "createDate"),
+            prefixes: networks
+                .into_iter()
+                .zip(entries)
+                .map(|(network, entry)| AwsPrefix {
+                    network,
+                    region: field(entry, "region"),
+                    service: field(entry, "service"),
+                })
+                .collect(),
+        })
+    }
+
+    /// The `createDate` of the document, the version of the dataset
+    pub fn create_date(&self) -> &str {
+        &self.create_date
+    }
+
+    /// Every prefix of the document
+    pub fn prefixes(&self) -> &[AwsPrefix] {
+        &self.prefixes
+    }
+
+    /// The networks of a service, e.g. `CLOUDFRONT`
+    pub fn service<'a>(&'a self, service: &'a str) -> impl Iterator<Item = IpNet> + 'a {
+        self.prefixes
+            .iter()
+            .filter(move |prefix| prefix.service == service)
+            .map(|prefix| prefix.network)
+    }
+}
+
+impl Config {
+    /// Create a configuration for services behind CloudFront
+    ///
+    /// The `CLOUDFRONT` ranges of the document are trusted. CloudFront appends the address of its
+    /// client to `X-Forwarded-For` and sets `X-Forwarded-Proto` through the
+    /// `CloudFront-Forwarded-Proto` origin request policy, both are trusted.
+    pub fn new_cloudfront(ranges: &AwsRanges) -> Self {
+        let mut config = Self::new();
+        config.add_cloudfront_ranges(ranges);
+
+        config.trust_x_forwarded_for();
+        config.trust_x_forwarded_proto();
+
+        config
+    }
+
+    /// Create a configuration for services behind CloudFront and an Application Load Balancer in
+    /// the given VPC, see the [`aws`](crate::aws) module
+    pub fn new_cloudfront_alb(ranges: &AwsRanges, vpc_cidr: &str) -> Result<Self, ConfigError> {
+        let mut config = Self::new_cloudfront(ranges);
+        config.add_aws_vpc(vpc_cidr)?;
+
+        Ok(config)
+    }
+
+    /// Trust the `CLOUDFRONT` ranges of the document in addition to the existing trusted proxies
+    pub fn add_cloudfront_ranges(&mut self, ranges: &AwsRanges) {
+        self.extend_trusted_ips(ranges.service("CLOUDFRONT"));
+    }
+
+    /// Trust the CIDR of the VPC of the service, where its load balancers live
+    pub fn add_aws_vpc(&mut self, vpc_cidr: &str) -> Result<(), ConfigError> {
+        self.add_trusted_ip(vpc_cidr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cloudfront_ranges() {
+        let ranges = AwsRanges::parse(
+            r#"{"createDate": "2024-01-01-00-00-00", "prefixes": [{"ip_prefix": "13.32.0.0/15", "region": "GLOBAL", "service": "CLOUDFRONT"}, {"ip_prefix": "3.5.140.0/22", "region": "ap-northeast-2", "service": "EC2"}], "ipv6_prefixes": [{"ipv6_prefix": "2600:9000::/28", "region": "GLOBAL", "service": "CLOUDFRONT"}]}"#,
+        )
+        .unwrap();
+        let config = Config::new_cloudfront(&ranges);
+
+        assert!(config.is_ip_trusted(&"13.33.0.1".parse().unwrap()));
+        assert!(config.is_ip_trusted(&"2600:9000::1".parse().unwrap()));
+        assert!(!config.is_ip_trusted(&"3.5.140.1".parse().unwrap()));
+    }
+
+    #[test]
+    fn parse_ranges() {
+        let ranges = AwsRanges::parse(
+            r#"{"createDate": "2024-01-01-00-00-00", "prefixes": [{"ip_prefix": "3.5.140.0/22", "region": "ap-northeast-2", "service": "EC2"}], "ipv6_prefixes": [{"ipv6_prefix": "2600:1f14::/35", "region": "us-west-2", "service": "EC2"}]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(ranges.create_date(), "2024-01-01-00-00-00");
+        assert_eq!(ranges.service("EC2").count(), 2);
+        assert_eq!(ranges.prefixes()[1].region, "us-west-2");
+
+        assert!(matches!(
+            AwsRanges::parse(r#"{"prefixes": [{"ip_prefix": "3.5.140.0/33"}]}"#),
+            Err(AwsRangesError::Ranges(_))
+        ));
+        assert!(matches!(
+            AwsRanges::parse("{}"),
+            Err(AwsRangesError::Layout)
+        ));
+        assert!(matches!(
+            AwsRanges::parse("[]"),
+            Err(AwsRangesError::Layout)
+        ));
+        assert!(matches!(
+            AwsRanges::parse(r#"{"prefixes": {}}"#),
+            Err(AwsRangesError::Layout)
+        ));
+        assert!(AwsRanges::parse(r#"{"prefixes": []}"#).is_ok());
+    }
+}
@@ -0,0 +1,52 @@
+//! [`axum`](https://docs.rs/axum) extractors
+//!
+//! The peer address is read from [`ConnectInfo<SocketAddr>`](::axum::extract::ConnectInfo), so
+//! the application must be served with `into_make_service_with_connect_info::<SocketAddr>()`.
+//! The configuration is read from an `Extension<Arc<Config>>` when present, the process-wide
+//! configuration is used otherwise, see [`crate::set_global_config`].
+
+use crate::{Config, Trusted};
+use ::axum::extract::{ConnectInfo, FromRequestParts};
+use ::axum::http::request::Parts;
+use core::convert::Infallible;
+use core::net::SocketAddr;
+use std::sync::Arc;
+
+/// Best-effort extractor, that never rejects the request
+///
+/// It yields `None` when the peer address is not available, for handlers where the client
+/// attribution is only used for metrics or logging.
+///
+/// # Example
+/// ```
+/// use trusted_proxies::axum::OptionalTrusted;
+///
+/// async fn handler(OptionalTrusted(trusted): OptionalTrusted) -> String {
+///     match trusted {
+///         Some(trusted) => trusted.ip().to_string(),
+///         None => "unknown".to_string(),
+///     }
+/// }
+///
+/// let app: axum::Router = axum::Router::new().route("/", axum::routing::get(handler));
+/// ```
+#[derive(Debug, Clone)]
+pub struct OptionalTrusted(pub Option<Trusted<'static>>);
+
+impl<S: Send + Sync> FromRequestParts<S> for OptionalTrusted {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Infallible> {
+        let Some(ConnectInfo(peer)) = parts.extensions.get::<ConnectInfo<SocketAddr>>().copied()
+        else {
+            return Ok(Self(None));
+        };
+
+        let trusted = match parts.extensions.get::<Arc<Config>>() {
+            Some(config) => Trusted::from(peer.ip(), &*parts, config.as_ref()),
+            None => Trusted::from(peer.ip(), &*parts, crate::global_config()),
+        };
+
+        Ok(Self(Some(trusted.into_owned())))
+    }
+}
@@ -0,0 +1,144 @@
+//! Validate a configuration against a request before rolling it out
+//!
+//! Usage: `trusted-proxies-check <config.json> <peer-ip> [<request-file>]`
+//!
+//! The configuration file uses the same format as the test fixtures, the request is a raw HTTP
+//! request (or only its headers) read from the given file or from the standard input.
+
+use std::io::{Read, Write};
+use std::net::IpAddr;
+use std::process::ExitCode;
+use trusted_proxies::testkit::{parse_request, FixtureConfig};
+use trusted_proxies::{Config, IgnoreReason, Trusted};
+
+fn main() -> ExitCode {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+
+    if args.len() < 2 || args.len() > 3 {
+        eprintln!("usage: trusted-proxies-check <config.json> <peer-ip> [<request-file>]");
+        return ExitCode::from(2);
+    }
+
+    match run(&args[0], &args[1], args.get(2).map(String::as_str)) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(config_path: &str, peer: &str, request_path: Option<&str>) -> Result<(), String> {
+    let config = load_config(config_path)?;
+    let peer = peer
+        .parse::<IpAddr>()
+        .map_err(|err| format!("invalid peer address {peer}: {err}"))?;
+
+    let mut content = String::new();
+
+    match request_path {
+        Some(path) if path != "-" => {
+            content = std::fs::read_to_string(path)
+                .map_err(|err| format!("cannot read request file {path}: {err}"))?;
+        }
+        _ => {
+            std::io::stdin()
+                .read_to_string(&mut content)
+                .map_err(|err| format!("cannot read request from stdin: {err}"))?;
+        }
+    }
+
+    let request = parse_request(&content)?;
+
+    report(&config, peer, &request, &mut std::io::stdout().lock())
+        .map_err(|err| format!("cannot write report: {err}"))
+}
+
+/// Write the information extracted from the request, with the trust decision of each hop
+fn report(
+    config: &Config,
+    peer: IpAddr,
+    request: &http::Request<()>,
+    out: &mut impl Write,
+) -> std::io::Result<()> {
+    let trusted = Trusted::from(peer, request, config);
+
+    writeln!(
+        out,
+        "client: {} (from {})",
+        trusted.ip(),
+        trusted.ip_source().as_str()
+    )?;
+    writeln!(out, "host: {}", trusted.host_with_port().unwrap_or("-"))?;
+    writeln!(out, "scheme: {}", trusted.scheme().unwrap_or("-"))?;
+    writeln!(out, "by: {}", trusted.by().unwrap_or("-"))?;
+
+    match (config.is_ip_trusted(&peer), trusted.via_group()) {
+        (true, Some(group)) => writeln!(out, "peer: {peer} (trusted, group {group})"),
+        (true, None) => writeln!(out, "peer: {peer} (trusted)"),
+        (false, _) => writeln!(out, "peer: {peer} (not trusted)"),
+    }?;
+
+    for proxy in trusted.proxies() {
+        writeln!(out, "proxy: {proxy} (trusted)")?;
+    }
+
+    for entry in trusted.untrusted_chain() {
+        writeln!(out, "claimed: {entry} (unverified)")?;
+    }
+
+    for ignored in trusted.ignored_headers() {
+        let reason = match ignored.reason {
+            IgnoreReason::UntrustedPeer => "peer is not trusted",
+            IgnoreReason::NotTrusted => "header is not trusted",
+            _ => "unknown reason",
+        };
+
+        writeln!(out, "ignored header: {} ({reason})", ignored.name)?;
+    }
+
+    for warning in trusted.warnings() {
+        writeln!(out, "warning: {warning}")?;
+    }
+
+    Ok(())
+}
+
+fn load_config(path: &str) -> Result<Config, String> {
+    let content =
+        std::fs::read_to_string(path).map_err(|err| format!("cannot read config {path}: {err}"))?;
+
+    serde_json::from_str::<FixtureConfig>(&content)
+        .map_err(|err| format!("invalid config {path}: {err}"))?
+        .to_config()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_hops() {
+        let config = Config::new_local();
+        let request = parse_request(
+            "Host: example.com\r\nX-Forwarded-For: 6.6.6.6, 1.2.3.4, 10.0.0.2\r\nX-Forwarded-Host: evil.com\r\n",
+        )
+        .unwrap();
+
+        let mut out = Vec::new();
+        report(&config, IpAddr::from([10, 0, 0, 1]), &request, &mut out).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "client: 1.2.3.4 (from x-forwarded-for)
+host: example.com
+scheme: -
+by: -
+peer: 10.0.0.1 (trusted)
+proxy: 10.0.0.2 (trusted)
+claimed: 6.6.6.6 (unverified)
+ignored header: x-forwarded-host (header is not trusted)
+"
+        );
+    }
+}
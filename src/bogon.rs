@@ -0,0 +1,98 @@
+//! IANA special-purpose address registries (bogons)
+//!
+//! These addresses should never be seen as the address of a client on the internet. They are
+//! used by [`crate::Config::set_private_client_ip`] to detect suspicious client addresses.
+//!
+//! See the [IPv4](https://www.iana.org/assignments/iana-ipv4-special-registry) and
+//! [IPv6](https://www.iana.org/assignments/iana-ipv6-special-registry) registries.
+//!
+//! # Example
+//! ```
+//! use trusted_proxies::bogon;
+//!
+//! assert!(bogon::is_bogon(&"10.1.2.3".parse().unwrap()));
+//! assert!(!bogon::is_bogon(&"1.1.1.1".parse().unwrap()));
+//! ```
+
+use core::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use ipnet::{Ipv4Net, Ipv6Net};
+
+const fn v4(a: u8, b: u8, c: u8, d: u8, prefix_len: u8) -> Ipv4Net {
+    Ipv4Net::new_assert(Ipv4Addr::new(a, b, c, d), prefix_len)
+}
+
+const fn v6(first: u16, second: u16, prefix_len: u8) -> Ipv6Net {
+    Ipv6Net::new_assert(Ipv6Addr::new(first, second, 0, 0, 0, 0, 0, 0), prefix_len)
+}
+
+/// IPv4 special-purpose and reserved networks
+pub const IPV4: &[Ipv4Net] = &[
+    // "this" network
+    v4(0, 0, 0, 0, 8),
+    // private-use
+    v4(10, 0, 0, 0, 8),
+    // shared address space (CGNAT)
+    v4(100, 64, 0, 0, 10),
+    // loopback
+    v4(127, 0, 0, 0, 8),
+    // link local
+    v4(169, 254, 0, 0, 16),
+    // private-use
+    v4(172, 16, 0, 0, 12),
+    // IETF protocol assignments
+    v4(192, 0, 0, 0, 24),
+    // documentation (TEST-NET-1)
+    v4(192, 0, 2, 0, 24),
+    // deprecated 6to4 relay anycast
+    v4(192, 88, 99, 0, 24),
+    // private-use
+    v4(192, 168, 0, 0, 16),
+    // benchmarking
+    v4(198, 18, 0, 0, 15),
+    // documentation (TEST-NET-2)
+    v4(198, 51, 100, 0, 24),
+    // documentation (TEST-NET-3)
+    v4(203, 0, 113, 0, 24),
+    // multicast
+    v4(224, 0, 0, 0, 4),
+    // reserved and limited broadcast
+    v4(240, 0, 0, 0, 4),
+];
+
+/// IPv6 special-purpose and reserved networks
+///
+/// IPv4-mapped addresses (`::ffff:0:0/96`) are not listed, [`is_bogon`] checks the mapped IPv4
+/// address instead.
+pub const IPV6: &[Ipv6Net] = &[
+    // unspecified and loopback addresses
+    v6(0, 0, 127),
+    // discard-only
+    v6(0x0100, 0, 64),
+    // IETF protocol assignments
+    v6(0x2001, 0, 23),
+    // documentation
+    v6(0x2001, 0x0db8, 32),
+    // documentation
+    v6(0x3fff, 0, 20),
+    // segment routing SIDs
+    v6(0x5f00, 0, 16),
+    // unique local
+    v6(0xfc00, 0, 7),
+    // link local
+    v6(0xfe80, 0, 10),
+    // deprecated site local
+    v6(0xfec0, 0, 10),
+    // multicast
+    v6(0xff00, 0, 8),
+];
+
+/// Check if an address is in one of the special-purpose or reserved networks
+pub fn is_bogon(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => IPV4.iter().any(|net| net.contains(ip)),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_bogon(&IpAddr::V4(ip)),
+            None => IPV6.iter().any(|net| net.contains(ip)),
+        },
+    }
+}
@@ -0,0 +1,161 @@
+use crate::config::parse_networks;
+use crate::{ranges, Config, ConfigError};
+
+/// A fluent builder of [`Config`]
+///
+/// Networks are only parsed by [`ConfigBuilder::build`], which reports every invalid entry at
+/// once with its 1-based position among the added networks, instead of failing on the first one.
+///
+/// # Example
+/// ```
+/// use trusted_proxies::ConfigBuilder;
+///
+/// let config = ConfigBuilder::new()
+///     .preset_local()
+///     .trusted_ip("203.0.113.0/24")
+///     .trust_x_forwarded_proto()
+///     .build()
+///     .unwrap();
+///
+/// assert!(config.is_ip_trusted(&core::net::IpAddr::from([203, 0, 113, 7])));
+///
+/// let error = ConfigBuilder::new()
+///     .trusted_ips(["10.0.0.0/33", "10.0.0.1", "localhost"])
+///     .build()
+///     .unwrap_err();
+///
+/// assert_eq!(error.errors().len(), 2);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ConfigBuilder {
+    config: Config,
+    trusted_ips: Vec<String>,
+}
+
+impl Default for ConfigBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ConfigBuilder {
+    /// Create a builder with no trusted proxies or headers, like [`Config::new`]
+    pub fn new() -> Self {
+        Self {
+            config: Config::new(),
+            trusted_ips: Vec::new(),
+        }
+    }
+
+    /// Trust a proxy, an IP address or a CIDR
+    pub fn trusted_ip(mut self, proxy: &str) -> Self {
+        self.trusted_ips.push(proxy.to_string());
+        self
+    }
+
+    /// Trust several proxies, IP addresses or CIDRs
+    pub fn trusted_ips<S: AsRef<str>>(mut self, proxies: impl IntoIterator<Item = S>) -> Self {
+        self.trusted_ips
+            .extend(proxies.into_iter().map(|proxy| proxy.as_ref().to_string()));
+        self
+    }
+
+    /// Trust the local and private networks and the `Forwarded` and `X-Forwarded-For` headers,
+    /// like [`Config::new_local`]
+    pub fn preset_local(mut self) -> Self {
+        self.config
+            .extend_trusted_ips(ranges::LOCAL.iter().copied());
+        self.config.trust_forwarded();
+        self.config.trust_x_forwarded_for();
+        self
+    }
+
+    /// Trust the `Forwarded` header
+    pub fn trust_forwarded(mut self) -> Self {
+        self.config.trust_forwarded();
+        self
+    }
+
+    /// Trust the `X-Forwarded-For` header
+    pub fn trust_x_forwarded_for(mut self) -> Self {
+        self.config.trust_x_forwarded_for();
+        self
+    }
+
+    /// Trust the `X-Forwarded-Host` header, see [`Config::trust_x_forwarded_host`]
+    pub fn trust_x_forwarded_host(mut self) -> Self {
+        self.config.trust_x_forwarded_host();
+        self
+    }
+
+    /// Trust the `X-Forwarded-Proto` header, see [`Config::trust_x_forwarded_proto`]
+    pub fn trust_x_forwarded_proto(mut self) -> Self {
+        self.config.trust_x_forwarded_proto();
+        self
+    }
+
+    /// Trust the `X-Forwarded-By` header, see [`Config::trust_x_forwarded_by`]
+    pub fn trust_x_forwarded_by(mut self) -> Self {
+        self.config.trust_x_forwarded_by();
+        self
+    }
+
+    /// Build the configuration, failing with every invalid network
+    pub fn build(self) -> Result<Config, ConfigError> {
+        let mut config = self.config;
+        let networks = parse_networks(
+            self.trusted_ips
+                .iter()
+                .enumerate()
+                .map(|(index, proxy)| (index + 1, proxy.as_str())),
+        )?;
+
+        config.extend_trusted_ips(networks);
+
+        Ok(config)
+    }
+}
+
+impl Config {
+    /// Create a [`ConfigBuilder`]
+    pub fn builder() -> ConfigBuilder {
+        ConfigBuilder::new()
+    }
+}
+
+#[cfg(all(test, feature = "http"))]
+mod tests {
+    use super::*;
+    use crate::Trusted;
+    use core::net::IpAddr;
+    use http::Request;
+
+    #[test]
+    fn config_builder() {
+        let config = Config::builder()
+            .trusted_ip("10.0.0.1")
+            .trust_x_forwarded_for()
+            .build()
+            .unwrap();
+
+        let mut request = Request::get("/").body(()).unwrap();
+        request
+            .headers_mut()
+            .insert("x-forwarded-for", "1.2.3.4".parse().unwrap());
+
+        let trusted = Trusted::from(IpAddr::from([10, 0, 0, 1]), &request, &config);
+        assert_eq!(trusted.ip(), IpAddr::from([1, 2, 3, 4]));
+        assert!(!config.is_ip_trusted(&IpAddr::from([127, 0, 0, 1])));
+
+        let error = Config::builder()
+            .preset_local()
+            .trusted_ips(["10.0.0.0/8", "10.0.0.0/33"])
+            .trusted_ip("localhost")
+            .build()
+            .unwrap_err();
+
+        assert_eq!(error.errors().len(), 2);
+        assert!(error.to_string().contains("`10.0.0.0/33` at position 2"));
+        assert!(error.to_string().contains("`localhost` at position 3"));
+    }
+}
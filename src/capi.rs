@@ -0,0 +1,228 @@
+//! C ABI to use the trust logic from non Rust servers
+//!
+//! The `trusted-proxies-capi` crate of the workspace builds this module as a shared and a static
+//! library, with `cargo build --release -p trusted-proxies-capi`. The matching declarations are
+//! available in `include/trusted_proxies.h`.
+//!
+//! All strings are NUL terminated and UTF-8 encoded, objects returned by this API must be freed
+//! with the matching `tp_*_free` function.
+
+use crate::extract::HeaderList;
+use crate::{Config, Trusted};
+use core::ffi::{c_char, c_int, CStr};
+use core::net::IpAddr;
+use core::ptr;
+use std::ffi::CString;
+
+/// A request header given to [`tp_extract`]
+#[repr(C)]
+pub struct TpHeader {
+    pub name: *const c_char,
+    pub value: *const c_char,
+}
+
+/// Information extracted by [`tp_extract`]
+pub struct TpTrusted {
+    ip: CString,
+    host: Option<CString>,
+    scheme: Option<CString>,
+    by: Option<CString>,
+}
+
+unsafe fn to_str<'a>(value: *const c_char) -> Option<&'a str> {
+    if value.is_null() {
+        return None;
+    }
+
+    CStr::from_ptr(value).to_str().ok()
+}
+
+fn to_c_string(value: Option<&str>) -> Option<CString> {
+    value.and_then(|value| CString::new(value).ok())
+}
+
+fn as_ptr(value: &Option<CString>) -> *const c_char {
+    value.as_ref().map_or(ptr::null(), |value| value.as_ptr())
+}
+
+/// Create a configuration trusting local and private networks, see [`Config::new_local`]
+#[no_mangle]
+pub extern "C" fn tp_config_new() -> *mut Config {
+    Box::into_raw(Box::new(Config::new_local()))
+}
+
+/// Add a trusted ip address or network to the configuration, returns 0 on success and -1 when
+/// the address is invalid
+///
+/// # Safety
+///
+/// `config` must be a pointer returned by [`tp_config_new`] and `ip` a valid C string.
+#[no_mangle]
+pub unsafe extern "C" fn tp_config_add_ip(config: *mut Config, ip: *const c_char) -> c_int {
+    let (Some(config), Some(ip)) = (config.as_mut(), to_str(ip)) else {
+        return -1;
+    };
+
+    match config.add_trusted_ip(ip) {
+        Ok(()) => 0,
+        Err(_) => -1,
+    }
+}
+
+/// Free a configuration
+///
+/// # Safety
+///
+/// `config` must be a pointer returned by [`tp_config_new`] or null, it must not be used after.
+#[no_mangle]
+pub unsafe extern "C" fn tp_config_free(config: *mut Config) {
+    if !config.is_null() {
+        drop(Box::from_raw(config));
+    }
+}
+
+/// Extract the trusted information of a request, returns null when the peer address is invalid
+///
+/// `scheme` is the scheme of the connection and may be null, invalid headers are ignored.
+///
+/// # Safety
+///
+/// `config` must be a pointer returned by [`tp_config_new`], `peer` and `scheme` valid C strings
+/// and `headers` must point to `headers_len` headers containing valid C strings.
+#[no_mangle]
+pub unsafe extern "C" fn tp_extract(
+    config: *const Config,
+    peer: *const c_char,
+    scheme: *const c_char,
+    headers: *const TpHeader,
+    headers_len: usize,
+) -> *mut TpTrusted {
+    let Some(config) = config.as_ref() else {
+        return ptr::null_mut();
+    };
+    let Some(peer) = to_str(peer).and_then(|peer| peer.parse::<IpAddr>().ok()) else {
+        return ptr::null_mut();
+    };
+    let headers = if headers.is_null() {
+        &[]
+    } else {
+        core::slice::from_raw_parts(headers, headers_len)
+    };
+
+    let request = HeaderList {
+        headers: headers
+            .iter()
+            .filter_map(|header| Some((to_str(header.name)?, to_str(header.value)?)))
+            .collect(),
+        scheme: to_str(scheme),
+    };
+    let trusted = Trusted::from(peer, &request, config);
+
+    Box::into_raw(Box::new(TpTrusted {
+        ip: CString::new(trusted.ip().to_string()).unwrap_or_default(),
+        host: to_c_string(trusted.host_with_port()),
+        scheme: to_c_string(trusted.scheme()),
+        by: to_c_string(trusted.by()),
+    }))
+}
+
+/// Get the trusted client address
+///
+/// # Safety
+///
+/// `trusted` must be a pointer returned by [`tp_extract`], the string lives as long as it.
+#[no_mangle]
+pub unsafe extern "C" fn tp_trusted_ip(trusted: *const TpTrusted) -> *const c_char {
+    trusted
+        .as_ref()
+        .map_or(ptr::null(), |trusted| trusted.ip.as_ptr())
+}
+
+/// Get the trusted host with its port if any, or null
+///
+/// # Safety
+///
+/// `trusted` must be a pointer returned by [`tp_extract`], the string lives as long as it.
+#[no_mangle]
+pub unsafe extern "C" fn tp_trusted_host(trusted: *const TpTrusted) -> *const c_char {
+    trusted
+        .as_ref()
+        .map_or(ptr::null(), |trusted| as_ptr(&trusted.host))
+}
+
+/// Get the trusted scheme, or null
+///
+/// # Safety
+///
+/// `trusted` must be a pointer returned by [`tp_extract`], the string lives as long as it.
+#[no_mangle]
+pub unsafe extern "C" fn tp_trusted_scheme(trusted: *const TpTrusted) -> *const c_char {
+    trusted
+        .as_ref()
+        .map_or(ptr::null(), |trusted| as_ptr(&trusted.scheme))
+}
+
+/// Get the trusted proxy identifier, or null
+///
+/// # Safety
+///
+/// `trusted` must be a pointer returned by [`tp_extract`], the string lives as long as it.
+#[no_mangle]
+pub unsafe extern "C" fn tp_trusted_by(trusted: *const TpTrusted) -> *const c_char {
+    trusted
+        .as_ref()
+        .map_or(ptr::null(), |trusted| as_ptr(&trusted.by))
+}
+
+/// Free extracted information
+///
+/// # Safety
+///
+/// `trusted` must be a pointer returned by [`tp_extract`] or null, it must not be used after.
+#[no_mangle]
+pub unsafe extern "C" fn tp_trusted_free(trusted: *mut TpTrusted) {
+    if !trusted.is_null() {
+        drop(Box::from_raw(trusted));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extract() {
+        unsafe {
+            let config = tp_config_new();
+            assert_eq!(tp_config_add_ip(config, c"1.2.3.0/24".as_ptr()), 0);
+            assert_eq!(tp_config_add_ip(config, c"invalid".as_ptr()), -1);
+
+            let headers = [
+                TpHeader {
+                    name: c"Host".as_ptr(),
+                    value: c"example.com".as_ptr(),
+                },
+                TpHeader {
+                    name: c"X-Forwarded-For".as_ptr(),
+                    value: c"8.8.8.8, 1.2.3.4".as_ptr(),
+                },
+            ];
+            let trusted = tp_extract(
+                config,
+                c"127.0.0.1".as_ptr(),
+                c"https".as_ptr(),
+                headers.as_ptr(),
+                headers.len(),
+            );
+
+            assert_eq!(CStr::from_ptr(tp_trusted_ip(trusted)), c"8.8.8.8");
+            assert_eq!(CStr::from_ptr(tp_trusted_host(trusted)), c"example.com");
+            assert_eq!(CStr::from_ptr(tp_trusted_scheme(trusted)), c"https");
+            assert!(tp_trusted_by(trusted).is_null());
+
+            tp_trusted_free(trusted);
+            assert!(tp_extract(config, c"invalid".as_ptr(), ptr::null(), ptr::null(), 0).is_null());
+            tp_config_free(config);
+        }
+    }
+}
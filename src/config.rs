@@ -1,9 +1,43 @@
-use core::net::IpAddr;
+use crate::extractor::IpExtractors;
+use crate::headers::HeaderNames;
+use crate::hostname::{HostnameRefresher, TrustedHostnames};
+use crate::matcher::{Ranges, RangesCache};
+use crate::normalizer::HostNormalizerHook;
+use crate::ranges;
+use crate::reputation::ReputationHook;
+use crate::spoof::SpoofHook;
+use crate::{
+    AzureClientIp, ConfigError, ConnectionInformation, HostNormalizer, IpExtractor,
+    ReputationProvider, SpoofEvent, TrueClientIp, TrustedMatcher,
+};
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use core::ops::RangeInclusive;
+use core::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use ipnet::{AddrParseError, IpNet};
+use ipnet::{IpNet, Ipv4Net, Ipv6Net};
+use smallvec::SmallVec;
+
+/// Number of trusted networks stored inline before spilling to the heap
+///
+/// Most configurations only trust a handful of networks (the local preset uses 6), so lookups
+/// stay in the `Config` itself and avoid a pointer chase. Beyond that, lookups use address ranges
+/// compiled on the first lookup, like [`TrustedMatcher`], instead of scanning every network.
+const INLINE_TRUSTED_IPS: usize = 8;
 
 /// Config for trusted proxies extractor
 ///
+/// Configurations are compared and hashed in a canonical form: networks are truncated to their
+/// network address, and lists are sorted and deduplicated, so the order in which proxies were
+/// added does not matter. Extractors keep their running order, hostnames are compared by name,
+/// and hooks (callbacks, normalizers, reputation providers and extractors) by identity, so only
+/// the clones of a configuration share them. Proxies trusted for a limited time are compared by
+/// their expiry [`Instant`], so two configurations calling [`Config::add_trusted_ip_for`] with the
+/// same arguments are not equal, as their proxies expire a moment apart.
+///
 /// By default, it trusts the following:
 ///   - IPV4 Loopback
 ///   - IPV4 Private Networks
@@ -23,12 +57,341 @@ use ipnet::{AddrParseError, IpNet};
 /// ```
 #[derive(Debug, Clone)]
 pub struct Config {
-    trusted_ips: Vec<IpNet>,
+    trusted_ips: SmallVec<[IpNet; INLINE_TRUSTED_IPS]>,
+    trusted_groups: Vec<(IpNet, Arc<str>)>,
+    internal_proxies: Vec<IpNet>,
+    expiring_ips: Vec<(IpNet, Instant)>,
+    untrusted_ips: Vec<IpNet>,
+    ranges: RangesCache,
+    trusted_hostnames: TrustedHostnames,
+    trusted_certificate_identities: Vec<String>,
+    trusted_local_ports: Vec<u16>,
+    trusted_peer_ports: Vec<RangeInclusive<u16>>,
+    server_addresses: Vec<IpAddr>,
+    server_identifiers: Vec<String>,
+    server_identity: Option<ServerIdentity>,
     pub(crate) is_forwarded_trusted: bool,
     pub(crate) is_x_forwarded_for_trusted: bool,
     pub(crate) is_x_forwarded_host_trusted: bool,
     pub(crate) is_x_forwarded_proto_trusted: bool,
     pub(crate) is_x_forwarded_by_trusted: bool,
+    pub(crate) max_header_value_length: Option<usize>,
+    pub(crate) max_total_header_length: Option<usize>,
+    pub(crate) max_chain_length: Option<usize>,
+    pub(crate) long_chain: LongChain,
+    pub(crate) is_proto_required: bool,
+    pub(crate) is_host_required: bool,
+    pub(crate) is_server_name_checked: bool,
+    pub(crate) is_port_corrected: bool,
+    pub(crate) is_mapped_ipv4_unmapped: bool,
+    pub(crate) trusted_hops: Option<usize>,
+    pub(crate) client_ip_strategy: ClientIpStrategy,
+    pub(crate) private_client_ip: PrivateClientIp,
+    pub(crate) spoof_hook: Option<SpoofHook>,
+    pub(crate) ip_extractors: IpExtractors,
+    pub(crate) header_names: HeaderNames,
+    pub(crate) client_ip_header: Option<(Box<str>, ClientIpHeaderPriority)>,
+    pub(crate) allowed_schemes: Vec<Box<str>>,
+    pub(crate) allowed_host_suffixes: Vec<Box<str>>,
+    pub(crate) host_normalizer: Option<HostNormalizerHook>,
+    pub(crate) reputation_provider: Option<ReputationHook>,
+    #[cfg(feature = "hmac")]
+    pub(crate) signed_client_ip: Option<crate::signed::SignedClientIp>,
+}
+
+/// What to do when the resolved client address is private or reserved
+///
+/// See [`Config::set_private_client_ip`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum PrivateClientIp {
+    /// Accept the address as the client address
+    #[default]
+    Accept,
+    /// Consider the address as a proxy and continue walking the chain past it
+    Skip,
+    /// Ignore the forwarding headers and use the peer address as the client address
+    UsePeer,
+}
+
+/// What to do when a forwarding header has more elements than the maximum chain length
+///
+/// See [`Config::set_max_chain_length`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
+pub enum LongChain {
+    /// Only walk the elements closest to the peer, up to the maximum length
+    #[default]
+    Truncate,
+    /// Ignore every forwarding header, as if the peer was not trusted
+    Untrust,
+}
+
+/// Which entry of the forwarding headers is selected as the client, once the peer is trusted
+///
+/// See [`Config::set_client_ip_strategy`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
+pub enum ClientIpStrategy {
+    /// The first entry from the right which address is not trusted
+    #[default]
+    RightmostUntrusted,
+    /// The first entry from the left which address is valid and neither private nor reserved
+    ///
+    /// The leftmost entries are set by the client and can be forged, this strategy is meant for
+    /// analytics that want the address claimed by the client, not for access control.
+    LeftmostNonPrivate,
+    /// The last entry, whatever its address, like nginx with `real_ip_recursive off`
+    Rightmost,
+    /// The first entry from the left which address is valid, whatever its address
+    ///
+    /// Like [`ClientIpStrategy::LeftmostNonPrivate`], it can be forged by clients, it is used by
+    /// [`Config::new_trust_all`].
+    Leftmost,
+}
+
+/// When the single address header set with [`Config::set_client_ip_header`] is read
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
+pub enum ClientIpHeaderPriority {
+    /// The header takes precedence over `Forwarded` and `X-Forwarded-For`
+    #[default]
+    BeforeForwarded,
+    /// The header is only read when `Forwarded` and `X-Forwarded-For` give no client address
+    AfterForwarded,
+}
+
+/// How the trust of two configurations is combined by [`Config::merge_with`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
+pub enum MergeMode {
+    /// Trust what either configuration trusts
+    #[default]
+    Union,
+    /// Only trust what both configurations trust
+    Intersection,
+}
+
+/// How this server identifies itself in the forwarding headers it emits
+///
+/// See [`Config::set_server_identity`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ServerIdentity {
+    /// An address of this server
+    Ip(IpAddr),
+    /// An obfuscated identifier, which should start with `_` (RFC 7239, section 6.3)
+    Obfuscated(String),
+    /// A host name, which is not a valid node for RFC 7239 but is understood by most proxies
+    Hostname(String),
+}
+
+impl fmt::Display for ServerIdentity {
+    /// Render the identity as a node of the `Forwarded` header, quoted when needed
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ip(IpAddr::V4(ip)) => write!(f, "{ip}"),
+            Self::Ip(IpAddr::V6(ip)) => write!(f, "\"[{ip}]\""),
+            Self::Obfuscated(token) => f.write_str(token),
+            Self::Hostname(hostname) => write!(f, "\"{hostname}\""),
+        }
+    }
+}
+
+/// Parse an IP address or a CIDR
+fn parse_network(proxy: &str, position: Option<usize>) -> Result<IpNet, ConfigError> {
+    match proxy.parse() {
+        Ok(v) => Ok(v),
+        Err(e) => match proxy.parse::<IpAddr>() {
+            Ok(v) => Ok(IpNet::from(v)),
+            _ => Err(ConfigError::invalid_network(proxy, position, e)),
+        },
+    }
+}
+
+/// Parse a list of networks with one entry per line, see [`Config::add_trusted_ip_list`]
+pub(crate) fn parse_network_list(list: &str) -> Result<Vec<IpNet>, ConfigError> {
+    parse_networks(
+        list.lines()
+            .enumerate()
+            .map(|(index, line)| (index + 1, line.split('#').next().unwrap_or("").trim()))
+            .filter(|(_, entry)| !entry.is_empty()),
+    )
+}
+
+/// Parse a list of positioned entries, collecting every invalid entry
+pub(crate) fn parse_networks<'a>(
+    entries: impl Iterator<Item = (usize, &'a str)>,
+) -> Result<Vec<IpNet>, ConfigError> {
+    let mut networks = Vec::new();
+    let mut errors = Vec::new();
+
+    for (position, entry) in entries {
+        match parse_network(entry, Some(position)) {
+            Ok(network) => networks.push(network),
+            Err(error) => errors.push(error),
+        }
+    }
+
+    match ConfigError::from_errors(errors) {
+        Some(error) => Err(error),
+        None => Ok(networks),
+    }
+}
+
+/// Check if a network contains an address and is more specific than the most specific untrusted
+/// network containing it
+fn is_more_specific(network: &IpNet, remote_addr: &IpAddr, untrusted: Option<u8>) -> bool {
+    network.contains(remote_addr) && untrusted.is_none_or(|len| network.prefix_len() > len)
+}
+
+/// Replace a setting by the one of another configuration when it is set
+fn overlay<T: Clone>(setting: &mut Option<T>, other: &Option<T>) {
+    if other.is_some() {
+        setting.clone_from(other);
+    }
+}
+
+/// Keep the requirements of both configurations, an empty list not requiring anything
+fn intersect_requirements<T: Clone + PartialEq>(requirements: &mut Vec<T>, other: &[T]) {
+    if requirements.is_empty() {
+        requirements.extend_from_slice(other);
+    } else if !other.is_empty() {
+        requirements.retain(|requirement| other.contains(requirement));
+    }
+}
+
+impl FromStr for Config {
+    type Err = ConfigError;
+
+    /// Parse a comma or space separated list of trusted proxies, see [`Config::from_cidr_list`]
+    fn from_str(list: &str) -> Result<Self, Self::Err> {
+        Self::from_cidr_list(list)
+    }
+}
+
+/// The canonical form of a configuration, compared and hashed by [`Config`]
+#[derive(PartialEq, Eq, Hash)]
+struct Canonical<'c> {
+    trusted_ips: Vec<IpNet>,
+    trusted_groups: Vec<(IpNet, &'c str)>,
+    internal_proxies: Vec<IpNet>,
+    expiring_ips: Vec<(IpNet, Instant)>,
+    untrusted_ips: Vec<IpNet>,
+    trusted_hostnames: Vec<&'c str>,
+    trusted_certificate_identities: Vec<&'c str>,
+    trusted_local_ports: Vec<u16>,
+    trusted_peer_ports: Vec<(u16, u16)>,
+    server_addresses: Vec<IpAddr>,
+    server_identifiers: Vec<&'c str>,
+    server_identity: Option<&'c ServerIdentity>,
+    flags: [bool; 10],
+    lengths: [Option<usize>; 3],
+    long_chain: LongChain,
+    trusted_hops: Option<usize>,
+    client_ip_strategy: ClientIpStrategy,
+    private_client_ip: PrivateClientIp,
+    ip_extractors: Vec<(i32, Option<&'c str>, *const ())>,
+    header_names: &'c HeaderNames,
+    client_ip_header: Option<(&'c str, ClientIpHeaderPriority)>,
+    allowed_schemes: Vec<&'c str>,
+    allowed_host_suffixes: Vec<&'c str>,
+    hooks: [Option<*const ()>; 3],
+    #[cfg(feature = "hmac")]
+    signed_client_ip: Option<&'c crate::signed::SignedClientIp>,
+}
+
+/// Sort and deduplicate the entries of a list
+fn sorted<T: Ord>(entries: impl Iterator<Item = T>) -> Vec<T> {
+    let mut entries = entries.collect::<Vec<_>>();
+
+    entries.sort_unstable();
+    entries.dedup();
+    entries
+}
+
+impl Config {
+    fn canonical(&self) -> Canonical<'_> {
+        Canonical {
+            trusted_ips: sorted(self.trusted_ips.iter().map(IpNet::trunc)),
+            trusted_groups: sorted(
+                self.trusted_groups
+                    .iter()
+                    .map(|(network, group)| (network.trunc(), group.as_ref())),
+            ),
+            internal_proxies: sorted(self.internal_proxies.iter().map(IpNet::trunc)),
+            expiring_ips: sorted(
+                self.expiring_ips
+                    .iter()
+                    .map(|(network, expires_at)| (network.trunc(), *expires_at)),
+            ),
+            untrusted_ips: sorted(self.untrusted_ips.iter().map(IpNet::trunc)),
+            trusted_hostnames: sorted(self.trusted_hostnames.names()),
+            trusted_certificate_identities: sorted(
+                self.trusted_certificate_identities
+                    .iter()
+                    .map(String::as_str),
+            ),
+            trusted_local_ports: sorted(self.trusted_local_ports.iter().copied()),
+            trusted_peer_ports: sorted(
+                self.trusted_peer_ports
+                    .iter()
+                    .map(|ports| (*ports.start(), *ports.end())),
+            ),
+            server_addresses: sorted(self.server_addresses.iter().copied()),
+            server_identifiers: sorted(self.server_identifiers.iter().map(String::as_str)),
+            server_identity: self.server_identity.as_ref(),
+            flags: [
+                self.is_forwarded_trusted,
+                self.is_x_forwarded_for_trusted,
+                self.is_x_forwarded_host_trusted,
+                self.is_x_forwarded_proto_trusted,
+                self.is_x_forwarded_by_trusted,
+                self.is_proto_required,
+                self.is_host_required,
+                self.is_server_name_checked,
+                self.is_port_corrected,
+                self.is_mapped_ipv4_unmapped,
+            ],
+            lengths: [
+                self.max_header_value_length,
+                self.max_total_header_length,
+                self.max_chain_length,
+            ],
+            long_chain: self.long_chain,
+            trusted_hops: self.trusted_hops,
+            client_ip_strategy: self.client_ip_strategy,
+            private_client_ip: self.private_client_ip,
+            ip_extractors: self.ip_extractors.entries().collect(),
+            header_names: &self.header_names,
+            client_ip_header: self
+                .client_ip_header
+                .as_ref()
+                .map(|(name, priority)| (name.as_ref(), *priority)),
+            allowed_schemes: sorted(self.allowed_schemes.iter().map(AsRef::as_ref)),
+            allowed_host_suffixes: sorted(self.allowed_host_suffixes.iter().map(AsRef::as_ref)),
+            hooks: [
+                self.spoof_hook.as_ref().map(SpoofHook::id),
+                self.host_normalizer.as_ref().map(HostNormalizerHook::id),
+                self.reputation_provider.as_ref().map(ReputationHook::id),
+            ],
+            #[cfg(feature = "hmac")]
+            signed_client_ip: self.signed_client_ip.as_ref(),
+        }
+    }
+}
+
+impl PartialEq for Config {
+    fn eq(&self, other: &Self) -> bool {
+        self.canonical() == other.canonical()
+    }
+}
+
+impl Eq for Config {}
+
+impl Hash for Config {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.canonical().hash(state);
+    }
 }
 
 impl Default for Config {
@@ -41,122 +404,2060 @@ impl Config {
     /// Create a new TrustedProxies instance with no trusted proxies or headers
     pub fn new() -> Self {
         Self {
-            trusted_ips: Vec::new(),
+            trusted_ips: SmallVec::new(),
+            trusted_groups: Vec::new(),
+            internal_proxies: Vec::new(),
+            expiring_ips: Vec::new(),
+            untrusted_ips: Vec::new(),
+            ranges: RangesCache::default(),
+            trusted_hostnames: TrustedHostnames::default(),
+            trusted_certificate_identities: Vec::new(),
+            trusted_local_ports: Vec::new(),
+            trusted_peer_ports: Vec::new(),
+            server_addresses: Vec::new(),
+            server_identifiers: Vec::new(),
+            server_identity: None,
             is_forwarded_trusted: false,
             is_x_forwarded_for_trusted: false,
             is_x_forwarded_host_trusted: false,
             is_x_forwarded_proto_trusted: false,
             is_x_forwarded_by_trusted: false,
+            max_header_value_length: None,
+            max_total_header_length: None,
+            max_chain_length: None,
+            long_chain: LongChain::Truncate,
+            is_proto_required: false,
+            is_host_required: false,
+            is_server_name_checked: false,
+            is_port_corrected: false,
+            is_mapped_ipv4_unmapped: false,
+            trusted_hops: None,
+            client_ip_strategy: ClientIpStrategy::RightmostUntrusted,
+            private_client_ip: PrivateClientIp::Accept,
+            spoof_hook: None,
+            ip_extractors: IpExtractors::default(),
+            header_names: HeaderNames::default(),
+            client_ip_header: None,
+            allowed_schemes: Vec::new(),
+            allowed_host_suffixes: Vec::new(),
+            host_normalizer: None,
+            reputation_provider: None,
+            #[cfg(feature = "hmac")]
+            signed_client_ip: None,
         }
     }
 
     /// Create a new TrustedProxies instance with local and private networks ip trusted and FORWARDED / X-Forwarded-For headers trusted
     pub fn new_local() -> Self {
         Self {
-            trusted_ips: vec![
-                // IPV4 Loopback
-                "127.0.0.0/8".parse().unwrap(),
-                // IPV4 Private Networks
-                "10.0.0.0/8".parse().unwrap(),
-                "172.16.0.0/12".parse().unwrap(),
-                "192.168.0.0/16".parse().unwrap(),
-                // IPV6 Loopback
-                "::1/128".parse().unwrap(),
-                // IPV6 Private network
-                "fd00::/8".parse().unwrap(),
-            ],
+            trusted_ips: ranges::LOCAL.iter().copied().collect(),
+            trusted_groups: Vec::new(),
+            internal_proxies: Vec::new(),
+            expiring_ips: Vec::new(),
+            untrusted_ips: Vec::new(),
+            ranges: RangesCache::default(),
+            trusted_hostnames: TrustedHostnames::default(),
+            trusted_certificate_identities: Vec::new(),
+            trusted_local_ports: Vec::new(),
+            trusted_peer_ports: Vec::new(),
+            server_addresses: Vec::new(),
+            server_identifiers: Vec::new(),
+            server_identity: None,
             is_forwarded_trusted: true,
             is_x_forwarded_for_trusted: true,
             is_x_forwarded_host_trusted: false,
             is_x_forwarded_proto_trusted: false,
             is_x_forwarded_by_trusted: false,
+            max_header_value_length: None,
+            max_total_header_length: None,
+            max_chain_length: None,
+            long_chain: LongChain::Truncate,
+            is_proto_required: false,
+            is_host_required: false,
+            is_server_name_checked: false,
+            is_port_corrected: false,
+            is_mapped_ipv4_unmapped: false,
+            trusted_hops: None,
+            client_ip_strategy: ClientIpStrategy::RightmostUntrusted,
+            private_client_ip: PrivateClientIp::Accept,
+            spoof_hook: None,
+            ip_extractors: IpExtractors::default(),
+            header_names: HeaderNames::default(),
+            client_ip_header: None,
+            allowed_schemes: Vec::new(),
+            allowed_host_suffixes: Vec::new(),
+            host_normalizer: None,
+            reputation_provider: None,
+            #[cfg(feature = "hmac")]
+            signed_client_ip: None,
         }
     }
 
-    /// Add a trusted proxy to the list of trusted proxies
+    /// Create a configuration trusting every peer and every forwarding header
     ///
-    /// proxy can be an IP address or a CIDR
-    pub fn add_trusted_ip(&mut self, proxy: &str) -> Result<(), AddrParseError> {
-        match proxy.parse() {
-            Ok(v) => {
-                self.trusted_ips.push(v);
+    /// **This is dangerous**: any client can set the forwarding headers, and choose its address,
+    /// host and scheme. Only use it for development and tests, or behind a mesh where every peer
+    /// is a controlled proxy that overwrites the headers sent by clients.
+    ///
+    /// Every IPv4 and IPv6 address is trusted, and the client is the farthest valid address of
+    /// the forwarding headers, see [`ClientIpStrategy::Leftmost`].
+    ///
+    /// # Example
+    /// ```
+    /// use core::net::IpAddr;
+    /// use trusted_proxies::{Config, Trusted};
+    ///
+    /// let config = Config::new_trust_all();
+    ///
+    /// let mut request = http::Request::get("/").body(()).unwrap();
+    /// request.headers_mut().insert("x-forwarded-for", "1.2.3.4, 8.8.8.8".parse().unwrap());
+    ///
+    /// let trusted = Trusted::from(IpAddr::from([9, 9, 9, 9]), &request, &config);
+    /// assert_eq!(trusted.ip(), IpAddr::from([1, 2, 3, 4]));
+    /// ```
+    pub fn new_trust_all() -> Self {
+        let mut config = Self::new();
+        config.extend_trusted_ips([
+            IpNet::V4(Ipv4Net::new_assert(Ipv4Addr::UNSPECIFIED, 0)),
+            IpNet::V6(Ipv6Net::new_assert(Ipv6Addr::UNSPECIFIED, 0)),
+        ]);
 
-                Ok(())
-            }
-            Err(e) => match proxy.parse::<IpAddr>() {
-                Ok(v) => {
-                    self.trusted_ips.push(IpNet::from(v));
+        config.trust_forwarded();
+        config.trust_x_forwarded_for();
+        config.trust_x_forwarded_host();
+        config.trust_x_forwarded_proto();
+        config.trust_x_forwarded_by();
+        config.set_client_ip_strategy(ClientIpStrategy::Leftmost);
 
-                    Ok(())
-                }
-                _ => Err(e),
-            },
+        config
+    }
+
+    /// Create a configuration trusting a fixed number of proxies in front of the service, see
+    /// [`Config::set_trusted_hops`]
+    ///
+    /// For platforms where the addresses of the proxies are unknown but their number is fixed,
+    /// like a Heroku-like router appending its peer to `X-Forwarded-For`: the `Forwarded` and
+    /// `X-Forwarded-For` headers are trusted, and the client is the `hops + 1`-th entry from the
+    /// right.
+    ///
+    /// # Example
+    /// ```
+    /// use core::net::IpAddr;
+    /// use trusted_proxies::{Config, Trusted};
+    ///
+    /// let config = Config::new_hop_count(1);
+    ///
+    /// let mut request = http::Request::get("/").body(()).unwrap();
+    /// request.headers_mut().insert("x-forwarded-for", "6.6.6.6, 1.2.3.4, 10.1.2.3".parse().unwrap());
+    ///
+    /// let trusted = Trusted::from(IpAddr::from([10, 1, 2, 4]), &request, &config);
+    /// assert_eq!(trusted.ip(), IpAddr::from([1, 2, 3, 4]));
+    /// ```
+    pub fn new_hop_count(hops: usize) -> Self {
+        let mut config = Self::new();
+        config.trust_forwarded();
+        config.trust_x_forwarded_for();
+        config.set_trusted_hops(hops);
+
+        config
+    }
+
+    /// Create a configuration for a service behind Varnish
+    ///
+    /// Varnish appends the address of its client to `X-Forwarded-For` and sets no other
+    /// forwarding header by default, so only `X-Forwarded-For` is trusted, from the given Varnish
+    /// addresses. With a single Varnish hop, the last element of the header is the client.
+    ///
+    /// # Example
+    /// ```
+    /// use trusted_proxies::Config;
+    ///
+    /// let config = Config::new_varnish(&["10.0.0.5"]).unwrap();
+    ///
+    /// assert!(config.is_ip_trusted(&core::net::IpAddr::from([10, 0, 0, 5])));
+    /// ```
+    pub fn new_varnish(trusted_ips: &[&str]) -> Result<Self, ConfigError> {
+        let mut config = Self::new();
+        config.add_trusted_ips(trusted_ips)?;
+
+        config.trust_x_forwarded_for();
+
+        Ok(config)
+    }
+
+    /// Create a configuration matching the `forwardedHeaders.trustedIPs` option of Traefik
+    ///
+    /// Traefik keeps the `X-Forwarded-*` headers sent by a trusted address and overwrites them
+    /// otherwise, before appending its client to `X-Forwarded-For`. Giving the Traefik addresses
+    /// and its `trustedIPs` here trusts `X-Forwarded-For`, `X-Forwarded-Host` and
+    /// `X-Forwarded-Proto` from the same hops, so the service resolves the same client.
+    ///
+    /// # Example
+    /// ```
+    /// use trusted_proxies::Config;
+    ///
+    /// let config = Config::new_traefik(&["10.0.0.0/24", "173.245.48.0/20"]).unwrap();
+    ///
+    /// assert!(config.is_ip_trusted(&core::net::IpAddr::from([173, 245, 48, 1])));
+    /// ```
+    pub fn new_traefik(trusted_ips: &[&str]) -> Result<Self, ConfigError> {
+        let mut config = Self::new();
+        config.add_trusted_ips(trusted_ips)?;
+
+        config.trust_x_forwarded_for();
+        config.trust_x_forwarded_host();
+        config.trust_x_forwarded_proto();
+
+        Ok(config)
+    }
+
+    /// Create a configuration matching the `trusted_proxies` option of Caddy, with its default
+    /// client address selection
+    ///
+    /// Caddy sets `X-Forwarded-For`, `X-Forwarded-Proto` and `X-Forwarded-Host` when proxying,
+    /// keeping the values sent by a trusted proxy. When the peer is trusted, Caddy takes the first
+    /// valid address from the left of `X-Forwarded-For` as the client, see
+    /// [`ClientIpStrategy::Leftmost`]. This address is set by the client and can be forged, use
+    /// [`Config::new_caddy_strict`] for access control.
+    ///
+    /// # Example
+    /// ```
+    /// use trusted_proxies::{Config, Trusted};
+    ///
+    /// let config = Config::new_caddy(&["192.168.0.0/16"]).unwrap();
+    /// let mut request = http::Request::get("/").body(()).unwrap();
+    /// request.headers_mut().insert("x-forwarded-for", "1.2.3.4, 5.6.7.8".parse().unwrap());
+    ///
+    /// let trusted = Trusted::from(core::net::IpAddr::from([192, 168, 1, 1]), &request, &config);
+    /// assert_eq!(trusted.ip(), core::net::IpAddr::from([1, 2, 3, 4]));
+    /// ```
+    pub fn new_caddy(trusted_ips: &[&str]) -> Result<Self, ConfigError> {
+        let mut config = Self::new_caddy_strict(trusted_ips)?;
+        config.set_client_ip_strategy(ClientIpStrategy::Leftmost);
+
+        Ok(config)
+    }
+
+    /// Create a configuration matching the `trusted_proxies` option of Caddy with
+    /// `trusted_proxies_strict`
+    ///
+    /// The headers are the same as [`Config::new_caddy`], but the client is the first untrusted
+    /// address from the right of `X-Forwarded-For`, which cannot be forged by the client.
+    ///
+    /// # Example
+    /// ```
+    /// use trusted_proxies::{Config, Trusted};
+    ///
+    /// let config = Config::new_caddy_strict(&["192.168.0.0/16"]).unwrap();
+    /// let mut request = http::Request::get("/").body(()).unwrap();
+    /// request.headers_mut().insert("x-forwarded-for", "1.2.3.4, 5.6.7.8".parse().unwrap());
+    ///
+    /// let trusted = Trusted::from(core::net::IpAddr::from([192, 168, 1, 1]), &request, &config);
+    /// assert_eq!(trusted.ip(), core::net::IpAddr::from([5, 6, 7, 8]));
+    /// ```
+    pub fn new_caddy_strict(trusted_ips: &[&str]) -> Result<Self, ConfigError> {
+        let mut config = Self::new();
+        config.add_trusted_ips(trusted_ips)?;
+
+        config.trust_x_forwarded_for();
+        config.trust_x_forwarded_host();
+        config.trust_x_forwarded_proto();
+
+        Ok(config)
+    }
+
+    /// Create a configuration like [`Config::new_local`], also trusting the link local, shared
+    /// and unique local networks
+    ///
+    /// Container and cloud NAT environments often present upstream peers from these networks:
+    /// `169.254.0.0/16` and `fe80::/10` link local addresses, the `100.64.0.0/10` carrier-grade
+    /// NAT range and the whole `fc00::/7` unique local range, see [`ranges::PRIVATE_EXTENDED`].
+    ///
+    /// # Example
+    /// ```
+    /// use core::net::IpAddr;
+    /// use trusted_proxies::Config;
+    ///
+    /// let config = Config::new_private_extended();
+    ///
+    /// assert!(config.is_ip_trusted(&IpAddr::from([100, 64, 0, 1])));
+    /// assert!(config.is_ip_trusted(&"fc00::1".parse().unwrap()));
+    /// assert!(!Config::new_local().is_ip_trusted(&"fc00::1".parse().unwrap()));
+    /// ```
+    pub fn new_private_extended() -> Self {
+        Self {
+            trusted_ips: ranges::PRIVATE_EXTENDED.iter().copied().collect(),
+            ..Self::new_local()
         }
     }
 
-    /// Check if a remote address is trusted given the list of trusted proxies
-    pub fn is_ip_trusted(&self, remote_addr: &IpAddr) -> bool {
-        for proxy in &self.trusted_ips {
-            if proxy.contains(remote_addr) {
-                return true;
-            }
+    /// Create a configuration for services fronted by proxies reached through a Tailscale tailnet
+    ///
+    /// The Tailscale address ranges, [`ranges::TAILSCALE_V4`] and [`ranges::TAILSCALE_V6`], are
+    /// trusted along with the `Forwarded` and `X-Forwarded-For` headers. Any node of the tailnet
+    /// is trusted, use [`Config::new_wireguard`] with the addresses of the proxies to restrict it.
+    ///
+    /// # Example
+    /// ```
+    /// use trusted_proxies::Config;
+    ///
+    /// let config = Config::new_tailscale();
+    ///
+    /// assert!(config.is_ip_trusted(&core::net::IpAddr::from([100, 101, 102, 103])));
+    /// assert!(!config.is_ip_trusted(&core::net::IpAddr::from([10, 0, 0, 1])));
+    /// ```
+    pub fn new_tailscale() -> Self {
+        let mut config = Self::new();
+        config.extend_trusted_ips(
+            ranges::TAILSCALE_V4
+                .iter()
+                .map(|network| IpNet::V4(*network))
+                .chain(
+                    ranges::TAILSCALE_V6
+                        .iter()
+                        .map(|network| IpNet::V6(*network)),
+                ),
+        );
+
+        config.trust_forwarded();
+        config.trust_x_forwarded_for();
+
+        config
+    }
+
+    /// Create a configuration for services behind Cloudflare
+    ///
+    /// The embedded Cloudflare edge ranges are trusted, see [`ranges::CLOUDFLARE_VERSION`] for
+    /// the date of the dataset. Cloudflare appends the address of its client to
+    /// `X-Forwarded-For` and sets `X-Forwarded-Proto`, both are trusted.
+    ///
+    /// # Example
+    /// ```
+    /// use trusted_proxies::Config;
+    ///
+    /// let config = Config::new_cloudflare();
+    ///
+    /// assert!(config.is_ip_trusted(&core::net::IpAddr::from([173, 245, 48, 1])));
+    /// assert!(!config.is_ip_trusted(&core::net::IpAddr::from([10, 0, 0, 1])));
+    /// ```
+    pub fn new_cloudflare() -> Self {
+        let mut config = Self::new();
+        config.add_cloudflare_ranges();
+
+        config.trust_x_forwarded_for();
+        config.trust_x_forwarded_proto();
+
+        config
+    }
+
+    /// Trust the embedded Cloudflare edge ranges, [`ranges::CLOUDFLARE_V4`] and
+    /// [`ranges::CLOUDFLARE_V6`], in addition to the existing trusted proxies
+    pub fn add_cloudflare_ranges(&mut self) {
+        self.extend_trusted_ips(
+            ranges::CLOUDFLARE_V4
+                .iter()
+                .map(|network| IpNet::V4(*network))
+                .chain(
+                    ranges::CLOUDFLARE_V6
+                        .iter()
+                        .map(|network| IpNet::V6(*network)),
+                ),
+        );
+    }
+
+    /// Create a configuration for services behind Azure Front Door
+    ///
+    /// The Front Door backend ranges, [`ranges::AZURE_FRONT_DOOR_V4`] and
+    /// [`ranges::AZURE_FRONT_DOOR_V6`], are trusted. The client address is read from
+    /// `X-Azure-ClientIP` with [`crate::AzureClientIp`], falling back to `X-Forwarded-For`, and
+    /// `X-Forwarded-Host` and `X-Forwarded-Proto` set by Front Door are trusted.
+    ///
+    /// # Example
+    /// ```
+    /// use core::net::IpAddr;
+    /// use trusted_proxies::{Config, Trusted};
+    ///
+    /// let config = Config::new_azure_front_door();
+    ///
+    /// let mut request = http::Request::get("/").body(()).unwrap();
+    /// request.headers_mut().insert("x-azure-clientip", "1.2.3.4".parse().unwrap());
+    ///
+    /// let trusted = Trusted::from(IpAddr::from([147, 243, 10, 1]), &request, &config);
+    /// assert_eq!(trusted.ip(), IpAddr::from([1, 2, 3, 4]));
+    /// ```
+    pub fn new_azure_front_door() -> Self {
+        let mut config = Self::new();
+        config.add_azure_front_door_ranges();
+
+        config.trust_x_forwarded_for();
+        config.trust_x_forwarded_host();
+        config.trust_x_forwarded_proto();
+        config.add_ip_extractor(0, AzureClientIp);
+
+        config
+    }
+
+    /// Trust the Azure Front Door backend ranges in addition to the existing trusted proxies
+    ///
+    /// Add the subnet of an Application Gateway placed behind Front Door with
+    /// [`Config::add_trusted_ip`], so the walk goes past both.
+    pub fn add_azure_front_door_ranges(&mut self) {
+        self.extend_trusted_ips(
+            ranges::AZURE_FRONT_DOOR_V4
+                .iter()
+                .map(|network| IpNet::V4(*network))
+                .chain(
+                    ranges::AZURE_FRONT_DOOR_V6
+                        .iter()
+                        .map(|network| IpNet::V6(*network)),
+                ),
+        );
+    }
+
+    /// Create a configuration for services behind Akamai
+    ///
+    /// Akamai does not publish a global list of its edge servers, the given ranges come from the
+    /// Site Shield map or the origin IP access control list of the account. They are trusted in
+    /// the `akamai` group, see [`Config::add_akamai_ranges`], and `X-Forwarded-For` is trusted.
+    ///
+    /// # Example
+    /// ```
+    /// use core::net::IpAddr;
+    /// use trusted_proxies::{Config, Trusted};
+    ///
+    /// let config = Config::new_akamai(&["23.32.0.0/11"]).unwrap();
+    ///
+    /// let mut request = http::Request::get("/").body(()).unwrap();
+    /// request.headers_mut().insert("true-client-ip", "1.2.3.4".parse().unwrap());
+    ///
+    /// let trusted = Trusted::from(IpAddr::from([23, 32, 1, 1]), &request, &config);
+    /// assert_eq!(trusted.ip(), IpAddr::from([1, 2, 3, 4]));
+    /// assert_eq!(trusted.via_group(), Some("akamai"));
+    /// ```
+    pub fn new_akamai(edge_ranges: &[&str]) -> Result<Self, ConfigError> {
+        let mut config = Self::new();
+        config.add_akamai_ranges(edge_ranges)?;
+
+        config.trust_x_forwarded_for();
+
+        Ok(config)
+    }
+
+    /// Trust Akamai edge ranges in the `akamai` group, and read the client address from
+    /// `True-Client-IP` with [`crate::TrueClientIp`] when the peer is one of them
+    ///
+    /// The header is ignored when the peer is another trusted proxy, so a client cannot spoof it
+    /// by going around Akamai. No range is added when one of them is invalid.
+    pub fn add_akamai_ranges(&mut self, edge_ranges: &[&str]) -> Result<(), ConfigError> {
+        let networks = parse_networks(
+            edge_ranges
+                .iter()
+                .enumerate()
+                .map(|(index, range)| (index + 1, *range)),
+        )?;
+
+        for network in networks {
+            self.extend_trusted_ips([network]);
+            self.trusted_groups.push((network, Arc::from("akamai")));
         }
 
-        false
+        self.add_ip_extractor_for_group("akamai", 0, TrueClientIp);
+
+        Ok(())
     }
 
-    /// Trust the `Forwarded` header
-    pub fn trust_forwarded(&mut self) {
-        self.is_forwarded_trusted = true;
+    /// Create a configuration for services fronted by proxies reached through a WireGuard
+    /// overlay network
+    ///
+    /// The overlay networks (the `AllowedIPs` of the proxy peers) are trusted along with the
+    /// `Forwarded` and `X-Forwarded-For` headers.
+    ///
+    /// # Example
+    /// ```
+    /// use trusted_proxies::Config;
+    ///
+    /// let config = Config::new_wireguard(&["10.8.0.0/24", "fd42:42:42::/64"]).unwrap();
+    ///
+    /// assert!(config.is_ip_trusted(&core::net::IpAddr::from([10, 8, 0, 2])));
+    /// ```
+    pub fn new_wireguard(overlay_networks: &[&str]) -> Result<Self, ConfigError> {
+        let mut config = Self::new();
+        config.add_trusted_ips(overlay_networks)?;
+
+        config.trust_forwarded();
+        config.trust_x_forwarded_for();
+
+        Ok(config)
     }
 
-    /// Trust the `X-Forwarded-For` header
-    pub fn trust_x_forwarded_for(&mut self) {
-        self.is_x_forwarded_for_trusted = true;
+    /// Add a trusted proxy to the list of trusted proxies
+    ///
+    /// proxy can be an IP address or a CIDR
+    pub fn add_trusted_ip(&mut self, proxy: &str) -> Result<(), ConfigError> {
+        self.extend_trusted_ips([parse_network(proxy, None)?]);
+
+        Ok(())
     }
 
-    /// Trust the `X-Forwarded-Host` header to fetch the host and optionally the port
+    /// Add several trusted proxies to the list of trusted proxies
     ///
-    /// It is not recommended to trust this header as it can be easily spoofed, however you can trust
-    /// it if you are behind a reverse proxy that **always** sets this header.
+    /// Every invalid entry is reported in the error with its 1-based position, and no proxy is
+    /// added when one of them is invalid.
+    pub fn add_trusted_ips<S: AsRef<str>>(
+        &mut self,
+        proxies: impl IntoIterator<Item = S>,
+    ) -> Result<(), ConfigError> {
+        let proxies = proxies.into_iter().collect::<Vec<_>>();
+        let networks = parse_networks(
+            proxies
+                .iter()
+                .enumerate()
+                .map(|(index, proxy)| (index + 1, proxy.as_ref())),
+        )?;
+
+        self.extend_trusted_ips(networks);
+
+        Ok(())
+    }
+
+    /// Add the trusted proxies of a list, with one IP address or CIDR per line
     ///
-    /// If there is multiple values in the header, the last one is used, even if there is multiple
-    /// proxies in the chain.
+    /// Empty lines and comments starting with `#` are ignored. Every invalid line is reported in
+    /// the error with its line number, and no proxy is added when one of them is invalid.
     ///
-    /// If you need to get the original value with multiple proxies in the chain, you can use the
-    /// `Forwarded` header that allows to do that in a secure way.
-    /// See [RFC7239](https://tools.ietf.org/html/rfc7239) for more information.
-    pub fn trust_x_forwarded_host(&mut self) {
-        self.is_x_forwarded_host_trusted = true;
+    /// # Example
+    /// ```
+    /// use trusted_proxies::Config;
+    ///
+    /// let mut config = Config::new();
+    /// let error = config.add_trusted_ip_list("# edge\n10.0.0.0/8\n10.0.0.0/33\n\nlocalhost\n").unwrap_err();
+    ///
+    /// assert_eq!(error.errors().len(), 2);
+    /// assert!(error.to_string().contains("`localhost` at position 5"));
+    /// ```
+    pub fn add_trusted_ip_list(&mut self, list: &str) -> Result<(), ConfigError> {
+        self.extend_trusted_ips(parse_network_list(list)?);
+
+        Ok(())
     }
 
-    /// Trust the `X-Forwarded-Proto` header to fetch the scheme
+    /// Create a configuration trusting the proxies of a comma or space separated list, e.g. the
+    /// value of a `--trusted-proxies` command line flag
     ///
-    /// It is not recommended to trust this header as it can be easily spoofed, however you can trust
-    /// it if you are behind a reverse proxy that **always** sets this header.
+    /// Like [`Config::new_local`], the `Forwarded` and `X-Forwarded-For` headers are trusted, but
+    /// only the listed proxies are. Every invalid entry is reported in the error with its 1-based
+    /// position. This is also the [`FromStr`](core::str::FromStr) implementation.
     ///
-    /// If there is multiple values in the header, the last one is used, even if there is multiple
-    /// proxies in the chain.
+    /// # Example
+    /// ```
+    /// use core::net::IpAddr;
+    /// use trusted_proxies::Config;
     ///
-    /// If you need to get the original value with multiple proxies in the chain, you can use the
-    /// `Forwarded` header that allows to do that in a secure way.
-    /// See [RFC7239](https://tools.ietf.org/html/rfc7239) for more information.
-    pub fn trust_x_forwarded_proto(&mut self) {
-        self.is_x_forwarded_proto_trusted = true;
+    /// let config: Config = "10.0.0.0/8, ::1".parse().unwrap();
+    /// assert!(config.is_ip_trusted(&IpAddr::from([10, 1, 2, 3])));
+    ///
+    /// let error = Config::from_cidr_list("10.0.0.0/8 localhost 10.0.0.0/33").unwrap_err();
+    /// assert_eq!(error.errors().len(), 2);
+    /// ```
+    pub fn from_cidr_list(list: &str) -> Result<Self, ConfigError> {
+        let networks = parse_networks(
+            list.split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|entry| !entry.is_empty())
+                .enumerate()
+                .map(|(index, entry)| (index + 1, entry)),
+        )?;
+        let mut config = Self::new();
+
+        config.extend_trusted_ips(networks);
+        config.trust_forwarded();
+        config.trust_x_forwarded_for();
+
+        Ok(config)
     }
 
-    /// Trust the `X-Forwarded-By` header to identify the proxy that sent the request
+    /// Add already parsed networks to the list of trusted proxies, e.g. the ones downloaded with
+    /// the [`fetch`](crate::fetch) module
+    pub fn add_trusted_networks(&mut self, networks: impl IntoIterator<Item = IpNet>) {
+        self.extend_trusted_ips(networks);
+    }
+
+    pub(crate) fn extend_trusted_ips(&mut self, networks: impl IntoIterator<Item = IpNet>) {
+        self.trusted_ips.extend(networks);
+        self.ranges.reset();
+    }
+
+    /// Remove a trusted proxy from the list of trusted proxies, returning whether it was trusted
     ///
-    /// It is not recommended to trust this header as it can be easily spoofed, however you can trust
-    /// it if you are behind a reverse proxy that **always** sets this header.
+    /// Only the entries of the same network are removed, including the ones added to a group, as
+    /// an internal proxy or for a limited time. A network inside a broader trusted network stays
+    /// trusted, see [`Config::add_untrusted_ip`] to exclude it.
     ///
-    /// If there is multiple values in the header, the last one is used, even if there is multiple
-    /// proxies in the chain.
+    /// proxy can be an IP address or a CIDR
     ///
-    /// If you need to get the original value with multiple proxies in the chain, you can use the
-    /// `Forwarded` header that allows to do that in a secure way.
-    /// See [RFC7239](https://tools.ietf.org/html/rfc7239) for more information.
-    pub fn trust_x_forwarded_by(&mut self) {
-        self.is_x_forwarded_by_trusted = true;
+    /// # Example
+    /// ```
+    /// use core::net::IpAddr;
+    /// use trusted_proxies::Config;
+    ///
+    /// let mut config = Config::new_local();
+    /// assert!(config.remove_trusted_ip("10.0.0.0/8").unwrap());
+    ///
+    /// assert!(!config.is_ip_trusted(&IpAddr::from([10, 0, 0, 1])));
+    /// assert!(config.is_ip_trusted(&IpAddr::from([127, 0, 0, 1])));
+    /// ```
+    pub fn remove_trusted_ip(&mut self, proxy: &str) -> Result<bool, ConfigError> {
+        let network = parse_network(proxy, None)?.trunc();
+        let count = self.trusted_ips.len() + self.expiring_ips.len();
+
+        self.trusted_ips
+            .retain(|trusted| trusted.trunc() != network);
+        self.trusted_groups
+            .retain(|(trusted, _)| trusted.trunc() != network);
+        self.internal_proxies
+            .retain(|trusted| trusted.trunc() != network);
+        self.expiring_ips
+            .retain(|(trusted, _)| trusted.trunc() != network);
+        self.ranges.reset();
+
+        Ok(self.trusted_ips.len() + self.expiring_ips.len() != count)
+    }
+
+    /// Remove every trusted proxy, including the ones added to a group, as an internal proxy or
+    /// for a limited time
+    ///
+    /// The trusted headers and the other settings are kept, as well as the trusted hostnames,
+    /// so a preset can be reused with other proxies.
+    pub fn clear_trusted_ips(&mut self) {
+        self.trusted_ips.clear();
+        self.ranges.reset();
+        self.trusted_groups.clear();
+        self.internal_proxies.clear();
+        self.expiring_ips.clear();
+    }
+
+    /// Add a trusted proxy that is only trusted for the given duration
+    ///
+    /// Once expired the proxy is ignored, so short-lived proxies (e.g. spot instances or canary
+    /// load balancers) do not accumulate as permanent trust. See
+    /// [`Config::remove_expired_ips`] to drop them from the configuration.
+    ///
+    /// proxy can be an IP address or a CIDR
+    ///
+    /// # Example
+    /// ```
+    /// use std::time::Duration;
+    /// use trusted_proxies::Config;
+    ///
+    /// let mut config = Config::new();
+    /// config.add_trusted_ip_for("203.0.113.7", Duration::from_secs(3600)).unwrap();
+    ///
+    /// assert!(config.is_ip_trusted(&core::net::IpAddr::from([203, 0, 113, 7])));
+    /// ```
+    pub fn add_trusted_ip_for(&mut self, proxy: &str, ttl: Duration) -> Result<(), ConfigError> {
+        self.add_trusted_ip_until(proxy, Instant::now() + ttl)
+    }
+
+    /// Add a trusted proxy that is only trusted until the given instant, see
+    /// [`Config::add_trusted_ip_for`]
+    ///
+    /// proxy can be an IP address or a CIDR
+    pub fn add_trusted_ip_until(
+        &mut self,
+        proxy: &str,
+        expires_at: Instant,
+    ) -> Result<(), ConfigError> {
+        self.expiring_ips
+            .push((parse_network(proxy, None)?, expires_at));
+
+        Ok(())
+    }
+
+    /// Remove the expired proxies added with [`Config::add_trusted_ip_for`]
+    pub fn remove_expired_ips(&mut self) {
+        let now = Instant::now();
+
+        self.expiring_ips
+            .retain(|(_, expires_at)| *expires_at > now);
+    }
+
+    /// Check if a remote address is trusted by a proxy added with [`Config::add_trusted_ip_for`]
+    /// that has not expired yet
+    pub(crate) fn is_expiring_ip_trusted(&self, remote_addr: &IpAddr) -> bool {
+        if self.expiring_ips.is_empty() {
+            return false;
+        }
+
+        let now = Instant::now();
+        let untrusted = self.untrusted_prefix_len(remote_addr);
+
+        self.expiring_ips.iter().any(|(network, expires_at)| {
+            *expires_at > now && is_more_specific(network, remote_addr, untrusted)
+        })
+    }
+
+    /// Whether proxies are trusted for a limited time or by hostname
+    pub(crate) fn has_dynamic_proxies(&self) -> bool {
+        !self.expiring_ips.is_empty() || !self.trusted_hostnames.is_empty()
+    }
+
+    /// Whether the peer is trusted whatever its address, when a number of trusted hops is
+    /// configured without trusted networks, see [`Config::set_trusted_hops`]
+    pub(crate) fn is_any_peer_trusted(&self) -> bool {
+        self.trusted_hops.is_some() && self.trusted_ips.is_empty() && !self.has_dynamic_proxies()
+    }
+
+    /// Exclude a sub-range of the trusted proxies
+    ///
+    /// The most specific network containing an address decides whether it is trusted: an
+    /// untrusted network carves a hole in a broader trusted network, and a narrower trusted
+    /// network can be trusted again inside an untrusted one. A network both trusted and untrusted
+    /// is not trusted.
+    ///
+    /// proxy can be an IP address or a CIDR
+    ///
+    /// # Example
+    /// ```
+    /// use core::net::IpAddr;
+    /// use trusted_proxies::Config;
+    ///
+    /// let mut config = Config::new();
+    /// config.add_trusted_ip("10.0.0.0/8").unwrap();
+    /// config.add_untrusted_ip("10.42.0.0/16").unwrap();
+    /// config.add_trusted_ip("10.42.0.1").unwrap();
+    ///
+    /// assert!(config.is_ip_trusted(&IpAddr::from([10, 1, 0, 1])));
+    /// assert!(!config.is_ip_trusted(&IpAddr::from([10, 42, 0, 2])));
+    /// assert!(config.is_ip_trusted(&IpAddr::from([10, 42, 0, 1])));
+    /// ```
+    pub fn add_untrusted_ip(&mut self, proxy: &str) -> Result<(), ConfigError> {
+        self.untrusted_ips.push(parse_network(proxy, None)?);
+        self.ranges.reset();
+
+        Ok(())
+    }
+
+    /// Get the networks excluded from the trusted proxies, see [`Config::add_untrusted_ip`]
+    pub fn untrusted_ips(&self) -> impl Iterator<Item = &IpNet> {
+        self.untrusted_ips.iter()
+    }
+
+    pub(crate) fn untrusted_networks(&self) -> &[IpNet] {
+        &self.untrusted_ips
+    }
+
+    /// Get the prefix length of the most specific untrusted network containing an address
+    fn untrusted_prefix_len(&self, remote_addr: &IpAddr) -> Option<u8> {
+        self.untrusted_ips
+            .iter()
+            .filter(|network| network.contains(remote_addr))
+            .map(IpNet::prefix_len)
+            .max()
+    }
+
+    /// Trust the addresses a hostname resolves to, e.g. the service discovery name of proxies
+    /// running on dynamic addresses
+    ///
+    /// The hostname is resolved with the system resolver before returning, and is not added when
+    /// it cannot be resolved. The addresses are only resolved again by
+    /// [`Config::resolve_trusted_hostnames`] or [`Config::refresh_trusted_hostnames`].
+    ///
+    /// # Example
+    /// ```
+    /// use trusted_proxies::Config;
+    ///
+    /// let mut config = Config::new();
+    /// config.add_trusted_hostname("localhost").unwrap();
+    ///
+    /// assert!(config.is_ip_trusted(&core::net::IpAddr::from([127, 0, 0, 1])));
+    /// ```
+    pub fn add_trusted_hostname(&mut self, hostname: &str) -> Result<(), ConfigError> {
+        self.trusted_hostnames.add(hostname)
+    }
+
+    /// Resolve the trusted hostnames again
+    ///
+    /// The addresses are shared with the clones of this configuration, including the ones held
+    /// by a [`crate::ConfigHandle`] or a [`TrustedMatcher`]. A hostname that cannot be resolved
+    /// keeps its previous addresses, and is reported in the error.
+    pub fn resolve_trusted_hostnames(&self) -> Result<(), ConfigError> {
+        self.trusted_hostnames.resolve()
+    }
+
+    /// Resolve the trusted hostnames on an interval in a background thread, until the returned
+    /// refresher is dropped, see [`Config::resolve_trusted_hostnames`]
+    ///
+    /// Hostnames added after this call are not resolved by the refresher.
+    pub fn refresh_trusted_hostnames(&self, interval: Duration) -> HostnameRefresher {
+        self.trusted_hostnames.refresh(interval)
+    }
+
+    pub(crate) fn is_hostname_trusted(&self, remote_addr: &IpAddr) -> bool {
+        // a resolved address is as specific as a network can be, only an untrusted network of
+        // this single address excludes it
+        self.trusted_hostnames.contains(remote_addr)
+            && !self.untrusted_ips.iter().any(|network| {
+                network.contains(remote_addr) && network.prefix_len() == network.max_prefix_len()
+            })
+    }
+
+    /// Add a trusted proxy to the list of trusted proxies, tagged with a group name
+    ///
+    /// The group of the peer that sent the request is available with [`crate::Trusted::via_group`],
+    /// so logging and policies can differ given the proxies the request went through (e.g.
+    /// `cloudflare` or `internal-lb`).
+    ///
+    /// proxy can be an IP address or a CIDR
+    pub fn add_trusted_ip_group(&mut self, group: &str, proxy: &str) -> Result<(), ConfigError> {
+        let network = parse_network(proxy, None)?;
+
+        self.extend_trusted_ips([network]);
+        self.trusted_groups.push((network, Arc::from(group)));
+
+        Ok(())
+    }
+
+    /// Add an internal proxy to the list of trusted proxies
+    ///
+    /// This follows the distinction of the Apache `mod_remoteip` module between
+    /// `RemoteIPInternalProxy` and `RemoteIPTrustedProxy`: both are trusted, but internal proxies
+    /// are removed from the chain reported by [`crate::Trusted::retained_proxies`], like they are
+    /// removed from the `RemoteIPProxiesHeader` header.
+    ///
+    /// Unlike `mod_remoteip`, private addresses reported by trusted proxies that are not internal
+    /// are accepted as the client address, see [`Config::set_private_client_ip`] to change it.
+    ///
+    /// proxy can be an IP address or a CIDR
+    pub fn add_internal_proxy(&mut self, proxy: &str) -> Result<(), ConfigError> {
+        let network = parse_network(proxy, None)?;
+
+        self.extend_trusted_ips([network]);
+        self.internal_proxies.push(network);
+
+        Ok(())
+    }
+
+    /// Check if a remote address is an internal proxy, see [`Config::add_internal_proxy`]
+    pub fn is_internal_proxy(&self, remote_addr: &IpAddr) -> bool {
+        let remote_addr = &self.unmap(remote_addr);
+
+        self.internal_proxies
+            .iter()
+            .any(|network| network.contains(remote_addr))
+    }
+
+    /// Get the group of a remote address, using the most specific network when several match
+    pub fn ip_group(&self, remote_addr: &IpAddr) -> Option<&str> {
+        self.find_group(remote_addr).map(|group| group.as_ref())
+    }
+
+    pub(crate) fn find_group(&self, remote_addr: &IpAddr) -> Option<&Arc<str>> {
+        let remote_addr = &self.unmap(remote_addr);
+
+        self.trusted_groups
+            .iter()
+            .filter(|(network, _)| network.contains(remote_addr))
+            .max_by_key(|(network, _)| network.prefix_len())
+            .map(|(_, group)| group)
+    }
+
+    /// Merge another configuration into this one, trusting the proxies and headers trusted by
+    /// either of them, see [`Config::merge_with`]
+    ///
+    /// # Example
+    /// ```
+    /// use core::net::IpAddr;
+    /// use trusted_proxies::Config;
+    ///
+    /// let mut config = Config::new_local();
+    ///
+    /// let mut service = Config::new();
+    /// service.add_trusted_ip("203.0.113.0/24").unwrap();
+    /// service.trust_x_forwarded_proto();
+    ///
+    /// config.merge(&service);
+    ///
+    /// assert!(config.is_ip_trusted(&IpAddr::from([10, 0, 0, 1])));
+    /// assert!(config.is_ip_trusted(&IpAddr::from([203, 0, 113, 7])));
+    /// ```
+    pub fn merge(&mut self, other: &Config) {
+        self.merge_with(other, MergeMode::Union);
+    }
+
+    /// Merge another configuration into this one, e.g. a per-service overlay into an
+    /// organization-wide base configuration
+    ///
+    /// With [`MergeMode::Union`], the trusted proxies are added and a header is trusted when it
+    /// is trusted by either configuration. With [`MergeMode::Intersection`], only the networks
+    /// trusted by both configurations are kept (the narrower one when they overlap), a header is
+    /// only trusted when it is trusted by both, and the groups, internal and expiring proxies of
+    /// this configuration are kept when a proxy of the other one covers them. Trusted hostnames
+    /// are dropped by an intersection.
+    ///
+    /// In both modes, the untrusted networks, the server addresses and the requirements of both
+    /// configurations apply, extractors are added, and the other settings set on the other
+    /// configuration (limits, hooks, custom header names, strategies, ...) replace the ones of
+    /// this configuration.
+    pub fn merge_with(&mut self, other: &Config, mode: MergeMode) {
+        match mode {
+            MergeMode::Union => {
+                for network in other.trusted_ips() {
+                    if !self.trusted_ips.contains(network) {
+                        self.trusted_ips.push(*network);
+                    }
+                }
+
+                self.trusted_groups
+                    .extend(other.trusted_groups.iter().cloned());
+                self.internal_proxies
+                    .extend(other.internal_proxies.iter().copied());
+                self.expiring_ips.extend(other.expiring_ips.iter().copied());
+                self.trusted_hostnames.extend(&other.trusted_hostnames);
+
+                self.is_forwarded_trusted |= other.is_forwarded_trusted;
+                self.is_x_forwarded_for_trusted |= other.is_x_forwarded_for_trusted;
+                self.is_x_forwarded_host_trusted |= other.is_x_forwarded_host_trusted;
+                self.is_x_forwarded_proto_trusted |= other.is_x_forwarded_proto_trusted;
+                self.is_x_forwarded_by_trusted |= other.is_x_forwarded_by_trusted;
+                self.trusted_hops = self.trusted_hops.max(other.trusted_hops);
+
+                self.trusted_certificate_identities
+                    .extend(other.trusted_certificate_identities.iter().cloned());
+                self.trusted_local_ports
+                    .extend(other.trusted_local_ports.iter().copied());
+                self.trusted_peer_ports
+                    .extend(other.trusted_peer_ports.iter().cloned());
+                self.allowed_schemes
+                    .extend(other.allowed_schemes.iter().cloned());
+                self.allowed_host_suffixes
+                    .extend(other.allowed_host_suffixes.iter().cloned());
+            }
+            MergeMode::Intersection => {
+                let covers =
+                    |network: &IpNet| other.trusted_ips().any(|trusted| trusted.contains(network));
+                let mut trusted_ips = SmallVec::new();
+
+                for network in &self.trusted_ips {
+                    for trusted in other.trusted_ips() {
+                        let narrower = if network.contains(trusted) {
+                            trusted
+                        } else if trusted.contains(network) {
+                            network
+                        } else {
+                            continue;
+                        };
+
+                        if !trusted_ips.contains(narrower) {
+                            trusted_ips.push(*narrower);
+                        }
+                    }
+                }
+
+                self.trusted_ips = trusted_ips;
+                self.trusted_groups.retain(|(network, _)| covers(network));
+                self.internal_proxies.retain(|network| covers(network));
+                self.expiring_ips.retain(|(network, _)| covers(network));
+                self.trusted_hostnames = TrustedHostnames::default();
+
+                self.is_forwarded_trusted &= other.is_forwarded_trusted;
+                self.is_x_forwarded_for_trusted &= other.is_x_forwarded_for_trusted;
+                self.is_x_forwarded_host_trusted &= other.is_x_forwarded_host_trusted;
+                self.is_x_forwarded_proto_trusted &= other.is_x_forwarded_proto_trusted;
+                self.is_x_forwarded_by_trusted &= other.is_x_forwarded_by_trusted;
+                self.trusted_hops = match (self.trusted_hops, other.trusted_hops) {
+                    (Some(hops), Some(other_hops)) => Some(hops.min(other_hops)),
+                    _ => None,
+                };
+
+                intersect_requirements(
+                    &mut self.trusted_certificate_identities,
+                    &other.trusted_certificate_identities,
+                );
+                intersect_requirements(&mut self.trusted_local_ports, &other.trusted_local_ports);
+                intersect_requirements(&mut self.trusted_peer_ports, &other.trusted_peer_ports);
+                intersect_requirements(&mut self.allowed_schemes, &other.allowed_schemes);
+                intersect_requirements(
+                    &mut self.allowed_host_suffixes,
+                    &other.allowed_host_suffixes,
+                );
+            }
+        }
+
+        self.untrusted_ips
+            .extend(other.untrusted_ips.iter().copied());
+        self.ranges.reset();
+        self.server_addresses
+            .extend(other.server_addresses.iter().copied());
+        self.server_identifiers
+            .extend(other.server_identifiers.iter().cloned());
+        self.is_proto_required |= other.is_proto_required;
+        self.is_host_required |= other.is_host_required;
+        self.is_server_name_checked |= other.is_server_name_checked;
+        self.is_port_corrected |= other.is_port_corrected;
+        self.is_mapped_ipv4_unmapped |= other.is_mapped_ipv4_unmapped;
+        self.ip_extractors.extend(&other.ip_extractors);
+        self.header_names.overlay(&other.header_names);
+
+        if other.client_ip_strategy != ClientIpStrategy::default() {
+            self.client_ip_strategy = other.client_ip_strategy;
+        }
+
+        if other.private_client_ip != PrivateClientIp::default() {
+            self.private_client_ip = other.private_client_ip;
+        }
+
+        if other.long_chain != LongChain::default() {
+            self.long_chain = other.long_chain;
+        }
+
+        overlay(&mut self.server_identity, &other.server_identity);
+        overlay(
+            &mut self.max_header_value_length,
+            &other.max_header_value_length,
+        );
+        overlay(
+            &mut self.max_total_header_length,
+            &other.max_total_header_length,
+        );
+        overlay(&mut self.max_chain_length, &other.max_chain_length);
+        overlay(&mut self.client_ip_header, &other.client_ip_header);
+        overlay(&mut self.spoof_hook, &other.spoof_hook);
+        overlay(&mut self.host_normalizer, &other.host_normalizer);
+        overlay(&mut self.reputation_provider, &other.reputation_provider);
+        #[cfg(feature = "hmac")]
+        overlay(&mut self.signed_client_ip, &other.signed_client_ip);
+    }
+
+    /// Compile this configuration into an immutable [`TrustedMatcher`] optimized for lookups
+    ///
+    /// Later changes to this configuration are not reflected in the returned matcher.
+    pub fn compile(&self) -> TrustedMatcher {
+        TrustedMatcher::new(self)
+    }
+
+    /// Get the trusted networks, e.g. to log the effective configuration at startup
+    ///
+    /// The proxies trusted for a limited time and the addresses of trusted hostnames are not
+    /// included.
+    ///
+    /// # Example
+    /// ```
+    /// use trusted_proxies::Config;
+    ///
+    /// let config = Config::new_local();
+    /// let networks = config.trusted_ips().map(ToString::to_string).collect::<Vec<_>>();
+    ///
+    /// assert!(networks.contains(&"10.0.0.0/8".to_string()));
+    /// assert!(config.is_forwarded_trusted());
+    /// ```
+    pub fn trusted_ips(&self) -> impl Iterator<Item = &IpNet> {
+        self.trusted_ips.iter()
+    }
+
+    /// Whether the `Forwarded` header is trusted, see [`Config::trust_forwarded`]
+    pub fn is_forwarded_trusted(&self) -> bool {
+        self.is_forwarded_trusted
+    }
+
+    /// Whether the `X-Forwarded-For` header is trusted, see [`Config::trust_x_forwarded_for`]
+    pub fn is_x_forwarded_for_trusted(&self) -> bool {
+        self.is_x_forwarded_for_trusted
+    }
+
+    /// Whether the `X-Forwarded-Host` header is trusted, see [`Config::trust_x_forwarded_host`]
+    pub fn is_x_forwarded_host_trusted(&self) -> bool {
+        self.is_x_forwarded_host_trusted
+    }
+
+    /// Whether the `X-Forwarded-Proto` header is trusted, see [`Config::trust_x_forwarded_proto`]
+    pub fn is_x_forwarded_proto_trusted(&self) -> bool {
+        self.is_x_forwarded_proto_trusted
+    }
+
+    /// Whether the `X-Forwarded-By` header is trusted, see [`Config::trust_x_forwarded_by`]
+    pub fn is_x_forwarded_by_trusted(&self) -> bool {
+        self.is_x_forwarded_by_trusted
+    }
+
+    /// Get the number of trusted hops, see [`Config::set_trusted_hops`]
+    pub fn trusted_hops(&self) -> Option<usize> {
+        self.trusted_hops
+    }
+
+    /// Check if a remote address is trusted given the list of trusted proxies
+    pub fn is_ip_trusted(&self, remote_addr: &IpAddr) -> bool {
+        let remote_addr = &self.unmap(remote_addr);
+
+        if self.trusted_ips.len() > INLINE_TRUSTED_IPS {
+            let ranges = self
+                .ranges
+                .get_or_init(|| Ranges::new(self.trusted_ips.iter(), &self.untrusted_ips));
+
+            if ranges.contains(remote_addr) {
+                return true;
+            }
+        } else {
+            let untrusted = self.untrusted_prefix_len(remote_addr);
+
+            for proxy in &self.trusted_ips {
+                if is_more_specific(proxy, remote_addr, untrusted) {
+                    return true;
+                }
+            }
+        }
+
+        self.is_expiring_ip_trusted(remote_addr) || self.is_hostname_trusted(remote_addr)
+    }
+
+    /// Require trusted proxies to authenticate with a client certificate having this identity
+    ///
+    /// Once called, a peer is only trusted if its address is trusted **and** its client
+    /// certificate has one of the required identities, see
+    /// [`crate::ConnectionInformation::client_certificate_identities`]. An identity ending with
+    /// `*` matches any identity starting with the same prefix, e.g. `spiffe://example.org/proxy/*`.
+    ///
+    /// Only [`crate::Trusted::from_connection`] can give the certificate information, with
+    /// [`crate::Trusted::from`] peers will never be trusted once an identity is required.
+    pub fn require_client_certificate(&mut self, identity: &str) {
+        self.trusted_certificate_identities
+            .push(identity.to_string());
+    }
+
+    /// Check if the client certificate of a connection is trusted
+    ///
+    /// Always true when no identity is required
+    pub fn is_certificate_trusted<C: ConnectionInformation + ?Sized>(
+        &self,
+        connection: &C,
+    ) -> bool {
+        if self.trusted_certificate_identities.is_empty() {
+            return true;
+        }
+
+        connection.client_certificate_identities().any(|identity| {
+            self.trusted_certificate_identities.iter().any(|required| {
+                match required.strip_suffix('*') {
+                    Some(prefix) => identity.starts_with(prefix),
+                    None => identity == required,
+                }
+            })
+        })
+    }
+
+    /// Only trust peers connected to one of the given local ports
+    ///
+    /// Once called, a peer is only trusted if its address is trusted **and** the connection was
+    /// accepted on one of the required ports, e.g. to trust forwarding headers on an internal
+    /// listener but never on the public one. See [`crate::ConnectionInformation::local_addr`],
+    /// with [`crate::Trusted::from`] peers will never be trusted once a port is required.
+    ///
+    /// # Example
+    /// ```
+    /// use core::net::SocketAddr;
+    /// use trusted_proxies::{Config, Trusted};
+    ///
+    /// let mut config = Config::new_local();
+    /// config.require_local_port(8443);
+    ///
+    /// let mut request = http::Request::get("/").body(()).unwrap();
+    /// request.headers_mut().insert("x-forwarded-for", "1.2.3.4".parse().unwrap());
+    /// let peer = SocketAddr::from(([10, 0, 0, 1], 50000));
+    ///
+    /// let internal = (peer, SocketAddr::from(([10, 0, 0, 2], 8443)));
+    /// let trusted = Trusted::from_connection(&internal, &request, &config);
+    /// assert_eq!(trusted.ip(), core::net::IpAddr::from([1, 2, 3, 4]));
+    ///
+    /// let public = (peer, SocketAddr::from(([10, 0, 0, 2], 443)));
+    /// let trusted = Trusted::from_connection(&public, &request, &config);
+    /// assert_eq!(trusted.ip(), core::net::IpAddr::from([10, 0, 0, 1]));
+    /// ```
+    pub fn require_local_port(&mut self, port: u16) {
+        self.trusted_local_ports.push(port);
+    }
+
+    /// Only trust peers connecting from a port in one of the given ranges
+    ///
+    /// Once called, a peer is only trusted if its address is trusted **and** its port is in one
+    /// of the required ranges, see [`crate::ConnectionInformation::peer_port`].
+    pub fn require_peer_ports(&mut self, ports: RangeInclusive<u16>) {
+        self.trusted_peer_ports.push(ports);
+    }
+
+    /// Check if the ports of a connection are trusted
+    ///
+    /// Always true when no port is required
+    pub fn is_port_trusted<C: ConnectionInformation + ?Sized>(&self, connection: &C) -> bool {
+        let local = self.trusted_local_ports.is_empty()
+            || connection
+                .local_addr()
+                .is_some_and(|addr| self.trusted_local_ports.contains(&addr.port()));
+        let peer = self.trusted_peer_ports.is_empty()
+            || connection.peer_port().is_some_and(|port| {
+                self.trusted_peer_ports
+                    .iter()
+                    .any(|ports| ports.contains(&port))
+            });
+
+        local && peer
+    }
+
+    /// Add an address of this server, to detect forwarding loops
+    ///
+    /// When an address of this server is the peer or one of the trusted proxies walked in
+    /// `Forwarded` or `X-Forwarded-For`, or when an identifier of this server appears in the `by`
+    /// parameters or the `X-Forwarded-By` values added by them, the request already went through
+    /// this server and a [`crate::Warning::ForwardingLoop`] is raised, so gateways can break the
+    /// loop early with [`crate::Trusted::from_strict`]. The entries sent by the client are not
+    /// considered, as they can be forged.
+    ///
+    /// # Example
+    /// ```
+    /// use trusted_proxies::{Config, Trusted, Warning};
+    ///
+    /// let mut config = Config::new_local();
+    /// config.add_server_address(core::net::IpAddr::from([10, 0, 0, 5]));
+    ///
+    /// let mut request = http::Request::get("/").body(()).unwrap();
+    /// request.headers_mut().insert("x-forwarded-for", "1.2.3.4, 10.0.0.5, 10.0.0.2".parse().unwrap());
+    ///
+    /// let trusted = Trusted::from(core::net::IpAddr::from([10, 0, 0, 1]), &request, &config);
+    /// assert_eq!(trusted.warnings(), &[Warning::ForwardingLoop { header: "x-forwarded-for" }]);
+    /// ```
+    pub fn add_server_address(&mut self, address: IpAddr) {
+        self.server_addresses.push(address);
+    }
+
+    /// Add an identifier of this server, as it appears in the `by` parameter of `Forwarded` or in
+    /// `X-Forwarded-By`, to detect forwarding loops
+    ///
+    /// Identifiers are compared case insensitively, see [`Config::add_server_address`].
+    pub fn add_server_identifier(&mut self, identifier: &str) {
+        self.server_identifiers.push(identifier.to_string());
+    }
+
+    /// Set how this server identifies itself in the forwarding headers it emits
+    ///
+    /// The identity is used as the `by` parameter of `Forwarded` and as `X-Forwarded-By` by
+    /// [`crate::upstream::insert_headers_with`], and is also considered as an address or
+    /// identifier of this server to detect forwarding loops, see [`Config::add_server_address`].
+    pub fn set_server_identity(&mut self, identity: ServerIdentity) {
+        self.server_identity = Some(identity);
+    }
+
+    /// Get the identity of this server, see [`Config::set_server_identity`]
+    pub fn server_identity(&self) -> Option<&ServerIdentity> {
+        self.server_identity.as_ref()
+    }
+
+    /// Check if a forwarding node designates this server, see [`Config::add_server_address`]
+    pub fn is_server_node(&self, node: &str) -> bool {
+        let is_identity = match &self.server_identity {
+            Some(ServerIdentity::Obfuscated(name) | ServerIdentity::Hostname(name)) => {
+                name.eq_ignore_ascii_case(node)
+            }
+            _ => false,
+        };
+
+        is_identity
+            || node
+                .parse::<IpAddr>()
+                .is_ok_and(|ip| self.is_server_ip(&ip))
+            || self
+                .server_identifiers
+                .iter()
+                .any(|identifier| identifier.eq_ignore_ascii_case(node))
+    }
+
+    /// Check if an address is one of this server, see [`Config::add_server_address`]
+    pub(crate) fn is_server_ip(&self, ip: &IpAddr) -> bool {
+        matches!(&self.server_identity, Some(ServerIdentity::Ip(identity)) if identity == ip)
+            || self.server_addresses.contains(ip)
+    }
+
+    pub(crate) fn is_loop_detected(&self) -> bool {
+        self.server_identity.is_some()
+            || !self.server_addresses.is_empty()
+            || !self.server_identifiers.is_empty()
+    }
+
+    /// Trust the `Forwarded` header
+    pub fn trust_forwarded(&mut self) {
+        self.is_forwarded_trusted = true;
+    }
+
+    /// Trust the `X-Forwarded-For` header
+    pub fn trust_x_forwarded_for(&mut self) {
+        self.is_x_forwarded_for_trusted = true;
+    }
+
+    /// Stop trusting the `Forwarded` header, e.g. to narrow a preset
+    pub fn untrust_forwarded(&mut self) {
+        self.is_forwarded_trusted = false;
+    }
+
+    /// Stop trusting the `X-Forwarded-For` header, e.g. to narrow a preset
+    pub fn untrust_x_forwarded_for(&mut self) {
+        self.is_x_forwarded_for_trusted = false;
+    }
+
+    /// Stop trusting the `X-Forwarded-Host` header, e.g. to narrow a preset
+    pub fn untrust_x_forwarded_host(&mut self) {
+        self.is_x_forwarded_host_trusted = false;
+    }
+
+    /// Stop trusting the `X-Forwarded-Proto` header, e.g. to narrow a preset
+    pub fn untrust_x_forwarded_proto(&mut self) {
+        self.is_x_forwarded_proto_trusted = false;
+    }
+
+    /// Stop trusting the `X-Forwarded-By` header, e.g. to narrow a preset
+    pub fn untrust_x_forwarded_by(&mut self) {
+        self.is_x_forwarded_by_trusted = false;
+    }
+
+    /// Trust the `X-Forwarded-Host` header to fetch the host and optionally the port
+    ///
+    /// It is not recommended to trust this header as it can be easily spoofed, however you can trust
+    /// it if you are behind a reverse proxy that **always** sets this header.
+    ///
+    /// If there is multiple values in the header, the last one is used, even if there is multiple
+    /// proxies in the chain.
+    ///
+    /// If you need to get the original value with multiple proxies in the chain, you can use the
+    /// `Forwarded` header that allows to do that in a secure way.
+    /// See [RFC7239](https://tools.ietf.org/html/rfc7239) for more information.
+    pub fn trust_x_forwarded_host(&mut self) {
+        self.is_x_forwarded_host_trusted = true;
+    }
+
+    /// Trust the `X-Forwarded-Proto` header to fetch the scheme
+    ///
+    /// It is not recommended to trust this header as it can be easily spoofed, however you can trust
+    /// it if you are behind a reverse proxy that **always** sets this header.
+    ///
+    /// If there is multiple values in the header, the last one is used, even if there is multiple
+    /// proxies in the chain.
+    ///
+    /// If you need to get the original value with multiple proxies in the chain, you can use the
+    /// `Forwarded` header that allows to do that in a secure way.
+    /// See [RFC7239](https://tools.ietf.org/html/rfc7239) for more information.
+    pub fn trust_x_forwarded_proto(&mut self) {
+        self.is_x_forwarded_proto_trusted = true;
+    }
+
+    /// Trust the `X-Forwarded-By` header to identify the proxy that sent the request
+    ///
+    /// It is not recommended to trust this header as it can be easily spoofed, however you can trust
+    /// it if you are behind a reverse proxy that **always** sets this header.
+    ///
+    /// If there is multiple values in the header, the last one is used, even if there is multiple
+    /// proxies in the chain.
+    ///
+    /// If you need to get the original value with multiple proxies in the chain, you can use the
+    /// `Forwarded` header that allows to do that in a secure way.
+    /// See [RFC7239](https://tools.ietf.org/html/rfc7239) for more information.
+    pub fn trust_x_forwarded_by(&mut self) {
+        self.is_x_forwarded_by_trusted = true;
+    }
+
+    /// Read the `Forwarded` values from a header with another name, e.g. a proxy emitting the
+    /// `Forwarded` syntax in `X-Forwarded`
+    ///
+    /// The header still needs to be trusted with [`Config::trust_forwarded`]. Warnings and
+    /// ignored headers keep reporting the standard name of the header role.
+    pub fn set_forwarded_header(&mut self, name: &str) {
+        self.header_names.forwarded = Some(name.to_ascii_lowercase().into());
+    }
+
+    /// Read the `X-Forwarded-For` values from a header with another name, e.g. `X-Client-IP`
+    /// with a comma separated list of addresses
+    ///
+    /// The header still needs to be trusted with [`Config::trust_x_forwarded_for`]. Warnings and
+    /// ignored headers keep reporting the standard name of the header role. The request must
+    /// return the values of the header with [`crate::RequestInformation::header`].
+    ///
+    /// # Example
+    /// ```
+    /// use core::net::IpAddr;
+    /// use trusted_proxies::{Config, Trusted};
+    ///
+    /// let mut config = Config::new_local();
+    /// config.set_forwarded_for_header("X-Client-IP");
+    ///
+    /// let mut request = http::Request::get("/").body(()).unwrap();
+    /// request.headers_mut().insert("x-client-ip", "1.2.3.4".parse().unwrap());
+    /// request.headers_mut().insert("x-forwarded-for", "6.6.6.6".parse().unwrap());
+    ///
+    /// let trusted = Trusted::from(IpAddr::from([127, 0, 0, 1]), &request, &config);
+    /// assert_eq!(trusted.ip(), IpAddr::from([1, 2, 3, 4]));
+    /// ```
+    pub fn set_forwarded_for_header(&mut self, name: &str) {
+        self.header_names.x_forwarded_for = Some(name.to_ascii_lowercase().into());
+    }
+
+    /// Read the `X-Forwarded-Host` values from a header with another name, see
+    /// [`Config::set_forwarded_for_header`]
+    pub fn set_forwarded_host_header(&mut self, name: &str) {
+        self.header_names.x_forwarded_host = Some(name.to_ascii_lowercase().into());
+    }
+
+    /// Read the `X-Forwarded-Proto` values from a header with another name, see
+    /// [`Config::set_forwarded_for_header`]
+    pub fn set_forwarded_proto_header(&mut self, name: &str) {
+        self.header_names.x_forwarded_proto = Some(name.to_ascii_lowercase().into());
+    }
+
+    /// Read the `X-Forwarded-By` values from a header with another name, see
+    /// [`Config::set_forwarded_for_header`]
+    pub fn set_forwarded_by_header(&mut self, name: &str) {
+        self.header_names.x_forwarded_by = Some(name.to_ascii_lowercase().into());
+    }
+
+    /// Set the maximum length of a single forwarding header value
+    ///
+    /// Longer values are ignored and a [`crate::Warning::HeaderValueTooLong`] is raised, this avoid
+    /// scanning arbitrarily large values sent by an attacker. There is no limit by default.
+    pub fn set_max_header_value_length(&mut self, length: usize) {
+        self.max_header_value_length = Some(length);
+    }
+
+    /// Set the maximum number of bytes examined across all forwarding header values
+    ///
+    /// Values are examined from the closest proxy to the farthest one, once the limit is reached
+    /// remaining values are ignored and a [`crate::Warning::TotalHeaderLengthExceeded`] is raised.
+    /// Each value is charged once, whichever pass reads it first, e.g. counting the elements for
+    /// [`Config::set_max_chain_length`] or finding the leftmost address: the passes reading a
+    /// header again see the same values, so enabling them does not change which values are
+    /// examined. There is no limit by default.
+    pub fn set_max_total_header_length(&mut self, length: usize) {
+        self.max_total_header_length = Some(length);
+    }
+
+    /// Set the maximum number of elements of the `Forwarded` and `X-Forwarded-For` headers
+    ///
+    /// A trusted header with more elements raises a [`crate::Warning::ChainTooLong`], and only
+    /// its closest elements are walked, or the whole request is treated as coming from an
+    /// untrusted peer with [`LongChain::Untrust`], see [`Config::set_long_chain`]. There is no
+    /// limit by default.
+    ///
+    /// # Example
+    /// ```
+    /// use trusted_proxies::{Config, Trusted};
+    ///
+    /// let mut config = Config::new_local();
+    /// config.set_max_chain_length(2);
+    ///
+    /// let mut request = http::Request::get("/").body(()).unwrap();
+    /// request.headers_mut().insert("x-forwarded-for", "1.2.3.4, 5.6.7.8, 10.0.0.1".parse().unwrap());
+    ///
+    /// let trusted = Trusted::from(core::net::IpAddr::from([127, 0, 0, 1]), &request, &config);
+    /// assert_eq!(trusted.ip(), core::net::IpAddr::from([5, 6, 7, 8]));
+    /// ```
+    pub fn set_max_chain_length(&mut self, length: usize) {
+        self.max_chain_length = Some(length);
+    }
+
+    /// Set what to do when a forwarding header is longer than the maximum chain length, see
+    /// [`Config::set_max_chain_length`]
+    ///
+    /// Default to [`LongChain::Truncate`].
+    pub fn set_long_chain(&mut self, policy: LongChain) {
+        self.long_chain = policy;
+    }
+
+    /// Only consider the last entry of the forwarding headers, like nginx with
+    /// `real_ip_recursive off`
+    ///
+    /// When the peer is trusted, the last `Forwarded` or `X-Forwarded-For` entry is the client
+    /// even if its address is trusted, the chain is not walked. Entries skipped with
+    /// [`Config::set_trusted_hops`] are still skipped first.
+    ///
+    /// # Example
+    /// ```
+    /// use trusted_proxies::{Config, Trusted};
+    ///
+    /// let mut config = Config::new_local();
+    /// config.disable_recursion();
+    ///
+    /// let mut request = http::Request::get("/").body(()).unwrap();
+    /// request.headers_mut().insert("x-forwarded-for", "1.2.3.4, 10.0.0.2".parse().unwrap());
+    ///
+    /// let trusted = Trusted::from(core::net::IpAddr::from([10, 0, 0, 1]), &request, &config);
+    /// assert_eq!(trusted.ip(), core::net::IpAddr::from([10, 0, 0, 2]));
+    /// ```
+    pub fn disable_recursion(&mut self) {
+        self.client_ip_strategy = ClientIpStrategy::Rightmost;
+    }
+
+    /// Set which entry of the forwarding headers is selected as the client
+    ///
+    /// Entries skipped with [`Config::set_trusted_hops`] are skipped first, except with
+    /// [`ClientIpStrategy::LeftmostNonPrivate`] and [`ClientIpStrategy::Leftmost`] which ignore
+    /// the number of hops. Default to
+    /// [`ClientIpStrategy::RightmostUntrusted`].
+    ///
+    /// # Example
+    /// ```
+    /// use trusted_proxies::{ClientIpStrategy, Config, Trusted};
+    ///
+    /// let mut config = Config::new_local();
+    /// config.set_client_ip_strategy(ClientIpStrategy::LeftmostNonPrivate);
+    ///
+    /// let mut request = http::Request::get("/").body(()).unwrap();
+    /// request.headers_mut().insert("x-forwarded-for", "192.168.1.1, 6.6.6.6, 1.2.3.4".parse().unwrap());
+    ///
+    /// let trusted = Trusted::from(core::net::IpAddr::from([10, 0, 0, 1]), &request, &config);
+    /// assert_eq!(trusted.ip(), core::net::IpAddr::from([6, 6, 6, 6]));
+    /// ```
+    pub fn set_client_ip_strategy(&mut self, strategy: ClientIpStrategy) {
+        self.client_ip_strategy = strategy;
+    }
+
+    /// Read the client address from a header holding a single address, like `X-Real-IP`
+    ///
+    /// When the peer is trusted, the last value of the header is taken verbatim as the client
+    /// address, with or without a port, either before or after `Forwarded` and
+    /// `X-Forwarded-For`. Extractors registered with [`Config::add_ip_extractor`] still take
+    /// precedence.
+    ///
+    /// # Example
+    /// ```
+    /// use core::net::IpAddr;
+    /// use trusted_proxies::{ClientIpHeaderPriority, Config, IpSource, Trusted};
+    ///
+    /// let mut config = Config::new_local();
+    /// config.trust_x_forwarded_for();
+    /// config.set_client_ip_header("X-Real-IP", ClientIpHeaderPriority::BeforeForwarded);
+    ///
+    /// let mut request = http::Request::get("/").body(()).unwrap();
+    /// request.headers_mut().insert("x-real-ip", "1.2.3.4".parse().unwrap());
+    /// request.headers_mut().insert("x-forwarded-for", "5.6.7.8".parse().unwrap());
+    ///
+    /// let trusted = Trusted::from(IpAddr::from([127, 0, 0, 1]), &request, &config);
+    /// assert_eq!(trusted.ip(), IpAddr::from([1, 2, 3, 4]));
+    /// assert_eq!(trusted.ip_source(), IpSource::ClientIpHeader);
+    /// ```
+    pub fn set_client_ip_header(&mut self, name: &str, priority: ClientIpHeaderPriority) {
+        self.client_ip_header = Some((name.to_ascii_lowercase().into(), priority));
+    }
+
+    /// Trust a fixed number of proxies in the forwarding headers, whatever their address
+    ///
+    /// For topologies where the number of honest proxies is known but not their addresses, the
+    /// peer is trusted and the `hops` rightmost entries of the `Forwarded` or `X-Forwarded-For`
+    /// header are skipped as proxies, the next entry being the client. When trusted networks are
+    /// configured too, both are combined: the peer must be trusted by address, and entries past
+    /// the skipped ones are still skipped while their address is trusted.
+    ///
+    /// # Example
+    /// ```
+    /// use trusted_proxies::{Config, Trusted};
+    ///
+    /// let mut config = Config::new();
+    /// config.trust_x_forwarded_for();
+    /// config.set_trusted_hops(1);
+    ///
+    /// let mut request = http::Request::get("/").body(()).unwrap();
+    /// request.headers_mut().insert("x-forwarded-for", "6.6.6.6, 1.2.3.4, 34.1.2.3".parse().unwrap());
+    ///
+    /// let trusted = Trusted::from(core::net::IpAddr::from([35, 1, 2, 3]), &request, &config);
+    /// assert_eq!(trusted.ip(), core::net::IpAddr::from([1, 2, 3, 4]));
+    /// ```
+    pub fn set_trusted_hops(&mut self, hops: usize) {
+        self.trusted_hops = Some(hops);
+    }
+
+    /// Only accept the given scheme from trusted proxies, e.g. `http`, `https` and `wss`
+    ///
+    /// Once called, a `Forwarded` proto or `X-Forwarded-Proto` value that is not one of the
+    /// allowed schemes, case insensitive, is ignored with a [`crate::Warning::SchemeRejected`]
+    /// warning and the scheme of the request is used instead. [`crate::Trusted::from_strict`]
+    /// rejects the request.
+    ///
+    /// # Example
+    /// ```
+    /// use trusted_proxies::{Config, Trusted};
+    ///
+    /// let mut config = Config::new_local();
+    /// config.allow_scheme("http");
+    /// config.allow_scheme("https");
+    ///
+    /// let mut request = http::Request::get("/").body(()).unwrap();
+    /// request.headers_mut().insert(http::header::FORWARDED, "proto=javascript".parse().unwrap());
+    ///
+    /// let trusted = Trusted::from(core::net::IpAddr::from([127, 0, 0, 1]), &request, &config);
+    /// assert_eq!(trusted.scheme(), None);
+    /// ```
+    pub fn allow_scheme(&mut self, scheme: &str) {
+        self.allowed_schemes
+            .push(scheme.to_ascii_lowercase().into());
+    }
+
+    pub(crate) fn is_scheme_allowed(&self, scheme: &str) -> bool {
+        self.allowed_schemes.is_empty()
+            || self
+                .allowed_schemes
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(scheme))
+    }
+
+    /// Only accept from trusted proxies the hosts of a domain, e.g. `.example.com`
+    ///
+    /// Once called, a host sent in the `Forwarded` or `X-Forwarded-Host` headers is only accepted
+    /// when it is the domain or one of its subdomains, whatever its port and case, otherwise it is
+    /// ignored with a [`crate::Warning::HostRejected`] warning and the host of the request is used
+    /// instead. This prevents host header poisoning when `X-Forwarded-Host` is trusted. The
+    /// leading dot is optional, `example.com` does not match `badexample.com`.
+    ///
+    /// # Example
+    /// ```
+    /// use trusted_proxies::{Config, Trusted};
+    ///
+    /// let mut config = Config::new_local();
+    /// config.trust_x_forwarded_host();
+    /// config.allow_host_suffix(".example.com");
+    ///
+    /// let mut request = http::Request::get("http://www.example.com/").body(()).unwrap();
+    /// request.headers_mut().insert("x-forwarded-host", "evil.com".parse().unwrap());
+    ///
+    /// let trusted = Trusted::from(core::net::IpAddr::from([127, 0, 0, 1]), &request, &config);
+    /// assert_eq!(trusted.host(), Some("www.example.com"));
+    /// ```
+    pub fn allow_host_suffix(&mut self, suffix: &str) {
+        let suffix = suffix.trim_start_matches('.').trim_end_matches('.');
+
+        self.allowed_host_suffixes
+            .push(suffix.to_ascii_lowercase().into());
+    }
+
+    pub(crate) fn is_host_allowed(&self, host: &str) -> bool {
+        if self.allowed_host_suffixes.is_empty() {
+            return true;
+        }
+
+        let name = match host.strip_prefix('[') {
+            Some(address) => address.split(']').next().unwrap_or(address),
+            None => host.split(':').next().unwrap_or(host),
+        };
+        let name = name.trim_end_matches('.');
+
+        self.allowed_host_suffixes.iter().any(|suffix| {
+            name.len() >= suffix.len()
+                && name.is_char_boundary(name.len() - suffix.len())
+                && name[name.len() - suffix.len()..].eq_ignore_ascii_case(suffix)
+                && (name.len() == suffix.len()
+                    || name.as_bytes()[name.len() - suffix.len() - 1] == b'.')
+        })
+    }
+
+    /// Require trusted proxies to send the scheme of the request
+    ///
+    /// When the peer is trusted but no trusted `Forwarded` proto or `X-Forwarded-Proto` value is
+    /// present, a [`crate::Warning::MissingProto`] is raised instead of silently falling back to
+    /// the scheme of the request, which helps catching broken proxy configurations early.
+    pub fn require_proto(&mut self) {
+        self.is_proto_required = true;
+    }
+
+    /// Require trusted proxies to send the host of the request
+    ///
+    /// When the peer is trusted but no trusted `Forwarded` host or `X-Forwarded-Host` value is
+    /// present, a [`crate::Warning::MissingHost`] is raised instead of silently falling back to
+    /// the host of the request.
+    pub fn require_host(&mut self) {
+        self.is_host_required = true;
+    }
+
+    /// Check that the TLS server name (SNI) of the connection matches the trusted host
+    ///
+    /// On mismatch, a [`crate::Warning::ServerNameMismatch`] is raised, which usually means
+    /// domain fronting or misrouted traffic. The server name is given by
+    /// [`crate::ConnectionInformation::server_name`] so this check is only done with
+    /// [`crate::Trusted::from_connection`].
+    pub fn check_server_name(&mut self) {
+        self.is_server_name_checked = true;
+    }
+
+    /// Correct the port of the trusted host when it does not match the trusted scheme
+    ///
+    /// A [`crate::Warning::SchemePortMismatch`] is always raised for combinations like `https`
+    /// with port 80 or `http` with port 443. Once enabled, the port is also removed from the host,
+    /// so the default port of the scheme is used.
+    pub fn correct_port_from_scheme(&mut self) {
+        self.is_port_corrected = true;
+    }
+
+    /// Match IPv4-mapped IPv6 addresses (`::ffff:a.b.c.d`) against the IPv4 networks
+    ///
+    /// Dual-stack listeners report IPv4 peers as mapped addresses, which do not match IPv4
+    /// networks like `10.0.0.0/8` otherwise. This applies to every trust check, groups and
+    /// internal proxies included, the addresses exposed by [`crate::Trusted`] are not changed.
+    ///
+    /// # Example
+    /// ```
+    /// use core::net::IpAddr;
+    /// use trusted_proxies::Config;
+    ///
+    /// let mut config = Config::new_local();
+    /// let mapped: IpAddr = "::ffff:10.0.0.1".parse().unwrap();
+    /// assert!(!config.is_ip_trusted(&mapped));
+    ///
+    /// config.unmap_ipv4_mapped_addresses();
+    /// assert!(config.is_ip_trusted(&mapped));
+    /// ```
+    pub fn unmap_ipv4_mapped_addresses(&mut self) {
+        self.is_mapped_ipv4_unmapped = true;
+    }
+
+    /// Get the address to match against the networks, see
+    /// [`Config::unmap_ipv4_mapped_addresses`]
+    pub(crate) fn unmap(&self, remote_addr: &IpAddr) -> IpAddr {
+        if self.is_mapped_ipv4_unmapped {
+            remote_addr.to_canonical()
+        } else {
+            *remote_addr
+        }
+    }
+
+    /// Set what to do when the resolved client address is private, loopback or reserved
+    ///
+    /// Such addresses are the ones listed in the [`crate::bogon`] module.
+    ///
+    /// Internet-facing services should almost never see such a client address, it usually means
+    /// that a proxy in the chain is not trusted or that the client spoofed the headers. In both
+    /// [`PrivateClientIp::Skip`] and [`PrivateClientIp::UsePeer`] modes, a
+    /// [`crate::Warning::PrivateClientIp`] is raised when such an address is found.
+    ///
+    /// Default to [`PrivateClientIp::Accept`].
+    pub fn set_private_client_ip(&mut self, policy: PrivateClientIp) {
+        self.private_client_ip = policy;
+    }
+
+    /// Trust client addresses signed by an edge proxy with a shared key
+    ///
+    /// The edge proxy sends the client address in the `X-Client-IP` header and its signature in
+    /// the `X-Client-IP-Sig` header, see [`crate::sign_client_ip`]. When the signature is valid,
+    /// the address is used as the client address whatever the peer and the other forwarding
+    /// headers are, which allows attributing clients across untrusted intermediate networks.
+    /// Otherwise a [`crate::Warning::InvalidSignature`] is raised and the address is ignored.
+    ///
+    /// The signature only covers the address, so a signed address can be replayed by anyone who
+    /// saw it, use it with short-lived keys or on networks where the signature stays private.
+    #[cfg(feature = "hmac")]
+    pub fn trust_signed_client_ip(&mut self, key: &[u8]) {
+        self.signed_client_ip = Some(crate::signed::SignedClientIp::new(key));
+    }
+
+    /// Change the headers used for signed client addresses, see [`Config::trust_signed_client_ip`]
+    ///
+    /// The headers can be changed before or after setting the key.
+    #[cfg(feature = "hmac")]
+    pub fn set_signed_client_ip_headers(&mut self, header: &str, signature_header: &str) {
+        self.header_names.signed_client_ip = Some(header.to_ascii_lowercase().into());
+        self.header_names.signed_client_ip_signature =
+            Some(signature_header.to_ascii_lowercase().into());
+    }
+
+    /// Register a callback invoked on probable spoofing attempts
+    ///
+    /// It is called when forwarding headers are sent by an untrusted peer, or when a value sent
+    /// by a trusted peer fails validation (see [`crate::Warning`]). This allows to count and alert
+    /// on such attempts while keeping the lenient extraction.
+    ///
+    /// The callback is shared between clones of this configuration, registering a new one
+    /// replaces the previous callback.
+    ///
+    /// # Example
+    /// ```
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    /// use std::sync::Arc;
+    /// use trusted_proxies::{Config, Trusted};
+    ///
+    /// let attempts = Arc::new(AtomicUsize::new(0));
+    /// let counter = attempts.clone();
+    ///
+    /// let mut config = Config::new_local();
+    /// config.on_spoof_attempt(move |_event| {
+    ///     counter.fetch_add(1, Ordering::Relaxed);
+    /// });
+    ///
+    /// let mut request = http::Request::get("/").body(()).unwrap();
+    /// request.headers_mut().insert("x-forwarded-for", "1.2.3.4".parse().unwrap());
+    ///
+    /// Trusted::from(core::net::IpAddr::from([8, 8, 8, 8]), &request, &config);
+    ///
+    /// assert_eq!(attempts.load(Ordering::Relaxed), 1);
+    /// ```
+    pub fn on_spoof_attempt<F: FnMut(SpoofEvent) + Send + 'static>(&mut self, callback: F) {
+        self.spoof_hook = Some(SpoofHook::new(callback));
+    }
+
+    /// Register an extractor reading the client address from a non standard header
+    ///
+    /// When the peer is trusted, extractors run by descending priority and the first address
+    /// found takes precedence over the `Forwarded` and `X-Forwarded-For` headers. Extractors with
+    /// the same priority run in registration order.
+    ///
+    /// # Example
+    /// ```
+    /// use core::net::IpAddr;
+    /// use trusted_proxies::{Config, IpExtractor, IpSource, Trusted};
+    ///
+    /// // reads `X-Client: ip=1.2.3.4; country=FR`
+    /// struct ClientInfo;
+    ///
+    /// impl IpExtractor for ClientInfo {
+    ///     fn header(&self) -> &str {
+    ///         "x-client"
+    ///     }
+    ///
+    ///     fn extract(&self, values: &[&str]) -> Option<IpAddr> {
+    ///         values.last()?.split(';').find_map(|item| item.trim().strip_prefix("ip=")?.parse().ok())
+    ///     }
+    /// }
+    ///
+    /// let mut config = Config::new_local();
+    /// config.add_ip_extractor(0, ClientInfo);
+    ///
+    /// let mut request = http::Request::get("/").body(()).unwrap();
+    /// request.headers_mut().insert("x-client", "ip=1.2.3.4; country=FR".parse().unwrap());
+    ///
+    /// let trusted = Trusted::from(IpAddr::from([127, 0, 0, 1]), &request, &config);
+    /// assert_eq!(trusted.ip(), IpAddr::from([1, 2, 3, 4]));
+    /// assert_eq!(trusted.ip_source(), IpSource::Extractor);
+    /// ```
+    pub fn add_ip_extractor<E: IpExtractor + 'static>(&mut self, priority: i32, extractor: E) {
+        self.ip_extractors.add(priority, None, Arc::new(extractor));
+    }
+
+    /// Register an extractor only run when the peer belongs to the given group, see
+    /// [`Config::add_trusted_ip_group`] and [`Config::add_ip_extractor`]
+    ///
+    /// This restricts headers set by a CDN to the requests received from its edge servers, when
+    /// other proxies that do not overwrite them are trusted too.
+    pub fn add_ip_extractor_for_group<E: IpExtractor + 'static>(
+        &mut self,
+        group: &str,
+        priority: i32,
+        extractor: E,
+    ) {
+        self.ip_extractors
+            .add(priority, Some(Arc::from(group)), Arc::new(extractor));
+    }
+
+    /// Set a normalizer applied to the trusted host before it is exposed
+    ///
+    /// It runs after [`Config::correct_port_from_scheme`], on the host sent by a trusted proxy as
+    /// well as on the host of the request itself. A rejected host is dropped with a
+    /// [`crate::Warning::HostRejected`] warning.
+    ///
+    /// # Example
+    /// ```
+    /// use trusted_proxies::{Config, Trusted};
+    ///
+    /// let mut config = Config::new_local();
+    /// config.trust_x_forwarded_host();
+    /// config.set_host_normalizer(|host: &str, _scheme: Option<&str>| {
+    ///     let host = host.to_ascii_lowercase();
+    ///
+    ///     match host.as_str() {
+    ///         "www.example.com" => Some("example.com".to_string()),
+    ///         "example.com" | "api.example.com" => Some(host),
+    ///         _ => None,
+    ///     }
+    /// });
+    ///
+    /// let mut request = http::Request::get("/").body(()).unwrap();
+    /// request.headers_mut().insert("x-forwarded-host", "WWW.example.com".parse().unwrap());
+    ///
+    /// let trusted = Trusted::from(core::net::IpAddr::from([127, 0, 0, 1]), &request, &config);
+    /// assert_eq!(trusted.host(), Some("example.com"));
+    /// ```
+    pub fn set_host_normalizer<N: HostNormalizer + 'static>(&mut self, normalizer: N) {
+        self.host_normalizer = Some(HostNormalizerHook::new(normalizer));
+    }
+
+    /// Set a provider giving a verdict on the resolved client address
+    ///
+    /// The verdict is available with [`crate::Trusted::reputation`], so internal threat
+    /// intelligence feeds are queried right after the attribution. Use
+    /// [`crate::Trusted::lookup_reputation`] for asynchronous providers.
+    ///
+    /// # Example
+    /// ```
+    /// use trusted_proxies::{Config, Reputation, Trusted};
+    ///
+    /// let mut config = Config::new_local();
+    /// config.set_reputation_provider(|ip: core::net::IpAddr| {
+    ///     if ip == core::net::IpAddr::from([6, 6, 6, 6]) {
+    ///         Reputation::Block
+    ///     } else {
+    ///         Reputation::Allow
+    ///     }
+    /// });
+    ///
+    /// let mut request = http::Request::get("/").body(()).unwrap();
+    /// request.headers_mut().insert("x-forwarded-for", "6.6.6.6".parse().unwrap());
+    ///
+    /// let trusted = Trusted::from(core::net::IpAddr::from([127, 0, 0, 1]), &request, &config);
+    /// assert_eq!(trusted.reputation(), Some(Reputation::Block));
+    /// ```
+    pub fn set_reputation_provider<R: ReputationProvider + 'static>(&mut self, provider: R) {
+        self.reputation_provider = Some(ReputationHook::new(provider));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_equality() {
+        use std::collections::HashSet;
+
+        let mut first = Config::new();
+        first.add_trusted_ip("10.0.0.0/8").unwrap();
+        first.add_trusted_ip("192.168.0.0/16").unwrap();
+        first.trust_forwarded();
+
+        let mut second = Config::new();
+        second.add_trusted_ip("192.168.1.1/16").unwrap();
+        second.add_trusted_ip("10.0.0.0/8").unwrap();
+        second.add_trusted_ip("10.0.0.0/8").unwrap();
+        second.trust_forwarded();
+
+        assert_eq!(first, second);
+        assert_eq!(HashSet::from([first.clone(), second.clone()]).len(), 1);
+
+        second.trust_x_forwarded_for();
+        assert_ne!(first, second);
+
+        first.on_spoof_attempt(|_| {});
+        let clone = first.clone();
+        assert_eq!(first, clone);
+
+        let mut other = clone.clone();
+        other.on_spoof_attempt(|_| {});
+        assert_ne!(first, other);
+
+        let mut expiring = Config::new();
+        expiring
+            .add_trusted_ip_for("10.0.0.0/8", Duration::from_secs(60))
+            .unwrap();
+        let mut later = Config::new();
+        later
+            .add_trusted_ip_until("10.0.0.0/8", Instant::now() + Duration::from_secs(120))
+            .unwrap();
+        assert_eq!(expiring, expiring.clone());
+        assert_ne!(expiring, later);
+
+        assert_eq!(Config::new_local(), Config::default());
+        assert_ne!(Config::new_local(), Config::new());
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn untrust_and_clear() {
+        use crate::Trusted;
+        use http::Request;
+
+        let mut config = Config::new_local();
+        config.trust_x_forwarded_for();
+        config
+            .add_trusted_ip_group("edge", "203.0.113.0/24")
+            .unwrap();
+        assert!(config.remove_trusted_ip("203.0.113.7/24").unwrap());
+        assert!(!config.remove_trusted_ip("203.0.113.0/24").unwrap());
+        assert_eq!(config.ip_group(&IpAddr::from([203, 0, 113, 7])), None);
+
+        let mut request = Request::get("/").body(()).unwrap();
+        request
+            .headers_mut()
+            .insert("x-forwarded-for", "1.2.3.4".parse().unwrap());
+
+        let trusted = Trusted::from(IpAddr::from([127, 0, 0, 1]), &request, &config);
+        assert_eq!(trusted.ip(), IpAddr::from([1, 2, 3, 4]));
+
+        config.untrust_x_forwarded_for();
+
+        let trusted = Trusted::from(IpAddr::from([127, 0, 0, 1]), &request, &config);
+        assert_eq!(trusted.ip(), IpAddr::from([127, 0, 0, 1]));
+
+        config.trust_x_forwarded_for();
+        config.clear_trusted_ips();
+
+        let trusted = Trusted::from(IpAddr::from([127, 0, 0, 1]), &request, &config);
+        assert_eq!(trusted.ip(), IpAddr::from([127, 0, 0, 1]));
+        assert!(!config.is_ip_trusted(&IpAddr::from([10, 0, 0, 1])));
+    }
+
+    #[test]
+    fn merge_configs() {
+        use crate::MergeMode;
+
+        let mut base = Config::new_local();
+        base.add_untrusted_ip("10.42.0.0/16").unwrap();
+
+        let mut service = Config::new();
+        service.add_trusted_ip("10.1.0.0/16").unwrap();
+        service.add_trusted_ip("203.0.113.0/24").unwrap();
+        service.trust_x_forwarded_for();
+        service.trust_x_forwarded_proto();
+
+        let mut union = base.clone();
+        union.merge(&service);
+        assert!(union.is_ip_trusted(&IpAddr::from([203, 0, 113, 7])));
+        assert!(union.is_ip_trusted(&IpAddr::from([10, 2, 0, 1])));
+        assert!(!union.is_ip_trusted(&IpAddr::from([10, 42, 0, 1])));
+        assert!(union.is_forwarded_trusted && union.is_x_forwarded_proto_trusted);
+
+        let mut intersection = base.clone();
+        intersection.merge_with(&service, MergeMode::Intersection);
+        assert!(intersection.is_ip_trusted(&IpAddr::from([10, 1, 0, 1])));
+        assert!(!intersection.is_ip_trusted(&IpAddr::from([10, 2, 0, 1])));
+        assert!(!intersection.is_ip_trusted(&IpAddr::from([203, 0, 113, 7])));
+        assert!(!intersection.is_ip_trusted(&IpAddr::from([127, 0, 0, 1])));
+        assert!(intersection.is_x_forwarded_for_trusted);
+        assert!(!intersection.is_forwarded_trusted && !intersection.is_x_forwarded_proto_trusted);
+    }
+
+    #[test]
+    fn introspection() {
+        let mut config = Config::new();
+        config.add_trusted_ip("10.0.0.0/8").unwrap();
+        config.add_untrusted_ip("10.42.0.0/16").unwrap();
+        config.trust_x_forwarded_proto();
+        config.set_trusted_hops(2);
+
+        assert_eq!(
+            config.trusted_ips().collect::<Vec<_>>(),
+            [&"10.0.0.0/8".parse::<ipnet::IpNet>().unwrap()]
+        );
+        assert_eq!(
+            config.untrusted_ips().collect::<Vec<_>>(),
+            [&"10.42.0.0/16".parse::<ipnet::IpNet>().unwrap()]
+        );
+        assert!(!config.is_forwarded_trusted());
+        assert!(!config.is_x_forwarded_for_trusted());
+        assert!(!config.is_x_forwarded_host_trusted());
+        assert!(config.is_x_forwarded_proto_trusted());
+        assert!(!config.is_x_forwarded_by_trusted());
+        assert_eq!(config.trusted_hops(), Some(2));
+    }
+
+    #[test]
+    fn config_from_str() {
+        let config: Config = "10.0.0.0/8,::1 192.168.1.1".parse().unwrap();
+        assert!(config.is_ip_trusted(&IpAddr::from([10, 1, 2, 3])));
+        assert!(config.is_ip_trusted(&IpAddr::from([192, 168, 1, 1])));
+        assert!(config.is_ip_trusted(&"::1".parse().unwrap()));
+        assert!(!config.is_ip_trusted(&IpAddr::from([127, 0, 0, 1])));
+        assert!(config.is_forwarded_trusted() && config.is_x_forwarded_for_trusted());
+
+        let error = "10.0.0.0/8, nope,, 1.2.3.4/40"
+            .parse::<Config>()
+            .unwrap_err();
+        assert_eq!(error.errors().len(), 2);
+        assert!(error.to_string().contains("`nope` at position 2"));
+        assert!(error.to_string().contains("at position 3"));
     }
 }
@@ -0,0 +1,172 @@
+use core::net::{IpAddr, SocketAddr};
+
+/// A trait to extract information about the connection a request was received on
+///
+/// It is implemented for [`IpAddr`], [`SocketAddr`] and a `(peer, local)` pair of [`SocketAddr`],
+/// implement it on your own connection type to give TLS information to
+/// [`crate::Trusted::from_connection`].
+pub trait ConnectionInformation {
+    /// Get the address of the peer
+    fn peer_addr(&self) -> IpAddr;
+
+    /// Get the identities of the client certificate sent by the peer, when using mutual TLS
+    ///
+    /// Identities are the subject alternative names of the certificate (DNS names, or URIs such
+    /// as SPIFFE IDs). The certificate must have already been verified by the TLS stack.
+    fn client_certificate_identities(&self) -> impl Iterator<Item = &str> {
+        core::iter::empty()
+    }
+
+    /// Get the server name sent by the client in the TLS handshake (SNI)
+    fn server_name(&self) -> Option<&str> {
+        None
+    }
+
+    /// Get the port of the peer
+    fn peer_port(&self) -> Option<u16> {
+        None
+    }
+
+    /// Get the local address the connection was accepted on
+    fn local_addr(&self) -> Option<SocketAddr> {
+        None
+    }
+
+    /// Decide whether the peer is trusted for this connection, regardless of the configuration
+    ///
+    /// When `Some`, the trusted networks, ports and certificate identities of the configuration
+    /// are not checked, see [`PeerTrust`]. `None` by default.
+    fn is_peer_trusted(&self) -> Option<bool> {
+        None
+    }
+}
+
+/// A connection which peer trust is decided by the caller, e.g. after a completed TLS client
+/// authentication or given an attribute of the tunnel the request came from
+///
+/// # Example
+/// ```
+/// use trusted_proxies::{Config, PeerTrust, Trusted};
+///
+/// let mut config = Config::new();
+/// config.trust_x_forwarded_for();
+///
+/// let mut request = http::Request::get("/").body(()).unwrap();
+/// request.headers_mut().insert("x-forwarded-for", "1.2.3.4".parse().unwrap());
+///
+/// let peer = core::net::IpAddr::from([203, 0, 113, 1]);
+///
+/// let trusted = Trusted::from_connection(&PeerTrust::trusted(peer), &request, &config);
+/// assert_eq!(trusted.ip(), core::net::IpAddr::from([1, 2, 3, 4]));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PeerTrust<C> {
+    connection: C,
+    trusted: bool,
+}
+
+impl<C: ConnectionInformation> PeerTrust<C> {
+    /// Trust the peer of the connection
+    pub fn trusted(connection: C) -> Self {
+        Self {
+            connection,
+            trusted: true,
+        }
+    }
+
+    /// Do not trust the peer of the connection
+    pub fn untrusted(connection: C) -> Self {
+        Self {
+            connection,
+            trusted: false,
+        }
+    }
+
+    /// Get the wrapped connection
+    pub fn into_inner(self) -> C {
+        self.connection
+    }
+}
+
+impl<C: ConnectionInformation> ConnectionInformation for PeerTrust<C> {
+    fn peer_addr(&self) -> IpAddr {
+        self.connection.peer_addr()
+    }
+
+    fn client_certificate_identities(&self) -> impl Iterator<Item = &str> {
+        self.connection.client_certificate_identities()
+    }
+
+    fn server_name(&self) -> Option<&str> {
+        self.connection.server_name()
+    }
+
+    fn peer_port(&self) -> Option<u16> {
+        self.connection.peer_port()
+    }
+
+    fn local_addr(&self) -> Option<SocketAddr> {
+        self.connection.local_addr()
+    }
+
+    fn is_peer_trusted(&self) -> Option<bool> {
+        Some(self.trusted)
+    }
+}
+
+impl ConnectionInformation for IpAddr {
+    fn peer_addr(&self) -> IpAddr {
+        *self
+    }
+}
+
+impl ConnectionInformation for SocketAddr {
+    fn peer_addr(&self) -> IpAddr {
+        self.ip()
+    }
+
+    fn peer_port(&self) -> Option<u16> {
+        Some(self.port())
+    }
+}
+
+/// The peer address and the local address of a connection
+impl ConnectionInformation for (SocketAddr, SocketAddr) {
+    fn peer_addr(&self) -> IpAddr {
+        self.0.ip()
+    }
+
+    fn peer_port(&self) -> Option<u16> {
+        Some(self.0.port())
+    }
+
+    fn local_addr(&self) -> Option<SocketAddr> {
+        Some(self.1)
+    }
+}
+
+impl<C: ConnectionInformation + ?Sized> ConnectionInformation for &C {
+    fn peer_addr(&self) -> IpAddr {
+        (**self).peer_addr()
+    }
+
+    fn client_certificate_identities(&self) -> impl Iterator<Item = &str> {
+        (**self).client_certificate_identities()
+    }
+
+    fn server_name(&self) -> Option<&str> {
+        (**self).server_name()
+    }
+
+    fn peer_port(&self) -> Option<u16> {
+        (**self).peer_port()
+    }
+
+    fn local_addr(&self) -> Option<SocketAddr> {
+        (**self).local_addr()
+    }
+
+    fn is_peer_trusted(&self) -> Option<bool> {
+        (**self).is_peer_trusted()
+    }
+}
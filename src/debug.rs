@@ -0,0 +1,63 @@
+//! Expose the extracted information as response headers, to verify a proxy configuration
+//!
+//! This module is meant for development and staging environments: the headers reveal the
+//! addresses of the client and of the proxies, they should not be sent in production.
+//!
+//! # Example
+//! ```
+//! use trusted_proxies::{Config, Trusted};
+//!
+//! let config = Config::new_local();
+//! let mut request = http::Request::get("/").body(()).unwrap();
+//! request.headers_mut().insert(http::header::FORWARDED, "for=1.2.3.4; proto=https; host=mydomain.com".parse().unwrap());
+//!
+//! let trusted = Trusted::from(core::net::IpAddr::from([127, 0, 0, 1]), &request, &config);
+//! let mut response = http::Response::new(());
+//! trusted_proxies::debug::insert_headers(&trusted, response.headers_mut());
+//!
+//! assert_eq!(response.headers()["x-debug-trusted-ip"], "1.2.3.4");
+//! assert_eq!(response.headers()["x-debug-trusted-host"], "mydomain.com");
+//! assert_eq!(response.headers()["x-debug-trusted-source"], "forwarded");
+//! ```
+
+use crate::Trusted;
+use http::header::{HeaderMap, HeaderName, HeaderValue};
+
+/// Header containing the trusted client address
+pub const TRUSTED_IP: HeaderName = HeaderName::from_static("x-debug-trusted-ip");
+/// Header containing the trusted host, with its port if any
+pub const TRUSTED_HOST: HeaderName = HeaderName::from_static("x-debug-trusted-host");
+/// Header containing the trusted scheme
+pub const TRUSTED_SCHEME: HeaderName = HeaderName::from_static("x-debug-trusted-scheme");
+/// Header containing where the client address comes from, see [`crate::IpSource`]
+pub const TRUSTED_SOURCE: HeaderName = HeaderName::from_static("x-debug-trusted-source");
+/// Header containing the address of the peer that sent the request
+pub const TRUSTED_PEER: HeaderName = HeaderName::from_static("x-debug-trusted-peer");
+/// Header containing the group of the trusted peer, see [`crate::Config::add_trusted_ip_group`]
+pub const TRUSTED_GROUP: HeaderName = HeaderName::from_static("x-debug-trusted-group");
+
+/// Insert the debug headers describing the extracted information into the given headers
+///
+/// Values that cannot be represented as a header value are omitted, existing debug headers are
+/// replaced.
+pub fn insert_headers(trusted: &Trusted, headers: &mut HeaderMap) {
+    let values = [
+        (TRUSTED_IP, Some(trusted.ip().to_string())),
+        (TRUSTED_HOST, trusted.host_with_port().map(str::to_string)),
+        (TRUSTED_SCHEME, trusted.scheme().map(str::to_string)),
+        (
+            TRUSTED_SOURCE,
+            Some(trusted.ip_source().as_str().to_string()),
+        ),
+        (TRUSTED_PEER, Some(trusted.peer().to_string())),
+        (TRUSTED_GROUP, trusted.via_group().map(str::to_string)),
+    ];
+
+    for (name, value) in values {
+        headers.remove(&name);
+
+        if let Some(value) = value.and_then(|v| HeaderValue::try_from(v).ok()) {
+            headers.insert(name, value);
+        }
+    }
+}
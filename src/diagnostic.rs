@@ -0,0 +1,179 @@
+use crate::Config;
+use core::fmt;
+use ipnet::IpNet;
+
+/// A likely mistake in a configuration, reported by [`Config::validate`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ConfigDiagnostic {
+    /// A trusted network covers the whole address space of its family, any client can forge its
+    /// address
+    WholeInternet { network: IpNet },
+    /// A trusted network is already covered by another trusted network
+    OverlappingRanges { network: IpNet, covered_by: IpNet },
+    /// An untrusted network is outside of every trusted network and has no effect
+    UselessUntrustedRange { network: IpNet },
+    /// Forwarding headers are trusted but no proxy is, so they are never read
+    NoTrustedProxies,
+    /// Proxies are trusted but no forwarding header is, so the client is always the peer
+    NoTrustedHeaders,
+}
+
+impl fmt::Display for ConfigDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::WholeInternet { network } => {
+                write!(f, "trusted network {network} covers the whole internet")
+            }
+            Self::OverlappingRanges {
+                network,
+                covered_by,
+            } => write!(
+                f,
+                "trusted network {network} is already covered by {covered_by}"
+            ),
+            Self::UselessUntrustedRange { network } => write!(
+                f,
+                "untrusted network {network} is outside of every trusted network"
+            ),
+            Self::NoTrustedProxies => write!(f, "forwarding headers are trusted without proxies"),
+            Self::NoTrustedHeaders => write!(f, "proxies are trusted without forwarding headers"),
+        }
+    }
+}
+
+impl Config {
+    /// Report the likely mistakes of this configuration, e.g. to log them at startup
+    ///
+    /// Diagnostics do not prevent using the configuration, some of them are expected for a
+    /// deliberate setup like [`Config::new_trust_all`].
+    ///
+    /// # Example
+    /// ```
+    /// use trusted_proxies::{Config, ConfigDiagnostic};
+    ///
+    /// let mut config = Config::new();
+    /// config.add_trusted_ip("10.0.0.0/8").unwrap();
+    /// config.add_trusted_ip("10.1.0.0/16").unwrap();
+    ///
+    /// let diagnostics = config.validate();
+    ///
+    /// assert_eq!(
+    ///     diagnostics[0],
+    ///     ConfigDiagnostic::OverlappingRanges {
+    ///         network: "10.1.0.0/16".parse().unwrap(),
+    ///         covered_by: "10.0.0.0/8".parse().unwrap(),
+    ///     }
+    /// );
+    /// assert_eq!(diagnostics[1], ConfigDiagnostic::NoTrustedHeaders);
+    /// ```
+    pub fn validate(&self) -> Vec<ConfigDiagnostic> {
+        let mut diagnostics = Vec::new();
+        let networks = self.trusted_ips().collect::<Vec<_>>();
+
+        for network in &networks {
+            if network.prefix_len() == 0 {
+                diagnostics.push(ConfigDiagnostic::WholeInternet { network: **network });
+            }
+        }
+
+        for (index, network) in networks.iter().enumerate() {
+            // a duplicate is reported once, as covered by its first occurrence
+            let covered_by = networks.iter().enumerate().find(|(other_index, other)| {
+                *other_index != index
+                    && other.contains(*network)
+                    && (other.prefix_len() < network.prefix_len() || *other_index < index)
+            });
+
+            if let Some((_, covered_by)) = covered_by {
+                diagnostics.push(ConfigDiagnostic::OverlappingRanges {
+                    network: **network,
+                    covered_by: **covered_by,
+                });
+            }
+        }
+
+        for network in self.untrusted_ips() {
+            if !networks.iter().any(|trusted| trusted.contains(network)) {
+                diagnostics.push(ConfigDiagnostic::UselessUntrustedRange { network: *network });
+            }
+        }
+
+        let has_proxies =
+            !networks.is_empty() || self.has_dynamic_proxies() || self.trusted_hops.is_some();
+        let has_headers = self.is_forwarded_trusted
+            || self.is_x_forwarded_for_trusted
+            || self.is_x_forwarded_host_trusted
+            || self.is_x_forwarded_proto_trusted
+            || self.is_x_forwarded_by_trusted
+            || self.client_ip_header.is_some()
+            || !self.ip_extractors.is_empty();
+        #[cfg(feature = "hmac")]
+        let has_headers = has_headers || self.signed_client_ip.is_some();
+
+        if has_headers && !has_proxies {
+            diagnostics.push(ConfigDiagnostic::NoTrustedProxies);
+        }
+
+        if has_proxies && !has_headers {
+            diagnostics.push(ConfigDiagnostic::NoTrustedHeaders);
+        }
+
+        diagnostics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_config() {
+        assert_eq!(Config::new_local().validate(), []);
+        assert_eq!(Config::new().validate(), []);
+
+        let mut config = Config::new();
+        config.trust_x_forwarded_for();
+        assert_eq!(config.validate(), [ConfigDiagnostic::NoTrustedProxies]);
+
+        let mut config = Config::new_local();
+        config.add_trusted_ip("0.0.0.0/0").unwrap();
+        config.add_trusted_ip("10.0.0.0/8").unwrap();
+        config.add_untrusted_ip("203.0.113.0/24").unwrap();
+        config.untrust_forwarded();
+        config.untrust_x_forwarded_for();
+
+        let diagnostics = config.validate();
+        let network = |network: &str| network.parse::<ipnet::IpNet>().unwrap();
+
+        assert_eq!(
+            diagnostics,
+            [
+                ConfigDiagnostic::WholeInternet {
+                    network: network("0.0.0.0/0")
+                },
+                ConfigDiagnostic::OverlappingRanges {
+                    network: network("127.0.0.0/8"),
+                    covered_by: network("0.0.0.0/0")
+                },
+                ConfigDiagnostic::OverlappingRanges {
+                    network: network("10.0.0.0/8"),
+                    covered_by: network("0.0.0.0/0")
+                },
+                ConfigDiagnostic::OverlappingRanges {
+                    network: network("172.16.0.0/12"),
+                    covered_by: network("0.0.0.0/0")
+                },
+                ConfigDiagnostic::OverlappingRanges {
+                    network: network("192.168.0.0/16"),
+                    covered_by: network("0.0.0.0/0")
+                },
+                ConfigDiagnostic::OverlappingRanges {
+                    network: network("10.0.0.0/8"),
+                    covered_by: network("10.0.0.0/8")
+                },
+                ConfigDiagnostic::NoTrustedHeaders,
+            ]
+        );
+    }
+}
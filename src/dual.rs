@@ -0,0 +1,98 @@
+use crate::shadow::differences;
+use crate::{Config, ConnectionInformation, RequestInformation, ShadowDifference, Trusted};
+
+/// Extract the information of requests twice, once from `Forwarded` only and once from
+/// `X-Forwarded-For` only, to monitor their agreement while migrating an edge from
+/// `X-Forwarded-For` to RFC 7239
+///
+/// # Example
+/// ```
+/// use trusted_proxies::{Config, DualExtractor};
+///
+/// let extractor = DualExtractor::new(&Config::new_local()).unwrap();
+///
+/// let mut request = http::Request::get("/").body(()).unwrap();
+/// request.headers_mut().insert("forwarded", "for=1.2.3.4".parse().unwrap());
+/// request.headers_mut().insert("x-forwarded-for", "5.6.7.8".parse().unwrap());
+///
+/// let extraction = extractor.extract(&core::net::IpAddr::from([127, 0, 0, 1]), &request);
+/// assert_eq!(extraction.forwarded().ip(), core::net::IpAddr::from([1, 2, 3, 4]));
+/// assert_eq!(extraction.x_forwarded_for().ip(), core::net::IpAddr::from([5, 6, 7, 8]));
+/// assert!(!extraction.is_same());
+/// ```
+#[derive(Debug, Clone)]
+pub struct DualExtractor {
+    forwarded: Config,
+    x_forwarded_for: Config,
+}
+
+impl DualExtractor {
+    /// Derive the configurations trusting a single header from the given one
+    ///
+    /// Returns `None` when the configuration does not trust both `Forwarded` and
+    /// `X-Forwarded-For`.
+    pub fn new(config: &Config) -> Option<Self> {
+        if !config.is_forwarded_trusted || !config.is_x_forwarded_for_trusted {
+            return None;
+        }
+
+        let mut forwarded = config.clone();
+        forwarded.is_x_forwarded_for_trusted = false;
+
+        let mut x_forwarded_for = config.clone();
+        x_forwarded_for.is_forwarded_trusted = false;
+
+        Some(Self {
+            forwarded,
+            x_forwarded_for,
+        })
+    }
+
+    /// Extract the information of a request from each header
+    pub fn extract<'a, C, T>(&self, connection: &C, request: &'a T) -> DualExtraction<'a>
+    where
+        C: ConnectionInformation + ?Sized,
+        T: RequestInformation + ?Sized,
+    {
+        let forwarded = Trusted::from_connection(connection, request, &self.forwarded);
+        let x_forwarded_for = Trusted::from_connection(connection, request, &self.x_forwarded_for);
+        let differences = differences(&forwarded, &x_forwarded_for);
+
+        DualExtraction {
+            forwarded,
+            x_forwarded_for,
+            differences,
+        }
+    }
+}
+
+/// The information extracted from `Forwarded` and from `X-Forwarded-For`, see
+/// [`DualExtractor`]
+#[derive(Debug, Clone)]
+pub struct DualExtraction<'a> {
+    forwarded: Trusted<'a>,
+    x_forwarded_for: Trusted<'a>,
+    differences: Vec<ShadowDifference>,
+}
+
+impl<'a> DualExtraction<'a> {
+    /// Get the information extracted from `Forwarded`
+    pub fn forwarded(&self) -> &Trusted<'a> {
+        &self.forwarded
+    }
+
+    /// Get the information extracted from `X-Forwarded-For`
+    pub fn x_forwarded_for(&self) -> &Trusted<'a> {
+        &self.x_forwarded_for
+    }
+
+    /// Get the fields that differ between both headers
+    pub fn differences(&self) -> &[ShadowDifference] {
+        &self.differences
+    }
+
+    /// Check if both headers gave the same client address, host and scheme
+    pub fn is_same(&self) -> bool {
+        self.differences.is_empty()
+    }
+}
@@ -0,0 +1,171 @@
+use crate::config::parse_networks;
+use crate::{Config, ConfigError};
+use core::fmt;
+
+/// Name of the variable listing the trusted proxies, see [`Config::from_env`]
+const TRUSTED_PROXIES: &str = "TRUSTED_PROXIES";
+
+/// A setter trusting a header
+type TrustHeader = fn(&mut Config);
+
+/// Variables trusting a header, with the setter they enable
+const FLAGS: [(&str, TrustHeader); 5] = [
+    ("TRUST_FORWARDED", Config::trust_forwarded),
+    ("TRUST_X_FORWARDED_FOR", Config::trust_x_forwarded_for),
+    ("TRUST_X_FORWARDED_HOST", Config::trust_x_forwarded_host),
+    ("TRUST_X_FORWARDED_PROTO", Config::trust_x_forwarded_proto),
+    ("TRUST_X_FORWARDED_BY", Config::trust_x_forwarded_by),
+];
+
+/// An error raised while reading a configuration from environment variables
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum EnvError {
+    /// A flag variable is not a boolean
+    InvalidFlag {
+        /// The name of the variable
+        variable: &'static str,
+        /// The value of the variable
+        value: String,
+    },
+    /// `TRUSTED_PROXIES` contains invalid networks
+    Ranges(ConfigError),
+}
+
+impl fmt::Display for EnvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidFlag { variable, value } => write!(
+                f,
+                "invalid value `{value}` for {variable}, expected one of 1, 0, true, false, yes, no, on, off"
+            ),
+            Self::Ranges(err) => write!(f, "invalid networks in {TRUSTED_PROXIES}: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for EnvError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::InvalidFlag { .. } => None,
+            Self::Ranges(err) => Some(err),
+        }
+    }
+}
+
+fn parse_flag(variable: &'static str, value: &str) -> Result<bool, EnvError> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Ok(true),
+        "0" | "false" | "no" | "off" | "" => Ok(false),
+        _ => Err(EnvError::InvalidFlag {
+            variable,
+            value: value.to_string(),
+        }),
+    }
+}
+
+impl Config {
+    /// Read a configuration from the environment variables of the process
+    ///
+    /// The configuration starts with no trusted proxies or headers, like [`Config::new`]:
+    ///
+    ///  * `TRUSTED_PROXIES`: a comma separated list of IP addresses or CIDRs, every invalid entry
+    ///    is reported in the error with its 1-based position
+    ///  * `TRUST_FORWARDED`, `TRUST_X_FORWARDED_FOR`, `TRUST_X_FORWARDED_HOST`,
+    ///    `TRUST_X_FORWARDED_PROTO` and `TRUST_X_FORWARDED_BY`: trust the matching header when set
+    ///    to `1`, `true`, `yes` or `on`, case insensitive
+    ///
+    /// Unset variables keep the default.
+    pub fn from_env() -> Result<Self, EnvError> {
+        Self::from_env_with(|name| {
+            std::env::var_os(name).map(|value| value.to_string_lossy().into_owned())
+        })
+    }
+
+    /// Read a configuration from variables returned by a lookup function, see
+    /// [`Config::from_env`]
+    ///
+    /// # Example
+    /// ```
+    /// use trusted_proxies::Config;
+    ///
+    /// let config = Config::from_env_with(|name| match name {
+    ///     "TRUSTED_PROXIES" => Some("10.0.0.0/8, 203.0.113.7".to_string()),
+    ///     "TRUST_X_FORWARDED_FOR" => Some("1".to_string()),
+    ///     _ => None,
+    /// })
+    /// .unwrap();
+    ///
+    /// assert!(config.is_ip_trusted(&core::net::IpAddr::from([203, 0, 113, 7])));
+    /// ```
+    pub fn from_env_with<F: Fn(&str) -> Option<String>>(lookup: F) -> Result<Self, EnvError> {
+        let mut config = Config::new();
+
+        if let Some(list) = lookup(TRUSTED_PROXIES) {
+            let networks = parse_networks(
+                list.split(',')
+                    .map(str::trim)
+                    .enumerate()
+                    .map(|(index, entry)| (index + 1, entry))
+                    .filter(|(_, entry)| !entry.is_empty()),
+            )
+            .map_err(EnvError::Ranges)?;
+
+            config.extend_trusted_ips(networks);
+        }
+
+        for (variable, trust) in FLAGS {
+            if let Some(value) = lookup(variable) {
+                if parse_flag(variable, &value)? {
+                    trust(&mut config);
+                }
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+#[cfg(all(test, feature = "http"))]
+mod tests {
+    use super::*;
+    use crate::Trusted;
+    use core::net::IpAddr;
+    use http::Request;
+
+    #[test]
+    fn config_from_env() {
+        let vars = |proxies: &'static str, flag: &'static str| {
+            move |name: &str| match name {
+                "TRUSTED_PROXIES" => Some(proxies.to_string()),
+                "TRUST_X_FORWARDED_FOR" | "TRUST_X_FORWARDED_PROTO" => Some(flag.to_string()),
+                _ => None,
+            }
+        };
+
+        let config = Config::from_env_with(vars("10.0.0.1, ", "On")).unwrap();
+        let mut request = Request::get("/").body(()).unwrap();
+        request
+            .headers_mut()
+            .insert("x-forwarded-for", "1.2.3.4".parse().unwrap());
+        request
+            .headers_mut()
+            .insert("x-forwarded-proto", "https".parse().unwrap());
+
+        let trusted = Trusted::from(IpAddr::from([10, 0, 0, 1]), &request, &config);
+        assert_eq!(trusted.ip(), IpAddr::from([1, 2, 3, 4]));
+        assert_eq!(trusted.scheme(), Some("https"));
+
+        let error = Config::from_env_with(vars("10.0.0.1", "maybe")).unwrap_err();
+        assert_eq!(
+            error,
+            EnvError::InvalidFlag {
+                variable: "TRUST_X_FORWARDED_FOR",
+                value: "maybe".to_string()
+            }
+        );
+
+        let error = Config::from_env_with(vars("10.0.0.0/33,10.0.0.1,localhost", "1")).unwrap_err();
+        assert!(error.to_string().contains("`localhost` at position 3"));
+    }
+}
@@ -0,0 +1,167 @@
+//! Conversion between a [`Config`] and the Envoy `X-Forwarded-For` settings
+//!
+//! Envoy detects the client address of a connection manager either by counting trusted hops in
+//! `X-Forwarded-For` (`xff_num_trusted_hops`), or with the `xff` original IP detection extension
+//! trusting the addresses in `xff_trusted_cidrs`. Both convert both ways, a number of hops with
+//! [`Config::set_trusted_hops`].
+//!
+//! Envoy only reads `X-Forwarded-For`, other forwarding headers trusted by a configuration are
+//! lost in the conversion.
+//!
+//! # Example
+//! ```
+//! use trusted_proxies::envoy::EnvoyXff;
+//! use trusted_proxies::Config;
+//!
+//! let mut config = Config::new();
+//! config.add_trusted_ip("10.0.0.0/8").unwrap();
+//! config.trust_x_forwarded_for();
+//!
+//! let envoy = EnvoyXff::from(&config);
+//! assert!(envoy.to_string().contains("address_prefix: 10.0.0.0"));
+//!
+//! let config = Config::from(&envoy);
+//! assert!(config.is_ip_trusted(&core::net::IpAddr::from([10, 1, 2, 3])));
+//! ```
+
+use crate::Config;
+use core::fmt;
+use ipnet::IpNet;
+
+/// The `X-Forwarded-For` settings of an Envoy HTTP connection manager
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EnvoyXff {
+    /// Number of trusted hops at the end of `X-Forwarded-For`, `xff_num_trusted_hops`
+    pub xff_num_trusted_hops: u32,
+    /// Trusted networks of the `xff` original IP detection extension, `xff_trusted_cidrs`
+    pub xff_trusted_cidrs: Vec<IpNet>,
+}
+
+impl From<&Config> for EnvoyXff {
+    /// Convert the trusted networks and hops, they are only trusted by Envoy when the
+    /// configuration trusts `X-Forwarded-For`
+    fn from(config: &Config) -> Self {
+        if !config.is_x_forwarded_for_trusted {
+            return Self::default();
+        }
+
+        Self {
+            xff_num_trusted_hops: config
+                .trusted_hops
+                .map_or(0, |hops| u32::try_from(hops).unwrap_or(u32::MAX)),
+            xff_trusted_cidrs: config.trusted_ips().copied().collect(),
+        }
+    }
+}
+
+impl From<&EnvoyXff> for Config {
+    fn from(envoy: &EnvoyXff) -> Self {
+        let mut config = Config::new();
+
+        for network in &envoy.xff_trusted_cidrs {
+            config
+                .add_trusted_ip(&network.to_string())
+                .expect("network is valid");
+        }
+
+        if envoy.xff_num_trusted_hops > 0 {
+            config.set_trusted_hops(envoy.xff_num_trusted_hops as usize);
+        }
+
+        if !envoy.xff_trusted_cidrs.is_empty() || envoy.xff_num_trusted_hops > 0 {
+            config.trust_x_forwarded_for();
+        }
+
+        config
+    }
+}
+
+impl fmt::Display for EnvoyXff {
+    /// Render the settings as the YAML of an HTTP connection manager
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.xff_trusted_cidrs.is_empty() {
+            return writeln!(f, "xff_num_trusted_hops: {}", self.xff_num_trusted_hops);
+        }
+
+        if self.xff_num_trusted_hops > 0 {
+            writeln!(f, "xff_num_trusted_hops: {}", self.xff_num_trusted_hops)?;
+        }
+
+        writeln!(f, "original_ip_detection_extensions:")?;
+        writeln!(f, "- name: envoy.extensions.http.original_ip_detection.xff")?;
+        writeln!(f, "  typed_config:")?;
+        writeln!(
+            f,
+            "    \"@type\": type.googleapis.com/envoy.extensions.http.original_ip_detection.xff.v3.XffConfig"
+        )?;
+        writeln!(f, "    xff_trusted_cidrs:")?;
+        writeln!(f, "      cidrs:")?;
+
+        for network in &self.xff_trusted_cidrs {
+            writeln!(f, "      - address_prefix: {}", network.network())?;
+            writeln!(f, "        prefix_len: {}", network.prefix_len())?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn trusted_hops() {
+        use crate::Trusted;
+        use core::net::IpAddr;
+
+        let envoy = EnvoyXff {
+            xff_num_trusted_hops: 2,
+            xff_trusted_cidrs: Vec::new(),
+        };
+        let config = Config::from(&envoy);
+
+        let mut request = http::Request::get("/").body(()).unwrap();
+        request.headers_mut().insert(
+            "x-forwarded-for",
+            "1.2.3.4, 5.6.7.8, 9.9.9.9".parse().unwrap(),
+        );
+
+        let trusted = Trusted::from(IpAddr::from([8, 8, 8, 8]), &request, &config);
+        assert_eq!(trusted.ip(), IpAddr::from([1, 2, 3, 4]));
+        assert_eq!(EnvoyXff::from(&config), envoy);
+        assert_eq!(envoy.to_string(), "xff_num_trusted_hops: 2\n");
+    }
+
+    #[test]
+    fn trusted_cidrs() {
+        let mut config = Config::new();
+        config.add_trusted_ip("10.0.0.0/8").unwrap();
+        config.add_trusted_ip("fd00::/8").unwrap();
+        config.trust_x_forwarded_for();
+
+        let envoy = EnvoyXff::from(&config);
+        assert_eq!(envoy.xff_num_trusted_hops, 0);
+        assert_eq!(
+            envoy.to_string(),
+            "original_ip_detection_extensions:
+- name: envoy.extensions.http.original_ip_detection.xff
+  typed_config:
+    \"@type\": type.googleapis.com/envoy.extensions.http.original_ip_detection.xff.v3.XffConfig
+    xff_trusted_cidrs:
+      cidrs:
+      - address_prefix: 10.0.0.0
+        prefix_len: 8
+      - address_prefix: fd00::
+        prefix_len: 8
+"
+        );
+        assert_eq!(Config::from(&envoy), config);
+
+        config.untrust_x_forwarded_for();
+        assert_eq!(EnvoyXff::from(&config), EnvoyXff::default());
+        assert_eq!(EnvoyXff::default().to_string(), "xff_num_trusted_hops: 0\n");
+        assert!(!Config::from(&EnvoyXff::default()).is_x_forwarded_for_trusted());
+    }
+}
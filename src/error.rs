@@ -0,0 +1,108 @@
+use core::fmt;
+use ipnet::AddrParseError;
+
+/// An error raised while building a [`crate::Config`]
+///
+/// When loading a list of networks, every invalid entry is reported at once, see
+/// [`crate::Config::add_trusted_ips`] and [`crate::Config::add_trusted_ip_list`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ConfigError {
+    /// An entry is neither an IP address nor a CIDR
+    InvalidNetwork {
+        /// The offending input
+        input: String,
+        /// The 1-based position of the entry in the list it comes from (the line number for
+        /// [`crate::Config::add_trusted_ip_list`]), `None` for a single entry
+        position: Option<usize>,
+        /// The parse error of the entry
+        source: AddrParseError,
+    },
+    /// A trusted hostname cannot be resolved
+    UnresolvedHostname {
+        /// The hostname
+        hostname: String,
+        /// The resolution error
+        message: String,
+    },
+    /// Several entries of a list are invalid
+    Multiple(Vec<ConfigError>),
+}
+
+impl ConfigError {
+    pub(crate) fn invalid_network(
+        input: &str,
+        position: Option<usize>,
+        source: AddrParseError,
+    ) -> Self {
+        Self::InvalidNetwork {
+            input: input.to_string(),
+            position,
+            source,
+        }
+    }
+
+    pub(crate) fn from_errors(mut errors: Vec<ConfigError>) -> Option<Self> {
+        match errors.len() {
+            0 => None,
+            1 => errors.pop(),
+            _ => Some(Self::Multiple(errors)),
+        }
+    }
+
+    /// Get every error of the report, a single error is returned as a one element slice
+    pub fn errors(&self) -> &[ConfigError] {
+        match self {
+            Self::Multiple(errors) => errors,
+            _ => core::slice::from_ref(self),
+        }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidNetwork {
+                input,
+                position: Some(position),
+                source,
+            } => write!(
+                f,
+                "invalid network `{input}` at position {position}: {source}"
+            ),
+            Self::InvalidNetwork {
+                input,
+                position: None,
+                source,
+            } => write!(f, "invalid network `{input}`: {source}"),
+            Self::UnresolvedHostname { hostname, message } => {
+                write!(f, "cannot resolve hostname `{hostname}`: {message}")
+            }
+            Self::Multiple(errors) => {
+                if errors
+                    .iter()
+                    .all(|error| matches!(error, Self::InvalidNetwork { .. }))
+                {
+                    write!(f, "{} invalid networks", errors.len())?;
+                } else {
+                    write!(f, "{} errors", errors.len())?;
+                }
+
+                for error in errors {
+                    write!(f, "\n  {error}")?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::InvalidNetwork { source, .. } => Some(source),
+            Self::UnresolvedHostname { .. } | Self::Multiple(_) => None,
+        }
+    }
+}
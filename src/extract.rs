@@ -29,6 +29,16 @@ pub trait RequestInformation {
     /// Get the `X-Forwarded-By` header values
     fn x_forwarded_by(&self) -> impl DoubleEndedIterator<Item = &str>;
 
+    /// Get the values of any other header, given its lowercase name
+    ///
+    /// It is used for non standard headers (e.g. `X-Client-IP`), the default implementation
+    /// returns no values.
+    fn header(&self, name: &str) -> impl DoubleEndedIterator<Item = &str> {
+        let _ = name;
+
+        core::iter::empty()
+    }
+
     /// Return the default host of the request when no trusted headers are found
     ///
     /// Default to host header if allowed or authority
@@ -43,6 +53,223 @@ pub trait RequestInformation {
     fn default_scheme(&self) -> Option<&str>;
 }
 
+/// An object-safe version of [`RequestInformation`], for servers handling requests as trait
+/// objects
+///
+/// It is implemented for every [`RequestInformation`], and `dyn DynRequestInformation`
+/// implements [`RequestInformation`] so it can be given to [`crate::Trusted::from`]. Iterators
+/// are boxed, prefer [`RequestInformation`] when the request type is known.
+///
+/// # Example
+/// ```
+/// use trusted_proxies::{Config, DynRequestInformation, Trusted};
+///
+/// let mut request = http::Request::get("/").body(()).unwrap();
+/// request.headers_mut().insert("x-forwarded-for", "1.2.3.4".parse().unwrap());
+///
+/// let request: Box<dyn DynRequestInformation> = Box::new(request);
+/// let trusted = Trusted::from(core::net::IpAddr::from([127, 0, 0, 1]), &*request, &Config::new_local());
+///
+/// assert_eq!(trusted.ip(), core::net::IpAddr::from([1, 2, 3, 4]));
+/// ```
+pub trait DynRequestInformation {
+    /// See [`RequestInformation::is_host_header_allowed`]
+    fn is_host_header_allowed(&self) -> bool;
+
+    /// See [`RequestInformation::host_header`]
+    fn host_header(&self) -> Option<&str>;
+
+    /// See [`RequestInformation::authority`]
+    fn authority(&self) -> Option<&str>;
+
+    /// See [`RequestInformation::forwarded`]
+    fn forwarded(&self) -> Box<dyn DoubleEndedIterator<Item = &str> + '_>;
+
+    /// See [`RequestInformation::x_forwarded_for`]
+    fn x_forwarded_for(&self) -> Box<dyn DoubleEndedIterator<Item = &str> + '_>;
+
+    /// See [`RequestInformation::x_forwarded_host`]
+    fn x_forwarded_host(&self) -> Box<dyn DoubleEndedIterator<Item = &str> + '_>;
+
+    /// See [`RequestInformation::x_forwarded_proto`]
+    fn x_forwarded_proto(&self) -> Box<dyn DoubleEndedIterator<Item = &str> + '_>;
+
+    /// See [`RequestInformation::x_forwarded_by`]
+    fn x_forwarded_by(&self) -> Box<dyn DoubleEndedIterator<Item = &str> + '_>;
+
+    /// See [`RequestInformation::header`]
+    fn header<'s, 'n>(&'s self, name: &'n str) -> Box<dyn DoubleEndedIterator<Item = &'s str> + 'n>
+    where
+        's: 'n;
+
+    /// See [`RequestInformation::default_host`]
+    fn default_host(&self) -> Option<&str>;
+
+    /// See [`RequestInformation::default_scheme`]
+    fn default_scheme(&self) -> Option<&str>;
+}
+
+impl<T: RequestInformation> DynRequestInformation for T {
+    fn is_host_header_allowed(&self) -> bool {
+        RequestInformation::is_host_header_allowed(self)
+    }
+
+    fn host_header(&self) -> Option<&str> {
+        RequestInformation::host_header(self)
+    }
+
+    fn authority(&self) -> Option<&str> {
+        RequestInformation::authority(self)
+    }
+
+    fn forwarded(&self) -> Box<dyn DoubleEndedIterator<Item = &str> + '_> {
+        Box::new(RequestInformation::forwarded(self))
+    }
+
+    fn x_forwarded_for(&self) -> Box<dyn DoubleEndedIterator<Item = &str> + '_> {
+        Box::new(RequestInformation::x_forwarded_for(self))
+    }
+
+    fn x_forwarded_host(&self) -> Box<dyn DoubleEndedIterator<Item = &str> + '_> {
+        Box::new(RequestInformation::x_forwarded_host(self))
+    }
+
+    fn x_forwarded_proto(&self) -> Box<dyn DoubleEndedIterator<Item = &str> + '_> {
+        Box::new(RequestInformation::x_forwarded_proto(self))
+    }
+
+    fn x_forwarded_by(&self) -> Box<dyn DoubleEndedIterator<Item = &str> + '_> {
+        Box::new(RequestInformation::x_forwarded_by(self))
+    }
+
+    fn header<'s, 'n>(&'s self, name: &'n str) -> Box<dyn DoubleEndedIterator<Item = &'s str> + 'n>
+    where
+        's: 'n,
+    {
+        Box::new(RequestInformation::header(self, name))
+    }
+
+    fn default_host(&self) -> Option<&str> {
+        RequestInformation::default_host(self)
+    }
+
+    fn default_scheme(&self) -> Option<&str> {
+        RequestInformation::default_scheme(self)
+    }
+}
+
+impl RequestInformation for dyn DynRequestInformation + '_ {
+    fn is_host_header_allowed(&self) -> bool {
+        DynRequestInformation::is_host_header_allowed(self)
+    }
+
+    fn host_header(&self) -> Option<&str> {
+        DynRequestInformation::host_header(self)
+    }
+
+    fn authority(&self) -> Option<&str> {
+        DynRequestInformation::authority(self)
+    }
+
+    fn forwarded(&self) -> impl DoubleEndedIterator<Item = &str> {
+        DynRequestInformation::forwarded(self)
+    }
+
+    fn x_forwarded_for(&self) -> impl DoubleEndedIterator<Item = &str> {
+        DynRequestInformation::x_forwarded_for(self)
+    }
+
+    fn x_forwarded_host(&self) -> impl DoubleEndedIterator<Item = &str> {
+        DynRequestInformation::x_forwarded_host(self)
+    }
+
+    fn x_forwarded_proto(&self) -> impl DoubleEndedIterator<Item = &str> {
+        DynRequestInformation::x_forwarded_proto(self)
+    }
+
+    fn x_forwarded_by(&self) -> impl DoubleEndedIterator<Item = &str> {
+        DynRequestInformation::x_forwarded_by(self)
+    }
+
+    fn header(&self, name: &str) -> impl DoubleEndedIterator<Item = &str> {
+        // the values are collected as the boxed iterator cannot outlive the name
+        DynRequestInformation::header(self, name)
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    fn default_host(&self) -> Option<&str> {
+        DynRequestInformation::default_host(self)
+    }
+
+    fn default_scheme(&self) -> Option<&str> {
+        DynRequestInformation::default_scheme(self)
+    }
+}
+
+/// A request given as a list of header names and values, used by the bindings to other languages
+#[cfg(any(feature = "capi", feature = "wasi"))]
+pub(crate) struct HeaderList<'a> {
+    pub(crate) headers: Vec<(&'a str, &'a str)>,
+    pub(crate) scheme: Option<&'a str>,
+}
+
+#[cfg(any(feature = "capi", feature = "wasi"))]
+impl HeaderList<'_> {
+    fn values<'s>(&'s self, name: &'s str) -> impl DoubleEndedIterator<Item = &'s str> {
+        self.headers
+            .iter()
+            .filter(move |(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| *v)
+    }
+}
+
+#[cfg(any(feature = "capi", feature = "wasi"))]
+impl RequestInformation for HeaderList<'_> {
+    fn is_host_header_allowed(&self) -> bool {
+        true
+    }
+
+    fn host_header(&self) -> Option<&str> {
+        self.values("host").next()
+    }
+
+    fn authority(&self) -> Option<&str> {
+        None
+    }
+
+    fn forwarded(&self) -> impl DoubleEndedIterator<Item = &str> {
+        self.values("forwarded")
+    }
+
+    fn x_forwarded_for(&self) -> impl DoubleEndedIterator<Item = &str> {
+        self.values("x-forwarded-for")
+    }
+
+    fn x_forwarded_host(&self) -> impl DoubleEndedIterator<Item = &str> {
+        self.values("x-forwarded-host")
+    }
+
+    fn x_forwarded_proto(&self) -> impl DoubleEndedIterator<Item = &str> {
+        self.values("x-forwarded-proto")
+    }
+
+    fn x_forwarded_by(&self) -> impl DoubleEndedIterator<Item = &str> {
+        self.values("x-forwarded-by")
+    }
+
+    fn header(&self, name: &str) -> impl DoubleEndedIterator<Item = &str> {
+        self.headers
+            .iter()
+            .filter(move |(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| *v)
+    }
+
+    fn default_scheme(&self) -> Option<&str> {
+        self.scheme
+    }
+}
+
 #[cfg(feature = "http")]
 mod http {
     use super::RequestInformation;
@@ -97,6 +324,13 @@ mod http {
                 .filter_map(|value| value.to_str().ok())
         }
 
+        fn header(&self, name: &str) -> impl DoubleEndedIterator<Item = &str> {
+            self.headers()
+                .get_all(name)
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+        }
+
         fn default_scheme(&self) -> Option<&str> {
             self.uri().scheme_str()
         }
@@ -152,6 +386,13 @@ mod http {
                 .filter_map(|value| value.to_str().ok())
         }
 
+        fn header(&self, name: &str) -> impl DoubleEndedIterator<Item = &str> {
+            self.headers
+                .get_all(name)
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+        }
+
         fn default_scheme(&self) -> Option<&str> {
             self.uri.scheme_str()
         }
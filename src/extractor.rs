@@ -0,0 +1,160 @@
+use crate::RequestInformation;
+use core::fmt;
+use core::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+
+/// Extract the client address from a non standard header, see [`crate::Config::add_ip_extractor`]
+///
+/// Extractors handle headers or encodings the crate does not know about (e.g. a base64 wrapped
+/// address or a JSON document describing the client), they are only run when the peer is trusted.
+pub trait IpExtractor: Send + Sync {
+    /// Lowercase name of the header read by this extractor
+    fn header(&self) -> &str;
+
+    /// Decode the client address from the values of the header, in the order they were received
+    ///
+    /// Returning `None` lets the next extractor run.
+    fn extract(&self, values: &[&str]) -> Option<IpAddr>;
+}
+
+/// Read the client address from the `X-Azure-ClientIP` header set by Azure Front Door
+///
+/// Front Door overwrites this header with the address of the client that connected to it, so it
+/// is the client address when the peer is a Front Door backend, see
+/// [`crate::Config::new_azure_front_door`]. An address with a port is accepted.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AzureClientIp;
+
+impl IpExtractor for AzureClientIp {
+    fn header(&self) -> &str {
+        "x-azure-clientip"
+    }
+
+    fn extract(&self, values: &[&str]) -> Option<IpAddr> {
+        last_address(values)
+    }
+}
+
+/// Read the client address from the `True-Client-IP` header set by Akamai and other CDNs
+///
+/// The CDN sets this header to the address of the client that connected to its edge, so it must
+/// only be read when the peer is an edge server of the CDN, see
+/// [`crate::Config::new_akamai`]. An address with a port is accepted.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TrueClientIp;
+
+impl IpExtractor for TrueClientIp {
+    fn header(&self) -> &str {
+        "true-client-ip"
+    }
+
+    fn extract(&self, values: &[&str]) -> Option<IpAddr> {
+        last_address(values)
+    }
+}
+
+/// Parse the last value of a single address header, with or without a port
+pub(crate) fn last_address(values: &[&str]) -> Option<IpAddr> {
+    let value = values.last()?.trim();
+
+    value
+        .parse()
+        .ok()
+        .or_else(|| value.parse::<SocketAddr>().ok().map(|addr| addr.ip()))
+}
+
+/// A registered extractor, only run for the peers of its group when it has one
+#[derive(Clone)]
+struct Entry {
+    priority: i32,
+    group: Option<Arc<str>>,
+    extractor: Arc<dyn IpExtractor>,
+}
+
+/// The extractors registered on a configuration, sorted by descending priority
+#[derive(Clone, Default)]
+pub(crate) struct IpExtractors(Vec<Entry>);
+
+impl IpExtractors {
+    pub(crate) fn add(
+        &mut self,
+        priority: i32,
+        group: Option<Arc<str>>,
+        extractor: Arc<dyn IpExtractor>,
+    ) {
+        // extractors with the same priority run in registration order
+        let index = self.0.partition_point(|entry| entry.priority >= priority);
+
+        self.0.insert(
+            index,
+            Entry {
+                priority,
+                group,
+                extractor,
+            },
+        );
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Get the priority, group and address of the extractors, in the order they run
+    pub(crate) fn entries(&self) -> impl Iterator<Item = (i32, Option<&str>, *const ())> {
+        self.0.iter().map(|entry| {
+            (
+                entry.priority,
+                entry.group.as_deref(),
+                Arc::as_ptr(&entry.extractor).cast(),
+            )
+        })
+    }
+
+    /// Add the extractors of another configuration, after the ones of the same priority
+    pub(crate) fn extend(&mut self, other: &Self) {
+        for entry in &other.0 {
+            self.add(entry.priority, entry.group.clone(), entry.extractor.clone());
+        }
+    }
+
+    /// Get the address given by the first extractor that succeeds, skipping the extractors of
+    /// other groups than the one of the peer
+    pub(crate) fn extract<T: RequestInformation + ?Sized>(
+        &self,
+        request: &T,
+        peer_group: Option<&str>,
+    ) -> Option<IpAddr> {
+        self.0.iter().find_map(|entry| {
+            if entry
+                .group
+                .as_deref()
+                .is_some_and(|group| Some(group) != peer_group)
+            {
+                return None;
+            }
+
+            let extractor = &entry.extractor;
+            let values = request.header(extractor.header()).collect::<Vec<_>>();
+
+            if values.is_empty() {
+                return None;
+            }
+
+            extractor.extract(&values)
+        })
+    }
+}
+
+impl fmt::Debug for IpExtractors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.0.iter().map(|entry| {
+                (
+                    entry.priority,
+                    entry.group.as_deref(),
+                    entry.extractor.header(),
+                )
+            }))
+            .finish()
+    }
+}
@@ -0,0 +1,311 @@
+//! Load trusted networks from a remote list
+//!
+//! With the `fetch` feature, [`RemoteRanges`] downloads a list of networks from an HTTP or HTTPS
+//! URL, such as a ranges file stored on S3, and publishes a new configuration to a
+//! [`ConfigHandle`] when the list changes. With the `fetch-async` feature, [`fetch_ranges`] and
+//! [`fetch_url`] download a list from an async context, e.g. at startup.
+//!
+//! The list is either a plain list with one network per line, or a JSON document from which every
+//! string that is a network is taken (e.g. the published ranges of a CDN). The official lists of
+//! some providers are available as a [`RangeSource`].
+//!
+//! # Example
+//! ```no_run
+//! # #[cfg(feature = "fetch")] {
+//! use trusted_proxies::fetch::RemoteRanges;
+//! use trusted_proxies::{Config, Trusted};
+//!
+//! let mut base = Config::new();
+//! base.trust_x_forwarded_for();
+//!
+//! let mut ranges = RemoteRanges::new("https://example.com/proxies.txt", base);
+//! ranges.fetch().unwrap();
+//!
+//! let handle = ranges.handle();
+//! // later, in a background thread
+//! let changed = ranges.fetch().unwrap();
+//! # }
+//! ```
+
+use crate::config::parse_network_list;
+use crate::ConfigError;
+#[cfg(feature = "fetch")]
+use crate::{Config, ConfigHandle};
+use core::fmt;
+use ipnet::IpNet;
+
+/// An official list of ranges published by a provider
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RangeSource {
+    /// The Cloudflare edge ranges, from the Cloudflare API
+    Cloudflare,
+    /// The CloudFront edge and regional edge ranges, IPv4 only
+    CloudFront,
+    /// The external ranges of Google Cloud, usable by any Google Cloud customer
+    GoogleCloud,
+    /// The Fastly edge ranges
+    Fastly,
+}
+
+impl RangeSource {
+    /// Get the URL of the list
+    pub fn url(&self) -> &'static str {
+        match self {
+            Self::Cloudflare => "https://api.cloudflare.com/client/v4/ips",
+            Self::CloudFront => "https://d7uri8nf7uskq.cloudfront.net/tools/list-cloudfront-ips",
+            Self::GoogleCloud => "https://www.gstatic.com/ipranges/cloud.json",
+            Self::Fastly => "https://api.fastly.com/public-ip-list",
+        }
+    }
+}
+
+/// An error raised while fetching a remote list
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum FetchError {
+    /// The request failed, or the server answered with an error status
+    #[cfg(feature = "fetch")]
+    Request(ureq::Error),
+    /// The async request failed, or the server answered with an error status
+    #[cfg(feature = "fetch-async")]
+    Http(reqwest::Error),
+    /// The list looks like JSON but is not valid JSON
+    Json(serde_json::Error),
+    /// The list contains invalid networks
+    Ranges(ConfigError),
+}
+
+impl fmt::Display for FetchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(feature = "fetch")]
+            Self::Request(err) => write!(f, "cannot fetch trusted networks: {err}"),
+            #[cfg(feature = "fetch-async")]
+            Self::Http(err) => write!(f, "cannot fetch trusted networks: {err}"),
+            Self::Json(err) => write!(f, "invalid JSON list of trusted networks: {err}"),
+            Self::Ranges(err) => write!(f, "invalid list of trusted networks: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for FetchError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            #[cfg(feature = "fetch")]
+            Self::Request(err) => Some(err),
+            #[cfg(feature = "fetch-async")]
+            Self::Http(err) => Some(err),
+            Self::Json(err) => Some(err),
+            Self::Ranges(err) => Some(err),
+        }
+    }
+}
+
+/// A list of trusted networks downloaded from a URL
+///
+/// The `ETag` of the last response is sent in `If-None-Match`, so an unchanged list is not
+/// downloaded nor parsed again.
+#[cfg(feature = "fetch")]
+#[derive(Debug)]
+pub struct RemoteRanges {
+    url: String,
+    base: Config,
+    etag: Option<String>,
+    handle: ConfigHandle,
+}
+
+#[cfg(feature = "fetch")]
+impl RemoteRanges {
+    /// Create a fetcher adding the networks of the list to the given base configuration
+    ///
+    /// The handle holds the base configuration until the first successful fetch.
+    pub fn new(url: &str, base: Config) -> Self {
+        Self {
+            url: url.to_string(),
+            handle: ConfigHandle::new(base.clone()),
+            base,
+            etag: None,
+        }
+    }
+
+    /// Get the handle the configurations are published to
+    pub fn handle(&self) -> ConfigHandle {
+        self.handle.clone()
+    }
+
+    /// Fetch the list, and publish a new configuration when it changed
+    ///
+    /// Returns whether a new configuration has been published. On error, the current
+    /// configuration is kept.
+    pub fn fetch(&mut self) -> Result<bool, FetchError> {
+        let mut request = ureq::get(&self.url);
+
+        if let Some(etag) = &self.etag {
+            request = request.header("if-none-match", etag);
+        }
+
+        let mut response = request.call().map_err(FetchError::Request)?;
+
+        if response.status() == 304 {
+            return Ok(false);
+        }
+
+        let etag = response
+            .headers()
+            .get("etag")
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let content = response
+            .body_mut()
+            .read_to_string()
+            .map_err(FetchError::Request)?;
+
+        let mut config = self.base.clone();
+        config.extend_trusted_ips(parse_ranges(&content)?);
+
+        self.handle.store(config);
+        self.etag = etag;
+
+        Ok(true)
+    }
+}
+
+/// Download and parse the official list of a provider
+///
+/// # Example
+/// ```no_run
+/// use trusted_proxies::fetch::{fetch_ranges, RangeSource};
+/// use trusted_proxies::Config;
+///
+/// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+/// let mut config = Config::new();
+/// config.add_trusted_networks(fetch_ranges(RangeSource::Cloudflare).await?);
+/// config.trust_x_forwarded_for();
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "fetch-async")]
+pub async fn fetch_ranges(source: RangeSource) -> Result<Vec<IpNet>, FetchError> {
+    fetch_url(source.url()).await
+}
+
+/// Download and parse a list of networks, see [`parse_ranges`] for the accepted formats
+#[cfg(feature = "fetch-async")]
+pub async fn fetch_url(url: &str) -> Result<Vec<IpNet>, FetchError> {
+    let content = reqwest::get(url)
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .map_err(FetchError::Http)?
+        .text()
+        .await
+        .map_err(FetchError::Http)?;
+
+    parse_ranges(&content)
+}
+
+/// Parse a list of networks, either a plain list or a JSON document
+///
+/// A plain list has one IP address or CIDR per line, empty lines and comments starting with `#`
+/// are ignored. In a JSON document, every string value that is an IP address or a CIDR is taken,
+/// whatever its key, other values are ignored.
+pub fn parse_ranges(content: &str) -> Result<Vec<IpNet>, FetchError> {
+    if !content.trim_start().starts_with(['{', '[']) {
+        return parse_network_list(content).map_err(FetchError::Ranges);
+    }
+
+    let document = serde_json::from_str(content).map_err(FetchError::Json)?;
+    let mut networks = Vec::new();
+
+    collect_networks(&document, &mut networks);
+
+    Ok(networks)
+}
+
+fn collect_networks(value: &serde_json::Value, networks: &mut Vec<IpNet>) {
+    match value {
+        serde_json::Value::String(value) => {
+            if let Ok(network) = value.parse() {
+                networks.push(network);
+            } else if let Ok(ip) = value.parse::<core::net::IpAddr>() {
+                networks.push(IpNet::from(ip));
+            }
+        }
+        serde_json::Value::Array(values) => {
+            values
+                .iter()
+                .for_each(|value| collect_networks(value, networks));
+        }
+        serde_json::Value::Object(values) => {
+            values
+                .values()
+                .for_each(|value| collect_networks(value, networks));
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_lists() {
+        let plain = parse_ranges("# edge\n173.245.48.0/20\n\n2400:cb00::/32\n").unwrap();
+        assert_eq!(plain.len(), 2);
+
+        let json = parse_ranges(
+            r#"{"syncToken": "1", "prefixes": [{"ip_prefix": "3.5.140.0/22", "region": "ap-northeast-2"}], "ipv6_prefixes": [{"ipv6_prefix": "2600:1f14::/35"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            json,
+            vec![
+                "2600:1f14::/35".parse::<IpNet>().unwrap(),
+                "3.5.140.0/22".parse().unwrap()
+            ]
+        );
+
+        assert!(matches!(
+            parse_ranges("10.0.0.0/8\nnope\n"),
+            Err(FetchError::Ranges(_))
+        ));
+    }
+
+    #[cfg(feature = "fetch-async")]
+    #[tokio::test]
+    async fn fetch_published_list() {
+        use std::io::{BufRead, BufReader, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/public-ip-list", listener.local_addr().unwrap());
+
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let body = r#"{"addresses": ["23.235.32.0/20"], "ipv6_addresses": ["2a04:4e40::/32"]}"#;
+
+            // read the request head, up to the empty line
+            BufReader::new(&stream)
+                .lines()
+                .map_while(Result::ok)
+                .find(|line| line.is_empty());
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                body.len()
+            )
+            .unwrap();
+        });
+
+        let ranges = fetch_url(&url).await.unwrap();
+        server.join().unwrap();
+
+        assert_eq!(
+            ranges,
+            vec![
+                "23.235.32.0/20".parse::<IpNet>().unwrap(),
+                "2a04:4e40::/32".parse().unwrap()
+            ]
+        );
+    }
+}
@@ -0,0 +1,261 @@
+//! Load a configuration from a TOML, YAML or JSON file
+//!
+//! The format is detected from the extension of the file, `.toml`, `.yaml` / `.yml` or `.json`.
+//! Every key is optional, unknown keys are rejected:
+//!
+//! ```toml
+//! # trust the local and private networks and the `Forwarded` and `X-Forwarded-For` headers,
+//! # like `Config::new_local`
+//! preset = "local"
+//! # IP addresses or CIDRs of the trusted proxies
+//! trusted_ips = ["203.0.113.0/24", "2001:db8::1"]
+//! # trusted headers, all false by default
+//! trust_forwarded = true
+//! trust_x_forwarded_for = true
+//! trust_x_forwarded_host = false
+//! trust_x_forwarded_proto = true
+//! trust_x_forwarded_by = false
+//! ```
+//!
+//! # Example
+//! ```no_run
+//! use trusted_proxies::Config;
+//!
+//! let config = Config::from_file("/etc/my-service/trusted-proxies.toml").unwrap();
+//! ```
+
+use crate::{Config, ConfigBuilder, ConfigError};
+use core::fmt;
+use serde::Deserialize;
+use std::path::Path;
+
+/// The format of a configuration file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FileFormat {
+    /// TOML, `.toml` files
+    Toml,
+    /// YAML, `.yaml` and `.yml` files
+    Yaml,
+    /// JSON, `.json` files
+    Json,
+}
+
+impl FileFormat {
+    /// Detect the format of a file from its extension
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "toml" => Some(Self::Toml),
+            "yaml" | "yml" => Some(Self::Yaml),
+            "json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+}
+
+/// A preset the file configuration starts from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[non_exhaustive]
+pub enum FilePreset {
+    /// No trusted proxies or headers, like [`Config::new`]
+    None,
+    /// Local and private networks, `Forwarded` and `X-Forwarded-For` trusted, like
+    /// [`Config::new_local`]
+    Local,
+}
+
+/// The content of a configuration file, see the [module](self) documentation for the schema
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigFile {
+    /// The preset the configuration starts from
+    pub preset: FilePreset,
+    /// IP addresses or CIDRs of the trusted proxies
+    pub trusted_ips: Vec<String>,
+    /// Trust the `Forwarded` header
+    pub trust_forwarded: bool,
+    /// Trust the `X-Forwarded-For` header
+    pub trust_x_forwarded_for: bool,
+    /// Trust the `X-Forwarded-Host` header
+    pub trust_x_forwarded_host: bool,
+    /// Trust the `X-Forwarded-Proto` header
+    pub trust_x_forwarded_proto: bool,
+    /// Trust the `X-Forwarded-By` header
+    pub trust_x_forwarded_by: bool,
+}
+
+impl Default for ConfigFile {
+    fn default() -> Self {
+        Self {
+            preset: FilePreset::None,
+            trusted_ips: Vec::new(),
+            trust_forwarded: false,
+            trust_x_forwarded_for: false,
+            trust_x_forwarded_host: false,
+            trust_x_forwarded_proto: false,
+            trust_x_forwarded_by: false,
+        }
+    }
+}
+
+/// An error raised while loading a configuration file
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum FileError {
+    /// The file cannot be read
+    Io(std::io::Error),
+    /// The extension of the file is not a supported format
+    UnknownFormat,
+    /// The file is not valid TOML, or does not follow the schema
+    Toml(toml::de::Error),
+    /// The file is not valid YAML, or does not follow the schema
+    Yaml(serde_yaml::Error),
+    /// The file is not valid JSON, or does not follow the schema
+    Json(serde_json::Error),
+    /// The file contains invalid networks
+    Ranges(ConfigError),
+}
+
+impl fmt::Display for FileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "cannot read configuration file: {err}"),
+            Self::UnknownFormat => write!(
+                f,
+                "unknown configuration file format, expected a .toml, .yaml, .yml or .json file"
+            ),
+            Self::Toml(err) => write!(f, "invalid TOML configuration file: {err}"),
+            Self::Yaml(err) => write!(f, "invalid YAML configuration file: {err}"),
+            Self::Json(err) => write!(f, "invalid JSON configuration file: {err}"),
+            Self::Ranges(err) => write!(f, "invalid trusted networks in configuration file: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for FileError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::UnknownFormat => None,
+            Self::Toml(err) => Some(err),
+            Self::Yaml(err) => Some(err),
+            Self::Json(err) => Some(err),
+            Self::Ranges(err) => Some(err),
+        }
+    }
+}
+
+impl ConfigFile {
+    /// Parse the content of a configuration file
+    pub fn parse(content: &str, format: FileFormat) -> Result<Self, FileError> {
+        match format {
+            FileFormat::Toml => toml::from_str(content).map_err(FileError::Toml),
+            FileFormat::Yaml => serde_yaml::from_str(content).map_err(FileError::Yaml),
+            FileFormat::Json => serde_json::from_str(content).map_err(FileError::Json),
+        }
+    }
+
+    /// Build the configuration, failing with every invalid network
+    pub fn to_config(&self) -> Result<Config, ConfigError> {
+        let mut builder = ConfigBuilder::new();
+
+        if self.preset == FilePreset::Local {
+            builder = builder.preset_local();
+        }
+
+        builder = builder.trusted_ips(&self.trusted_ips);
+
+        if self.trust_forwarded {
+            builder = builder.trust_forwarded();
+        }
+
+        if self.trust_x_forwarded_for {
+            builder = builder.trust_x_forwarded_for();
+        }
+
+        if self.trust_x_forwarded_host {
+            builder = builder.trust_x_forwarded_host();
+        }
+
+        if self.trust_x_forwarded_proto {
+            builder = builder.trust_x_forwarded_proto();
+        }
+
+        if self.trust_x_forwarded_by {
+            builder = builder.trust_x_forwarded_by();
+        }
+
+        builder.build()
+    }
+}
+
+impl Config {
+    /// Load a configuration from a TOML, YAML or JSON file, see the [`file`](crate::file) module
+    /// for the schema
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, FileError> {
+        let path = path.as_ref();
+        let format = FileFormat::from_path(path).ok_or(FileError::UnknownFormat)?;
+        let content = std::fs::read_to_string(path).map_err(FileError::Io)?;
+
+        ConfigFile::parse(&content, format)?
+            .to_config()
+            .map_err(FileError::Ranges)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::net::IpAddr;
+
+    #[test]
+    fn parse_formats() {
+        let expected = ConfigFile {
+            trusted_ips: vec!["10.0.0.0/8".to_string(), "203.0.113.7".to_string()],
+            trust_x_forwarded_for: true,
+            ..ConfigFile::default()
+        };
+
+        let toml =
+            "trusted_ips = [\"10.0.0.0/8\", \"203.0.113.7\"]\ntrust_x_forwarded_for = true\n";
+        let yaml = "trusted_ips:\n  - 10.0.0.0/8\n  - 203.0.113.7\ntrust_x_forwarded_for: true\n";
+        let json =
+            r#"{"trusted_ips": ["10.0.0.0/8", "203.0.113.7"], "trust_x_forwarded_for": true}"#;
+
+        assert_eq!(ConfigFile::parse(toml, FileFormat::Toml).unwrap(), expected);
+        assert_eq!(ConfigFile::parse(yaml, FileFormat::Yaml).unwrap(), expected);
+        assert_eq!(ConfigFile::parse(json, FileFormat::Json).unwrap(), expected);
+
+        assert!(matches!(
+            ConfigFile::parse("trust_x_forwarded = true", FileFormat::Toml),
+            Err(FileError::Toml(_))
+        ));
+    }
+
+    #[test]
+    fn from_file() {
+        let path = std::env::temp_dir().join(format!("trusted-proxies-{}.yml", std::process::id()));
+        std::fs::write(&path, "preset: local\ntrusted_ips: [203.0.113.7]\n").unwrap();
+
+        let config = Config::from_file(&path).unwrap();
+        assert!(config.is_ip_trusted(&IpAddr::from([127, 0, 0, 1])));
+        assert!(config.is_ip_trusted(&IpAddr::from([203, 0, 113, 7])));
+        assert!(config.is_x_forwarded_for_trusted);
+
+        std::fs::write(&path, "trusted_ips: [10.0.0.0/33, localhost]\n").unwrap();
+
+        let error = Config::from_file(&path).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+
+        let FileError::Ranges(error) = error else {
+            panic!("unexpected error {error}");
+        };
+        assert_eq!(error.errors().len(), 2);
+
+        assert!(matches!(
+            Config::from_file("trusted-proxies.ini"),
+            Err(FileError::UnknownFormat)
+        ));
+    }
+}
@@ -0,0 +1,293 @@
+//! Structure-aware fuzzing inputs, see the `fuzz` directory for the crate's own targets
+//!
+//! [`Config`] implements [`Arbitrary`] and [`FuzzRequest`] generates forwarding headers that are
+//! mostly well formed, so the fuzzer spends its time in the trust walk instead of being rejected
+//! by the first parse error.
+//!
+//! # Example
+//! ```
+//! use arbitrary::{Arbitrary, Unstructured};
+//! use trusted_proxies::fuzz::FuzzRequest;
+//! use trusted_proxies::{Config, Trusted};
+//!
+//! let data = [42u8; 256];
+//! let mut u = Unstructured::new(&data);
+//! let config = Config::arbitrary(&mut u).unwrap();
+//! let request = FuzzRequest::arbitrary(&mut u).unwrap();
+//!
+//! let trusted = Trusted::from(request.peer, &request, &config);
+//! assert!(!config.is_ip_trusted(&trusted.ip()) || trusted.ip() == request.peer);
+//! ```
+
+use crate::{Config, PrivateClientIp, RequestInformation};
+use arbitrary::{Arbitrary, Result, Unstructured};
+use core::net::IpAddr;
+
+impl<'a> Arbitrary<'a> for Config {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let mut config = if u.arbitrary()? {
+            Config::new_local()
+        } else {
+            Config::new()
+        };
+
+        for _ in 0..u.int_in_range(0..=8)? {
+            let network = match IpAddr::arbitrary(u)? {
+                IpAddr::V4(ip) => format!("{ip}/{}", u.int_in_range(0..=32)?),
+                IpAddr::V6(ip) => format!("{ip}/{}", u.int_in_range(0..=128)?),
+            };
+
+            config
+                .add_trusted_ip(&network)
+                .expect("generated network is valid");
+        }
+
+        if u.arbitrary()? {
+            config.trust_forwarded();
+        }
+
+        if u.arbitrary()? {
+            config.trust_x_forwarded_for();
+        }
+
+        if u.arbitrary()? {
+            config.trust_x_forwarded_host();
+        }
+
+        if u.arbitrary()? {
+            config.trust_x_forwarded_proto();
+        }
+
+        if u.arbitrary()? {
+            config.trust_x_forwarded_by();
+        }
+
+        if u.arbitrary()? {
+            config.set_max_header_value_length(u.int_in_range(0..=1024)?);
+        }
+
+        if u.arbitrary()? {
+            config.set_max_total_header_length(u.int_in_range(0..=4096)?);
+        }
+
+        config.set_private_client_ip(*u.choose(&[
+            PrivateClientIp::Accept,
+            PrivateClientIp::Skip,
+            PrivateClientIp::UsePeer,
+        ])?);
+
+        Ok(config)
+    }
+}
+
+/// A node of a `Forwarded` element or an `X-Forwarded-For` element
+#[derive(Debug, Clone, Arbitrary)]
+pub enum FuzzNode {
+    Ip(IpAddr),
+    IpWithPort(IpAddr, u16),
+    Unknown,
+    Obfuscated(String),
+    Raw(String),
+}
+
+impl FuzzNode {
+    fn render(&self, forwarded: bool) -> String {
+        match self {
+            Self::Ip(IpAddr::V6(ip)) if forwarded => format!("\"[{ip}]\""),
+            Self::Ip(ip) => ip.to_string(),
+            Self::IpWithPort(IpAddr::V4(ip), port) => format!("{ip}:{port}"),
+            Self::IpWithPort(IpAddr::V6(ip), port) if forwarded => format!("\"[{ip}]:{port}\""),
+            Self::IpWithPort(IpAddr::V6(ip), port) => format!("[{ip}]:{port}"),
+            Self::Unknown => "unknown".to_string(),
+            Self::Obfuscated(name) => format!("_{name}"),
+            Self::Raw(raw) => raw.clone(),
+        }
+    }
+}
+
+/// An element of the `Forwarded` header
+#[derive(Debug, Clone, Arbitrary)]
+pub struct FuzzForwardedElement {
+    pub node: Option<FuzzNode>,
+    pub proto: Option<String>,
+    pub host: Option<String>,
+    pub by: Option<FuzzNode>,
+}
+
+impl FuzzForwardedElement {
+    fn render(&self) -> String {
+        let mut pairs = Vec::new();
+
+        if let Some(node) = &self.node {
+            pairs.push(format!("for={}", node.render(true)));
+        }
+
+        if let Some(proto) = &self.proto {
+            pairs.push(format!("proto={proto}"));
+        }
+
+        if let Some(host) = &self.host {
+            pairs.push(format!("host={host}"));
+        }
+
+        if let Some(by) = &self.by {
+            pairs.push(format!("by={}", by.render(true)));
+        }
+
+        pairs.join(";")
+    }
+}
+
+/// A request with a synthetic chain of forwarding headers
+///
+/// Each inner vector is a header value, made of comma separated elements.
+#[derive(Debug, Clone)]
+pub struct FuzzRequest {
+    pub peer: IpAddr,
+    pub host: Option<String>,
+    pub scheme: Option<String>,
+    pub forwarded: Vec<String>,
+    pub x_forwarded_for: Vec<String>,
+    pub x_forwarded_host: Vec<String>,
+    pub x_forwarded_proto: Vec<String>,
+    pub x_forwarded_by: Vec<String>,
+}
+
+impl<'a> Arbitrary<'a> for FuzzRequest {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let forwarded = Vec::<Vec<FuzzForwardedElement>>::arbitrary(u)?
+            .iter()
+            .map(|elements| {
+                elements
+                    .iter()
+                    .map(FuzzForwardedElement::render)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            })
+            .collect();
+        let x_forwarded_for = Vec::<Vec<FuzzNode>>::arbitrary(u)?
+            .iter()
+            .map(|nodes| {
+                nodes
+                    .iter()
+                    .map(|node| node.render(false))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            })
+            .collect();
+
+        Ok(Self {
+            peer: u.arbitrary()?,
+            host: u.arbitrary()?,
+            scheme: u.arbitrary()?,
+            forwarded,
+            x_forwarded_for,
+            x_forwarded_host: u.arbitrary()?,
+            x_forwarded_proto: u.arbitrary()?,
+            x_forwarded_by: u.arbitrary()?,
+        })
+    }
+}
+
+impl RequestInformation for FuzzRequest {
+    fn is_host_header_allowed(&self) -> bool {
+        true
+    }
+
+    fn host_header(&self) -> Option<&str> {
+        self.host.as_deref()
+    }
+
+    fn authority(&self) -> Option<&str> {
+        None
+    }
+
+    fn forwarded(&self) -> impl DoubleEndedIterator<Item = &str> {
+        self.forwarded.iter().map(String::as_str)
+    }
+
+    fn x_forwarded_for(&self) -> impl DoubleEndedIterator<Item = &str> {
+        self.x_forwarded_for.iter().map(String::as_str)
+    }
+
+    fn x_forwarded_host(&self) -> impl DoubleEndedIterator<Item = &str> {
+        self.x_forwarded_host.iter().map(String::as_str)
+    }
+
+    fn x_forwarded_proto(&self) -> impl DoubleEndedIterator<Item = &str> {
+        self.x_forwarded_proto.iter().map(String::as_str)
+    }
+
+    fn x_forwarded_by(&self) -> impl DoubleEndedIterator<Item = &str> {
+        self.x_forwarded_by.iter().map(String::as_str)
+    }
+
+    fn default_scheme(&self) -> Option<&str> {
+        self.scheme.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Trusted;
+
+    #[test]
+    fn render_nodes() {
+        let ip = "2001:db8::1".parse().unwrap();
+
+        assert_eq!(FuzzNode::Ip(ip).render(true), "\"[2001:db8::1]\"");
+        assert_eq!(FuzzNode::Ip(ip).render(false), "2001:db8::1");
+        assert_eq!(
+            FuzzNode::IpWithPort(ip, 8080).render(false),
+            "[2001:db8::1]:8080"
+        );
+        assert_eq!(
+            FuzzNode::IpWithPort(IpAddr::from([1, 2, 3, 4]), 8080).render(true),
+            "1.2.3.4:8080"
+        );
+
+        let element = FuzzForwardedElement {
+            node: Some(FuzzNode::IpWithPort(ip, 443)),
+            proto: Some("https".to_string()),
+            host: None,
+            by: Some(FuzzNode::Obfuscated("proxy".to_string())),
+        };
+        assert_eq!(
+            element.render(),
+            "for=\"[2001:db8::1]:443\";proto=https;by=_proxy"
+        );
+    }
+
+    #[test]
+    fn untrusted_peer_is_client() {
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let mut untrusted = 0;
+
+        for _ in 0..256 {
+            let data = (0..1024)
+                .map(|_| {
+                    state ^= state << 13;
+                    state ^= state >> 7;
+                    state ^= state << 17;
+                    state as u8
+                })
+                .collect::<Vec<_>>();
+            let mut u = Unstructured::new(&data);
+
+            let (Ok(config), Ok(request)) =
+                (Config::arbitrary(&mut u), FuzzRequest::arbitrary(&mut u))
+            else {
+                continue;
+            };
+            let trusted = Trusted::from(request.peer, &request, &config);
+
+            if !config.is_ip_trusted(&request.peer) {
+                assert_eq!(trusted.ip(), request.peer);
+                untrusted += 1;
+            }
+        }
+
+        assert!(untrusted > 0);
+    }
+}
@@ -0,0 +1,35 @@
+use crate::Config;
+use std::sync::OnceLock;
+
+static GLOBAL_CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// Set the process-wide configuration used by [`crate::Trusted::from_global`]
+///
+/// The configuration can only be set once, and must be set before its first use: the default
+/// configuration is used and kept otherwise. The given configuration is returned as an error
+/// when a configuration is already set.
+///
+/// # Example
+/// ```
+/// use trusted_proxies::{set_global_config, Config, Trusted};
+///
+/// let mut config = Config::new_local();
+/// config.trust_x_forwarded_proto();
+/// set_global_config(config).unwrap();
+///
+/// let mut request = http::Request::get("/").body(()).unwrap();
+/// request.headers_mut().insert("x-forwarded-proto", "https".parse().unwrap());
+///
+/// let trusted = Trusted::from_global(core::net::IpAddr::from([127, 0, 0, 1]), &request);
+/// assert_eq!(trusted.scheme(), Some("https"));
+/// ```
+pub fn set_global_config(config: Config) -> Result<(), Box<Config>> {
+    GLOBAL_CONFIG.set(config).map_err(Box::new)
+}
+
+/// Get the process-wide configuration, see [`set_global_config`]
+///
+/// When no configuration has been set, [`Config::default`] is used from now on.
+pub fn global_config() -> &'static Config {
+    GLOBAL_CONFIG.get_or_init(Config::default)
+}
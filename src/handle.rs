@@ -0,0 +1,42 @@
+use crate::Config;
+use std::sync::{Arc, RwLock};
+
+/// A shared configuration that can be replaced while requests are being extracted
+///
+/// Cloning the handle shares the same configuration. Readers get a snapshot with
+/// [`ConfigHandle::load`], which stays valid and unchanged when a new configuration is stored.
+///
+/// # Example
+/// ```
+/// use trusted_proxies::{Config, ConfigHandle, Trusted};
+///
+/// let handle = ConfigHandle::new(Config::new());
+/// let mut request = http::Request::get("/").body(()).unwrap();
+/// request.headers_mut().insert("x-forwarded-for", "1.2.3.4".parse().unwrap());
+///
+/// handle.store(Config::new_local());
+///
+/// let config = handle.load();
+/// let trusted = Trusted::from(core::net::IpAddr::from([127, 0, 0, 1]), &request, &*config);
+/// assert_eq!(trusted.ip(), core::net::IpAddr::from([1, 2, 3, 4]));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ConfigHandle(Arc<RwLock<Arc<Config>>>);
+
+impl ConfigHandle {
+    /// Create a new handle sharing the given configuration
+    pub fn new(config: Config) -> Self {
+        Self(Arc::new(RwLock::new(Arc::new(config))))
+    }
+
+    /// Get a snapshot of the current configuration
+    pub fn load(&self) -> Arc<Config> {
+        // the lock only guards a pointer swap, a poisoned lock still holds a valid configuration
+        self.0.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Replace the configuration, snapshots already loaded are left unchanged
+    pub fn store(&self, config: Config) {
+        *self.0.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(config);
+    }
+}
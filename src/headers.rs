@@ -0,0 +1,123 @@
+use crate::RequestInformation;
+
+/// Custom names of the forwarding headers, see [`crate::Config::set_forwarded_for_header`]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub(crate) struct HeaderNames {
+    pub(crate) forwarded: Option<Box<str>>,
+    pub(crate) x_forwarded_for: Option<Box<str>>,
+    pub(crate) x_forwarded_host: Option<Box<str>>,
+    pub(crate) x_forwarded_proto: Option<Box<str>>,
+    pub(crate) x_forwarded_by: Option<Box<str>>,
+    pub(crate) signed_client_ip: Option<Box<str>>,
+    pub(crate) signed_client_ip_signature: Option<Box<str>>,
+}
+
+impl HeaderNames {
+    /// Take the custom names set in another configuration
+    pub(crate) fn overlay(&mut self, other: &Self) {
+        for (name, other) in [
+            (&mut self.forwarded, &other.forwarded),
+            (&mut self.x_forwarded_for, &other.x_forwarded_for),
+            (&mut self.x_forwarded_host, &other.x_forwarded_host),
+            (&mut self.x_forwarded_proto, &other.x_forwarded_proto),
+            (&mut self.x_forwarded_by, &other.x_forwarded_by),
+            (&mut self.signed_client_ip, &other.signed_client_ip),
+            (
+                &mut self.signed_client_ip_signature,
+                &other.signed_client_ip_signature,
+            ),
+        ] {
+            if other.is_some() {
+                name.clone_from(other);
+            }
+        }
+    }
+
+    /// Name of the header giving a signed client address, see
+    /// [`crate::Config::set_signed_client_ip_headers`]
+    #[cfg(feature = "hmac")]
+    pub(crate) fn signed_client_ip(&self) -> &str {
+        self.signed_client_ip.as_deref().unwrap_or("x-client-ip")
+    }
+
+    /// Name of the header giving the signature of the client address
+    #[cfg(feature = "hmac")]
+    pub(crate) fn signed_client_ip_signature(&self) -> &str {
+        self.signed_client_ip_signature
+            .as_deref()
+            .unwrap_or("x-client-ip-sig")
+    }
+}
+
+/// The values of either a standard or a custom header
+enum Values<S, C> {
+    Standard(S),
+    Custom(C),
+}
+
+impl<'a, S, C> Iterator for Values<S, C>
+where
+    S: Iterator<Item = &'a str>,
+    C: Iterator<Item = &'a str>,
+{
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        match self {
+            Self::Standard(values) => values.next(),
+            Self::Custom(values) => values.next(),
+        }
+    }
+}
+
+impl<'a, S, C> DoubleEndedIterator for Values<S, C>
+where
+    S: DoubleEndedIterator<Item = &'a str>,
+    C: DoubleEndedIterator<Item = &'a str>,
+{
+    fn next_back(&mut self) -> Option<&'a str> {
+        match self {
+            Self::Standard(values) => values.next_back(),
+            Self::Custom(values) => values.next_back(),
+        }
+    }
+}
+
+/// The forwarding headers of a request, read under their custom names when configured
+///
+/// The values borrow the request for `'a`, the iterators also borrow the names for `'n`.
+pub(crate) struct Headers<'a, 'n, T: ?Sized> {
+    request: &'a T,
+    names: &'n HeaderNames,
+}
+
+impl<T: ?Sized> Clone for Headers<'_, '_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: ?Sized> Copy for Headers<'_, '_, T> {}
+
+macro_rules! header {
+    ($name:ident) => {
+        pub(crate) fn $name(self) -> impl DoubleEndedIterator<Item = &'a str> + 'n {
+            match self.names.$name.as_deref() {
+                Some(name) => Values::Custom(self.request.header(name)),
+                None => Values::Standard(self.request.$name()),
+            }
+        }
+    };
+}
+
+impl<'a: 'n, 'n, T: RequestInformation + ?Sized> Headers<'a, 'n, T> {
+    pub(crate) fn new(request: &'a T, names: &'n HeaderNames) -> Self {
+        Self { request, names }
+    }
+
+    header!(forwarded);
+    header!(x_forwarded_for);
+    header!(x_forwarded_host);
+    header!(x_forwarded_proto);
+    header!(x_forwarded_by);
+}
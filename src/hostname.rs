@@ -0,0 +1,146 @@
+use crate::ConfigError;
+use core::net::IpAddr;
+use std::net::ToSocketAddrs;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// A trusted hostname with the addresses it last resolved to
+#[derive(Debug)]
+struct TrustedHostname {
+    hostname: String,
+    addresses: RwLock<Vec<IpAddr>>,
+}
+
+impl TrustedHostname {
+    fn resolve(&self) -> Result<(), ConfigError> {
+        let unresolved = |message: String| ConfigError::UnresolvedHostname {
+            hostname: self.hostname.clone(),
+            message,
+        };
+        let mut addresses = (self.hostname.as_str(), 0)
+            .to_socket_addrs()
+            .map_err(|err| unresolved(err.to_string()))?
+            .map(|addr| addr.ip())
+            .collect::<Vec<_>>();
+
+        if addresses.is_empty() {
+            return Err(unresolved("no address".to_string()));
+        }
+
+        addresses.sort_unstable();
+        addresses.dedup();
+
+        *self.addresses.write().unwrap_or_else(|e| e.into_inner()) = addresses;
+
+        Ok(())
+    }
+}
+
+/// The trusted hostnames of a configuration
+///
+/// The resolved addresses are shared between clones of the configuration, so resolving the
+/// hostnames of one of them updates all of them.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TrustedHostnames(Vec<Arc<TrustedHostname>>);
+
+impl TrustedHostnames {
+    /// Resolve a hostname and add it, it is not added when it cannot be resolved
+    pub(crate) fn add(&mut self, hostname: &str) -> Result<(), ConfigError> {
+        let hostname = TrustedHostname {
+            hostname: hostname.to_string(),
+            addresses: RwLock::new(Vec::new()),
+        };
+
+        hostname.resolve()?;
+        self.0.push(Arc::new(hostname));
+
+        Ok(())
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub(crate) fn names(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().map(|hostname| hostname.hostname.as_str())
+    }
+
+    /// Add the hostnames of another configuration, sharing their resolved addresses
+    pub(crate) fn extend(&mut self, other: &Self) {
+        for hostname in &other.0 {
+            if !self.0.iter().any(|known| Arc::ptr_eq(known, hostname)) {
+                self.0.push(hostname.clone());
+            }
+        }
+    }
+
+    /// Resolve every hostname again, a hostname that cannot be resolved keeps its addresses
+    pub(crate) fn resolve(&self) -> Result<(), ConfigError> {
+        let errors = self
+            .0
+            .iter()
+            .filter_map(|hostname| hostname.resolve().err())
+            .collect();
+
+        match ConfigError::from_errors(errors) {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+
+    pub(crate) fn contains(&self, remote_addr: &IpAddr) -> bool {
+        self.0.iter().any(|hostname| {
+            hostname
+                .addresses
+                .read()
+                .unwrap_or_else(|e| e.into_inner())
+                .binary_search(remote_addr)
+                .is_ok()
+        })
+    }
+
+    /// Resolve the hostnames on an interval in a background thread
+    pub(crate) fn refresh(&self, interval: Duration) -> HostnameRefresher {
+        let hostnames = self.clone();
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = stop.clone();
+        let thread = std::thread::spawn(move || loop {
+            std::thread::park_timeout(interval);
+
+            if stopped.load(Ordering::Relaxed) {
+                break;
+            }
+
+            // a failed resolution keeps the previous addresses, it is retried on the next tick
+            let _ = hostnames.resolve();
+        });
+
+        HostnameRefresher {
+            stop,
+            thread: Some(thread),
+        }
+    }
+}
+
+/// Resolves the trusted hostnames of a configuration in a background thread, see
+/// [`crate::Config::refresh_trusted_hostnames`]
+///
+/// Resolving stops when it is dropped.
+#[derive(Debug)]
+pub struct HostnameRefresher {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for HostnameRefresher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+
+        if let Some(thread) = self.thread.take() {
+            thread.thread().unpark();
+            let _ = thread.join();
+        }
+    }
+}
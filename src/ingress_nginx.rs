@@ -0,0 +1,82 @@
+//! Configuration of a service behind the Kubernetes ingress-nginx controller
+//!
+//! The forwarding headers received by the service depend on three settings of the controller
+//! ConfigMap:
+//!
+//!  * `use-forwarded-headers`: the controller trusts the `X-Forwarded-*` headers sent by the
+//!    addresses of `proxy-real-ip-cidr` and passes them on, otherwise it sets its own values.
+//!  * `proxy-real-ip-cidr`: the addresses trusted by the controller, `0.0.0.0/0` by default.
+//!  * `compute-full-forwarded-for`: the controller appends its peer to the received
+//!    `X-Forwarded-For`, otherwise it only sends the client address it resolved.
+//!
+//! [`IngressNginx::to_config`] produces the [`Config`] resolving the same client as the
+//! controller.
+//!
+//! # Example
+//! ```
+//! use trusted_proxies::ingress_nginx::IngressNginx;
+//!
+//! let settings = IngressNginx {
+//!     use_forwarded_headers: true,
+//!     proxy_real_ip_cidr: "173.245.48.0/20".to_string(),
+//!     compute_full_forwarded_for: true,
+//! };
+//! let config = settings.to_config(&["10.244.0.0/16"]).unwrap();
+//!
+//! assert!(config.is_ip_trusted(&core::net::IpAddr::from([173, 245, 48, 1])));
+//! ```
+
+use crate::{Config, ConfigError};
+
+/// The forwarding settings of an ingress-nginx controller
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IngressNginx {
+    /// The `use-forwarded-headers` setting
+    pub use_forwarded_headers: bool,
+    /// The `proxy-real-ip-cidr` setting, a comma separated list of networks
+    pub proxy_real_ip_cidr: String,
+    /// The `compute-full-forwarded-for` setting
+    pub compute_full_forwarded_for: bool,
+}
+
+impl Default for IngressNginx {
+    /// The default settings of the controller
+    fn default() -> Self {
+        Self {
+            use_forwarded_headers: false,
+            proxy_real_ip_cidr: "0.0.0.0/0".to_string(),
+            compute_full_forwarded_for: false,
+        }
+    }
+}
+
+impl IngressNginx {
+    /// Create the configuration of a service receiving requests from the controller pods at the
+    /// given addresses
+    ///
+    /// The controller always sets `X-Forwarded-For`, `X-Forwarded-Host` and
+    /// `X-Forwarded-Proto`, they are trusted from the controller pods. When the controller passes
+    /// the whole chain, the networks of `proxy-real-ip-cidr` are trusted too, so the walk goes
+    /// past the same hops as the controller.
+    pub fn to_config(&self, controller_ips: &[&str]) -> Result<Config, ConfigError> {
+        let mut config = Config::new();
+
+        for ip in controller_ips {
+            config.add_trusted_ip(ip)?;
+        }
+
+        if self.use_forwarded_headers && self.compute_full_forwarded_for {
+            for network in self.proxy_real_ip_cidr.split(',').map(str::trim) {
+                if !network.is_empty() {
+                    config.add_trusted_ip(network)?;
+                }
+            }
+        }
+
+        config.trust_x_forwarded_for();
+        config.trust_x_forwarded_host();
+        config.trust_x_forwarded_proto();
+
+        Ok(config)
+    }
+}
@@ -0,0 +1,108 @@
+use crate::Config;
+use if_addrs::IfAddr;
+use ipnet::IpNet;
+use std::io;
+
+/// Get the subnet of an address of a network interface
+fn subnet(addr: &IfAddr) -> Option<IpNet> {
+    let network = match addr {
+        IfAddr::V4(addr) => IpNet::new(addr.ip.into(), addr.prefixlen),
+        IfAddr::V6(addr) => IpNet::new(addr.ip.into(), addr.prefixlen),
+    };
+
+    network.ok().map(|network| network.trunc())
+}
+
+impl Config {
+    /// Create a configuration trusting the subnets of the network interfaces of this host
+    ///
+    /// This is what "trust my LAN" usually means in on-premises deployments: the proxies are on
+    /// the same networks as this host. Like [`Config::new_local`], the `Forwarded` and
+    /// `X-Forwarded-For` headers are trusted.
+    ///
+    /// Every subnet is trusted, including the one of an interface with a public address, use
+    /// [`Config::remove_trusted_ip`] to drop it. Interfaces added later are not trusted, see
+    /// [`Config::add_local_interfaces`].
+    ///
+    /// # Example
+    /// ```
+    /// use trusted_proxies::Config;
+    ///
+    /// let config = Config::from_local_interfaces().unwrap();
+    ///
+    /// for network in config.trusted_ips() {
+    ///     println!("trusting {network}");
+    /// }
+    /// ```
+    pub fn from_local_interfaces() -> io::Result<Self> {
+        let mut config = Self::new();
+
+        config.add_local_interfaces()?;
+        config.trust_forwarded();
+        config.trust_x_forwarded_for();
+
+        Ok(config)
+    }
+
+    /// Add the subnets of the network interfaces of this host to the trusted proxies, see
+    /// [`Config::from_local_interfaces`]
+    pub fn add_local_interfaces(&mut self) -> io::Result<()> {
+        let mut networks = Vec::new();
+
+        for interface in if_addrs::get_if_addrs()? {
+            if let Some(network) = subnet(&interface.addr) {
+                if !networks.contains(&network) && !self.trusted_ips().any(|n| *n == network) {
+                    networks.push(network);
+                }
+            }
+        }
+
+        self.extend_trusted_ips(networks);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use if_addrs::{Ifv4Addr, Ifv6Addr};
+
+    #[test]
+    fn subnets() {
+        let v4 = IfAddr::V4(Ifv4Addr {
+            ip: [192, 168, 1, 12].into(),
+            netmask: [255, 255, 255, 0].into(),
+            prefixlen: 24,
+            broadcast: None,
+        });
+        let v6 = IfAddr::V6(Ifv6Addr {
+            ip: "fd00::12".parse().unwrap(),
+            netmask: "ffff:ffff:ffff:ffff::".parse().unwrap(),
+            prefixlen: 64,
+            broadcast: None,
+        });
+        let invalid = IfAddr::V4(Ifv4Addr {
+            ip: [192, 168, 1, 12].into(),
+            netmask: [255, 255, 255, 255].into(),
+            prefixlen: 33,
+            broadcast: None,
+        });
+
+        assert_eq!(subnet(&v4), Some("192.168.1.0/24".parse().unwrap()));
+        assert_eq!(subnet(&v6), Some("fd00::/64".parse().unwrap()));
+        assert_eq!(subnet(&invalid), None);
+    }
+
+    #[test]
+    fn local_interfaces() {
+        let interfaces = if_addrs::get_if_addrs().unwrap();
+        let config = Config::from_local_interfaces().unwrap();
+
+        for interface in &interfaces {
+            assert!(config.is_ip_trusted(&interface.ip()), "{}", interface.name);
+        }
+
+        assert!(config.is_forwarded_trusted() && config.is_x_forwarded_for_trusted());
+    }
+}
@@ -27,15 +27,177 @@
 //!  * Fall back to the `X-Forwarded-For` header if the `Forwarded` header is not present or not trusted.
 //!  * Can extract information from the `X-Forwarded-Host` / `X-Forwarded-Proto` / `X-Forwarded-By` headers if they are trusted.
 //!
+//! ## Cargo features
+//!
+//!  * `http` (default): implement [`RequestInformation`] for the [`http`](https://docs.rs/http) request types, and
+//!    add the [`upstream`] module.
+//!  * `access-log` (default): add the [`access_log`] module, rendering access log lines with the trusted client
+//!    information.
+//!  * `envoy` (default): add the [`envoy`] module, converting configurations from and to the Envoy settings.
+//!  * `ingress-nginx` (default): add the [`ingress_nginx`] module, configuring a service behind the Kubernetes
+//!    ingress-nginx controller.
+//!  * `metrics`: emit counters and histograms through the [`metrics`](https://docs.rs/metrics) facade during the
+//!    extraction (`trusted_proxies_requests_total`, `trusted_proxies_spoof_attempts_total`,
+//!    `trusted_proxies_chain_depth`, `trusted_proxies_parse_failures_total`), and when comparing configurations
+//!    with [`ShadowEvaluation`] (`trusted_proxies_shadow_evaluations_total`,
+//!    `trusted_proxies_shadow_differences_total`).
+//!  * `hmac`: verify client addresses signed by an edge proxy with a shared key, see
+//!    [`Config::trust_signed_client_ip`].
+//!  * `arbitrary`: implement [`arbitrary::Arbitrary`](https://docs.rs/arbitrary) for [`Config`] and add the
+//!    [`fuzz`] module, generating synthetic forwarding chains for structure-aware fuzzing.
+//!  * `aws`: add the [`aws`] module, with presets for services behind CloudFront and Application Load Balancers
+//!    from the published AWS ranges.
+//!  * `axum`: add the [`axum`] module, with extractors for [`axum`](https://docs.rs/axum) handlers.
+//!  * `fetch`: add the [`fetch`] module, loading trusted networks from a remote list and publishing new
+//!    configurations to a [`ConfigHandle`] when it changes.
+//!  * `fetch-async`: add async functions to the [`fetch`] module, downloading the official ranges of CDNs and
+//!    cloud providers with [`reqwest`](https://docs.rs/reqwest).
+//!  * `refresh`: add the [`refresh`] module, periodically reloading remote lists and files of trusted networks
+//!    on a [`tokio`](https://docs.rs/tokio) interval.
+//!  * `file`: add the [`file`] module and [`Config::from_file`], loading a configuration from a TOML, YAML or
+//!    JSON file.
+//!  * `maxmind`: add the [`maxmind`] module, flagging anonymous client addresses with the MaxMind GeoIP2 Anonymous IP
+//!    database, see [`Trusted::is_anonymous_proxy`].
+//!  * `tower`: add the [`tower`](mod@tower) module, a middleware recording the trusted client on the request span.
+//!  * `actix`: add the [`actix`] module, a middleware recording the trusted client on the request span, and
+//!    implement [`RequestInformation`] for the `actix-web` requests.
+//!  * `tungstenite`: add the [`tungstenite`](mod@tungstenite) module, attributing WebSocket connections from the
+//!    handshake request of [`tungstenite`](https://docs.rs/tungstenite) and `tokio-tungstenite`.
+//!  * `watch`: add the [`watch`] module, reloading trusted networks from a file when it changes.
+//!  * `local-interfaces`: add [`Config::from_local_interfaces`], trusting the subnets of the network interfaces of
+//!    this host with [`if-addrs`](https://docs.rs/if-addrs).
+//!  * `arc-swap`: add [`SharedConfig`], a configuration shared between threads and replaced without locking the
+//!    extraction, with [`arc-swap`](https://docs.rs/arc-swap).
+//!  * `capi`: add the [`capi`] module, a small C ABI to reuse the trust logic from non Rust servers, built as a
+//!    shared and a static library by the `trusted-proxies-capi` crate.
+//!  * `cli`: build the `trusted-proxies-check` binary, printing the information extracted from a request with a
+//!    given configuration, to validate a configuration before rolling it out.
+//!  * `debug-headers`: add the [`debug`] module, exposing the extracted information as response headers during
+//!    development.
+//!  * `wasi`: export the extraction as a WASI component, following the `wit/trusted-proxies.wit` interface.
+//!  * `proptest`: add the [`strategies`] module, [`proptest`](https://docs.rs/proptest) strategies generating valid
+//!    and near-valid forwarding headers, and invariant helpers to property-test a configuration.
+//!  * `testkit`: add the [`testkit`] module, a parser and runner for the fixture format used by the tests of this crate,
+//!    to validate other integrations against the same corpus.
+//!  * `tracing`: emit [`tracing`](https://docs.rs/tracing) events describing each step of the trust walk, at the
+//!    `trace` level for every element considered and at the `debug` level for every decision.
+//!  * `opentelemetry`: add the [`opentelemetry`](mod@opentelemetry) module, extracting the propagated
+//!    [`opentelemetry`](https://docs.rs/opentelemetry) context together with the trusted client attributes.
+//!
+//! With `default-features = false`, only the trust engine is built: the configuration, the extraction from any
+//! [`RequestInformation`] implementation and the vetted [`ranges`], with `ipnet` and `smallvec` as the only
+//! dependencies. Every other feature is purely additive.
+//!
 //! ## Implementation
 //!
 //! This crate try to follow the [RFC 7239](https://tools.ietf.org/html/rfc7239) specifications but may differ on real
 //! world usage.
 
+#[cfg(feature = "access-log")]
+pub mod access_log;
+#[cfg(feature = "actix")]
+pub mod actix;
+mod audit;
+#[cfg(feature = "aws")]
+pub mod aws;
+#[cfg(feature = "axum")]
+pub mod axum;
+pub mod bogon;
+mod builder;
+#[cfg(feature = "capi")]
+pub mod capi;
 mod config;
+mod connection;
+#[cfg(feature = "debug-headers")]
+pub mod debug;
+mod diagnostic;
+mod dual;
+mod env;
+#[cfg(feature = "envoy")]
+pub mod envoy;
+mod error;
 mod extract;
+mod extractor;
+#[cfg(any(feature = "fetch", feature = "fetch-async"))]
+pub mod fetch;
+#[cfg(feature = "file")]
+pub mod file;
+#[cfg(feature = "arbitrary")]
+pub mod fuzz;
+mod global;
+mod handle;
+mod headers;
+mod hostname;
+#[cfg(feature = "ingress-nginx")]
+pub mod ingress_nginx;
+#[cfg(feature = "local-interfaces")]
+mod interfaces;
+pub mod lint;
+mod listener;
+mod matcher;
+#[cfg(feature = "maxmind")]
+pub mod maxmind;
+mod normalizer;
+#[cfg(feature = "opentelemetry")]
+pub mod opentelemetry;
+mod policy;
+pub mod ranges;
+#[cfg(feature = "refresh")]
+pub mod refresh;
+mod reputation;
+mod resolver;
+mod shadow;
+#[cfg(feature = "arc-swap")]
+mod shared;
+#[cfg(feature = "hmac")]
+mod signed;
+mod spoof;
+#[cfg(feature = "proptest")]
+pub mod strategies;
+mod telemetry;
+pub mod template;
+#[cfg(feature = "testkit")]
+pub mod testkit;
+#[cfg(feature = "tower")]
+pub mod tower;
 mod trusted;
+#[cfg(feature = "tungstenite")]
+pub mod tungstenite;
+#[cfg(feature = "http")]
+pub mod upstream;
+mod warning;
+#[cfg(feature = "wasi")]
+mod wasi;
+#[cfg(feature = "watch")]
+pub mod watch;
 
-pub use config::Config;
-pub use extract::RequestInformation;
+pub use audit::{IgnoreReason, IgnoredHeader, IpSource};
+pub use builder::ConfigBuilder;
+pub use config::{
+    ClientIpHeaderPriority, ClientIpStrategy, Config, LongChain, MergeMode, PrivateClientIp,
+    ServerIdentity,
+};
+pub use connection::{ConnectionInformation, PeerTrust};
+pub use diagnostic::ConfigDiagnostic;
+pub use dual::{DualExtraction, DualExtractor};
+pub use env::EnvError;
+pub use error::ConfigError;
+pub use extract::{DynRequestInformation, RequestInformation};
+pub use extractor::{AzureClientIp, IpExtractor, TrueClientIp};
+pub use global::{global_config, set_global_config};
+pub use handle::ConfigHandle;
+pub use hostname::HostnameRefresher;
+pub use listener::ListenerConfigs;
+pub use matcher::TrustedMatcher;
+pub use normalizer::HostNormalizer;
+pub use policy::{AsyncTrustPolicy, TrustPolicy};
+pub use reputation::{AsyncReputationProvider, Reputation, ReputationProvider};
+pub use resolver::ConfigResolver;
+pub use shadow::{ShadowDifference, ShadowEvaluation};
+#[cfg(feature = "arc-swap")]
+pub use shared::{SharedConfig, SharedConfigGuard};
+#[cfg(feature = "hmac")]
+pub use signed::sign_client_ip;
+pub use spoof::SpoofEvent;
 pub use trusted::Trusted;
+pub use warning::Warning;
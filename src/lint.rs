@@ -0,0 +1,182 @@
+//! Audit the forwarding headers sent by upstream proxies
+//!
+//! The extraction is lenient and accepts common deviations from RFC 7239, [`lint`] reports them so
+//! gateway operators can fix what their proxies emit. Linting does not depend on a configuration:
+//! every forwarding header of the request is examined.
+//!
+//! # Example
+//! ```
+//! use trusted_proxies::lint::{lint, Lint};
+//!
+//! let mut request = http::Request::get("/").body(()).unwrap();
+//! request.headers_mut().insert(http::header::FORWARDED, "for=2001:db8::1;proto=https;proto=http".parse().unwrap());
+//!
+//! let lints = lint(&request);
+//!
+//! assert!(lints.contains(&Lint::UnquotedIpv6 { value: "2001:db8::1".to_string() }));
+//! assert!(lints.contains(&Lint::DuplicateParameter { element: "for=2001:db8::1;proto=https;proto=http".to_string(), parameter: "proto".to_string() }));
+//! ```
+
+use crate::RequestInformation;
+use core::fmt;
+use core::net::Ipv6Addr;
+
+/// A deviation found in the forwarding headers of a request
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Lint {
+    /// An IPv6 node of the `Forwarded` header is not quoted, e.g. `for=2001:db8::1` instead of
+    /// `for="[2001:db8::1]"`
+    UnquotedIpv6 { value: String },
+    /// A quoted IPv6 node of the `Forwarded` header has no square brackets, e.g.
+    /// `for="2001:db8::1"` instead of `for="[2001:db8::1]"`
+    MissingBrackets { value: String },
+    /// A parameter appears several times in the same `Forwarded` element
+    DuplicateParameter { element: String, parameter: String },
+    /// The request has both a `Forwarded` header and the given `X-Forwarded-*` headers, which
+    /// may disagree
+    MixedHeaderStyles { headers: Vec<&'static str> },
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnquotedIpv6 { value } => {
+                write!(f, "IPv6 node {value} must be quoted and bracketed")
+            }
+            Self::MissingBrackets { value } => {
+                write!(f, "IPv6 node {value} must be enclosed in square brackets")
+            }
+            Self::DuplicateParameter { element, parameter } => {
+                write!(f, "parameter {parameter} is repeated in element {element}")
+            }
+            Self::MixedHeaderStyles { headers } => write!(
+                f,
+                "forwarded header is sent along with {}",
+                headers.join(", ")
+            ),
+        }
+    }
+}
+
+/// Examine the forwarding headers of a request
+///
+/// Lints are returned in the order of the headers, `MixedHeaderStyles` coming last.
+pub fn lint<T: RequestInformation + ?Sized>(request: &T) -> Vec<Lint> {
+    let mut lints = Vec::new();
+    let mut has_forwarded = false;
+
+    for element in request.forwarded().flat_map(|value| value.split(',')) {
+        has_forwarded = true;
+
+        let element = element.trim();
+        let mut parameters = Vec::new();
+
+        for (key, value) in element
+            .split(';')
+            .filter_map(|pair| pair.split_once('='))
+            .map(|(key, value)| (key.trim().to_ascii_lowercase(), value.trim()))
+        {
+            if (key == "for" || key == "by") && value.matches(':').count() > 1 {
+                match value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+                    None => lints.push(Lint::UnquotedIpv6 {
+                        value: value.to_string(),
+                    }),
+                    Some(quoted) if quoted.parse::<Ipv6Addr>().is_ok() => {
+                        lints.push(Lint::MissingBrackets {
+                            value: quoted.to_string(),
+                        })
+                    }
+                    Some(_) => {}
+                }
+            }
+
+            if parameters.contains(&key) {
+                lints.push(Lint::DuplicateParameter {
+                    element: element.to_string(),
+                    parameter: key,
+                });
+            } else {
+                parameters.push(key);
+            }
+        }
+    }
+
+    if has_forwarded {
+        let headers = [
+            (
+                "x-forwarded-for",
+                request.x_forwarded_for().next().is_some(),
+            ),
+            (
+                "x-forwarded-host",
+                request.x_forwarded_host().next().is_some(),
+            ),
+            (
+                "x-forwarded-proto",
+                request.x_forwarded_proto().next().is_some(),
+            ),
+            ("x-forwarded-by", request.x_forwarded_by().next().is_some()),
+        ]
+        .into_iter()
+        .filter_map(|(name, present)| present.then_some(name))
+        .collect::<Vec<_>>();
+
+        if !headers.is_empty() {
+            lints.push(Lint::MixedHeaderStyles { headers });
+        }
+    }
+
+    lints
+}
+
+#[cfg(all(test, feature = "http"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn forwarded_lints() {
+        let mut request = http::Request::get("/").body(()).unwrap();
+        request.headers_mut().insert(
+            http::header::FORWARDED,
+            "for=\"[2001:db8::1]:4711\", for=\"2001:db8::2\";by=2001:db8::3, For=1.2.3.4;for=5.6.7.8"
+                .parse()
+                .unwrap(),
+        );
+        request
+            .headers_mut()
+            .insert("x-forwarded-for", "1.2.3.4".parse().unwrap());
+
+        assert_eq!(
+            lint(&request),
+            vec![
+                Lint::MissingBrackets {
+                    value: "2001:db8::2".to_string()
+                },
+                Lint::UnquotedIpv6 {
+                    value: "2001:db8::3".to_string()
+                },
+                Lint::DuplicateParameter {
+                    element: "For=1.2.3.4;for=5.6.7.8".to_string(),
+                    parameter: "for".to_string()
+                },
+                Lint::MixedHeaderStyles {
+                    headers: vec!["x-forwarded-for"]
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn clean_headers() {
+        let mut request = http::Request::get("/").body(()).unwrap();
+        request.headers_mut().insert(
+            http::header::FORWARDED,
+            "for=\"[2001:db8::1]\";proto=https, for=1.2.3.4"
+                .parse()
+                .unwrap(),
+        );
+
+        assert!(lint(&request).is_empty());
+    }
+}
@@ -0,0 +1,82 @@
+use crate::{Config, ConnectionInformation};
+use core::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::collections::HashMap;
+
+/// Select a [`Config`] given the local address a connection was accepted on, for processes
+/// serving several listeners
+///
+/// A listener bound to an unspecified address (`0.0.0.0` or `::`) matches connections accepted on
+/// any local address with the same port, the exact address is preferred. When no listener
+/// matches, the default configuration is used.
+///
+/// # Example
+/// ```
+/// use core::net::SocketAddr;
+/// use trusted_proxies::{Config, ListenerConfigs, Trusted};
+///
+/// let mut listeners = ListenerConfigs::new(Config::new());
+/// listeners.add_listener(SocketAddr::from(([0, 0, 0, 0], 8443)), Config::new_local());
+///
+/// let mut request = http::Request::get("/").body(()).unwrap();
+/// request.headers_mut().insert("x-forwarded-for", "1.2.3.4".parse().unwrap());
+///
+/// let peer = SocketAddr::from(([10, 0, 0, 1], 50000));
+/// let internal = (peer, SocketAddr::from(([10, 0, 0, 2], 8443)));
+/// let public = (peer, SocketAddr::from(([203, 0, 113, 2], 443)));
+///
+/// let trusted = Trusted::from_connection(&internal, &request, listeners.resolve_connection(&internal));
+/// assert_eq!(trusted.ip(), core::net::IpAddr::from([1, 2, 3, 4]));
+///
+/// let trusted = Trusted::from_connection(&public, &request, listeners.resolve_connection(&public));
+/// assert_eq!(trusted.ip(), core::net::IpAddr::from([10, 0, 0, 1]));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ListenerConfigs {
+    default: Config,
+    listeners: HashMap<SocketAddr, Config>,
+}
+
+impl ListenerConfigs {
+    /// Create a new mapping using the given configuration when no listener matches
+    pub fn new(default: Config) -> Self {
+        Self {
+            default,
+            listeners: HashMap::new(),
+        }
+    }
+
+    /// Use a configuration for the connections accepted on a local address
+    pub fn add_listener(&mut self, local_addr: SocketAddr, config: Config) {
+        self.listeners.insert(local_addr, config);
+    }
+
+    /// Get the configuration used when no listener matches
+    pub fn default_config(&self) -> &Config {
+        &self.default
+    }
+
+    /// Get the configuration for a local address
+    pub fn resolve(&self, local_addr: Option<SocketAddr>) -> &Config {
+        let Some(local_addr) = local_addr else {
+            return &self.default;
+        };
+
+        let unspecified = match local_addr.ip() {
+            IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+        };
+
+        self.listeners
+            .get(&local_addr)
+            .or_else(|| {
+                self.listeners
+                    .get(&SocketAddr::new(unspecified, local_addr.port()))
+            })
+            .unwrap_or(&self.default)
+    }
+
+    /// Get the configuration for a connection, see [`ConnectionInformation::local_addr`]
+    pub fn resolve_connection<C: ConnectionInformation + ?Sized>(&self, connection: &C) -> &Config {
+        self.resolve(connection.local_addr())
+    }
+}
@@ -0,0 +1,204 @@
+use crate::{Config, TrustPolicy};
+use core::fmt;
+use core::net::IpAddr;
+use ipnet::IpNet;
+use std::sync::OnceLock;
+
+/// An immutable and compiled version of a [`Config`], optimized for lookups
+///
+/// Trusted networks are stored as sorted and merged address ranges per family, so checking an
+/// address is a binary search instead of a scan of every network. It is `Send + Sync` and can
+/// be shared across threads (e.g. in an `Arc`) and given to [`crate::Trusted::from`].
+///
+/// # Example
+/// ```
+/// use trusted_proxies::{Config, Trusted};
+///
+/// let matcher = Config::new_local().compile();
+/// let request = http::Request::get("/").body(()).unwrap();
+///
+/// let trusted = Trusted::from(core::net::IpAddr::from([127, 0, 0, 1]), &request, &matcher);
+/// ```
+#[derive(Debug, Clone)]
+pub struct TrustedMatcher {
+    config: Config,
+    ranges: Ranges,
+}
+
+/// Trusted networks stored as sorted and merged address ranges per family
+#[derive(Debug, Clone)]
+pub(crate) struct Ranges {
+    v4: Box<[(u32, u32)]>,
+    v6: Box<[(u128, u128)]>,
+}
+
+/// Sort ranges and merge the overlapping or adjacent ones
+fn merge<T: Ord + Copy>(mut ranges: Vec<(T, T)>, is_next: impl Fn(T, T) -> bool) -> Box<[(T, T)]> {
+    ranges.sort_unstable();
+
+    let mut merged: Vec<(T, T)> = Vec::with_capacity(ranges.len());
+
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 || is_next(last.1, start) => {
+                last.1 = last.1.max(end);
+            }
+            _ => merged.push((start, end)),
+        }
+    }
+
+    merged.into_boxed_slice()
+}
+
+/// Remove sorted and non overlapping holes, all included in the range, from a range
+fn subtract<T: Ord + Copy>(
+    range: (T, T),
+    holes: &[(T, T)],
+    next: impl Fn(T) -> T,
+    previous: impl Fn(T) -> T,
+) -> Vec<(T, T)> {
+    let mut ranges = Vec::new();
+    let mut start = range.0;
+
+    for &(hole_start, hole_end) in holes {
+        if hole_start > start {
+            ranges.push((start, previous(hole_start)));
+        }
+
+        if hole_end == range.1 {
+            return ranges;
+        }
+
+        start = next(hole_end);
+    }
+
+    ranges.push((start, range.1));
+    ranges
+}
+
+/// Check if a value is in one of the sorted and non overlapping ranges
+fn contains<T: Ord + Copy>(ranges: &[(T, T)], value: T) -> bool {
+    let index = ranges.partition_point(|(start, _)| *start <= value);
+
+    index > 0 && value <= ranges[index - 1].1
+}
+
+impl Ranges {
+    /// Compile trusted networks, carving out the untrusted networks
+    pub(crate) fn new<'n>(trusted: impl Iterator<Item = &'n IpNet>, untrusted: &[IpNet]) -> Self {
+        let mut v4 = Vec::new();
+        let mut v6 = Vec::new();
+
+        for network in trusted {
+            // untrusted networks at least as specific are carved out of the trusted network
+            let holes = untrusted.iter().filter(|untrusted| {
+                untrusted.prefix_len() >= network.prefix_len()
+                    && network.contains(&untrusted.network())
+            });
+
+            match network {
+                IpNet::V4(net) => {
+                    let holes = holes.filter_map(|untrusted| match untrusted {
+                        IpNet::V4(hole) => Some((hole.network().into(), hole.broadcast().into())),
+                        IpNet::V6(_) => None,
+                    });
+
+                    v4.extend(subtract(
+                        (net.network().into(), net.broadcast().into()),
+                        &merge(holes.collect(), |end: u32, start| {
+                            end.checked_add(1) == Some(start)
+                        }),
+                        |value| value + 1,
+                        |value| value - 1,
+                    ));
+                }
+                IpNet::V6(net) => {
+                    let holes = holes.filter_map(|untrusted| match untrusted {
+                        IpNet::V6(hole) => Some((hole.network().into(), hole.broadcast().into())),
+                        IpNet::V4(_) => None,
+                    });
+
+                    v6.extend(subtract(
+                        (net.network().into(), net.broadcast().into()),
+                        &merge(holes.collect(), |end: u128, start| {
+                            end.checked_add(1) == Some(start)
+                        }),
+                        |value| value + 1,
+                        |value| value - 1,
+                    ));
+                }
+            }
+        }
+
+        Self {
+            v4: merge(v4, |end: u32, start| end.checked_add(1) == Some(start)),
+            v6: merge(v6, |end: u128, start| end.checked_add(1) == Some(start)),
+        }
+    }
+
+    /// Check if an address is in one of the ranges
+    pub(crate) fn contains(&self, remote_addr: &IpAddr) -> bool {
+        match remote_addr {
+            IpAddr::V4(ip) => contains(&self.v4, u32::from(*ip)),
+            IpAddr::V6(ip) => contains(&self.v6, u128::from(*ip)),
+        }
+    }
+}
+
+/// The ranges of a configuration, compiled on the first lookup and reset when its networks
+/// change
+#[derive(Clone, Default)]
+pub(crate) struct RangesCache(OnceLock<Ranges>);
+
+impl RangesCache {
+    pub(crate) fn get_or_init(&self, init: impl FnOnce() -> Ranges) -> &Ranges {
+        self.0.get_or_init(init)
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.0.take();
+    }
+}
+
+impl fmt::Debug for RangesCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RangesCache")
+            .field("compiled", &self.0.get().is_some())
+            .finish()
+    }
+}
+
+impl TrustedMatcher {
+    pub(crate) fn new(config: &Config) -> Self {
+        Self {
+            config: config.clone(),
+            ranges: Ranges::new(config.trusted_ips(), config.untrusted_networks()),
+        }
+    }
+
+    /// Get the configuration this matcher was compiled from
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Check if a remote address is trusted given the list of trusted proxies
+    pub fn is_ip_trusted(&self, remote_addr: &IpAddr) -> bool {
+        let remote_addr = &self.config.unmap(remote_addr);
+        let trusted = self.ranges.contains(remote_addr);
+
+        // proxies with an expiry and hostnames are checked on each lookup, they are not compiled
+        trusted
+            || self.config.is_expiring_ip_trusted(remote_addr)
+            || self.config.is_hostname_trusted(remote_addr)
+    }
+}
+
+impl TrustPolicy for TrustedMatcher {
+    fn config(&self) -> &Config {
+        &self.config
+    }
+
+    fn is_ip_trusted(&self, remote_addr: &IpAddr) -> bool {
+        TrustedMatcher::is_ip_trusted(self, remote_addr)
+    }
+}
@@ -0,0 +1,158 @@
+//! Anonymous network detection with the MaxMind
+//! [GeoIP2 Anonymous IP](https://dev.maxmind.com/geoip/docs/databases/anonymous-ip) database
+//!
+//! Attribution is usually followed by a reputation check of the client address, an address
+//! flagged as a VPN, a Tor exit node or a hosting provider hides the real client.
+//!
+//! # Example
+//! ```no_run
+//! use trusted_proxies::maxmind::AnonymousIpReader;
+//! use trusted_proxies::{Config, Trusted};
+//!
+//! let reader = AnonymousIpReader::open("/var/lib/GeoIP/GeoIP2-Anonymous-IP.mmdb").unwrap();
+//!
+//! let request = http::Request::get("/").body(()).unwrap();
+//! let trusted = Trusted::from(core::net::IpAddr::from([1, 2, 3, 4]), &request, &Config::new_local());
+//!
+//! if trusted.is_anonymous_proxy(&reader) {
+//!     // require a captcha
+//! }
+//! ```
+
+use core::fmt;
+use core::net::IpAddr;
+use maxminddb::{geoip2, MaxMindDbError, Reader};
+use std::path::Path;
+
+/// A reader of the GeoIP2 Anonymous IP database
+pub struct AnonymousIpReader<S: AsRef<[u8]> = Vec<u8>> {
+    reader: Reader<S>,
+}
+
+impl AnonymousIpReader {
+    /// Read the database from a file
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, MaxMindDbError> {
+        Ok(Self {
+            reader: Reader::open_readfile(path)?,
+        })
+    }
+}
+
+impl<S: AsRef<[u8]>> AnonymousIpReader<S> {
+    /// Read the database from its content
+    pub fn from_source(source: S) -> Result<Self, MaxMindDbError> {
+        Ok(Self {
+            reader: Reader::from_source(source)?,
+        })
+    }
+
+    /// Get the flags of an address, `None` when the address is not in the database
+    pub fn lookup(&self, ip: IpAddr) -> Result<Option<AnonymousIp>, MaxMindDbError> {
+        let record = self.reader.lookup(ip)?.decode::<geoip2::AnonymousIp>()?;
+
+        Ok(record.map(|record| AnonymousIp {
+            is_anonymous: record.is_anonymous.unwrap_or(false),
+            is_anonymous_vpn: record.is_anonymous_vpn.unwrap_or(false),
+            is_hosting_provider: record.is_hosting_provider.unwrap_or(false),
+            is_public_proxy: record.is_public_proxy.unwrap_or(false),
+            is_residential_proxy: record.is_residential_proxy.unwrap_or(false),
+            is_tor_exit_node: record.is_tor_exit_node.unwrap_or(false),
+        }))
+    }
+}
+
+impl<S: AsRef<[u8]>> fmt::Debug for AnonymousIpReader<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AnonymousIpReader")
+            .field("metadata", self.reader.metadata())
+            .finish_non_exhaustive()
+    }
+}
+
+/// The flags of an address in the GeoIP2 Anonymous IP database
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AnonymousIp {
+    /// The address belongs to any sort of anonymous network
+    pub is_anonymous: bool,
+    /// The address is registered to an anonymous VPN provider
+    pub is_anonymous_vpn: bool,
+    /// The address belongs to a hosting or VPN provider
+    pub is_hosting_provider: bool,
+    /// The address belongs to a public proxy
+    pub is_public_proxy: bool,
+    /// The address belongs to a residential ISP and is suspected to be an anonymizing network
+    pub is_residential_proxy: bool,
+    /// The address is a Tor exit node
+    pub is_tor_exit_node: bool,
+}
+
+impl AnonymousIp {
+    /// Check if any flag is set
+    pub fn is_anonymous_proxy(&self) -> bool {
+        self.is_anonymous
+            || self.is_anonymous_vpn
+            || self.is_hosting_provider
+            || self.is_public_proxy
+            || self.is_residential_proxy
+            || self.is_tor_exit_node
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build an IPv4 database where `0.0.0.0/1` is a Tor exit node and `128.0.0.0/1` is absent
+    fn database() -> Vec<u8> {
+        let string = |value: &str| {
+            let mut bytes = vec![0x40 | value.len() as u8];
+            bytes.extend(value.as_bytes());
+            bytes
+        };
+
+        // a single node, the left record points to the first data, the right one to nothing
+        let mut database = vec![0, 0, 17, 0, 0, 1];
+        database.extend([0; 16]);
+
+        database.push(0xe2);
+        database.extend(string("is_anonymous"));
+        database.extend([0x01, 0x07]);
+        database.extend(string("is_tor_exit_node"));
+        database.extend([0x01, 0x07]);
+
+        database.extend(b"\xab\xcd\xefMaxMind.com");
+        database.push(0xe9);
+        database.extend(string("binary_format_major_version"));
+        database.extend([0xa1, 0x02]);
+        database.extend(string("binary_format_minor_version"));
+        database.push(0xa0);
+        database.extend(string("build_epoch"));
+        database.extend([0x00, 0x02]);
+        database.extend(string("database_type"));
+        database.extend(string("GeoIP2-Anonymous-IP"));
+        database.extend(string("description"));
+        database.push(0xe0);
+        database.extend(string("ip_version"));
+        database.extend([0xa1, 0x04]);
+        database.extend(string("languages"));
+        database.extend([0x00, 0x04]);
+        database.extend(string("node_count"));
+        database.extend([0xc1, 0x01]);
+        database.extend(string("record_size"));
+        database.extend([0xa1, 0x18]);
+
+        database
+    }
+
+    #[test]
+    fn lookup() {
+        let reader = AnonymousIpReader::from_source(database()).unwrap();
+
+        let flags = reader.lookup(IpAddr::from([1, 2, 3, 4])).unwrap().unwrap();
+        assert!(flags.is_tor_exit_node);
+        assert!(!flags.is_anonymous_vpn);
+        assert!(flags.is_anonymous_proxy());
+
+        assert_eq!(reader.lookup(IpAddr::from([200, 1, 1, 1])).unwrap(), None);
+    }
+}
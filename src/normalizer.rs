@@ -0,0 +1,60 @@
+use core::fmt;
+use std::borrow::Cow;
+use std::sync::Arc;
+
+/// Normalize or validate the trusted host before it is exposed, see
+/// [`crate::Config::set_host_normalizer`]
+///
+/// It is implemented for closures taking the host and the scheme and returning the normalized
+/// host.
+pub trait HostNormalizer: Send + Sync {
+    /// Get the normalized host, or `None` to reject it
+    ///
+    /// The host may contain a port. Returning a borrowed value avoids an allocation when the host
+    /// is left unchanged or only trimmed.
+    fn normalize<'h>(&self, host: &'h str, scheme: Option<&str>) -> Option<Cow<'h, str>>;
+}
+
+impl<F> HostNormalizer for F
+where
+    F: Fn(&str, Option<&str>) -> Option<String> + Send + Sync,
+{
+    fn normalize<'h>(&self, host: &'h str, scheme: Option<&str>) -> Option<Cow<'h, str>> {
+        self(host, scheme).map(|normalized| {
+            if normalized == host {
+                Cow::Borrowed(host)
+            } else {
+                Cow::Owned(normalized)
+            }
+        })
+    }
+}
+
+/// A shared host normalizer
+#[derive(Clone)]
+pub(crate) struct HostNormalizerHook(Arc<dyn HostNormalizer>);
+
+impl HostNormalizerHook {
+    /// Get the address of the shared hook, configurations compare hooks by identity
+    pub(crate) fn id(&self) -> *const () {
+        Arc::as_ptr(&self.0).cast()
+    }
+
+    pub(crate) fn new<N: HostNormalizer + 'static>(normalizer: N) -> Self {
+        Self(Arc::new(normalizer))
+    }
+
+    pub(crate) fn normalize<'h>(
+        &self,
+        host: &'h str,
+        scheme: Option<&str>,
+    ) -> Option<Cow<'h, str>> {
+        self.0.normalize(host, scheme)
+    }
+}
+
+impl fmt::Debug for HostNormalizerHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("HostNormalizer")
+    }
+}
@@ -0,0 +1,130 @@
+//! [`opentelemetry`](https://docs.rs/opentelemetry) propagation integration
+//!
+//! [`extract`] runs the propagator and the trust walk on the same request, and stores the
+//! attributes of the trusted client in the extracted context as [`ClientAttributes`], so the
+//! tracing middleware can add them to the server span without parsing the forwarding headers
+//! again.
+//!
+//! # Example
+//! ```
+//! use opentelemetry::propagation::TextMapCompositePropagator;
+//! use trusted_proxies::opentelemetry::{extract, ClientAttributes};
+//! use trusted_proxies::Config;
+//!
+//! let config = Config::new_local();
+//! let propagator = TextMapCompositePropagator::new(Vec::new());
+//! let mut request = http::Request::get("/").body(()).unwrap();
+//! request.headers_mut().insert("x-forwarded-for", "1.2.3.4".parse().unwrap());
+//!
+//! let (cx, trusted) = extract(&propagator, core::net::IpAddr::from([127, 0, 0, 1]), &request, &config);
+//! let attributes = cx.get::<ClientAttributes>().unwrap();
+//!
+//! assert_eq!(trusted.ip(), core::net::IpAddr::from([1, 2, 3, 4]));
+//! assert!(attributes.0.iter().any(|kv| kv.key.as_str() == "client.address" && kv.value.as_str() == "1.2.3.4"));
+//! ```
+
+use crate::{TrustPolicy, Trusted};
+use ::opentelemetry::propagation::TextMapPropagator;
+use ::opentelemetry::{Context, KeyValue};
+use core::net::IpAddr;
+use opentelemetry_http::HeaderExtractor;
+
+/// Attributes of the trusted client, stored in the context returned by [`extract`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClientAttributes(pub Vec<KeyValue>);
+
+/// Build the attributes describing the trusted client, following the semantic conventions
+///
+/// `client.address` and `network.peer.address` are always set, `server.address`,
+/// `server.port` and `url.scheme` when they are known. `trusted_proxies.ip_source` tells which
+/// header the client address comes from.
+pub fn attributes(trusted: &Trusted) -> Vec<KeyValue> {
+    let mut attributes = vec![
+        KeyValue::new("client.address", trusted.ip().to_string()),
+        KeyValue::new("network.peer.address", trusted.peer().to_string()),
+        KeyValue::new("trusted_proxies.ip_source", trusted.ip_source().as_str()),
+    ];
+
+    if let Some(host) = trusted.host() {
+        attributes.push(KeyValue::new("server.address", host.to_string()));
+    }
+
+    if let Some(port) = trusted.port() {
+        attributes.push(KeyValue::new("server.port", i64::from(port)));
+    }
+
+    if let Some(scheme) = trusted.scheme() {
+        attributes.push(KeyValue::new("url.scheme", scheme.to_string()));
+    }
+
+    attributes
+}
+
+/// Extract the propagated context and the trusted information from a request
+///
+/// The returned context is the one extracted by the propagator, with the [`ClientAttributes`]
+/// of the trusted client as a value.
+pub fn extract<'a, B, P>(
+    propagator: &dyn TextMapPropagator,
+    peer: IpAddr,
+    request: &'a http::Request<B>,
+    policy: &P,
+) -> (Context, Trusted<'a>)
+where
+    P: TrustPolicy + ?Sized,
+{
+    let trusted = Trusted::from(peer, request, policy);
+    let cx = propagator
+        .extract(&HeaderExtractor(request.headers()))
+        .with_value(ClientAttributes(attributes(&trusted)));
+
+    (cx, trusted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Config;
+    use ::opentelemetry::propagation::TextMapCompositePropagator;
+
+    #[test]
+    fn client_attributes() {
+        let config = Config::new_local();
+        let propagator = TextMapCompositePropagator::new(Vec::new());
+        let mut request = http::Request::get("/").body(()).unwrap();
+        request.headers_mut().insert(
+            http::header::FORWARDED,
+            "for=1.2.3.4;proto=https;host=example.com:8443"
+                .parse()
+                .unwrap(),
+        );
+
+        let (cx, trusted) = extract(&propagator, IpAddr::from([10, 0, 0, 1]), &request, &config);
+
+        assert_eq!(
+            attributes(&trusted),
+            [
+                KeyValue::new("client.address", "1.2.3.4"),
+                KeyValue::new("network.peer.address", "10.0.0.1"),
+                KeyValue::new("trusted_proxies.ip_source", "forwarded"),
+                KeyValue::new("server.address", "example.com"),
+                KeyValue::new("server.port", 8443),
+                KeyValue::new("url.scheme", "https"),
+            ]
+        );
+        assert_eq!(
+            cx.get::<ClientAttributes>(),
+            Some(&ClientAttributes(attributes(&trusted)))
+        );
+
+        let trusted = Trusted::from(IpAddr::from([8, 8, 8, 8]), &request, &config);
+        assert_eq!(
+            attributes(&trusted),
+            [
+                KeyValue::new("client.address", "8.8.8.8"),
+                KeyValue::new("network.peer.address", "8.8.8.8"),
+                KeyValue::new("trusted_proxies.ip_source", "peer"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,96 @@
+use crate::Config;
+use core::future::Future;
+use core::net::IpAddr;
+use std::sync::Arc;
+
+/// A trust policy used to extract trusted information from a request
+///
+/// It is implemented by [`Config`] and by its compiled version [`crate::TrustedMatcher`], so both
+/// can be given to [`crate::Trusted::from`].
+pub trait TrustPolicy {
+    /// Get the configuration holding the trusted headers
+    fn config(&self) -> &Config;
+
+    /// Check if a remote address is trusted
+    fn is_ip_trusted(&self, remote_addr: &IpAddr) -> bool;
+}
+
+impl TrustPolicy for Config {
+    fn config(&self) -> &Config {
+        self
+    }
+
+    fn is_ip_trusted(&self, remote_addr: &IpAddr) -> bool {
+        Config::is_ip_trusted(self, remote_addr)
+    }
+}
+
+impl<P: TrustPolicy + ?Sized> TrustPolicy for &P {
+    fn config(&self) -> &Config {
+        (**self).config()
+    }
+
+    fn is_ip_trusted(&self, remote_addr: &IpAddr) -> bool {
+        (**self).is_ip_trusted(remote_addr)
+    }
+}
+
+/// An asynchronous trust policy, used with [`crate::Trusted::from_async`]
+///
+/// It allows the trust decision to be backed by an external system (e.g. an allowlist stored in
+/// Redis), without blocking the extraction. Implementations can use an `async fn`.
+///
+/// # Example
+/// ```
+/// use core::future::Future;
+/// use core::net::IpAddr;
+/// use trusted_proxies::{AsyncTrustPolicy, Config};
+///
+/// struct RemoteAllowlist {
+///     config: Config,
+/// }
+///
+/// impl AsyncTrustPolicy for RemoteAllowlist {
+///     fn config(&self) -> &Config {
+///         &self.config
+///     }
+///
+///     async fn is_ip_trusted(&self, remote_addr: &IpAddr) -> bool {
+///         // query the allowlist
+///         remote_addr.is_loopback()
+///     }
+/// }
+/// ```
+pub trait AsyncTrustPolicy {
+    /// Get the configuration holding the trusted headers
+    fn config(&self) -> &Config;
+
+    /// Check if a remote address is trusted
+    fn is_ip_trusted(&self, remote_addr: &IpAddr) -> impl Future<Output = bool> + Send;
+}
+
+/// The trust decisions taken by an [`AsyncTrustPolicy`] for the addresses of a request
+pub(crate) struct ResolvedPolicy<'c> {
+    pub(crate) config: &'c Config,
+    pub(crate) trusted: Vec<IpAddr>,
+}
+
+impl TrustPolicy for ResolvedPolicy<'_> {
+    fn config(&self) -> &Config {
+        self.config
+    }
+
+    fn is_ip_trusted(&self, remote_addr: &IpAddr) -> bool {
+        self.trusted.contains(remote_addr)
+    }
+}
+
+impl<P: TrustPolicy + ?Sized> TrustPolicy for Arc<P> {
+    fn config(&self) -> &Config {
+        (**self).config()
+    }
+
+    fn is_ip_trusted(&self, remote_addr: &IpAddr) -> bool {
+        (**self).is_ip_trusted(remote_addr)
+    }
+}
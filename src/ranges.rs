@@ -0,0 +1,144 @@
+//! Vetted network ranges to compose configurations
+//!
+//! [`LOCAL`] is the set of networks trusted by [`crate::Config::new_local`], other groups can be
+//! added with [`crate::Config::add_trusted_ip`].
+//!
+//! # Example
+//! ```
+//! use trusted_proxies::{ranges, Config};
+//!
+//! let mut config = Config::new();
+//!
+//! for network in ranges::PRIVATE_V4.iter().chain(ranges::LOOPBACK_V4) {
+//!     config.add_trusted_ip(&network.to_string()).unwrap();
+//! }
+//!
+//! assert!(config.is_ip_trusted(&"192.168.1.1".parse().unwrap()));
+//! assert!(!config.is_ip_trusted(&"::1".parse().unwrap()));
+//! ```
+
+use core::net::{Ipv4Addr, Ipv6Addr};
+use ipnet::{IpNet, Ipv4Net, Ipv6Net};
+
+const fn v4(a: u8, b: u8, c: u8, d: u8, prefix_len: u8) -> Ipv4Net {
+    Ipv4Net::new_assert(Ipv4Addr::new(a, b, c, d), prefix_len)
+}
+
+const fn v6(first: u16, second: u16, prefix_len: u8) -> Ipv6Net {
+    Ipv6Net::new_assert(Ipv6Addr::new(first, second, 0, 0, 0, 0, 0, 0), prefix_len)
+}
+
+/// IPv4 loopback network, `127.0.0.0/8`
+pub const LOOPBACK_V4: &[Ipv4Net] = &[v4(127, 0, 0, 0, 8)];
+
+/// IPv4 private networks from RFC 1918
+pub const PRIVATE_V4: &[Ipv4Net] = &[
+    v4(10, 0, 0, 0, 8),
+    v4(172, 16, 0, 0, 12),
+    v4(192, 168, 0, 0, 16),
+];
+
+/// IPv4 shared address space used by carrier-grade NAT, `100.64.0.0/10`
+pub const SHARED_V4: &[Ipv4Net] = &[v4(100, 64, 0, 0, 10)];
+
+/// IPv4 link local network, `169.254.0.0/16`
+pub const LINK_LOCAL_V4: &[Ipv4Net] = &[v4(169, 254, 0, 0, 16)];
+
+/// IPv6 loopback address, `::1/128`
+pub const LOOPBACK_V6: &[Ipv6Net] = &[Ipv6Net::new_assert(Ipv6Addr::LOCALHOST, 128)];
+
+/// IPv6 locally assigned unique local network, `fd00::/8`
+///
+/// This is the half of `fc00::/7` (RFC 4193) used in practice, the other half is not assigned.
+pub const UNIQUE_LOCAL_V6: &[Ipv6Net] = &[v6(0xfd00, 0, 8)];
+
+/// IPv6 unique local network, `fc00::/7` (RFC 4193), including the unassigned half
+/// `fc00::/8` used by some container and cloud networks
+pub const UNIQUE_LOCAL_FULL_V6: &[Ipv6Net] = &[v6(0xfc00, 0, 7)];
+
+/// IPv6 link local network, `fe80::/10`
+pub const LINK_LOCAL_V6: &[Ipv6Net] = &[v6(0xfe80, 0, 10)];
+
+/// Tailscale IPv4 addresses, allocated from the shared address space `100.64.0.0/10`
+pub const TAILSCALE_V4: &[Ipv4Net] = SHARED_V4;
+
+/// Tailscale IPv6 addresses, `fd7a:115c:a1e0::/48`
+pub const TAILSCALE_V6: &[Ipv6Net] = &[Ipv6Net::new_assert(
+    Ipv6Addr::new(0xfd7a, 0x115c, 0xa1e0, 0, 0, 0, 0, 0),
+    48,
+)];
+
+/// Date the Cloudflare ranges, [`CLOUDFLARE_V4`] and [`CLOUDFLARE_V6`], were added to the crate
+///
+/// The ranges are the lists published by Cloudflare at <https://www.cloudflare.com/ips-v4> and
+/// <https://www.cloudflare.com/ips-v6>, which carry no revision number. They were transcribed
+/// from those lists, not downloaded, on this date: it is not a Cloudflare revision, and the
+/// embedded lists have not been compared with a download since. Compare them with the published
+/// lists, or download the current ones with the `fetch` feature, before relying on them.
+pub const CLOUDFLARE_VERSION: &str = "2026-10-15";
+
+/// Cloudflare IPv4 edge ranges, see [`CLOUDFLARE_VERSION`]
+pub const CLOUDFLARE_V4: &[Ipv4Net] = &[
+    v4(173, 245, 48, 0, 20),
+    v4(103, 21, 244, 0, 22),
+    v4(103, 22, 200, 0, 22),
+    v4(103, 31, 4, 0, 22),
+    v4(141, 101, 64, 0, 18),
+    v4(108, 162, 192, 0, 18),
+    v4(190, 93, 240, 0, 20),
+    v4(188, 114, 96, 0, 20),
+    v4(197, 234, 240, 0, 22),
+    v4(198, 41, 128, 0, 17),
+    v4(162, 158, 0, 0, 15),
+    v4(104, 16, 0, 0, 13),
+    v4(104, 24, 0, 0, 14),
+    v4(172, 64, 0, 0, 13),
+    v4(131, 0, 72, 0, 22),
+];
+
+/// Cloudflare IPv6 edge ranges, see [`CLOUDFLARE_VERSION`]
+pub const CLOUDFLARE_V6: &[Ipv6Net] = &[
+    v6(0x2400, 0xcb00, 32),
+    v6(0x2606, 0x4700, 32),
+    v6(0x2803, 0xf800, 32),
+    v6(0x2405, 0xb500, 32),
+    v6(0x2405, 0x8100, 32),
+    v6(0x2a06, 0x98c0, 29),
+    v6(0x2c0f, 0xf248, 32),
+];
+
+/// Azure Front Door IPv4 backend range, the addresses Front Door connects to origins from
+///
+/// This range is shared by every Front Door profile, check the `X-Azure-FDID` header to only
+/// accept the requests of your own profile.
+pub const AZURE_FRONT_DOOR_V4: &[Ipv4Net] = &[v4(147, 243, 0, 0, 16)];
+
+/// Azure Front Door IPv6 backend range, `2a01:111:2050::/44`, see [`AZURE_FRONT_DOOR_V4`]
+pub const AZURE_FRONT_DOOR_V6: &[Ipv6Net] = &[Ipv6Net::new_assert(
+    Ipv6Addr::new(0x2a01, 0x111, 0x2050, 0, 0, 0, 0, 0),
+    44,
+)];
+
+/// Loopback, private, shared, link local and unique local networks trusted by
+/// [`crate::Config::new_private_extended`]
+pub const PRIVATE_EXTENDED: &[IpNet] = &[
+    IpNet::V4(LOOPBACK_V4[0]),
+    IpNet::V4(PRIVATE_V4[0]),
+    IpNet::V4(PRIVATE_V4[1]),
+    IpNet::V4(PRIVATE_V4[2]),
+    IpNet::V4(SHARED_V4[0]),
+    IpNet::V4(LINK_LOCAL_V4[0]),
+    IpNet::V6(LOOPBACK_V6[0]),
+    IpNet::V6(UNIQUE_LOCAL_FULL_V6[0]),
+    IpNet::V6(LINK_LOCAL_V6[0]),
+];
+
+/// Loopback and private networks trusted by [`crate::Config::new_local`]
+pub const LOCAL: &[IpNet] = &[
+    IpNet::V4(LOOPBACK_V4[0]),
+    IpNet::V4(PRIVATE_V4[0]),
+    IpNet::V4(PRIVATE_V4[1]),
+    IpNet::V4(PRIVATE_V4[2]),
+    IpNet::V6(LOOPBACK_V6[0]),
+    IpNet::V6(UNIQUE_LOCAL_V6[0]),
+];
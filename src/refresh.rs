@@ -0,0 +1,253 @@
+//! Periodically reload trusted networks from remote lists and files
+//!
+//! [`RefreshingConfig`] reloads its sources on a [`tokio`](https://docs.rs/tokio) interval, and
+//! publishes a new configuration to a [`ConfigHandle`] when the networks changed. Requests being
+//! extracted keep the snapshot they loaded, see [`ConfigHandle::load`].
+//!
+//! # Example
+//! ```no_run
+//! use std::time::Duration;
+//! use trusted_proxies::fetch::RangeSource;
+//! use trusted_proxies::refresh::{RefreshSource, RefreshingConfig};
+//! use trusted_proxies::Config;
+//!
+//! # async fn run() -> Result<(), trusted_proxies::refresh::RefreshError> {
+//! let mut base = Config::new();
+//! base.trust_x_forwarded_for();
+//!
+//! let sources = vec![
+//!     RefreshSource::Remote(RangeSource::Cloudflare),
+//!     RefreshSource::File("/etc/proxies/internal.txt".into()),
+//! ];
+//! // refreshing stops when dropped
+//! let refreshing = RefreshingConfig::new(base, sources, Duration::from_secs(3600)).await?;
+//! let config = refreshing.load();
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::fetch::{fetch_ranges, fetch_url, parse_ranges, FetchError, RangeSource};
+use crate::{Config, ConfigHandle};
+use core::fmt;
+use core::time::Duration;
+use ipnet::IpNet;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+
+/// A list of networks reloaded by [`RefreshingConfig`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RefreshSource {
+    /// The official list of a provider
+    Remote(RangeSource),
+    /// A list downloaded from a URL, see [`crate::fetch::parse_ranges`] for the accepted formats
+    Url(String),
+    /// A list read from a file, in the same formats as a downloaded list
+    File(PathBuf),
+}
+
+/// An error raised while reloading a source
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum RefreshError {
+    /// A list cannot be downloaded or parsed
+    Fetch(FetchError),
+    /// A file cannot be read
+    Io(std::io::Error),
+}
+
+impl fmt::Display for RefreshError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Fetch(err) => err.fmt(f),
+            Self::Io(err) => write!(f, "cannot read trusted networks: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for RefreshError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Fetch(err) => Some(err),
+            Self::Io(err) => Some(err),
+        }
+    }
+}
+
+impl RefreshSource {
+    async fn load(&self) -> Result<Vec<IpNet>, RefreshError> {
+        match self {
+            Self::Remote(source) => fetch_ranges(*source).await.map_err(RefreshError::Fetch),
+            Self::Url(url) => fetch_url(url).await.map_err(RefreshError::Fetch),
+            Self::File(path) => {
+                let content = tokio::fs::read_to_string(path)
+                    .await
+                    .map_err(RefreshError::Io)?;
+
+                parse_ranges(&content).map_err(RefreshError::Fetch)
+            }
+        }
+    }
+}
+
+/// State shared with the refresh task
+#[derive(Debug)]
+struct Refresher {
+    base: Config,
+    sources: Vec<RefreshSource>,
+    handle: ConfigHandle,
+    networks: Mutex<Vec<IpNet>>,
+    error: Mutex<Option<RefreshError>>,
+}
+
+impl Refresher {
+    /// Reload every source, publishing a new configuration when the networks changed
+    ///
+    /// Nothing is published when a source fails, so a partial list is never used.
+    async fn refresh(&self) -> Result<(), RefreshError> {
+        let mut networks = Vec::new();
+
+        for source in &self.sources {
+            networks.extend(source.load().await?);
+        }
+
+        let mut current = self.networks.lock().unwrap_or_else(|e| e.into_inner());
+
+        if *current != networks {
+            let mut config = self.base.clone();
+            config.add_trusted_networks(networks.iter().copied());
+
+            self.handle.store(config);
+            *current = networks;
+        }
+
+        Ok(())
+    }
+}
+
+/// A configuration reloading its trusted networks on an interval
+///
+/// Refreshing stops when it is dropped.
+#[derive(Debug)]
+pub struct RefreshingConfig {
+    refresher: Arc<Refresher>,
+    task: JoinHandle<()>,
+}
+
+impl RefreshingConfig {
+    /// Load the sources and start refreshing them on the current tokio runtime
+    ///
+    /// The networks of every source are added to the given base configuration. The sources are
+    /// loaded before returning, an error is returned when one of them cannot be loaded.
+    pub async fn new(
+        base: Config,
+        sources: Vec<RefreshSource>,
+        interval: Duration,
+    ) -> Result<Self, RefreshError> {
+        let refresher = Arc::new(Refresher {
+            handle: ConfigHandle::new(base.clone()),
+            base,
+            sources,
+            networks: Mutex::new(Vec::new()),
+            error: Mutex::new(None),
+        });
+
+        refresher.refresh().await?;
+
+        let shared = refresher.clone();
+        let task = tokio::spawn(async move {
+            let mut ticks = tokio::time::interval(interval);
+            ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            // the first tick completes immediately, the sources have just been loaded
+            ticks.tick().await;
+
+            loop {
+                ticks.tick().await;
+
+                if let Err(err) = shared.refresh().await {
+                    *shared.error.lock().unwrap_or_else(|e| e.into_inner()) = Some(err);
+                }
+            }
+        });
+
+        Ok(Self { refresher, task })
+    }
+
+    /// Get the handle the configurations are published to
+    pub fn handle(&self) -> ConfigHandle {
+        self.refresher.handle.clone()
+    }
+
+    /// Get a snapshot of the current configuration
+    pub fn load(&self) -> Arc<Config> {
+        self.refresher.handle.load()
+    }
+
+    /// Take the last error raised while refreshing the sources, if any
+    ///
+    /// When a refresh fails, the previous configuration is kept.
+    pub fn take_error(&self) -> Option<RefreshError> {
+        self.refresher
+            .error
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take()
+    }
+}
+
+impl Drop for RefreshingConfig {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::net::IpAddr;
+
+    #[tokio::test]
+    async fn refresh_file() {
+        let path = std::env::temp_dir().join(format!(
+            "trusted-proxies-refresh-{}.txt",
+            std::process::id()
+        ));
+        std::fs::write(&path, "10.0.0.0/8\n").unwrap();
+
+        let refreshing = RefreshingConfig::new(
+            Config::new(),
+            vec![RefreshSource::File(path.clone())],
+            Duration::from_millis(10),
+        )
+        .await
+        .unwrap();
+
+        assert!(refreshing
+            .load()
+            .is_ip_trusted(&IpAddr::from([10, 0, 0, 1])));
+
+        std::fs::write(&path, "10.0.0.0/8\nnope\n").unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        assert!(matches!(
+            refreshing.take_error(),
+            Some(RefreshError::Fetch(FetchError::Ranges(_)))
+        ));
+        assert!(refreshing
+            .load()
+            .is_ip_trusted(&IpAddr::from([10, 0, 0, 1])));
+
+        let handle = refreshing.handle();
+        std::fs::write(&path, "192.168.0.0/16\n").unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let config = handle.load();
+        assert!(config.is_ip_trusted(&IpAddr::from([192, 168, 0, 1])));
+        assert!(!config.is_ip_trusted(&IpAddr::from([10, 0, 0, 1])));
+
+        drop(refreshing);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
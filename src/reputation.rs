@@ -0,0 +1,64 @@
+use core::fmt;
+use core::future::Future;
+use core::net::IpAddr;
+use std::sync::Arc;
+
+/// The verdict of a [`ReputationProvider`] on a client address
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Reputation {
+    /// The address is not known to be malicious
+    Allow,
+    /// The address should be watched, e.g. rate limited or challenged
+    Suspicious,
+    /// The address should be denied
+    Block,
+}
+
+/// Give a verdict on the resolved client address, see [`crate::Config::set_reputation_provider`]
+///
+/// It is implemented for closures taking the client address.
+pub trait ReputationProvider: Send + Sync {
+    /// Get the verdict on a client address
+    fn reputation(&self, ip: IpAddr) -> Reputation;
+}
+
+impl<F> ReputationProvider for F
+where
+    F: Fn(IpAddr) -> Reputation + Send + Sync,
+{
+    fn reputation(&self, ip: IpAddr) -> Reputation {
+        self(ip)
+    }
+}
+
+/// A [`ReputationProvider`] querying an asynchronous source, such as a threat intelligence
+/// service, see [`crate::Trusted::lookup_reputation`]
+pub trait AsyncReputationProvider {
+    /// Get the verdict on a client address
+    fn reputation(&self, ip: IpAddr) -> impl Future<Output = Reputation> + Send;
+}
+
+/// A shared reputation provider
+#[derive(Clone)]
+pub(crate) struct ReputationHook(Arc<dyn ReputationProvider>);
+
+impl ReputationHook {
+    /// Get the address of the shared hook, configurations compare hooks by identity
+    pub(crate) fn id(&self) -> *const () {
+        Arc::as_ptr(&self.0).cast()
+    }
+
+    pub(crate) fn new<R: ReputationProvider + 'static>(provider: R) -> Self {
+        Self(Arc::new(provider))
+    }
+
+    pub(crate) fn reputation(&self, ip: IpAddr) -> Reputation {
+        self.0.reputation(ip)
+    }
+}
+
+impl fmt::Debug for ReputationHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ReputationProvider")
+    }
+}
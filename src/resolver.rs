@@ -0,0 +1,91 @@
+use crate::{Config, RequestInformation};
+use std::collections::HashMap;
+
+/// Select a [`Config`] given the host of the request, for gateways serving multiple tenants
+///
+/// Hosts are matched case-insensitively and without port. A host starting with `*.` matches
+/// any subdomain, the most specific host is used. When no host matches, the default
+/// configuration is used.
+///
+/// # Example
+/// ```
+/// use trusted_proxies::{Config, ConfigResolver, Trusted};
+///
+/// let mut tenant = Config::new();
+/// tenant.add_trusted_ip("203.0.113.0/24").unwrap();
+/// tenant.trust_x_forwarded_for();
+///
+/// let mut resolver = ConfigResolver::new(Config::new_local());
+/// resolver.add_host("*.tenant.com", tenant);
+///
+/// let mut request = http::Request::get("/").body(()).unwrap();
+/// request.headers_mut().insert("host", "www.tenant.com".parse().unwrap());
+/// request.headers_mut().insert("x-forwarded-for", "1.2.3.4".parse().unwrap());
+///
+/// let config = resolver.resolve_request(&request);
+/// let trusted = Trusted::from(core::net::IpAddr::from([203, 0, 113, 1]), &request, config);
+///
+/// assert_eq!(trusted.ip(), core::net::IpAddr::from([1, 2, 3, 4]));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ConfigResolver {
+    default: Config,
+    hosts: HashMap<String, Config>,
+}
+
+impl ConfigResolver {
+    /// Create a new resolver using the given configuration when no host matches
+    pub fn new(default: Config) -> Self {
+        Self {
+            default,
+            hosts: HashMap::new(),
+        }
+    }
+
+    /// Use a configuration for a host, or for all its subdomains when starting with `*.`
+    pub fn add_host(&mut self, host: &str, config: Config) {
+        self.hosts.insert(host.to_ascii_lowercase(), config);
+    }
+
+    /// Get the configuration used when no host matches
+    pub fn default_config(&self) -> &Config {
+        &self.default
+    }
+
+    /// Get the configuration for a host, which can be the TLS server name (SNI)
+    pub fn resolve(&self, host: Option<&str>) -> &Config {
+        let Some(host) = host else {
+            return &self.default;
+        };
+
+        let host = host
+            .rsplit_once(':')
+            .filter(|(_, port)| port.parse::<u16>().is_ok())
+            .map_or(host, |(host, _)| host)
+            .to_ascii_lowercase();
+
+        if let Some(config) = self.hosts.get(&host) {
+            return config;
+        }
+
+        let mut domain = host.as_str();
+
+        while let Some((_, parent)) = domain.split_once('.') {
+            if let Some(config) = self.hosts.get(&format!("*.{parent}")) {
+                return config;
+            }
+
+            domain = parent;
+        }
+
+        &self.default
+    }
+
+    /// Get the configuration for the host of a request
+    ///
+    /// The host header (or authority) is used, as forwarded hosts cannot be trusted before
+    /// knowing which configuration to use.
+    pub fn resolve_request<T: RequestInformation + ?Sized>(&self, request: &T) -> &Config {
+        self.resolve(request.default_host())
+    }
+}
@@ -0,0 +1,131 @@
+use crate::{telemetry, ConnectionInformation, RequestInformation, TrustPolicy, Trusted};
+use core::fmt;
+
+/// A field of the extracted information that differs between two configurations
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ShadowDifference {
+    /// The client address, see [`Trusted::ip`]
+    Ip,
+    /// The host, see [`Trusted::host`]
+    Host,
+    /// The scheme, see [`Trusted::scheme`]
+    Scheme,
+}
+
+impl ShadowDifference {
+    /// Name of the field, as used in the `field` label of the metrics
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Ip => "ip",
+            Self::Host => "host",
+            Self::Scheme => "scheme",
+        }
+    }
+}
+
+impl fmt::Display for ShadowDifference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Compare the client address, host and scheme of two extractions
+pub(crate) fn differences(left: &Trusted, right: &Trusted) -> Vec<ShadowDifference> {
+    let mut differences = Vec::new();
+
+    if left.ip() != right.ip() {
+        differences.push(ShadowDifference::Ip);
+    }
+
+    if left.host() != right.host() {
+        differences.push(ShadowDifference::Host);
+    }
+
+    if left.scheme() != right.scheme() {
+        differences.push(ShadowDifference::Scheme);
+    }
+
+    differences
+}
+
+/// The information extracted from a request with the current configuration and a candidate one,
+/// to roll out a trust policy change in shadow mode
+///
+/// The current result should be used to serve the request, the candidate one is only compared.
+/// With the `metrics` feature, every evaluation increments
+/// `trusted_proxies_shadow_evaluations_total` with a `result` label of `same` or `different`,
+/// and `trusted_proxies_shadow_differences_total` with a `field` label for each difference.
+///
+/// # Example
+/// ```
+/// use trusted_proxies::{Config, ShadowDifference, ShadowEvaluation};
+///
+/// let current = Config::new_local();
+/// let mut candidate = Config::new_local();
+/// candidate.trust_x_forwarded_host();
+///
+/// let mut request = http::Request::get("/").body(()).unwrap();
+/// request.headers_mut().insert("x-forwarded-for", "1.2.3.4".parse().unwrap());
+/// request.headers_mut().insert("x-forwarded-host", "example.com".parse().unwrap());
+///
+/// let peer = core::net::IpAddr::from([127, 0, 0, 1]);
+/// let evaluation = ShadowEvaluation::evaluate(&peer, &request, &current, &candidate);
+///
+/// assert_eq!(evaluation.differences(), &[ShadowDifference::Host]);
+/// assert_eq!(evaluation.current().host(), None);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ShadowEvaluation<'a> {
+    current: Trusted<'a>,
+    candidate: Trusted<'a>,
+    differences: Vec<ShadowDifference>,
+}
+
+impl<'a> ShadowEvaluation<'a> {
+    /// Extract the information of a request with both configurations and compare them
+    pub fn evaluate<C, T, P, Q>(connection: &C, request: &'a T, current: &P, candidate: &Q) -> Self
+    where
+        C: ConnectionInformation + ?Sized,
+        T: RequestInformation + ?Sized,
+        P: TrustPolicy + ?Sized,
+        Q: TrustPolicy + ?Sized,
+    {
+        let current = Trusted::from_connection(connection, request, current);
+        let candidate = Trusted::from_connection(connection, request, candidate);
+        let differences = differences(&current, &candidate);
+
+        telemetry::shadow_evaluation(&differences);
+
+        Self {
+            current,
+            candidate,
+            differences,
+        }
+    }
+
+    /// Get the information extracted with the current configuration
+    pub fn current(&self) -> &Trusted<'a> {
+        &self.current
+    }
+
+    /// Get the information extracted with the candidate configuration
+    pub fn candidate(&self) -> &Trusted<'a> {
+        &self.candidate
+    }
+
+    /// Get the fields that differ between both configurations
+    pub fn differences(&self) -> &[ShadowDifference] {
+        &self.differences
+    }
+
+    /// Check if both configurations extracted the same client address, host and scheme
+    pub fn is_same(&self) -> bool {
+        self.differences.is_empty()
+    }
+
+    /// Keep the information extracted with the current configuration
+    pub fn into_current(self) -> Trusted<'a> {
+        self.current
+    }
+}
@@ -0,0 +1,115 @@
+use crate::{Config, TrustPolicy};
+use arc_swap::{ArcSwap, Guard};
+use core::net::IpAddr;
+use std::sync::Arc;
+
+/// A shared configuration that can be replaced without locking the extraction
+///
+/// Unlike [`crate::ConfigHandle`], loading the configuration never takes a lock, so a
+/// long-running server can publish a new configuration from another thread (e.g. on `SIGHUP`)
+/// while requests are being extracted. Cloning shares the same configuration.
+///
+/// # Example
+/// ```
+/// use trusted_proxies::{Config, SharedConfig, Trusted};
+///
+/// let shared = SharedConfig::new(Config::new());
+/// let mut request = http::Request::get("/").body(()).unwrap();
+/// request.headers_mut().insert("x-forwarded-for", "1.2.3.4".parse().unwrap());
+///
+/// let reloader = shared.clone();
+/// std::thread::spawn(move || reloader.store(Config::new_local())).join().unwrap();
+///
+/// let trusted = Trusted::from(core::net::IpAddr::from([127, 0, 0, 1]), &request, &shared.load());
+/// assert_eq!(trusted.ip(), core::net::IpAddr::from([1, 2, 3, 4]));
+/// ```
+#[derive(Debug, Clone)]
+pub struct SharedConfig(Arc<ArcSwap<Config>>);
+
+impl SharedConfig {
+    /// Create a new shared configuration
+    pub fn new(config: Config) -> Self {
+        Self(Arc::new(ArcSwap::from_pointee(config)))
+    }
+
+    /// Get the current configuration, to give to [`crate::Trusted::from`]
+    ///
+    /// The guard is meant to be held for the extraction of a request only, use
+    /// [`SharedConfig::load_full`] to keep the configuration longer.
+    pub fn load(&self) -> SharedConfigGuard {
+        SharedConfigGuard(self.0.load())
+    }
+
+    /// Get a snapshot of the current configuration, which stays unchanged when a new
+    /// configuration is stored
+    pub fn load_full(&self) -> Arc<Config> {
+        self.0.load_full()
+    }
+
+    /// Atomically publish a new configuration, extractions in progress keep the previous one
+    pub fn store(&self, config: Config) {
+        self.0.store(Arc::new(config));
+    }
+}
+
+impl Default for SharedConfig {
+    fn default() -> Self {
+        Self::new(Config::default())
+    }
+}
+
+impl From<Config> for SharedConfig {
+    fn from(config: Config) -> Self {
+        Self::new(config)
+    }
+}
+
+/// The configuration loaded from a [`SharedConfig`]
+pub struct SharedConfigGuard(Guard<Arc<Config>>);
+
+impl core::ops::Deref for SharedConfigGuard {
+    type Target = Config;
+
+    fn deref(&self) -> &Config {
+        &self.0
+    }
+}
+
+impl TrustPolicy for SharedConfigGuard {
+    fn config(&self) -> &Config {
+        &self.0
+    }
+
+    fn is_ip_trusted(&self, remote_addr: &IpAddr) -> bool {
+        self.0.is_ip_trusted(remote_addr)
+    }
+}
+
+#[cfg(all(test, feature = "http"))]
+mod tests {
+    use super::*;
+    use crate::Trusted;
+    use http::Request;
+
+    #[test]
+    fn shared_config() {
+        let shared = SharedConfig::new(Config::new());
+        let mut request = Request::get("/").body(()).unwrap();
+        request
+            .headers_mut()
+            .insert("x-forwarded-for", "1.2.3.4".parse().unwrap());
+
+        let previous = shared.load_full();
+        let trusted = Trusted::from(IpAddr::from([127, 0, 0, 1]), &request, &shared.load());
+        assert_eq!(trusted.ip(), IpAddr::from([127, 0, 0, 1]));
+
+        let reloader = shared.clone();
+        std::thread::spawn(move || reloader.store(Config::new_local()))
+            .join()
+            .unwrap();
+
+        let trusted = Trusted::from(IpAddr::from([127, 0, 0, 1]), &request, &shared.load());
+        assert_eq!(trusted.ip(), IpAddr::from([1, 2, 3, 4]));
+        assert!(!previous.is_ip_trusted(&IpAddr::from([127, 0, 0, 1])));
+    }
+}
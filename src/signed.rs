@@ -0,0 +1,86 @@
+use crate::headers::HeaderNames;
+use crate::RequestInformation;
+use core::fmt;
+use core::net::IpAddr;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::sync::Arc;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Sign a client address, as an edge proxy would do, see [`crate::Config::trust_signed_client_ip`]
+///
+/// The signature is the hex encoded HMAC-SHA256 of the address with the shared key.
+pub fn sign_client_ip(key: &[u8], ip: &IpAddr) -> String {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC can take key of any size");
+    mac.update(ip.to_string().as_bytes());
+
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+fn decode_hex(value: &str) -> Option<Vec<u8>> {
+    if !value.len().is_multiple_of(2) {
+        return None;
+    }
+
+    (0..value.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(value.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Verify client addresses signed by an edge proxy
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub(crate) struct SignedClientIp {
+    key: Arc<[u8]>,
+}
+
+impl SignedClientIp {
+    pub(crate) fn new(key: &[u8]) -> Self {
+        Self {
+            key: Arc::from(key),
+        }
+    }
+
+    /// Get the signed client address of a request
+    ///
+    /// `None` is returned when there is no signed address, and an error when the signature
+    /// does not match.
+    pub(crate) fn verify<T: RequestInformation + ?Sized>(
+        &self,
+        request: &T,
+        names: &HeaderNames,
+    ) -> Option<Result<IpAddr, ()>> {
+        let value = request.header(names.signed_client_ip()).next_back()?.trim();
+        let signature = request
+            .header(names.signed_client_ip_signature())
+            .next_back();
+
+        let verified = signature
+            .and_then(|signature| decode_hex(signature.trim()))
+            .is_some_and(|signature| {
+                let mut mac =
+                    HmacSha256::new_from_slice(&self.key).expect("HMAC can take key of any size");
+                mac.update(value.as_bytes());
+
+                mac.verify_slice(&signature).is_ok()
+            });
+
+        if !verified {
+            return Some(Err(()));
+        }
+
+        Some(value.parse().map_err(|_| ()))
+    }
+}
+
+impl fmt::Debug for SignedClientIp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // never print the shared key
+        f.debug_struct("SignedClientIp").finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,44 @@
+use crate::Warning;
+use core::fmt;
+use core::net::IpAddr;
+use std::sync::{Arc, Mutex};
+
+/// An event describing a probable spoofing attempt, see [`crate::Config::on_spoof_attempt`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SpoofEvent {
+    /// A forwarding header was sent by a peer that is not trusted, it has been ignored
+    UntrustedPeer { peer: IpAddr, header: &'static str },
+    /// A forwarding header value sent by a trusted peer failed validation
+    InvalidValue { peer: IpAddr, warning: Warning },
+}
+
+type Callback = dyn FnMut(SpoofEvent) + Send;
+
+/// A shared callback invoked on spoofing attempts
+#[derive(Clone)]
+pub(crate) struct SpoofHook(Arc<Mutex<Callback>>);
+
+impl SpoofHook {
+    /// Get the address of the shared hook, configurations compare hooks by identity
+    pub(crate) fn id(&self) -> *const () {
+        Arc::as_ptr(&self.0).cast()
+    }
+
+    pub(crate) fn new<F: FnMut(SpoofEvent) + Send + 'static>(callback: F) -> Self {
+        Self(Arc::new(Mutex::new(callback)))
+    }
+
+    pub(crate) fn call(&self, event: SpoofEvent) {
+        // a panicking callback should not disable the hook for the next requests
+        let mut callback = self.0.lock().unwrap_or_else(|e| e.into_inner());
+
+        callback(event);
+    }
+}
+
+impl fmt::Debug for SpoofHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SpoofHook")
+    }
+}
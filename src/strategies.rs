@@ -0,0 +1,185 @@
+//! [`proptest`](https://docs.rs/proptest) strategies generating forwarding headers, and invariant
+//! helpers to property-test a configuration
+//!
+//! Strategies come in two flavors: valid values following RFC 7239 and the de facto
+//! `X-Forwarded-For` format, and near-valid values mixing valid elements with the kind of
+//! mistakes found in real traffic (odd whitespace and case, unquoted IPv6, empty elements).
+//!
+//! # Example
+//! ```
+//! use proptest::prelude::*;
+//! use trusted_proxies::strategies::{self, resolved_ip_is_not_a_trusted_proxy};
+//! use trusted_proxies::{Config, Trusted};
+//!
+//! proptest!(|(peer in strategies::ip_addr(), xff in strategies::near_valid_x_forwarded_for())| {
+//!     let config = Config::new_local();
+//!     let mut request = http::Request::get("/").body(()).unwrap();
+//!     request.headers_mut().insert("x-forwarded-for", xff.parse().unwrap());
+//!
+//!     let trusted = Trusted::from(peer, &request, &config);
+//!     resolved_ip_is_not_a_trusted_proxy(&trusted, &config)?;
+//! });
+//! ```
+
+use crate::{TrustPolicy, Trusted};
+use core::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use proptest::prelude::*;
+use proptest::test_runner::TestCaseError;
+
+/// Generate IPv4 and IPv6 addresses, often private or loopback ones to hit trusted ranges
+pub fn ip_addr() -> impl Strategy<Value = IpAddr> {
+    prop_oneof![
+        any::<[u8; 4]>().prop_map(|octets| IpAddr::V4(Ipv4Addr::from(octets))),
+        any::<[u8; 16]>().prop_map(|octets| IpAddr::V6(Ipv6Addr::from(octets))),
+        (any::<u8>(), any::<u8>()).prop_map(|(a, b)| IpAddr::from([10, 0, a, b])),
+        (any::<u8>(), any::<u8>()).prop_map(|(a, b)| IpAddr::from([192, 168, a, b])),
+        Just(IpAddr::from([127, 0, 0, 1])),
+        Just(IpAddr::V6(Ipv6Addr::LOCALHOST)),
+    ]
+}
+
+/// Generate a `for` or `by` node of a `Forwarded` element
+pub fn forwarded_node() -> impl Strategy<Value = String> {
+    prop_oneof![
+        4 => ip_addr().prop_map(|ip| match ip {
+            IpAddr::V4(ip) => ip.to_string(),
+            IpAddr::V6(ip) => format!("\"[{ip}]\""),
+        }),
+        1 => (ip_addr(), any::<u16>()).prop_map(|(ip, port)| match ip {
+            IpAddr::V4(ip) => format!("{ip}:{port}"),
+            IpAddr::V6(ip) => format!("\"[{ip}]:{port}\""),
+        }),
+        1 => Just("unknown".to_string()),
+        1 => "_[a-zA-Z0-9._-]{1,16}",
+    ]
+}
+
+/// Generate a valid element of the `Forwarded` header
+pub fn forwarded_element() -> impl Strategy<Value = String> {
+    (
+        proptest::option::of(forwarded_node()),
+        proptest::option::of(prop_oneof![Just("http"), Just("https")]),
+        proptest::option::of("[a-z]{1,10}\\.[a-z]{2,3}(:[0-9]{1,5})?"),
+        proptest::option::of(forwarded_node()),
+    )
+        .prop_map(|(node, proto, host, by)| {
+            let mut pairs = Vec::new();
+            pairs.extend(node.map(|node| format!("for={node}")));
+            pairs.extend(proto.map(|proto| format!("proto={proto}")));
+            pairs.extend(host.map(|host| format!("host={host}")));
+            pairs.extend(by.map(|by| format!("by={by}")));
+            pairs.join(";")
+        })
+}
+
+/// Generate a valid `Forwarded` header value
+pub fn forwarded() -> impl Strategy<Value = String> {
+    proptest::collection::vec(forwarded_element(), 1..8).prop_map(|elements| elements.join(", "))
+}
+
+/// Generate a `Forwarded` header value with common mistakes
+pub fn near_valid_forwarded() -> impl Strategy<Value = String> {
+    let element = prop_oneof![
+        4 => forwarded_element(),
+        1 => forwarded_element().prop_map(|element| element.to_uppercase()),
+        1 => forwarded_element().prop_map(|element| element.replace(';', " ; ")),
+        1 => ip_addr().prop_map(|ip| format!("for={ip}")),
+        1 => Just(String::new()),
+        1 => "[ -~]{0,24}",
+    ];
+
+    proptest::collection::vec(element, 1..8).prop_map(|elements| elements.join(","))
+}
+
+/// Generate a valid `X-Forwarded-For` header value
+pub fn x_forwarded_for() -> impl Strategy<Value = String> {
+    proptest::collection::vec(ip_addr(), 1..8).prop_map(|ips| {
+        ips.iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", ")
+    })
+}
+
+/// Generate an `X-Forwarded-For` header value with common mistakes
+pub fn near_valid_x_forwarded_for() -> impl Strategy<Value = String> {
+    let element = prop_oneof![
+        6 => ip_addr().prop_map(|ip| ip.to_string()),
+        1 => (ip_addr(), any::<u16>()).prop_map(|(ip, port)| match ip {
+            IpAddr::V4(ip) => format!("{ip}:{port}"),
+            IpAddr::V6(ip) => format!("[{ip}]:{port}"),
+        }),
+        1 => ip_addr().prop_map(|ip| format!("  {ip}\t")),
+        1 => Just("unknown".to_string()),
+        1 => Just(String::new()),
+        1 => "[!-+\\--~]{1,16}",
+    ];
+
+    proptest::collection::vec(element, 1..8).prop_map(|elements| elements.join(","))
+}
+
+/// Check that the resolved client address is not a trusted proxy, unless it is the peer itself
+///
+/// A trusted address can only be the client when the whole chain is trusted, in which case the
+/// peer address is used.
+pub fn resolved_ip_is_not_a_trusted_proxy<P: TrustPolicy + ?Sized>(
+    trusted: &Trusted,
+    policy: &P,
+) -> Result<(), TestCaseError> {
+    let ip = trusted.ip();
+
+    prop_assert!(
+        !policy.is_ip_trusted(&ip) || ip == trusted.peer(),
+        "resolved address {} is a trusted proxy",
+        ip
+    );
+
+    Ok(())
+}
+
+/// Check that the resolved client address is the peer when the peer is not trusted
+///
+/// This does not hold when signed client addresses are trusted, see
+/// `Config::trust_signed_client_ip`.
+pub fn untrusted_peer_is_the_client<P: TrustPolicy + ?Sized>(
+    trusted: &Trusted,
+    policy: &P,
+) -> Result<(), TestCaseError> {
+    if !policy.is_ip_trusted(&trusted.peer()) {
+        prop_assert_eq!(trusted.ip(), trusted.peer());
+    }
+
+    Ok(())
+}
+
+#[cfg(all(test, feature = "http"))]
+mod tests {
+    use super::*;
+    use crate::Config;
+
+    proptest! {
+        #[test]
+        fn forwarded_chain(peer in ip_addr(), forwarded in near_valid_forwarded(), xff in near_valid_x_forwarded_for()) {
+            let mut config = Config::new_local();
+            config.trust_x_forwarded_for();
+
+            let mut request = http::Request::get("/").body(()).unwrap();
+            request.headers_mut().insert(http::header::FORWARDED, forwarded.parse().unwrap());
+            request.headers_mut().insert("x-forwarded-for", xff.parse().unwrap());
+
+            let trusted = Trusted::from(peer, &request, &config);
+            resolved_ip_is_not_a_trusted_proxy(&trusted, &config)?;
+            untrusted_peer_is_the_client(&trusted, &config)?;
+        }
+
+        #[test]
+        fn valid_x_forwarded_for(xff in x_forwarded_for()) {
+            let config = Config::new_local();
+            let mut request = http::Request::get("/").body(()).unwrap();
+            request.headers_mut().insert("x-forwarded-for", xff.parse().unwrap());
+
+            let trusted = Trusted::from(IpAddr::from([127, 0, 0, 1]), &request, &config);
+            prop_assert!(xff.contains(&trusted.ip().to_string()) || trusted.ip() == trusted.peer());
+        }
+    }
+}
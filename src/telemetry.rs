@@ -0,0 +1,225 @@
+//! Metrics and events emitted during the extraction when the `metrics` or `tracing` features are
+//! enabled
+//!
+//! Every function and macro is a no-op when the matching feature is disabled.
+
+use crate::{ShadowDifference, SpoofEvent};
+
+/// Record an extraction, with the number of forwarding elements walked for trusted peers
+pub(crate) fn extraction(peer_trusted: bool, chain_depth: usize) {
+    #[cfg(feature = "metrics")]
+    {
+        let peer = if peer_trusted { "trusted" } else { "untrusted" };
+
+        ::metrics::counter!("trusted_proxies_requests_total", "peer" => peer).increment(1);
+
+        if peer_trusted {
+            ::metrics::histogram!("trusted_proxies_chain_depth").record(chain_depth as f64);
+        }
+    }
+
+    #[cfg(not(feature = "metrics"))]
+    let _ = (peer_trusted, chain_depth);
+}
+
+/// Record a spoofing attempt
+pub(crate) fn spoof_attempt(event: &SpoofEvent) {
+    #[cfg(feature = "metrics")]
+    {
+        let kind = match event {
+            SpoofEvent::UntrustedPeer { .. } => "untrusted_peer",
+            SpoofEvent::InvalidValue { .. } => "invalid_value",
+        };
+
+        ::metrics::counter!("trusted_proxies_spoof_attempts_total", "kind" => kind).increment(1);
+    }
+
+    #[cfg(not(feature = "metrics"))]
+    let _ = event;
+}
+
+/// Record a forwarding element which address could not be parsed
+pub(crate) fn parse_failure(header: &'static str) {
+    #[cfg(feature = "metrics")]
+    ::metrics::counter!("trusted_proxies_parse_failures_total", "header" => header).increment(1);
+
+    #[cfg(not(feature = "metrics"))]
+    let _ = header;
+}
+
+/// Record the comparison of two configurations, see [`crate::ShadowEvaluation`]
+pub(crate) fn shadow_evaluation(differences: &[ShadowDifference]) {
+    #[cfg(feature = "metrics")]
+    {
+        let result = if differences.is_empty() {
+            "same"
+        } else {
+            "different"
+        };
+
+        ::metrics::counter!("trusted_proxies_shadow_evaluations_total", "result" => result)
+            .increment(1);
+
+        for difference in differences {
+            ::metrics::counter!(
+                "trusted_proxies_shadow_differences_total",
+                "field" => difference.as_str()
+            )
+            .increment(1);
+        }
+    }
+
+    #[cfg(not(feature = "metrics"))]
+    let _ = differences;
+}
+
+/// Record the trusted client on the current span, for the middlewares
+#[cfg(any(feature = "tower", feature = "actix"))]
+pub(crate) fn record_client(trusted: &crate::Trusted) {
+    let span = ::tracing::Span::current();
+
+    span.record("client.address", ::tracing::field::display(trusted.ip()));
+
+    if let Some(scheme) = trusted.scheme() {
+        span.record("url.scheme", scheme);
+    }
+
+    if let Some(host) = trusted.host() {
+        span.record("server.address", host);
+    }
+}
+
+/// Emit a `trace!` event describing a step of the trust walk
+macro_rules! trace {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        ::tracing::trace!($($arg)*);
+    };
+}
+
+/// Emit a `debug!` event describing a decision of the trust walk
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        ::tracing::debug!($($arg)*);
+    };
+}
+
+pub(crate) use {debug, trace};
+
+#[cfg(all(test, feature = "metrics", feature = "http"))]
+mod tests {
+    use crate::{Config, Trusted};
+    use core::net::IpAddr;
+    use metrics::{
+        Counter, CounterFn, Gauge, Histogram, HistogramFn, Key, KeyName, Metadata, Recorder,
+        SharedString, Unit,
+    };
+    use std::collections::BTreeMap;
+    use std::sync::{Arc, Mutex};
+
+    /// A recorder keeping every counter and histogram value, keyed by name and labels
+    #[derive(Default)]
+    struct Recorded(Arc<Mutex<BTreeMap<String, Vec<f64>>>>);
+
+    /// A counter or a histogram of [`Recorded`]
+    struct Metric(Arc<Mutex<BTreeMap<String, Vec<f64>>>>, String);
+
+    impl CounterFn for Metric {
+        fn increment(&self, value: u64) {
+            let mut metrics = self.0.lock().unwrap();
+            let values = metrics.entry(self.1.clone()).or_default();
+
+            match values.first_mut() {
+                Some(total) => *total += value as f64,
+                None => values.push(value as f64),
+            }
+        }
+
+        fn absolute(&self, _value: u64) {}
+    }
+
+    impl HistogramFn for Metric {
+        fn record(&self, value: f64) {
+            let mut metrics = self.0.lock().unwrap();
+            metrics.entry(self.1.clone()).or_default().push(value);
+        }
+    }
+
+    impl Recorded {
+        fn metric(&self, key: &Key) -> Arc<Metric> {
+            let labels = key
+                .labels()
+                .map(|label| format!("{}={}", label.key(), label.value()))
+                .collect::<Vec<_>>();
+
+            Arc::new(Metric(
+                self.0.clone(),
+                format!("{}{{{}}}", key.name(), labels.join(",")),
+            ))
+        }
+
+        fn get(&self, name: &str) -> Vec<f64> {
+            self.0
+                .lock()
+                .unwrap()
+                .get(name)
+                .cloned()
+                .unwrap_or_default()
+        }
+    }
+
+    impl Recorder for Recorded {
+        fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+        fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+        fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+        fn register_counter(&self, key: &Key, _: &Metadata<'_>) -> Counter {
+            Counter::from_arc(self.metric(key))
+        }
+
+        fn register_gauge(&self, _: &Key, _: &Metadata<'_>) -> Gauge {
+            Gauge::noop()
+        }
+
+        fn register_histogram(&self, key: &Key, _: &Metadata<'_>) -> Histogram {
+            Histogram::from_arc(self.metric(key))
+        }
+    }
+
+    #[test]
+    fn extraction_metrics() {
+        let config = Config::new_local();
+        let recorder = Recorded::default();
+        let mut request = http::Request::get("/").body(()).unwrap();
+        request.headers_mut().insert(
+            "x-forwarded-for",
+            "1.2.3.4, nope, 10.0.0.2".parse().unwrap(),
+        );
+
+        metrics::with_local_recorder(&recorder, || {
+            Trusted::from(IpAddr::from([10, 0, 0, 1]), &request, &config);
+            Trusted::from(IpAddr::from([8, 8, 8, 8]), &request, &config);
+        });
+
+        assert_eq!(
+            recorder.get("trusted_proxies_requests_total{peer=trusted}"),
+            [1.0]
+        );
+        assert_eq!(
+            recorder.get("trusted_proxies_requests_total{peer=untrusted}"),
+            [1.0]
+        );
+        assert_eq!(recorder.get("trusted_proxies_chain_depth{}"), [2.0]);
+        assert_eq!(
+            recorder.get("trusted_proxies_parse_failures_total{header=x-forwarded-for}"),
+            [1.0]
+        );
+        assert_eq!(
+            recorder.get("trusted_proxies_spoof_attempts_total{kind=untrusted_peer}"),
+            [1.0]
+        );
+    }
+}
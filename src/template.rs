@@ -0,0 +1,254 @@
+//! Validate the topology of the proxies a request went through
+//!
+//! A [`ChainTemplate`] declares the layers of proxies in front of a service, from the edge to the
+//! closest one (e.g. Cloudflare, then an internal nginx), with the networks of each layer and the
+//! headers it is expected to set. [`ChainTemplate::validate`] matches every hop of an extraction
+//! with a layer and reports the hops that do not follow the declared order.
+//!
+//! # Example
+//! ```
+//! use trusted_proxies::template::ChainTemplate;
+//! use trusted_proxies::Trusted;
+//!
+//! let mut template = ChainTemplate::new();
+//! template.add_layer("cloudflare", &["173.245.48.0/20"], &["cf-ray"]).unwrap();
+//! template.add_layer("nginx", &["10.0.0.0/8"], &[]).unwrap();
+//!
+//! let config = template.to_config();
+//!
+//! let mut request = http::Request::get("/").body(()).unwrap();
+//! request.headers_mut().insert("x-forwarded-for", "1.2.3.4, 173.245.48.1".parse().unwrap());
+//! request.headers_mut().insert("cf-ray", "8a1b2c3d4e5f6a7b-CDG".parse().unwrap());
+//!
+//! let trusted = Trusted::from(core::net::IpAddr::from([10, 0, 0, 1]), &request, &config);
+//! let chain = template.validate(&trusted, &request);
+//!
+//! assert!(chain.is_valid());
+//! assert_eq!(chain.hops()[0].layer(), Some("cloudflare"));
+//! assert_eq!(chain.hops()[1].layer(), Some("nginx"));
+//! ```
+
+use crate::config::parse_networks;
+use crate::{Config, ConfigError, IpSource, RequestInformation, Trusted};
+use core::fmt;
+use core::net::IpAddr;
+use ipnet::IpNet;
+
+/// A layer of proxies, see [`ChainTemplate::add_layer`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ChainLayer {
+    name: String,
+    networks: Vec<IpNet>,
+    headers: Vec<String>,
+}
+
+/// An ordered topology of proxy layers, from the edge to the closest proxy
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChainTemplate {
+    layers: Vec<ChainLayer>,
+}
+
+impl ChainTemplate {
+    /// Create a template without any layer
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a layer, closer to the service than the layers already added
+    ///
+    /// networks are IP addresses or CIDRs, headers are the lowercase names of the headers the
+    /// layer is expected to set, read with [`RequestInformation::header`].
+    pub fn add_layer(
+        &mut self,
+        name: &str,
+        networks: &[&str],
+        headers: &[&str],
+    ) -> Result<(), ConfigError> {
+        let networks = parse_networks(
+            networks
+                .iter()
+                .enumerate()
+                .map(|(index, network)| (index + 1, *network)),
+        )?;
+
+        self.layers.push(ChainLayer {
+            name: name.to_string(),
+            networks,
+            headers: headers.iter().map(|header| header.to_string()).collect(),
+        });
+
+        Ok(())
+    }
+
+    /// Create a configuration trusting the networks of every layer, in a group named after the
+    /// layer, and the `Forwarded` and `X-Forwarded-For` headers
+    pub fn to_config(&self) -> Config {
+        let mut config = Config::new();
+
+        for layer in &self.layers {
+            for network in &layer.networks {
+                config
+                    .add_trusted_ip_group(&layer.name, &network.to_string())
+                    .expect("network is valid");
+            }
+        }
+
+        config.trust_forwarded();
+        config.trust_x_forwarded_for();
+
+        config
+    }
+
+    /// Match the hops of an extraction, from the farthest proxy to the peer, with the layers
+    pub fn validate<T: RequestInformation + ?Sized>(
+        &self,
+        trusted: &Trusted,
+        request: &T,
+    ) -> ChainMatch<'_> {
+        let hops: Vec<IpAddr> = if trusted.ip_source() == IpSource::Peer {
+            Vec::new()
+        } else {
+            trusted
+                .proxies()
+                .iter()
+                .copied()
+                .chain([trusted.peer()])
+                .collect()
+        };
+
+        let mut matches = Vec::with_capacity(hops.len());
+        let mut violations = Vec::new();
+        let mut traversed = vec![false; self.layers.len()];
+        let mut current = 0;
+
+        for ip in hops {
+            let contains = |layer: &ChainLayer| layer.networks.iter().any(|n| n.contains(&ip));
+            let position = self.layers[current..]
+                .iter()
+                .position(contains)
+                .map(|position| current + position);
+
+            let layer = match position {
+                Some(position) => {
+                    current = position;
+                    Some(position)
+                }
+                None => {
+                    let layer = self.layers.iter().position(contains);
+
+                    violations.push(match layer {
+                        Some(layer) => ChainViolation::OutOfOrder {
+                            ip,
+                            layer: &self.layers[layer].name,
+                        },
+                        None => ChainViolation::UnknownHop { ip },
+                    });
+
+                    layer
+                }
+            };
+
+            if let Some(layer) = layer {
+                traversed[layer] = true;
+            }
+
+            matches.push(HopMatch {
+                ip,
+                layer: layer.map(|layer| self.layers[layer].name.as_str()),
+            });
+        }
+
+        for (layer, traversed) in self.layers.iter().zip(traversed) {
+            if !traversed {
+                violations.push(ChainViolation::MissingLayer { layer: &layer.name });
+                continue;
+            }
+
+            for header in &layer.headers {
+                if request.header(header).next().is_none() {
+                    violations.push(ChainViolation::MissingHeader {
+                        layer: &layer.name,
+                        header,
+                    });
+                }
+            }
+        }
+
+        ChainMatch {
+            hops: matches,
+            violations,
+        }
+    }
+}
+
+/// A hop of the chain and the layer it belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HopMatch<'t> {
+    ip: IpAddr,
+    layer: Option<&'t str>,
+}
+
+impl<'t> HopMatch<'t> {
+    /// Get the address of the hop
+    pub fn ip(&self) -> IpAddr {
+        self.ip
+    }
+
+    /// Get the name of the layer the hop belongs to, `None` when it matches no layer
+    pub fn layer(&self) -> Option<&'t str> {
+        self.layer
+    }
+}
+
+/// A difference between the hops of a request and a template
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ChainViolation<'t> {
+    /// A hop does not belong to any layer
+    UnknownHop { ip: IpAddr },
+    /// A hop belongs to a layer farther than a previous hop
+    OutOfOrder { ip: IpAddr, layer: &'t str },
+    /// No hop belongs to a layer
+    MissingLayer { layer: &'t str },
+    /// A header expected from a traversed layer is missing
+    MissingHeader { layer: &'t str, header: &'t str },
+}
+
+impl fmt::Display for ChainViolation<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownHop { ip } => write!(f, "hop {ip} does not belong to any layer"),
+            Self::OutOfOrder { ip, layer } => {
+                write!(f, "hop {ip} of layer {layer} is out of order")
+            }
+            Self::MissingLayer { layer } => write!(f, "layer {layer} has not been traversed"),
+            Self::MissingHeader { layer, header } => {
+                write!(f, "header {header} expected from layer {layer} is missing")
+            }
+        }
+    }
+}
+
+/// The result of [`ChainTemplate::validate`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainMatch<'t> {
+    hops: Vec<HopMatch<'t>>,
+    violations: Vec<ChainViolation<'t>>,
+}
+
+impl<'t> ChainMatch<'t> {
+    /// Get the hops, from the farthest proxy to the peer, with their layer
+    pub fn hops(&self) -> &[HopMatch<'t>] {
+        &self.hops
+    }
+
+    /// Get the differences with the template
+    pub fn violations(&self) -> &[ChainViolation<'t>] {
+        &self.violations
+    }
+
+    /// Check if the request traversed every layer in order
+    pub fn is_valid(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
@@ -0,0 +1,269 @@
+//! Parser and runner for the `.test` fixture format used by this crate's own tests
+//!
+//! A fixture is made of four sections separated by a line of 23 dashes: the peer address, a raw
+//! HTTP request, the configuration as JSON and the expected values as JSON.
+//!
+//! ```text
+//! 127.0.0.1
+//! -----------------------
+//! GET /index.html HTTP/1.1
+//! Host: example.domain
+//!
+//! -----------------------
+//! {}
+//! -----------------------
+//! {
+//!     "host": "example.domain"
+//! }
+//! ```
+//!
+//! Integrations of other request types can validate their [`crate::RequestInformation`]
+//! implementation by building their request from [`Fixture::request`], extracting it and calling
+//! [`Fixture::check`].
+//!
+//! [`ProxyChain`] builds realistic requests, as received after going through several proxies.
+//!
+//! # Example
+//! ```
+//! use trusted_proxies::testkit::Fixture;
+//!
+//! let fixture = Fixture::parse("127.0.0.1
+//! -----------------------
+//! GET / HTTP/1.1
+//! Host: example.domain
+//! X-Forwarded-For: 1.2.3.4
+//!
+//! -----------------------
+//! {}
+//! -----------------------
+//! {\"host\": \"example.domain\", \"ip\": \"1.2.3.4\"}
+//! ").unwrap();
+//!
+//! fixture.run().unwrap();
+//! ```
+
+use crate::{Config, Trusted};
+use core::net::IpAddr;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+mod chain;
+
+pub use chain::{ProxyChain, ProxyStyle};
+
+const SEPARATOR: &str = "-----------------------\n";
+
+/// The configuration section of a fixture
+///
+/// The configuration starts from [`Config::new_local`], or from [`Config::new`] when `empty` is
+/// set, then the given addresses and headers are trusted.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct FixtureConfig {
+    pub trusted_ips: Vec<String>,
+    pub empty: bool,
+    pub is_forwarded_trusted: bool,
+    pub is_x_forwarded_for_trusted: bool,
+    pub is_x_forwarded_host_trusted: bool,
+    pub is_x_forwarded_proto_trusted: bool,
+    pub is_x_forwarded_by_trusted: bool,
+}
+
+impl FixtureConfig {
+    /// Build the configuration described by this section
+    pub fn to_config(&self) -> Result<Config, String> {
+        let mut config = if self.empty {
+            Config::new()
+        } else {
+            Config::new_local()
+        };
+
+        for trusted_ip in &self.trusted_ips {
+            config
+                .add_trusted_ip(trusted_ip)
+                .map_err(|err| err.to_string())?;
+        }
+
+        if self.is_forwarded_trusted {
+            config.trust_forwarded();
+        }
+
+        if self.is_x_forwarded_for_trusted {
+            config.trust_x_forwarded_for();
+        }
+
+        if self.is_x_forwarded_host_trusted {
+            config.trust_x_forwarded_host();
+        }
+
+        if self.is_x_forwarded_proto_trusted {
+            config.trust_x_forwarded_proto();
+        }
+
+        if self.is_x_forwarded_by_trusted {
+            config.trust_x_forwarded_by();
+        }
+
+        Ok(config)
+    }
+}
+
+/// The expected section of a fixture, the address is only checked when given
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct Expected {
+    pub host: Option<String>,
+    pub scheme: Option<String>,
+    pub ip: Option<IpAddr>,
+}
+
+/// A parsed fixture
+#[derive(Debug)]
+pub struct Fixture {
+    pub peer: IpAddr,
+    pub request: http::Request<()>,
+    pub config: Config,
+    pub expected: Expected,
+}
+
+impl Fixture {
+    /// Parse a fixture from its content
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let split = content.split(SEPARATOR).collect::<Vec<&str>>();
+
+        let [peer, request, config, expected] = split.as_slice() else {
+            return Err(format!("expected 4 sections, found {}", split.len()));
+        };
+
+        let peer = peer
+            .trim()
+            .parse::<IpAddr>()
+            .map_err(|err| format!("invalid peer address {}: {err}", peer.trim()))?;
+        let config = serde_json::from_str::<FixtureConfig>(config)
+            .map_err(|err| format!("invalid config: {err}"))?
+            .to_config()?;
+        let expected = serde_json::from_str::<Expected>(expected)
+            .map_err(|err| format!("invalid expected values: {err}"))?;
+
+        Ok(Self {
+            peer,
+            request: parse_request(request)?,
+            config,
+            expected,
+        })
+    }
+
+    /// Read and parse a fixture file
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .map_err(|err| format!("cannot read {}: {err}", path.display()))?;
+
+        Self::parse(&content).map_err(|err| format!("{}: {err}", path.display()))
+    }
+
+    /// Compare information extracted from the fixture request with the expected values
+    pub fn check(&self, trusted: &Trusted) -> Result<(), String> {
+        if trusted.host() != self.expected.host.as_deref() {
+            return Err(format!(
+                "host is {:?}, expected {:?}",
+                trusted.host(),
+                self.expected.host
+            ));
+        }
+
+        if trusted.scheme() != self.expected.scheme.as_deref() {
+            return Err(format!(
+                "scheme is {:?}, expected {:?}",
+                trusted.scheme(),
+                self.expected.scheme
+            ));
+        }
+
+        match self.expected.ip {
+            Some(ip) if trusted.ip() != ip => Err(format!("ip is {}, expected {ip}", trusted.ip())),
+            _ => Ok(()),
+        }
+    }
+
+    /// Extract the fixture request with the `http` implementation and check the result
+    pub fn run(&self) -> Result<(), String> {
+        self.check(&Trusted::from(self.peer, &self.request, &self.config))
+    }
+}
+
+/// List the `.test` fixture files of a directory and of its sub directories, sorted by path
+pub fn fixture_files(dir: impl AsRef<Path>) -> Result<Vec<PathBuf>, String> {
+    let dir = dir.as_ref();
+    let mut files = Vec::new();
+    let entries = std::fs::read_dir(dir)
+        .map_err(|err| format!("cannot read directory {}: {err}", dir.display()))?;
+
+    for entry in entries {
+        let path = entry.map_err(|err| err.to_string())?.path();
+
+        if path.is_dir() {
+            files.extend(fixture_files(&path)?);
+        } else if path.extension().is_some_and(|ext| ext == "test") {
+            files.push(path);
+        }
+    }
+
+    files.sort();
+
+    Ok(files)
+}
+
+/// Parse a raw HTTP request, or only its headers, into an `http` request
+pub fn parse_request(content: &str) -> Result<http::Request<()>, String> {
+    let mut raw = String::new();
+
+    // allow giving only the headers of the request
+    if !content
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .contains(" HTTP/")
+    {
+        raw.push_str("GET / HTTP/1.1\r\n");
+    }
+
+    raw.push_str(content.trim_end());
+    raw.push_str("\r\n\r\n");
+
+    let mut headers = [httparse::EMPTY_HEADER; 64];
+    let mut parsed_request = httparse::Request::new(&mut headers);
+
+    parsed_request
+        .parse(raw.as_bytes())
+        .map_err(|err| format!("invalid request: {err}"))?;
+
+    let mut request = http::Request::new(());
+
+    for header in parsed_request.headers.iter() {
+        let name = http::HeaderName::from_bytes(header.name.as_bytes())
+            .map_err(|err| format!("invalid header name {}: {err}", header.name))?;
+        let value = http::HeaderValue::from_bytes(header.value)
+            .map_err(|err| format!("invalid header value for {}: {err}", header.name))?;
+
+        request.headers_mut().append(name, value);
+    }
+
+    *request.version_mut() = match parsed_request.version {
+        Some(2) => http::Version::HTTP_2,
+        _ => http::Version::HTTP_11,
+    };
+
+    if let Some(method) = parsed_request.method {
+        *request.method_mut() = method
+            .parse()
+            .map_err(|err| format!("invalid method {method}: {err}"))?;
+    }
+
+    if let Some(path) = parsed_request.path {
+        *request.uri_mut() = path
+            .parse()
+            .map_err(|err| format!("invalid path {path}: {err}"))?;
+    }
+
+    Ok(request)
+}
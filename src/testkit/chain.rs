@@ -0,0 +1,199 @@
+use core::net::IpAddr;
+use http::header::{HeaderName, HeaderValue, FORWARDED, HOST};
+
+/// How a simulated proxy forwards the request information
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ProxyStyle {
+    /// Append the previous address to `X-Forwarded-For`, set `X-Forwarded-Proto` and
+    /// `X-Forwarded-Host` when they are missing, like the usual nginx configuration
+    Nginx,
+    /// Append the previous address to `X-Forwarded-For` and set `X-Forwarded-Proto` when it is
+    /// missing, like Envoy with `use_remote_address`
+    Envoy,
+    /// Like [`ProxyStyle::Envoy`], and set `CF-Connecting-IP` to the previous address
+    Cloudflare,
+    /// Append an element with `for`, `proto` and `host` to the `Forwarded` header
+    Forwarded,
+}
+
+/// Simulate a request going through a chain of proxies, each one adding its own forwarding
+/// headers
+///
+/// The first proxy receives the request from the client with the configured scheme, then each
+/// proxy sends it to the next one over `http`. The peer of the resulting request is the last proxy.
+///
+/// # Example
+/// ```
+/// use trusted_proxies::testkit::{ProxyChain, ProxyStyle};
+/// use trusted_proxies::{Config, Trusted};
+///
+/// let (peer, request) = ProxyChain::new([1, 2, 3, 4].into())
+///     .scheme("https")
+///     .host("example.com")
+///     .proxy(ProxyStyle::Cloudflare, [173, 245, 48, 1].into())
+///     .proxy(ProxyStyle::Nginx, [10, 0, 0, 1].into())
+///     .build();
+///
+/// let mut config = Config::new_local();
+/// config.add_trusted_ip("173.245.48.0/20").unwrap();
+/// config.trust_x_forwarded_proto();
+///
+/// let trusted = Trusted::from(peer, &request, &config);
+///
+/// assert_eq!(trusted.ip(), core::net::IpAddr::from([1, 2, 3, 4]));
+/// assert_eq!(trusted.scheme(), Some("https"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct ProxyChain {
+    client: IpAddr,
+    scheme: String,
+    host: String,
+    headers: Vec<(HeaderName, HeaderValue)>,
+    proxies: Vec<(ProxyStyle, IpAddr)>,
+}
+
+impl ProxyChain {
+    /// Start a chain from a client address, sending a `http` request to `localhost`
+    pub fn new(client: IpAddr) -> Self {
+        Self {
+            client,
+            scheme: "http".to_string(),
+            host: "localhost".to_string(),
+            headers: Vec::new(),
+            proxies: Vec::new(),
+        }
+    }
+
+    /// Set the scheme used by the client to reach the first proxy
+    pub fn scheme(mut self, scheme: &str) -> Self {
+        self.scheme = scheme.to_string();
+        self
+    }
+
+    /// Set the host requested by the client
+    pub fn host(mut self, host: &str) -> Self {
+        self.host = host.to_string();
+        self
+    }
+
+    /// Add a header sent by the client, e.g. to simulate a spoofed `X-Forwarded-For`
+    pub fn client_header(mut self, name: &'static str, value: &str) -> Self {
+        self.headers.push((
+            HeaderName::from_static(name),
+            HeaderValue::from_str(value).expect("invalid header value"),
+        ));
+        self
+    }
+
+    /// Add a proxy at the end of the chain
+    pub fn proxy(mut self, style: ProxyStyle, ip: IpAddr) -> Self {
+        self.proxies.push((style, ip));
+        self
+    }
+
+    /// Build the request received from the last proxy, returned with the address of that proxy
+    pub fn build(&self) -> (IpAddr, http::Request<()>) {
+        let mut request = http::Request::new(());
+        let headers = request.headers_mut();
+
+        headers.insert(HOST, value(&self.host));
+
+        for (name, value) in &self.headers {
+            headers.append(name.clone(), value.clone());
+        }
+
+        let mut previous = self.client;
+        let mut scheme = self.scheme.as_str();
+
+        for (style, ip) in &self.proxies {
+            match style {
+                ProxyStyle::Nginx | ProxyStyle::Envoy | ProxyStyle::Cloudflare => {
+                    append_x_forwarded_for(headers, previous);
+
+                    if !headers.contains_key("x-forwarded-proto") {
+                        headers.insert("x-forwarded-proto", value(scheme));
+                    }
+
+                    if *style == ProxyStyle::Nginx && !headers.contains_key("x-forwarded-host") {
+                        headers.insert("x-forwarded-host", value(&self.host));
+                    }
+
+                    if *style == ProxyStyle::Cloudflare {
+                        headers.insert("cf-connecting-ip", value(&previous.to_string()));
+                    }
+                }
+                ProxyStyle::Forwarded => {
+                    let element = format!(
+                        "for={}; proto={scheme}; host={}",
+                        forwarded_node(previous),
+                        self.host
+                    );
+
+                    headers.append(FORWARDED, value(&element));
+                }
+            }
+
+            previous = *ip;
+            scheme = "http";
+        }
+
+        (previous, request)
+    }
+}
+
+fn value(value: &str) -> HeaderValue {
+    HeaderValue::from_str(value).expect("invalid header value")
+}
+
+fn append_x_forwarded_for(headers: &mut http::HeaderMap, ip: IpAddr) {
+    let mut values = headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    if !values.is_empty() {
+        values.push_str(", ");
+    }
+
+    values.push_str(&ip.to_string());
+    headers.insert("x-forwarded-for", value(&values));
+}
+
+fn forwarded_node(ip: IpAddr) -> String {
+    match ip {
+        IpAddr::V4(ip) => ip.to_string(),
+        IpAddr::V6(ip) => format!("\"[{ip}]\""),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build() {
+        let (peer, request) = ProxyChain::new([1, 2, 3, 4].into())
+            .scheme("https")
+            .host("example.com")
+            .client_header("x-forwarded-for", "6.6.6.6")
+            .proxy(ProxyStyle::Cloudflare, [173, 245, 48, 1].into())
+            .proxy(ProxyStyle::Forwarded, "fd00::1".parse().unwrap())
+            .proxy(ProxyStyle::Nginx, [10, 0, 0, 1].into())
+            .build();
+
+        let headers = request.headers();
+
+        assert_eq!(peer, IpAddr::from([10, 0, 0, 1]));
+        assert_eq!(headers["x-forwarded-for"], "6.6.6.6, 1.2.3.4, fd00::1");
+        assert_eq!(headers["x-forwarded-proto"], "https");
+        assert_eq!(headers["x-forwarded-host"], "example.com");
+        assert_eq!(headers["cf-connecting-ip"], "1.2.3.4");
+        assert_eq!(
+            headers[FORWARDED],
+            "for=173.245.48.1; proto=http; host=example.com"
+        );
+    }
+}
@@ -0,0 +1,149 @@
+//! [`tower`](https://docs.rs/tower) middleware recording the trusted client on the request span
+//!
+//! [`ClientSpanLayer`] extracts the trusted information of every request and records it on the
+//! current span, usually the one created by `tower_http::trace::TraceLayer`, which must be added
+//! before this layer. Fields are only recorded when the span declares them, e.g. with
+//! `tracing::field::Empty`:
+//!
+//!  * `client.address`: the client address
+//!  * `url.scheme`: the scheme, when known
+//!  * `server.address`: the host, when known
+//!
+//! The trusted information is also inserted in the request extensions as a `Trusted<'static>`.
+//!
+//! The peer address is read from a [`SocketAddr`] in the request extensions, or from axum's
+//! `ConnectInfo<SocketAddr>` when the `axum` feature is enabled. Requests without a peer address
+//! are forwarded unchanged.
+//!
+//! # Example
+//! ```
+//! use std::sync::Arc;
+//! use trusted_proxies::tower::ClientSpanLayer;
+//! use trusted_proxies::Config;
+//!
+//! let layer = ClientSpanLayer::new(Arc::new(Config::new_local()));
+//! ```
+
+use crate::{telemetry, Config, Trusted};
+use core::net::{IpAddr, SocketAddr};
+use core::task::{Context, Poll};
+use std::sync::Arc;
+use tower_layer::Layer;
+use tower_service::Service;
+
+/// A layer recording the trusted client on the request span, see the [module](self)
+/// documentation
+#[derive(Debug, Clone)]
+pub struct ClientSpanLayer {
+    config: Arc<Config>,
+}
+
+impl ClientSpanLayer {
+    /// Create a layer extracting the trusted information with the given configuration
+    pub fn new(config: Arc<Config>) -> Self {
+        Self { config }
+    }
+}
+
+impl<S> Layer<S> for ClientSpanLayer {
+    type Service = ClientSpan<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ClientSpan {
+            inner,
+            config: self.config.clone(),
+        }
+    }
+}
+
+/// The service created by [`ClientSpanLayer`]
+#[derive(Debug, Clone)]
+pub struct ClientSpan<S> {
+    inner: S,
+    config: Arc<Config>,
+}
+
+impl<S, B> Service<http::Request<B>> for ClientSpan<S>
+where
+    S: Service<http::Request<B>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: http::Request<B>) -> Self::Future {
+        if let Some(peer) = peer_addr(&request) {
+            let trusted = Trusted::from(peer, &request, self.config.as_ref()).into_owned();
+            telemetry::record_client(&trusted);
+            request.extensions_mut().insert(trusted);
+        }
+
+        self.inner.call(request)
+    }
+}
+
+fn peer_addr<B>(request: &http::Request<B>) -> Option<IpAddr> {
+    let extensions = request.extensions();
+    let peer = extensions.get::<SocketAddr>().copied();
+
+    #[cfg(feature = "axum")]
+    let peer = peer.or_else(|| {
+        extensions
+            .get::<::axum::extract::ConnectInfo<SocketAddr>>()
+            .map(|info| info.0)
+    });
+
+    peer.map(|peer| peer.ip())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::future::{ready, Future, Ready};
+    use core::task::Waker;
+
+    /// A service returning the client address found in the extensions
+    struct Echo;
+
+    impl Service<http::Request<()>> for Echo {
+        type Response = Option<IpAddr>;
+        type Error = ();
+        type Future = Ready<Result<Option<IpAddr>, ()>>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), ()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: http::Request<()>) -> Self::Future {
+            ready(Ok(request
+                .extensions()
+                .get::<Trusted<'static>>()
+                .map(|trusted| trusted.ip())))
+        }
+    }
+
+    #[test]
+    fn client_span() {
+        let mut service = ClientSpanLayer::new(Arc::new(Config::new_local())).layer(Echo);
+        let mut context = Context::from_waker(Waker::noop());
+
+        let mut request = http::Request::get("/").body(()).unwrap();
+        request
+            .headers_mut()
+            .insert("x-forwarded-for", "1.2.3.4".parse().unwrap());
+
+        let response = core::pin::pin!(service.call(request.clone())).poll(&mut context);
+        assert_eq!(response, Poll::Ready(Ok(None)));
+
+        request
+            .extensions_mut()
+            .insert(SocketAddr::from(([127, 0, 0, 1], 50000)));
+
+        let response = core::pin::pin!(service.call(request)).poll(&mut context);
+        assert_eq!(response, Poll::Ready(Ok(Some(IpAddr::from([1, 2, 3, 4])))));
+    }
+}
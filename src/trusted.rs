@@ -1,6 +1,21 @@
+use crate::bogon;
 use crate::extract::RequestInformation;
-use crate::Config;
+use crate::extractor::last_address;
+use crate::headers::Headers;
+use crate::policy::ResolvedPolicy;
+use crate::telemetry::{self, debug, trace};
+use crate::ConnectionInformation;
+use crate::{
+    AsyncReputationProvider, AsyncTrustPolicy, ClientIpHeaderPriority, ClientIpStrategy, Config,
+    IgnoreReason, IgnoredHeader, IpSource, LongChain, PrivateClientIp, Reputation, SpoofEvent,
+    TrustPolicy, Warning,
+};
+use core::cell::{Cell, RefCell};
 use core::net::IpAddr;
+use core::ops::Range;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::Arc;
 
 /// Trusted data extracted from a request
 ///
@@ -24,25 +39,367 @@ use core::net::IpAddr;
 /// assert_eq!(trusted.ip(), core::net::IpAddr::from([1, 2, 3, 4]));
 /// ```
 #[derive(Debug, Clone)]
-pub enum Trusted<'a> {
-    Borrowed(TrustedBorrowed<'a>),
-    Owned(TrustedOwned),
+pub struct Trusted<'a> {
+    buffer: String,
+    host: Option<Value<'a>>,
+    scheme: Option<Value<'a>>,
+    by: Option<Value<'a>>,
+    ip: IpAddr,
+    source: IpSource,
+    peer: IpAddr,
+    proxies: Vec<IpAddr>,
+    untrusted_chain: Vec<Value<'a>>,
+    host_chain: Vec<Value<'a>>,
+    retained_proxies: Vec<IpAddr>,
+    warnings: Vec<Warning>,
+    ignored_headers: Vec<IgnoredHeader>,
+    via_group: Option<Arc<str>>,
+    reputation: Option<Reputation>,
 }
 
+/// A string of the trusted data
+///
+/// Values are borrowed from the request, or computed during the extraction, until
+/// [`Trusted::into_owned`] copies all of them in a single buffer, each value being a range inside
+/// it, so converting the trusted data only needs one allocation.
 #[derive(Debug, Clone)]
-pub struct TrustedBorrowed<'a> {
-    host: Option<&'a str>,
-    scheme: Option<&'a str>,
-    by: Option<&'a str>,
-    ip: IpAddr,
+enum Value<'a> {
+    Cow(Cow<'a, str>),
+    Range(Range<usize>),
 }
 
-#[derive(Debug, Clone)]
-pub struct TrustedOwned {
-    host: Option<String>,
-    scheme: Option<String>,
-    by: Option<String>,
-    ip: IpAddr,
+/// State of a single extraction
+///
+/// Interior mutability is used so the checks can be done while lazily iterating over the headers
+struct Context<'c> {
+    config: &'c Config,
+    examined: Cell<usize>,
+    /// Whether the header values already examined fit in the total length budget, by header and
+    /// position from the closest proxy
+    charged: RefCell<HashMap<(&'static str, usize), bool>>,
+    /// Number of values of each header already checked by [`Context::accept`], which warnings
+    /// have been raised
+    checked: RefCell<Vec<(&'static str, usize)>>,
+    warnings: RefCell<Vec<Warning>>,
+}
+
+impl<'c> Context<'c> {
+    fn new(config: &'c Config) -> Self {
+        Self {
+            config,
+            examined: Cell::new(0),
+            charged: RefCell::new(HashMap::new()),
+            checked: RefCell::new(Vec::new()),
+            warnings: RefCell::new(Vec::new()),
+        }
+    }
+
+    fn warn(&self, warning: Warning) {
+        self.warnings.borrow_mut().push(warning);
+    }
+
+    /// Check if a client address should be skipped as it is private or reserved
+    fn skip_private(&self, ip: &IpAddr) -> bool {
+        if self.config.private_client_ip != PrivateClientIp::Skip || !bogon::is_bogon(ip) {
+            return false;
+        }
+
+        trace!(%ip, "client address is private or reserved, skipped");
+        self.warn(Warning::PrivateClientIp { ip: *ip });
+
+        true
+    }
+
+    /// Check if a header value is longer than the configured maximum, without raising warnings
+    fn is_too_long(&self, value: &str) -> bool {
+        self.config
+            .max_header_value_length
+            .is_some_and(|max| value.len() > max)
+    }
+
+    /// Get the elements of a comma separated forwarding header, from the closest to the farthest
+    /// proxy
+    ///
+    /// The values that do not fit in the size limits are skipped, see [`Context::accept`].
+    fn elements<'s, 'v: 's>(
+        &'s self,
+        header: &'static str,
+        values: impl DoubleEndedIterator<Item = &'v str> + 's,
+    ) -> impl Iterator<Item = &'v str> + 's {
+        values
+            .rev()
+            .enumerate()
+            .filter(move |(index, value)| self.accept(header, *index, value))
+            .flat_map(|(_, value)| value.split(',').rev())
+            .map(str::trim)
+    }
+
+    /// Get the number of elements of a forwarding header, ignoring the values that do not fit in
+    /// the size limits
+    fn chain_length<'a>(
+        &self,
+        header: &'static str,
+        values: impl DoubleEndedIterator<Item = &'a str>,
+    ) -> usize {
+        self.elements(header, values).count()
+    }
+
+    /// Get the number of elements to skip before the client, counted from the closest proxy
+    ///
+    /// `addresses` are the addresses of the elements of a forwarding header, from the closest to
+    /// the farthest proxy.
+    fn skipped_elements(&self, addresses: impl Iterator<Item = Option<IpAddr>>) -> Option<usize> {
+        match self.config.client_ip_strategy {
+            // skip everything when there is no public address, so the peer is used
+            ClientIpStrategy::LeftmostNonPrivate => Some(
+                addresses
+                    .enumerate()
+                    .filter(|(_, ip)| ip.is_some_and(|ip| !bogon::is_bogon(&ip)))
+                    .last()
+                    .map_or(usize::MAX, |(index, _)| index),
+            ),
+            ClientIpStrategy::Leftmost => Some(
+                addresses
+                    .enumerate()
+                    .filter(|(_, ip)| ip.is_some())
+                    .last()
+                    .map_or(usize::MAX, |(index, _)| index),
+            ),
+            _ => self.config.trusted_hops,
+        }
+    }
+
+    /// Charge a header value against the total length budget, returns whether it fits
+    ///
+    /// `index` is the position of the value from the closest proxy. Each value is charged once,
+    /// passes reading a header again get the same answer for the values already examined, so
+    /// they do not exhaust the budget.
+    fn charge(&self, header: &'static str, index: usize, value: &str) -> bool {
+        let Some(max) = self.config.max_total_header_length else {
+            return true;
+        };
+
+        if let Some(fits) = self.charged.borrow().get(&(header, index)) {
+            return *fits;
+        }
+
+        let examined = self.examined.get() + value.len();
+        let fits = examined <= max;
+
+        if fits {
+            self.examined.set(examined);
+        }
+
+        self.charged.borrow_mut().insert((header, index), fits);
+
+        fits
+    }
+
+    /// Check if a header value fits in the configured size limits, without raising warnings
+    fn fits(&self, header: &'static str, index: usize, value: &str) -> bool {
+        !self.is_too_long(value) && self.charge(header, index, value)
+    }
+
+    /// Check if a header value can be examined given the configured size limits
+    ///
+    /// `index` is the position of the value from the closest proxy, the warnings of a value are
+    /// only raised the first time it is checked. Every pass checks the values in order, so the
+    /// values already checked are the first ones of the header.
+    fn accept(&self, header: &'static str, index: usize, value: &str) -> bool {
+        let mut checked = self.checked.borrow_mut();
+        let already_checked = match checked.iter_mut().find(|(name, _)| *name == header) {
+            Some((_, count)) if index < *count => true,
+            Some((_, count)) => {
+                *count = index + 1;
+                false
+            }
+            None => {
+                checked.push((header, index + 1));
+                false
+            }
+        };
+        drop(checked);
+
+        if already_checked {
+            return self.fits(header, index, value);
+        }
+
+        if let Some(max) = self.config.max_header_value_length {
+            if value.len() > max {
+                trace!(
+                    header,
+                    length = value.len(),
+                    max,
+                    "header value too long, skipped"
+                );
+
+                self.warn(Warning::HeaderValueTooLong {
+                    header,
+                    length: value.len(),
+                    max,
+                });
+
+                return false;
+            }
+        }
+
+        if let Some(max) = self.config.max_total_header_length {
+            if !self.charge(header, index, value) {
+                trace!(header, max, "total header length exceeded, skipped");
+
+                let warning = Warning::TotalHeaderLengthExceeded { max };
+
+                if !self.warnings.borrow().contains(&warning) {
+                    self.warn(warning);
+                }
+
+                return false;
+            }
+        }
+
+        if value.chars().any(is_invalid_char) {
+            trace!(header, "header value contains invalid characters");
+
+            self.warn(Warning::InvalidCharacters { header });
+        }
+
+        true
+    }
+
+    /// Whether spoofing attempts are reported, either to the hook or as metrics
+    fn is_reporting(&self) -> bool {
+        self.config.spoof_hook.is_some() || cfg!(feature = "metrics")
+    }
+
+    fn report(&self, event: SpoofEvent) {
+        telemetry::spoof_attempt(&event);
+
+        if let Some(hook) = &self.config.spoof_hook {
+            hook.call(event);
+        }
+    }
+
+    /// Get the forwarding headers present on the request that are ignored given the peer trust
+    fn ignored_headers<T: RequestInformation + ?Sized>(
+        &self,
+        peer_trusted: bool,
+        chain_rejected: bool,
+        request: &T,
+    ) -> Vec<IgnoredHeader> {
+        let config = self.config;
+        let request = Headers::new(request, &config.header_names);
+        let headers = [
+            (
+                "forwarded",
+                config.is_forwarded_trusted,
+                request.forwarded().next().is_some(),
+            ),
+            (
+                "x-forwarded-for",
+                config.is_x_forwarded_for_trusted,
+                request.x_forwarded_for().next().is_some(),
+            ),
+            (
+                "x-forwarded-host",
+                config.is_x_forwarded_host_trusted,
+                request.x_forwarded_host().next().is_some(),
+            ),
+            (
+                "x-forwarded-proto",
+                config.is_x_forwarded_proto_trusted,
+                request.x_forwarded_proto().next().is_some(),
+            ),
+            (
+                "x-forwarded-by",
+                config.is_x_forwarded_by_trusted,
+                request.x_forwarded_by().next().is_some(),
+            ),
+        ];
+
+        headers
+            .into_iter()
+            .filter(|(_, _, present)| *present)
+            .filter_map(|(name, trusted, _)| {
+                let reason = if !peer_trusted {
+                    IgnoreReason::UntrustedPeer
+                } else if chain_rejected {
+                    IgnoreReason::ChainTooLong
+                } else if !trusted {
+                    IgnoreReason::NotTrusted
+                } else {
+                    return None;
+                };
+
+                Some(IgnoredHeader { name, reason })
+            })
+            .collect()
+    }
+
+    /// Report forwarding headers sent by an untrusted peer
+    fn report_untrusted_peer(&self, peer: IpAddr, ignored_headers: &[IgnoredHeader]) {
+        if !self.is_reporting() {
+            return;
+        }
+
+        for ignored in ignored_headers {
+            if ignored.reason == IgnoreReason::UntrustedPeer {
+                self.report(SpoofEvent::UntrustedPeer {
+                    peer,
+                    header: ignored.name,
+                });
+            }
+        }
+    }
+
+    /// Report the warnings raised by the extraction
+    fn report_warnings(&self, peer: IpAddr) {
+        if !self.is_reporting() {
+            return;
+        }
+
+        for warning in self.warnings.borrow().iter() {
+            self.report(SpoofEvent::InvalidValue {
+                peer,
+                warning: warning.clone(),
+            });
+        }
+    }
+
+    /// Get the last value of a comma separated header, used for the `X-Forwarded-*` headers
+    fn last_value<'a>(
+        &self,
+        header: &'static str,
+        values: impl DoubleEndedIterator<Item = &'a str>,
+    ) -> Option<&'a str> {
+        values
+            .rev()
+            .enumerate()
+            .filter(|(index, value)| self.accept(header, *index, value))
+            .flat_map(|(_, vals)| vals.split(',').rev())
+            .map(|s| s.trim())
+            .next()
+    }
+}
+
+/// Check if a character is not allowed in a forwarding header value
+///
+/// Control characters (except horizontal tab), NUL bytes and non ASCII characters have nothing to
+/// do in these headers and are usually a sign of log injection or header smuggling attempts.
+fn is_invalid_char(c: char) -> bool {
+    !c.is_ascii() || (c.is_ascii_control() && c != '\t')
+}
+
+/// Check if a port is the default port of another scheme
+fn is_mismatched_port(scheme: &str, port: u16) -> bool {
+    match port {
+        80 => ["https", "wss"]
+            .iter()
+            .any(|s| scheme.eq_ignore_ascii_case(s)),
+        443 => ["http", "ws"]
+            .iter()
+            .any(|s| scheme.eq_ignore_ascii_case(s)),
+        _ => false,
+    }
 }
 
 /// Trim whitespace then any quote marks.
@@ -50,6 +407,82 @@ fn unquote(val: &str) -> &str {
     val.trim().trim_start_matches('"').trim_end_matches('"')
 }
 
+/// Get the addresses found in the trusted forwarding headers, from the closest to the farthest
+fn forwarded_addresses<'r, T: RequestInformation + ?Sized>(
+    request: &'r T,
+    context: &'r Context<'r>,
+) -> impl Iterator<Item = IpAddr> + 'r {
+    let config = context.config;
+    let request = Headers::new(request, &config.header_names);
+    let forwarded = context
+        .elements(
+            "forwarded",
+            request
+                .forwarded()
+                .filter(move |_| config.is_forwarded_trusted),
+        )
+        .filter_map(forwarded_for);
+
+    let x_forwarded_for = context.elements(
+        "x-forwarded-for",
+        request
+            .x_forwarded_for()
+            .filter(move |_| config.is_x_forwarded_for_trusted),
+    );
+
+    forwarded
+        .chain(x_forwarded_for)
+        .filter_map(|value| bare_address(value).parse().ok())
+}
+
+/// Get the unquoted `for` parameter of a `Forwarded` element
+fn forwarded_for(element: &str) -> Option<&str> {
+    forwarded_param(element, "for")
+}
+
+/// Get an unquoted parameter of a `Forwarded` element
+fn forwarded_param<'e>(element: &'e str, name: &str) -> Option<&'e str> {
+    element.split(';').find_map(|item| {
+        let (key, value) = item.split_once('=')?;
+
+        key.trim()
+            .eq_ignore_ascii_case(name)
+            .then(|| unquote(value.trim()))
+    })
+}
+
+/// Check if schemes seen along a chain, from the closest to the farthest proxy, go from `https`
+/// to `http`
+fn is_downgraded<'s>(schemes: impl Iterator<Item = &'s str>) -> bool {
+    let mut seen_http = false;
+
+    for scheme in schemes {
+        if scheme.eq_ignore_ascii_case("http") {
+            seen_http = true;
+        } else if scheme.eq_ignore_ascii_case("https") && seen_http {
+            return true;
+        }
+    }
+
+    false
+}
+
+/// Check if hosts seen along a chain disagree, hosts are case insensitive
+fn is_mismatched(hosts: &[&str]) -> bool {
+    hosts
+        .split_first()
+        .is_some_and(|(first, rest)| rest.iter().any(|host| !host.eq_ignore_ascii_case(first)))
+}
+
+/// Check if a node of a chain designates this server, see [`Config::add_server_address`]
+fn is_looped<'n>(config: &Config, mut nodes: impl Iterator<Item = &'n str>) -> bool {
+    nodes.any(|node| {
+        let node = node.trim();
+
+        config.is_server_node(node) || config.is_server_node(bare_address(node))
+    })
+}
+
 /// Remove port and IPv6 square brackets from a peer specification.
 fn bare_address(val: &str) -> &str {
     if val.starts_with('[') {
@@ -65,34 +498,71 @@ fn bare_address(val: &str) -> &str {
 }
 
 impl Trusted<'_> {
+    /// Copy the values borrowed from the request, to keep the trusted data after the request
+    ///
+    /// All the strings are copied in a single buffer.
     pub fn into_owned(self) -> Trusted<'static> {
-        match self {
-            Self::Borrowed(trusted) => Trusted::Owned(TrustedOwned {
-                host: trusted.host.map(|s| s.to_string()),
-                scheme: trusted.scheme.map(|s| s.to_string()),
-                by: trusted.by.map(|s| s.to_string()),
-                ip: trusted.ip,
-            }),
-            Self::Owned(trusted) => Trusted::Owned(trusted),
+        let capacity = [&self.host, &self.scheme, &self.by]
+            .into_iter()
+            .flatten()
+            .chain(&self.untrusted_chain)
+            .chain(&self.host_chain)
+            .map(|value| self.value(value).len())
+            .sum();
+        let mut buffer = String::with_capacity(capacity);
+        let mut push = |value: &Value| {
+            let start = buffer.len();
+            buffer.push_str(self.value(value));
+
+            Value::Range(start..buffer.len())
+        };
+
+        let host = self.host.as_ref().map(&mut push);
+        let scheme = self.scheme.as_ref().map(&mut push);
+        let by = self.by.as_ref().map(&mut push);
+        let untrusted_chain = self.untrusted_chain.iter().map(&mut push).collect();
+        let host_chain = self.host_chain.iter().map(&mut push).collect();
+
+        Trusted {
+            buffer,
+            host,
+            scheme,
+            by,
+            ip: self.ip,
+            source: self.source,
+            peer: self.peer,
+            proxies: self.proxies,
+            untrusted_chain,
+            host_chain,
+            retained_proxies: self.retained_proxies,
+            warnings: self.warnings,
+            ignored_headers: self.ignored_headers,
+            via_group: self.via_group,
+            reputation: self.reputation,
         }
     }
 }
 
 impl<'a> Trusted<'a> {
+    fn value<'s>(&'s self, value: &'s Value<'a>) -> &'s str {
+        match value {
+            Value::Cow(value) => value,
+            Value::Range(range) => &self.buffer[range.clone()],
+        }
+    }
+
+    fn get<'s>(&'s self, value: &'s Option<Value<'a>>) -> Option<&'s str> {
+        value.as_ref().map(|value| self.value(value))
+    }
+
     /// Get the scheme of the request
     pub fn scheme(&self) -> Option<&str> {
-        match self {
-            Self::Borrowed(trusted) => trusted.scheme,
-            Self::Owned(trusted) => trusted.scheme.as_deref(),
-        }
+        self.get(&self.scheme)
     }
 
     /// Get the host and potential port of the request
     pub fn host_with_port(&self) -> Option<&str> {
-        match self {
-            Self::Borrowed(trusted) => trusted.host,
-            Self::Owned(trusted) => trusted.host.as_deref(),
-        }
+        self.get(&self.host)
     }
 
     /// Get the host of the request (without port)
@@ -112,161 +582,949 @@ impl<'a> Trusted<'a> {
 
     /// Get the proxy that forwarded the request
     pub fn by(&self) -> Option<&str> {
-        match self {
-            Self::Borrowed(trusted) => trusted.by,
-            Self::Owned(trusted) => trusted.by.as_deref(),
-        }
+        self.get(&self.by)
     }
 
     /// Get first untrusted IP address from the request, which should be in most cases the real client IP address
     pub fn ip(&self) -> IpAddr {
-        match self {
-            Self::Borrowed(trusted) => trusted.ip,
-            Self::Owned(trusted) => trusted.ip,
+        self.ip
+    }
+
+    /// Get where the client address returned by [`Trusted::ip`] comes from
+    pub fn ip_source(&self) -> IpSource {
+        self.source
+    }
+
+    /// Get the address of the peer that sent the request
+    pub fn peer(&self) -> IpAddr {
+        self.peer
+    }
+
+    /// Get the addresses of the trusted proxies found in the forwarding headers, between the
+    /// client and the peer
+    ///
+    /// Addresses are ordered from the farthest to the closest proxy, as in `X-Forwarded-For`
+    pub fn proxies(&self) -> &[IpAddr] {
+        &self.proxies
+    }
+
+    /// Get the trusted proxies the request went through, internal proxies excepted
+    ///
+    /// This is the chain reported by the Apache `mod_remoteip` module in its
+    /// `RemoteIPProxiesHeader` header, see [`Config::add_internal_proxy`]. Addresses are ordered
+    /// from the farthest to the closest proxy, the peer being the last one when it is trusted.
+    ///
+    /// # Example
+    /// ```
+    /// use trusted_proxies::{Config, Trusted};
+    ///
+    /// let mut config = Config::new();
+    /// config.add_internal_proxy("10.0.0.0/8").unwrap();
+    /// config.add_trusted_ip("203.0.113.0/24").unwrap();
+    /// config.trust_x_forwarded_for();
+    ///
+    /// let mut request = http::Request::get("/").body(()).unwrap();
+    /// request.headers_mut().insert("x-forwarded-for", "1.2.3.4, 203.0.113.7".parse().unwrap());
+    ///
+    /// let trusted = Trusted::from(core::net::IpAddr::from([10, 0, 0, 1]), &request, &config);
+    ///
+    /// assert_eq!(trusted.ip(), core::net::IpAddr::from([1, 2, 3, 4]));
+    /// assert_eq!(trusted.retained_proxies(), &[core::net::IpAddr::from([203, 0, 113, 7])]);
+    /// ```
+    pub fn retained_proxies(&self) -> &[IpAddr] {
+        &self.retained_proxies
+    }
+
+    /// Get the unverified entries found beyond the client in the forwarding headers
+    ///
+    /// These entries are claimed by the client, or by proxies that are not trusted: they can be
+    /// forged and must only be recorded, never used for a decision. They are ordered from the
+    /// farthest to the closest, as in `X-Forwarded-For`, and are given as sent (e.g. `unknown` or
+    /// an obfuscated identifier for the `Forwarded` header).
+    ///
+    /// # Example
+    /// ```
+    /// use trusted_proxies::{Config, Trusted};
+    ///
+    /// let config = Config::new_local();
+    /// let mut request = http::Request::get("/").body(()).unwrap();
+    /// request.headers_mut().insert("x-forwarded-for", "6.6.6.6, unknown, 1.2.3.4, 10.0.0.1".parse().unwrap());
+    ///
+    /// let trusted = Trusted::from(core::net::IpAddr::from([127, 0, 0, 1]), &request, &config);
+    ///
+    /// assert_eq!(trusted.ip(), core::net::IpAddr::from([1, 2, 3, 4]));
+    /// assert!(trusted.untrusted_chain().eq(["6.6.6.6", "unknown"]));
+    /// ```
+    pub fn untrusted_chain(
+        &self,
+    ) -> impl DoubleEndedIterator<Item = &str> + ExactSizeIterator + '_ {
+        self.untrusted_chain.iter().map(|entry| self.value(entry))
+    }
+
+    /// Get every host given by the trusted part of the chain, from the farthest to the closest
+    /// proxy
+    ///
+    /// [`Trusted::host`] only returns the farthest one, a [`Warning::HostMismatch`] is raised when
+    /// they disagree. Only the hosts added by the trusted hops walked to find the client are
+    /// considered, the ones sent by the client are ignored.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trusted_proxies::{Config, Trusted, Warning};
+    ///
+    /// let mut config = Config::new_local();
+    /// config.trust_x_forwarded_host();
+    ///
+    /// let mut request = http::Request::get("/").body(()).unwrap();
+    /// request.headers_mut().insert("x-forwarded-for", "1.2.3.4, 10.0.0.1".parse().unwrap());
+    /// request.headers_mut().insert("x-forwarded-host", "example.com, internal.local".parse().unwrap());
+    ///
+    /// let trusted = Trusted::from(core::net::IpAddr::from([127, 0, 0, 1]), &request, &config);
+    ///
+    /// assert!(trusted.host_chain().eq(["example.com", "internal.local"]));
+    /// assert_eq!(trusted.warnings(), &[Warning::HostMismatch { header: "x-forwarded-host" }]);
+    /// ```
+    pub fn host_chain(&self) -> impl DoubleEndedIterator<Item = &str> + ExactSizeIterator + '_ {
+        self.host_chain.iter().map(|host| self.value(host))
+    }
+
+    /// Get a sanitized `X-Forwarded-For` value containing only the verified chain
+    ///
+    /// The value contains the client address, the trusted proxies and the peer address, so a
+    /// proxy can forward this minimal chain upstream instead of the one sent by the client.
+    ///
+    /// # Example
+    /// ```
+    /// use trusted_proxies::{Config, Trusted};
+    ///
+    /// let config = Config::new_local();
+    /// let mut request = http::Request::get("/").body(()).unwrap();
+    /// request.headers_mut().insert("x-forwarded-for", "6.6.6.6, 1.2.3.4, 10.0.0.1".parse().unwrap());
+    ///
+    /// let trusted = Trusted::from(core::net::IpAddr::from([10, 0, 0, 2]), &request, &config);
+    ///
+    /// assert_eq!(trusted.sanitized_x_forwarded_for(), "1.2.3.4, 10.0.0.1, 10.0.0.2");
+    /// ```
+    pub fn sanitized_x_forwarded_for(&self) -> String {
+        let mut chain = vec![self.ip];
+
+        if self.ip != self.peer {
+            chain.extend(&self.proxies);
+            chain.push(self.peer);
+        }
+
+        chain
+            .iter()
+            .map(|ip| ip.to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Check if the client address belongs to an anonymous network (VPN, Tor exit node, hosting
+    /// provider or public proxy) according to the MaxMind Anonymous IP database
+    ///
+    /// Addresses missing from the database, and lookup errors, are not considered anonymous.
+    #[cfg(feature = "maxmind")]
+    pub fn is_anonymous_proxy<S: AsRef<[u8]>>(
+        &self,
+        reader: &crate::maxmind::AnonymousIpReader<S>,
+    ) -> bool {
+        reader
+            .lookup(self.ip)
+            .ok()
+            .flatten()
+            .is_some_and(|flags| flags.is_anonymous_proxy())
+    }
+
+    /// Get the verdict of the reputation provider on the client address
+    ///
+    /// `None` when no provider is configured, see [`Config::set_reputation_provider`].
+    pub fn reputation(&self) -> Option<Reputation> {
+        self.reputation
+    }
+
+    /// Ask an asynchronous provider for its verdict on the client address, which is then
+    /// returned by [`Trusted::reputation`]
+    pub async fn lookup_reputation<R: AsyncReputationProvider + ?Sized>(
+        &mut self,
+        provider: &R,
+    ) -> Reputation {
+        let reputation = provider.reputation(self.ip).await;
+        self.reputation = Some(reputation);
+
+        reputation
+    }
+
+    /// Get the warnings raised during the extraction
+    ///
+    /// Values that raised a warning have been ignored
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
+    /// Get the forwarding headers present on the request that have been ignored
+    ///
+    /// Either because the peer is not trusted or because the header is not trusted in the
+    /// configuration. This allows to discover which headers are actually sent by your proxies
+    /// before trusting them.
+    pub fn ignored_headers(&self) -> &[IgnoredHeader] {
+        &self.ignored_headers
+    }
+
+    /// Get the group of the trusted peer that sent the request
+    ///
+    /// See [`Config::add_trusted_ip_group`], `None` is returned when the peer is not trusted or
+    /// when it does not belong to a group.
+    pub fn via_group(&self) -> Option<&str> {
+        self.via_group.as_deref()
+    }
+
+    /// Create a new `Trusted` struct from a peer address, a request and a configuration, failing
+    /// on the first warning raised during the extraction
+    pub fn from_strict<T: RequestInformation + ?Sized, P: TrustPolicy + ?Sized>(
+        ip_addr: IpAddr,
+        request: &'a T,
+        config: &P,
+    ) -> Result<Self, Warning> {
+        let trusted = Self::from(ip_addr, request, config);
+
+        match trusted.warnings().first() {
+            Some(warning) => Err(warning.clone()),
+            None => Ok(trusted),
         }
     }
 
     /// Create a new `Trusted` struct from a peer address, a request and a configuration
-    pub fn from<T: RequestInformation>(ip_addr: IpAddr, request: &'a T, config: &Config) -> Self {
-        let (trusted_host, trusted_scheme, trusted_by, trusted_ip) =
-            if !config.is_ip_trusted(&ip_addr) {
-                // if the peer address is not trusted, we can't trust the headers
-                // set the host and scheme to the server's configuration
+    ///
+    /// The configuration can either be a [`Config`] or a compiled [`crate::TrustedMatcher`]
+    pub fn from<T: RequestInformation + ?Sized, P: TrustPolicy + ?Sized>(
+        ip_addr: IpAddr,
+        request: &'a T,
+        policy: &P,
+    ) -> Self {
+        Self::from_connection(&ip_addr, request, policy)
+    }
+
+    /// Create a new `Trusted` struct from a peer address and a request, using the process-wide
+    /// configuration
+    ///
+    /// See [`crate::set_global_config`].
+    pub fn from_global<T: RequestInformation + ?Sized>(ip_addr: IpAddr, request: &'a T) -> Self {
+        Self::from(ip_addr, request, crate::global_config())
+    }
+
+    /// Create a new `Trusted` struct from a peer address, a request and an asynchronous policy
+    ///
+    /// The policy is asked about the peer then, when the peer is trusted, about every address
+    /// found in the trusted forwarding headers, before extracting the information like
+    /// [`Trusted::from`].
+    ///
+    /// # Example
+    /// ```
+    /// # use core::net::IpAddr;
+    /// # use trusted_proxies::{AsyncTrustPolicy, Config, Trusted};
+    /// # struct Loopback(Config);
+    /// # impl AsyncTrustPolicy for Loopback {
+    /// #     fn config(&self) -> &Config { &self.0 }
+    /// #     async fn is_ip_trusted(&self, ip: &IpAddr) -> bool { ip.is_loopback() }
+    /// # }
+    /// async fn client_ip(request: &http::Request<()>, peer: IpAddr, policy: &Loopback) -> IpAddr {
+    ///     Trusted::from_async(peer, request, policy).await.ip()
+    /// }
+    /// ```
+    pub async fn from_async<T: RequestInformation + ?Sized, P: AsyncTrustPolicy + ?Sized>(
+        ip_addr: IpAddr,
+        request: &'a T,
+        policy: &P,
+    ) -> Trusted<'a> {
+        let config = policy.config();
+        let mut checked = Vec::new();
+        let mut trusted = Vec::new();
+
+        if config.is_any_peer_trusted() || policy.is_ip_trusted(&ip_addr).await {
+            trusted.push(ip_addr);
+
+            // collected first so the extraction state is not held across the awaits
+            let addresses = forwarded_addresses(request, &Context::new(config)).collect::<Vec<_>>();
+
+            for ip in addresses {
+                if checked.contains(&ip) {
+                    continue;
+                }
+
+                checked.push(ip);
+
+                if policy.is_ip_trusted(&ip).await {
+                    trusted.push(ip);
+                }
+            }
+        }
+
+        Self::from(ip_addr, request, &ResolvedPolicy { config, trusted })
+    }
+
+    /// Create a new `Trusted` struct from a connection, a request and a configuration
+    ///
+    /// Unlike [`Trusted::from`], the connection can give TLS information such as the client
+    /// certificate of the peer, see [`ConnectionInformation`].
+    pub fn from_connection<C, T, P>(connection: &C, request: &'a T, policy: &P) -> Self
+    where
+        C: ConnectionInformation + ?Sized,
+        T: RequestInformation + ?Sized,
+        P: TrustPolicy + ?Sized,
+    {
+        let ip_addr = connection.peer_addr();
+        let config = policy.config();
+        let context = Context::new(config);
+        let peer_trusted = connection.is_peer_trusted().unwrap_or_else(|| {
+            (config.is_any_peer_trusted() || policy.is_ip_trusted(&ip_addr))
+                && config.is_certificate_trusted(connection)
+                && config.is_port_trusted(connection)
+        });
+        // whether an element of a forwarding header is one of the trusted hops
+        let is_hop = |index: usize, skipped: Option<usize>| skipped.is_some_and(|n| index < n);
+        // whether an address found in a forwarding header is a proxy to walk past
+        let is_proxy = |ip: &IpAddr| {
+            config.client_ip_strategy == ClientIpStrategy::RightmostUntrusted
+                && (policy.is_ip_trusted(ip) || context.skip_private(ip))
+        };
+        let headers = Headers::new(request, &config.header_names);
+        // whether the forwarding headers are ignored for being longer than the maximum chain length
+        let mut chain_rejected = false;
+
+        if let (true, Some(max)) = (peer_trusted, config.max_chain_length) {
+            let lengths = [
                 (
-                    request.default_host(),
-                    request.default_scheme(),
-                    None,
-                    ip_addr,
-                )
-            } else {
-                // if the peer address is trusted, we can start to check trusted header to get correct information
-                let mut host = None;
-                let mut scheme = None;
-                let mut by = None;
-                let mut realip_remote_addr = None;
-
-                // first check the forwarded header if it is trusted
-                if config.is_forwarded_trusted {
-                    // quote from RFC 7239:
-                    // A proxy server that wants to add a new "Forwarded" header field value
-                    //    can either append it to the last existing "Forwarded" header field
-                    //    after a comma separator or add a new field at the end of the header
-                    //    block.
-                    // --- https://datatracker.ietf.org/doc/html/rfc7239#section-4
-                    // so we get the values in reverse order as we want to get the first untrusted value
-                    let forwarded_list = request
-                        .forwarded()
-                        // "for=1.2.3.4, for=5.6.7.8; scheme=https"
-                        .flat_map(|vals| vals.split(','))
-                        // ["for=1.2.3.4", "for=5.6.7.8; scheme=https"]
-                        .rev();
-
-                    'forwaded: for forwarded in forwarded_list {
-                        for (key, value) in forwarded.split(';').map(|item| {
-                            let mut kv = item.splitn(2, '=');
-
-                            (
-                                kv.next().map(|s| s.trim()).unwrap_or_default(),
-                                kv.next().map(|s| unquote(s.trim())).unwrap_or_default(),
-                            )
-                        }) {
-                            match key.to_lowercase().as_str() {
-                                "for" => {
-                                    if let Ok(ip) = bare_address(value).parse::<IpAddr>() {
-                                        realip_remote_addr = Some(ip);
-
-                                        if config.is_ip_trusted(&ip) {
-                                            host = None;
-                                            scheme = None;
-                                            by = None;
-                                            realip_remote_addr = None;
-
-                                            continue 'forwaded;
-                                        }
+                    "forwarded",
+                    config
+                        .is_forwarded_trusted
+                        .then(|| context.chain_length("forwarded", headers.forwarded())),
+                ),
+                (
+                    "x-forwarded-for",
+                    config.is_x_forwarded_for_trusted.then(|| {
+                        context.chain_length("x-forwarded-for", headers.x_forwarded_for())
+                    }),
+                ),
+            ];
+
+            for (header, length) in lengths {
+                if let Some(length) = length.filter(|length| *length > max) {
+                    debug!(
+                        header,
+                        length, max, "forwarding header is longer than the maximum chain length"
+                    );
+                    context.warn(Warning::ChainTooLong {
+                        header,
+                        length,
+                        max,
+                    });
+                    chain_rejected |= config.long_chain == LongChain::Untrust;
+                }
+            }
+        }
+
+        let ignored_headers = context.ignored_headers(peer_trusted, chain_rejected, request);
+        let peer_trusted = peer_trusted && !chain_rejected;
+        // number of elements walked in each forwarding header
+        let max_elements = config.max_chain_length.unwrap_or(usize::MAX);
+        // number of forwarding elements walked, only used for metrics
+        let mut depth = 0;
+
+        // trusted proxies walked in the forwarding headers, from the closest to the farthest
+        let mut proxies = Vec::new();
+        // unverified entries beyond the client, from the closest to the farthest
+        let mut untrusted_chain = Vec::new();
+        let mut host_chain = Vec::new();
+        let mut source = IpSource::Peer;
+        // set once the client is found, remaining values are only collected as the untrusted
+        // chain and do not raise warnings
+        let client_found = Cell::new(false);
+
+        let (mut trusted_host, trusted_scheme, trusted_by, trusted_ip) = if !peer_trusted {
+            // if the peer address is not trusted, we can't trust the headers
+            // set the host and scheme to the server's configuration
+            debug!(peer = %ip_addr, "peer is not trusted, forwarding headers are ignored");
+            context.report_untrusted_peer(ip_addr, &ignored_headers);
+
+            (
+                request.default_host(),
+                request.default_scheme(),
+                None,
+                ip_addr,
+            )
+        } else {
+            // if the peer address is trusted, we can start to check trusted header to get correct information
+            let mut host = None;
+            let mut scheme = None;
+            let mut by = None;
+            let mut realip_remote_addr = None;
+            // elements of the trusted part of the `Forwarded` chain, written by the trusted proxies
+            let mut walked = Vec::new();
+            // header giving the trusted proxies
+            let mut walked_header = None;
+
+            // first check the forwarded header if it is trusted
+            if config.is_forwarded_trusted {
+                // quote from RFC 7239:
+                // A proxy server that wants to add a new "Forwarded" header field value
+                //    can either append it to the last existing "Forwarded" header field
+                //    after a comma separator or add a new field at the end of the header
+                //    block.
+                // --- https://datatracker.ietf.org/doc/html/rfc7239#section-4
+                // so we get the values in reverse order as we want to get the first untrusted value
+                let mut forwarded_list = headers
+                    .forwarded()
+                    .rev()
+                    .enumerate()
+                    .filter(|(index, vals)| {
+                        if client_found.get() {
+                            context.fits("forwarded", *index, vals)
+                        } else {
+                            context.accept("forwarded", *index, vals)
+                        }
+                    })
+                    // "for=1.2.3.4, for=5.6.7.8; scheme=https"
+                    .flat_map(|(_, vals)| vals.split(',').rev())
+                    .take(max_elements);
+                // ["for=5.6.7.8; scheme=https", "for=1.2.3.4"]
+                let skipped = context.skipped_elements(
+                    context
+                        .elements("forwarded", headers.forwarded())
+                        .take(max_elements)
+                        .map(|forwarded| {
+                            forwarded_for(forwarded)
+                                .and_then(|value| bare_address(value).parse().ok())
+                        }),
+                );
+
+                'forwaded: for (index, forwarded) in forwarded_list.by_ref().enumerate() {
+                    depth += 1;
+                    walked.push(forwarded);
+                    walked_header = Some("forwarded");
+                    trace!(element = forwarded, "considering forwarded element");
+
+                    if is_hop(index, skipped) {
+                        trace!(
+                            element = forwarded,
+                            "forwarded element is a trusted hop, skipped"
+                        );
+                        proxies.extend(
+                            forwarded_for(forwarded)
+                                .and_then(|value| bare_address(value).parse::<IpAddr>().ok()),
+                        );
+
+                        continue;
+                    }
+
+                    for (key, value) in forwarded.split(';').map(|item| {
+                        let mut kv = item.splitn(2, '=');
+
+                        (
+                            kv.next().map(|s| s.trim()).unwrap_or_default(),
+                            kv.next().map(|s| unquote(s.trim())).unwrap_or_default(),
+                        )
+                    }) {
+                        match key.to_lowercase().as_str() {
+                            "for" => {
+                                if let Ok(ip) = bare_address(value).parse::<IpAddr>() {
+                                    realip_remote_addr = Some(ip);
+
+                                    if is_proxy(&ip) {
+                                        trace!(%ip, "forwarded element is a trusted proxy, skipped");
+                                        proxies.push(ip);
+
+                                        host = None;
+                                        scheme = None;
+                                        by = None;
+                                        realip_remote_addr = None;
+
+                                        continue 'forwaded;
                                     }
+                                } else {
+                                    trace!(value, "forwarded element has an invalid for address");
+                                    telemetry::parse_failure("forwarded");
                                 }
-                                "proto" => {
-                                    scheme = Some(value);
-                                }
-                                "host" => {
-                                    host = Some(value);
-                                }
-                                "by" => {
-                                    by = Some(value);
-                                }
-                                _ => {}
                             }
+                            "proto" => {
+                                scheme = Some(value);
+                            }
+                            "host" => {
+                                host = Some(value);
+                            }
+                            "by" => {
+                                by = Some(value);
+                            }
+                            _ => {}
                         }
+                    }
 
-                        break;
+                    debug!(
+                        ip = ?realip_remote_addr,
+                        host,
+                        scheme,
+                        by,
+                        "forwarded element accepted"
+                    );
+
+                    if realip_remote_addr.is_some() {
+                        source = IpSource::Forwarded;
                     }
+
+                    break;
                 }
 
-                if realip_remote_addr.is_none() && config.is_x_forwarded_for_trusted {
-                    for value in request
-                        .x_forwarded_for()
-                        .flat_map(|vals| vals.split(','))
-                        .map(|s| s.trim())
-                        .rev()
-                    {
-                        if let Ok(ip) = bare_address(value).parse::<IpAddr>() {
-                            if config.is_ip_trusted(&ip) {
-                                continue;
-                            }
+                if realip_remote_addr.is_some() {
+                    client_found.set(true);
+                    untrusted_chain.extend(forwarded_list.filter_map(forwarded_for));
+                }
+
+                let schemes = walked
+                    .iter()
+                    .filter_map(|forwarded| forwarded_param(forwarded, "proto"));
+
+                if is_downgraded(schemes) {
+                    debug!("forwarded header shows a scheme downgrade");
+                    context.warn(Warning::SchemeDowngrade {
+                        header: "forwarded",
+                    });
+                }
+
+                host_chain.extend(
+                    walked
+                        .iter()
+                        .filter_map(|forwarded| forwarded_param(forwarded, "host")),
+                );
+
+                if is_mismatched(&host_chain) {
+                    debug!(?host_chain, "forwarded header shows different hosts");
+                    context.warn(Warning::HostMismatch {
+                        header: "forwarded",
+                    });
+                }
+            }
+
+            if realip_remote_addr.is_none() && config.is_x_forwarded_for_trusted {
+                proxies.clear();
+
+                let mut x_forwarded_for_list = headers
+                    .x_forwarded_for()
+                    .rev()
+                    .enumerate()
+                    .filter(|(index, vals)| {
+                        if client_found.get() {
+                            context.fits("x-forwarded-for", *index, vals)
+                        } else {
+                            context.accept("x-forwarded-for", *index, vals)
+                        }
+                    })
+                    .flat_map(|(_, vals)| vals.split(',').rev())
+                    .take(max_elements)
+                    .map(|s| s.trim());
+                let skipped = context.skipped_elements(
+                    context
+                        .elements("x-forwarded-for", headers.x_forwarded_for())
+                        .take(max_elements)
+                        .map(|value| bare_address(value).parse().ok()),
+                );
+
+                for (index, value) in x_forwarded_for_list.by_ref().enumerate() {
+                    depth += 1;
+                    walked_header = Some("x-forwarded-for");
+                    trace!(element = value, "considering x-forwarded-for element");
+
+                    if is_hop(index, skipped) {
+                        trace!(
+                            element = value,
+                            "x-forwarded-for element is a trusted hop, skipped"
+                        );
+                        proxies.extend(bare_address(value).parse::<IpAddr>().ok());
+
+                        continue;
+                    }
 
-                            realip_remote_addr = Some(ip);
+                    if let Ok(ip) = bare_address(value).parse::<IpAddr>() {
+                        if is_proxy(&ip) {
+                            trace!(%ip, "x-forwarded-for element is a trusted proxy, skipped");
+                            proxies.push(ip);
+
+                            continue;
                         }
 
-                        break;
+                        debug!(%ip, "x-forwarded-for element accepted");
+                        realip_remote_addr = Some(ip);
+                        source = IpSource::XForwardedFor;
+                    } else {
+                        trace!(value, "x-forwarded-for element is not a valid address");
+                        telemetry::parse_failure("x-forwarded-for");
+                    }
+
+                    break;
+                }
+
+                if realip_remote_addr.is_some() {
+                    client_found.set(true);
+                    untrusted_chain.extend(x_forwarded_for_list);
+                }
+            }
+
+            if host.is_none() && config.is_x_forwarded_host_trusted {
+                host = context.last_value("x-forwarded-host", headers.x_forwarded_host());
+                debug!(host, "host taken from x-forwarded-host");
+
+                // values added by the client in front of the first trusted hop can be forged
+                host_chain.extend(
+                    context
+                        .elements("x-forwarded-host", headers.x_forwarded_host())
+                        .take(proxies.len() + 1)
+                        .filter(|value| !value.is_empty()),
+                );
+
+                if is_mismatched(&host_chain) {
+                    debug!(?host_chain, "x-forwarded-host header shows different hosts");
+                    context.warn(Warning::HostMismatch {
+                        header: "x-forwarded-host",
+                    });
+                }
+            }
+
+            if scheme.is_none() && config.is_x_forwarded_proto_trusted {
+                scheme = context.last_value("x-forwarded-proto", headers.x_forwarded_proto());
+                debug!(scheme, "scheme taken from x-forwarded-proto");
+
+                // values added by the client in front of the first trusted hop can be forged
+                let schemes = context
+                    .elements("x-forwarded-proto", headers.x_forwarded_proto())
+                    .take(proxies.len() + 1);
+
+                if is_downgraded(schemes) {
+                    debug!("x-forwarded-proto header shows a scheme downgrade");
+                    context.warn(Warning::SchemeDowngrade {
+                        header: "x-forwarded-proto",
+                    });
+                }
+            }
+
+            if by.is_none() && config.is_x_forwarded_by_trusted {
+                by = context.last_value("x-forwarded-by", headers.x_forwarded_by());
+                debug!(by, "proxy taken from x-forwarded-by");
+            }
+
+            if config.is_loop_detected() {
+                // only the trusted hops walked to find the client are checked, the entries sent
+                // by the client can be forged
+                let mut looped = Vec::new();
+
+                if let Some(header) = walked_header {
+                    if proxies
+                        .iter()
+                        .chain([&ip_addr])
+                        .any(|ip| config.is_server_ip(ip))
+                    {
+                        looped.push(header);
                     }
                 }
 
-                if host.is_none() && config.is_x_forwarded_host_trusted {
-                    host = request
-                        .x_forwarded_host()
-                        .flat_map(|vals| vals.split(','))
-                        .map(|s| s.trim())
-                        .next_back();
+                let forwarded_nodes = walked
+                    .iter()
+                    .filter_map(|forwarded| forwarded_param(forwarded, "by"));
+
+                if is_looped(config, forwarded_nodes) {
+                    looped.push("forwarded");
                 }
 
-                if scheme.is_none() && config.is_x_forwarded_proto_trusted {
-                    scheme = request
-                        .x_forwarded_proto()
-                        .flat_map(|vals| vals.split(','))
-                        .map(|s| s.trim())
-                        .next_back();
+                let x_forwarded_by_nodes = context
+                    .elements("x-forwarded-by", headers.x_forwarded_by())
+                    .take(proxies.len() + 1);
+
+                if config.is_x_forwarded_by_trusted && is_looped(config, x_forwarded_by_nodes) {
+                    looped.push("x-forwarded-by");
                 }
 
-                if by.is_none() && config.is_x_forwarded_by_trusted {
-                    by = request
-                        .x_forwarded_by()
-                        .flat_map(|vals| vals.split(','))
-                        .map(|s| s.trim())
-                        .next_back();
+                looped.dedup();
+
+                for header in looped {
+                    debug!(header, "forwarding header goes through this server");
+                    context.warn(Warning::ForwardingLoop { header });
                 }
+            }
+
+            if let Some((name, priority)) = &config.client_ip_header {
+                let ip = if *priority == ClientIpHeaderPriority::BeforeForwarded
+                    || realip_remote_addr.is_none()
+                {
+                    last_address(&request.header(name).collect::<Vec<_>>())
+                } else {
+                    None
+                };
+
+                if let Some(ip) = ip {
+                    debug!(%ip, header = &**name, "client address given by a single address header");
+                    realip_remote_addr = Some(ip);
+                    proxies.clear();
+                    untrusted_chain.clear();
+                    source = IpSource::ClientIpHeader;
+                }
+            }
+
+            if let Some(ip) = config
+                .ip_extractors
+                .extract(request, config.ip_group(&ip_addr))
+            {
+                debug!(%ip, "client address given by an extractor");
+                realip_remote_addr = Some(ip);
+                proxies.clear();
+                untrusted_chain.clear();
+                source = IpSource::Extractor;
+            }
+
+            if config.private_client_ip == PrivateClientIp::UsePeer {
+                if let Some(ip) = realip_remote_addr.filter(bogon::is_bogon) {
+                    debug!(%ip, "client address is private or reserved, using peer address");
+                    context.warn(Warning::PrivateClientIp { ip });
+                    realip_remote_addr = None;
+                    proxies.clear();
+                    untrusted_chain.clear();
+                    source = IpSource::Peer;
+                }
+            }
+
+            if let Some(rejected) = scheme.filter(|scheme| !config.is_scheme_allowed(scheme)) {
+                debug!(scheme = rejected, "scheme is not allowed");
+                context.warn(Warning::SchemeRejected {
+                    scheme: rejected.to_string(),
+                });
+                scheme = None;
+            }
+
+            if let Some(rejected) = host.filter(|host| !config.is_host_allowed(host)) {
+                debug!(host = rejected, "host is not allowed");
+                context.warn(Warning::HostRejected {
+                    host: rejected.to_string(),
+                });
+                host = None;
+            }
+
+            if scheme.is_none() && config.is_proto_required {
+                context.warn(Warning::MissingProto);
+            }
+
+            if host.is_none() && config.is_host_required {
+                context.warn(Warning::MissingHost);
+            }
+
+            (
+                host.or_else(|| request.default_host()),
+                scheme.or_else(|| request.default_scheme()),
+                by,
+                realip_remote_addr.unwrap_or(ip_addr),
+            )
+        };
+
+        debug!(
+            peer = %ip_addr,
+            ip = %trusted_ip,
+            host = trusted_host,
+            scheme = trusted_scheme,
+            by = trusted_by,
+            "trusted information extracted"
+        );
 
-                (
-                    host.or_else(|| request.default_host()),
-                    scheme.or_else(|| request.default_scheme()),
-                    by,
-                    realip_remote_addr.unwrap_or(ip_addr),
-                )
-            };
-
-        Self::Borrowed(TrustedBorrowed {
-            host: trusted_host,
-            scheme: trusted_scheme,
-            by: trusted_by,
+        #[cfg(feature = "hmac")]
+        let trusted_ip = match &config.signed_client_ip {
+            Some(signed) => match signed.verify(request, &config.header_names) {
+                Some(Ok(ip)) => {
+                    debug!(%ip, "signed client address accepted");
+                    source = IpSource::Signed;
+                    // the chain walked in the forwarding headers does not lead to this address
+                    proxies.clear();
+                    untrusted_chain.clear();
+                    host_chain.clear();
+                    ip
+                }
+                Some(Err(())) => {
+                    let header = config.header_names.signed_client_ip();
+                    debug!(header, "signed client address rejected");
+                    context.warn(Warning::InvalidSignature {
+                        header: header.to_string(),
+                    });
+                    trusted_ip
+                }
+                None => trusted_ip,
+            },
+            None => trusted_ip,
+        };
+
+        if let (Some(host), Some(scheme)) = (trusted_host, trusted_scheme) {
+            if let Some((name, port)) = host.split_once(':') {
+                if let Some(port) = port.parse().ok().filter(|p| is_mismatched_port(scheme, *p)) {
+                    debug!(scheme, port, "port does not match scheme");
+                    context.warn(Warning::SchemePortMismatch {
+                        scheme: scheme.to_string(),
+                        port,
+                    });
+
+                    if config.is_port_corrected {
+                        trusted_host = Some(name);
+                    }
+                }
+            }
+        }
+
+        let mut host = trusted_host.map(Cow::Borrowed);
+
+        if let (Some(normalizer), Some(trusted_host)) = (&config.host_normalizer, trusted_host) {
+            host = normalizer.normalize(trusted_host, trusted_scheme);
+
+            if host.is_none() {
+                debug!(host = trusted_host, "host rejected by the normalizer");
+                context.warn(Warning::HostRejected {
+                    host: trusted_host.to_string(),
+                });
+            }
+        }
+
+        if config.is_server_name_checked {
+            let host = host.as_deref().and_then(|host| host.split(':').next());
+
+            if let (Some(server_name), Some(host)) = (connection.server_name(), host) {
+                if !server_name.eq_ignore_ascii_case(host) {
+                    debug!(server_name, host, "server name does not match host");
+                    context.warn(Warning::ServerNameMismatch {
+                        server_name: server_name.to_string(),
+                        host: host.to_string(),
+                    });
+                }
+            }
+        }
+
+        context.report_warnings(ip_addr);
+        telemetry::extraction(peer_trusted, depth);
+
+        proxies.reverse();
+        untrusted_chain.reverse();
+        host_chain.reverse();
+
+        let retained_proxies = proxies
+            .iter()
+            .chain(peer_trusted.then_some(&ip_addr))
+            .filter(|ip| !config.is_internal_proxy(ip))
+            .copied()
+            .collect();
+
+        Self {
+            buffer: String::new(),
+            host: host.map(Value::Cow),
+            scheme: trusted_scheme.map(|scheme| Value::Cow(Cow::Borrowed(scheme))),
+            by: trusted_by.map(|by| Value::Cow(Cow::Borrowed(by))),
             ip: trusted_ip,
-        })
+            source,
+            peer: ip_addr,
+            proxies,
+            untrusted_chain: untrusted_chain
+                .into_iter()
+                .map(|entry| Value::Cow(Cow::Borrowed(entry)))
+                .collect(),
+            host_chain: host_chain
+                .into_iter()
+                .map(|host| Value::Cow(Cow::Borrowed(host)))
+                .collect(),
+            retained_proxies,
+            warnings: context.warnings.into_inner(),
+            ignored_headers,
+            via_group: peer_trusted
+                .then(|| config.find_group(&ip_addr).cloned())
+                .flatten(),
+            reputation: config
+                .reputation_provider
+                .as_ref()
+                .map(|provider| provider.reputation(trusted_ip)),
+        }
+    }
+}
+
+/// Extract a `http` request with the process-wide configuration, see [`crate::set_global_config`]
+///
+/// The conversion is strict and fails on the first warning, like [`Trusted::from_strict`].
+///
+/// # Example
+/// ```
+/// use core::net::SocketAddr;
+/// use trusted_proxies::Trusted;
+///
+/// let mut request = http::Request::get("/").body(()).unwrap();
+/// request.headers_mut().insert("x-forwarded-for", "1.2.3.4".parse().unwrap());
+/// let peer = SocketAddr::from(([127, 0, 0, 1], 4321));
+///
+/// let trusted: Trusted = (&peer, &request).try_into().unwrap();
+/// assert_eq!(trusted.ip(), core::net::IpAddr::from([1, 2, 3, 4]));
+/// ```
+#[cfg(feature = "http")]
+impl<'a, T> TryFrom<(&core::net::SocketAddr, &'a http::Request<T>)> for Trusted<'a> {
+    type Error = Warning;
+
+    fn try_from(
+        (peer, request): (&core::net::SocketAddr, &'a http::Request<T>),
+    ) -> Result<Self, Warning> {
+        Self::from_strict(peer.ip(), request, crate::global_config())
     }
 }
 
 #[cfg(all(test, feature = "http"))]
 mod tests {
     use super::*;
+    use crate::{
+        ConfigError, DualExtractor, DynRequestInformation, PeerTrust, ServerIdentity,
+        ShadowDifference, ShadowEvaluation,
+    };
     use http::{header, Request, Version};
 
+    /// A request giving raw header values, without the validation done by the `http` crate
+    #[derive(Default)]
+    struct RawRequest {
+        forwarded: Vec<&'static str>,
+        x_forwarded_for: Vec<&'static str>,
+    }
+
+    impl RequestInformation for RawRequest {
+        fn is_host_header_allowed(&self) -> bool {
+            true
+        }
+
+        fn host_header(&self) -> Option<&str> {
+            None
+        }
+
+        fn authority(&self) -> Option<&str> {
+            None
+        }
+
+        fn forwarded(&self) -> impl DoubleEndedIterator<Item = &str> {
+            self.forwarded.iter().copied()
+        }
+
+        fn x_forwarded_for(&self) -> impl DoubleEndedIterator<Item = &str> {
+            self.x_forwarded_for.iter().copied()
+        }
+
+        fn x_forwarded_host(&self) -> impl DoubleEndedIterator<Item = &str> {
+            core::iter::empty()
+        }
+
+        fn x_forwarded_proto(&self) -> impl DoubleEndedIterator<Item = &str> {
+            core::iter::empty()
+        }
+
+        fn x_forwarded_by(&self) -> impl DoubleEndedIterator<Item = &str> {
+            core::iter::empty()
+        }
+
+        fn default_scheme(&self) -> Option<&str> {
+            None
+        }
+    }
+
     #[test]
     fn default() {
         let request = Request::get("http://localhost:8080/").body(()).unwrap();
@@ -694,4 +1952,2102 @@ mod tests {
         assert_eq!(trusted.ip(), "192.0.2.60".parse::<IpAddr>().unwrap());
         assert_eq!(trusted.scheme(), None);
     }
+
+    #[test]
+    fn into_owned() {
+        let mut request = Request::get("/").body(()).unwrap();
+        request.headers_mut().append(
+            header::HeaderName::from_static("forwarded"),
+            "for=192.0.2.60; proto=https; by=203.0.113.43; host=rust-lang.org:8080"
+                .parse()
+                .unwrap(),
+        );
+
+        let config = Config::default();
+        let trusted = Trusted::from("127.0.0.1".parse().unwrap(), &request, &config).into_owned();
+        drop(request);
+
+        assert_eq!(
+            trusted.buffer,
+            "rust-lang.org:8080https203.0.113.43rust-lang.org:8080"
+        );
+        assert!(matches!(trusted.host, Some(Value::Range(_))));
+        assert_eq!(trusted.scheme(), Some("https"));
+        assert_eq!(trusted.host(), Some("rust-lang.org"));
+        assert_eq!(trusted.port(), Some(8080));
+        assert_eq!(trusted.by(), Some("203.0.113.43"));
+        assert_eq!(trusted.ip(), "192.0.2.60".parse::<IpAddr>().unwrap());
+
+        let request = Request::get("/").body(()).unwrap();
+        let trusted = Trusted::from("127.0.0.1".parse().unwrap(), &request, &config).into_owned();
+
+        assert_eq!(trusted.scheme(), None);
+        assert_eq!(trusted.host(), None);
+        assert_eq!(trusted.by(), None);
+    }
+
+    #[test]
+    fn header_value_too_long() {
+        let mut request = Request::get("/").body(()).unwrap();
+        request.headers_mut().append(
+            header::HeaderName::from_static("x-forwarded-for"),
+            "1.1.1.1".parse().unwrap(),
+        );
+        request.headers_mut().append(
+            header::HeaderName::from_static("x-forwarded-for"),
+            "8.8.8.8, 192.168.0.1, 192.168.0.2".parse().unwrap(),
+        );
+
+        let mut config = Config::default();
+        config.set_max_header_value_length(16);
+
+        let trusted = Trusted::from("127.0.0.1".parse().unwrap(), &request, &config);
+        assert_eq!(trusted.ip(), "1.1.1.1".parse::<IpAddr>().unwrap());
+        assert_eq!(
+            trusted.warnings(),
+            &[Warning::HeaderValueTooLong {
+                header: "x-forwarded-for",
+                length: 33,
+                max: 16
+            }]
+        );
+
+        let error = Trusted::from_strict("127.0.0.1".parse().unwrap(), &request, &config)
+            .expect_err("strict extraction should fail");
+        assert_eq!(
+            error,
+            Warning::HeaderValueTooLong {
+                header: "x-forwarded-for",
+                length: 33,
+                max: 16
+            }
+        );
+    }
+
+    #[test]
+    fn total_header_length_exceeded() {
+        let mut request = Request::get("/").body(()).unwrap();
+        request.headers_mut().append(
+            header::HeaderName::from_static("forwarded"),
+            "for=1.1.1.1".parse().unwrap(),
+        );
+        request.headers_mut().append(
+            header::HeaderName::from_static("forwarded"),
+            "for=192.168.0.1".parse().unwrap(),
+        );
+        request.headers_mut().append(
+            header::HeaderName::from_static("x-forwarded-proto"),
+            "https".parse().unwrap(),
+        );
+
+        let mut config = Config::default();
+        config.trust_x_forwarded_proto();
+        config.set_max_total_header_length(20);
+
+        let trusted = Trusted::from("127.0.0.1".parse().unwrap(), &request, &config);
+        assert_eq!(trusted.ip(), "127.0.0.1".parse::<IpAddr>().unwrap());
+        assert_eq!(trusted.scheme(), Some("https"));
+        assert_eq!(
+            trusted.warnings(),
+            &[Warning::TotalHeaderLengthExceeded { max: 20 }]
+        );
+
+        config.set_max_total_header_length(64);
+
+        let trusted = Trusted::from_strict("127.0.0.1".parse().unwrap(), &request, &config)
+            .expect("strict extraction should succeed");
+        assert_eq!(trusted.ip(), "1.1.1.1".parse::<IpAddr>().unwrap());
+        assert!(trusted.warnings().is_empty());
+    }
+
+    #[test]
+    fn total_header_length_charges_values_once() {
+        let request = RawRequest {
+            x_forwarded_for: vec![
+                "1.2.3.4, 10.0.0.1, 10.0.0.2",
+                "10.0.0.3, 10.0.0.4, 10.0.0.5",
+            ],
+            ..RawRequest::default()
+        };
+        let options: [fn(&mut Config); 3] = [
+            |_| {},
+            |config| config.set_max_chain_length(10),
+            |config| config.set_client_ip_strategy(ClientIpStrategy::Leftmost),
+        ];
+
+        for option in options {
+            let mut unlimited = Config::new_local();
+            option(&mut unlimited);
+
+            let mut limited = unlimited.clone();
+            limited.set_max_total_header_length(100);
+
+            let expected = Trusted::from(IpAddr::from([127, 0, 0, 1]), &request, &unlimited);
+            let trusted = Trusted::from(IpAddr::from([127, 0, 0, 1]), &request, &limited);
+            assert_eq!(trusted.ip(), expected.ip());
+            assert_eq!(trusted.proxies(), expected.proxies());
+            assert!(trusted.warnings().is_empty());
+        }
+
+        let request = RawRequest {
+            x_forwarded_for: vec!["10.0.0.1"; 100],
+            ..RawRequest::default()
+        };
+
+        let mut config = Config::new_local();
+        config.set_max_total_header_length(64);
+
+        let context = Context::new(&config);
+        assert_eq!(
+            context.chain_length("x-forwarded-for", request.x_forwarded_for.iter().copied()),
+            8
+        );
+        assert_eq!(
+            context
+                .elements("x-forwarded-for", request.x_forwarded_for.iter().copied())
+                .count(),
+            8
+        );
+
+        // the same 8 values are examined, Leftmost takes the farthest one as the client
+        for (option, proxies) in options.into_iter().zip([8, 8, 7]) {
+            let mut config = config.clone();
+            option(&mut config);
+
+            let trusted = Trusted::from(IpAddr::from([127, 0, 0, 1]), &request, &config);
+            assert_eq!(trusted.proxies().len(), proxies);
+            assert_eq!(
+                trusted.warnings(),
+                &[Warning::TotalHeaderLengthExceeded { max: 64 }]
+            );
+        }
+    }
+
+    #[test]
+    fn compiled_matcher() {
+        let mut config = Config::new();
+        config.trust_forwarded();
+        config.add_trusted_ip("10.0.0.0/8").unwrap();
+        config.add_trusted_ip("10.1.0.0/16").unwrap();
+        config.add_trusted_ip("11.0.0.0/8").unwrap();
+        config.add_trusted_ip("192.168.1.1").unwrap();
+        config.add_trusted_ip("fd00::/8").unwrap();
+
+        let matcher = config.compile();
+
+        for ip in [
+            "10.0.0.0",
+            "10.1.2.3",
+            "11.255.255.255",
+            "192.168.1.1",
+            "fd12::1",
+            "1.1.1.1",
+            "12.0.0.0",
+            "192.168.1.2",
+            "9.255.255.255",
+            "fe00::1",
+            "::1",
+        ] {
+            let ip = ip.parse::<IpAddr>().unwrap();
+            assert_eq!(
+                matcher.is_ip_trusted(&ip),
+                config.is_ip_trusted(&ip),
+                "{ip}"
+            );
+        }
+
+        let mut request = Request::get("/").body(()).unwrap();
+        request.headers_mut().append(
+            header::HeaderName::from_static("forwarded"),
+            "for=1.1.1.1, for=10.2.3.4".parse().unwrap(),
+        );
+
+        let matcher = std::sync::Arc::new(matcher);
+        let trusted = Trusted::from("11.0.0.1".parse().unwrap(), &request, &matcher);
+        assert_eq!(trusted.ip(), "1.1.1.1".parse::<IpAddr>().unwrap());
+    }
+
+    #[test]
+    fn spoof_attempt() {
+        let events = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = events.clone();
+
+        let mut config = Config::default();
+        config.set_max_header_value_length(16);
+        config.on_spoof_attempt(move |event| recorded.lock().unwrap().push(event));
+
+        let mut request = Request::get("/").body(()).unwrap();
+        request.headers_mut().append(
+            header::HeaderName::from_static("forwarded"),
+            "for=1.1.1.1".parse().unwrap(),
+        );
+        request.headers_mut().append(
+            header::HeaderName::from_static("x-forwarded-host"),
+            "example.com".parse().unwrap(),
+        );
+
+        Trusted::from("8.8.8.8".parse().unwrap(), &request, &config);
+
+        assert_eq!(
+            events.lock().unwrap().drain(..).collect::<Vec<_>>(),
+            vec![
+                SpoofEvent::UntrustedPeer {
+                    peer: "8.8.8.8".parse().unwrap(),
+                    header: "forwarded"
+                },
+                SpoofEvent::UntrustedPeer {
+                    peer: "8.8.8.8".parse().unwrap(),
+                    header: "x-forwarded-host"
+                },
+            ]
+        );
+
+        Trusted::from("127.0.0.1".parse().unwrap(), &request, &config);
+        assert!(events.lock().unwrap().is_empty());
+
+        request.headers_mut().append(
+            header::HeaderName::from_static("forwarded"),
+            "for=1.1.1.1;host=example.com".parse().unwrap(),
+        );
+
+        Trusted::from("127.0.0.1".parse().unwrap(), &request, &config);
+        assert_eq!(
+            events.lock().unwrap().drain(..).collect::<Vec<_>>(),
+            vec![SpoofEvent::InvalidValue {
+                peer: "127.0.0.1".parse().unwrap(),
+                warning: Warning::HeaderValueTooLong {
+                    header: "forwarded",
+                    length: 28,
+                    max: 16
+                }
+            }]
+        );
+    }
+
+    #[test]
+    fn ignored_headers() {
+        let mut request = Request::get("/").body(()).unwrap();
+        request.headers_mut().append(
+            header::HeaderName::from_static("x-forwarded-for"),
+            "1.1.1.1".parse().unwrap(),
+        );
+        request.headers_mut().append(
+            header::HeaderName::from_static("x-forwarded-proto"),
+            "https".parse().unwrap(),
+        );
+
+        let config = Config::default();
+
+        let trusted = Trusted::from("127.0.0.1".parse().unwrap(), &request, &config);
+        assert_eq!(
+            trusted.ignored_headers(),
+            &[IgnoredHeader {
+                name: "x-forwarded-proto",
+                reason: IgnoreReason::NotTrusted
+            }]
+        );
+
+        let trusted = Trusted::from("8.8.8.8".parse().unwrap(), &request, &config).into_owned();
+        assert_eq!(
+            trusted.ignored_headers(),
+            &[
+                IgnoredHeader {
+                    name: "x-forwarded-for",
+                    reason: IgnoreReason::UntrustedPeer
+                },
+                IgnoredHeader {
+                    name: "x-forwarded-proto",
+                    reason: IgnoreReason::UntrustedPeer
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_characters() {
+        let request = RawRequest {
+            forwarded: vec!["for=1.1.1.1;host=\"example.com\r\nX-Injected: 1\""],
+            ..Default::default()
+        };
+        let config = Config::default();
+
+        let trusted = Trusted::from("127.0.0.1".parse().unwrap(), &request, &config);
+        assert_eq!(trusted.ip(), "1.1.1.1".parse::<IpAddr>().unwrap());
+        assert_eq!(
+            trusted.warnings(),
+            &[Warning::InvalidCharacters {
+                header: "forwarded"
+            }]
+        );
+
+        let error = Trusted::from_strict("127.0.0.1".parse().unwrap(), &request, &config)
+            .expect_err("strict extraction should fail");
+        assert_eq!(
+            error,
+            Warning::InvalidCharacters {
+                header: "forwarded"
+            }
+        );
+
+        let request = RawRequest {
+            x_forwarded_for: vec!["1.1.1.1\0", "\t192.168.0.1"],
+            ..Default::default()
+        };
+
+        let error = Trusted::from_strict("127.0.0.1".parse().unwrap(), &request, &config)
+            .expect_err("strict extraction should fail");
+        assert_eq!(
+            error,
+            Warning::InvalidCharacters {
+                header: "x-forwarded-for"
+            }
+        );
+    }
+
+    #[test]
+    fn required_fields() {
+        let mut request = Request::get("http://localhost/").body(()).unwrap();
+        request.headers_mut().append(
+            header::HeaderName::from_static("forwarded"),
+            "for=1.1.1.1;proto=https".parse().unwrap(),
+        );
+
+        let mut config = Config::default();
+        config.require_proto();
+        config.require_host();
+
+        let trusted = Trusted::from("127.0.0.1".parse().unwrap(), &request, &config);
+        assert_eq!(trusted.scheme(), Some("https"));
+        assert_eq!(trusted.host(), Some("localhost"));
+        assert_eq!(trusted.warnings(), &[Warning::MissingHost]);
+
+        let error = Trusted::from_strict("127.0.0.1".parse().unwrap(), &request, &config)
+            .expect_err("strict extraction should fail");
+        assert_eq!(error, Warning::MissingHost);
+
+        // an untrusted peer is not expected to send anything
+        let trusted = Trusted::from_strict("8.8.8.8".parse().unwrap(), &request, &config)
+            .expect("strict extraction should succeed");
+        assert_eq!(trusted.scheme(), Some("http"));
+    }
+
+    #[test]
+    fn private_client_ip() {
+        let mut request = Request::get("/").body(()).unwrap();
+        request.headers_mut().append(
+            header::HeaderName::from_static("x-forwarded-for"),
+            "1.1.1.1, 100.64.0.1, 192.168.0.1".parse().unwrap(),
+        );
+
+        let mut config = Config::new();
+        config.trust_x_forwarded_for();
+        config.add_trusted_ip("192.168.0.0/16").unwrap();
+
+        let trusted = Trusted::from("192.168.0.2".parse().unwrap(), &request, &config);
+        assert_eq!(trusted.ip(), "100.64.0.1".parse::<IpAddr>().unwrap());
+        assert!(trusted.warnings().is_empty());
+
+        config.set_private_client_ip(PrivateClientIp::Skip);
+
+        let trusted = Trusted::from("192.168.0.2".parse().unwrap(), &request, &config);
+        assert_eq!(trusted.ip(), "1.1.1.1".parse::<IpAddr>().unwrap());
+        assert_eq!(
+            trusted.warnings(),
+            &[Warning::PrivateClientIp {
+                ip: "100.64.0.1".parse().unwrap()
+            }]
+        );
+
+        config.set_private_client_ip(PrivateClientIp::UsePeer);
+
+        let trusted = Trusted::from("192.168.0.2".parse().unwrap(), &request, &config);
+        assert_eq!(trusted.ip(), "192.168.0.2".parse::<IpAddr>().unwrap());
+        assert_eq!(
+            trusted.warnings(),
+            &[Warning::PrivateClientIp {
+                ip: "100.64.0.1".parse().unwrap()
+            }]
+        );
+
+        for ip in [
+            "::ffff:10.0.0.1",
+            "::",
+            "::1",
+            "fc00::1",
+            "fe80::1",
+            "2001:db8::1",
+            "127.0.0.1",
+            "224.0.0.1",
+            "240.0.0.1",
+            "255.255.255.255",
+        ] {
+            assert!(bogon::is_bogon(&ip.parse().unwrap()), "{ip}");
+        }
+
+        for ip in ["::ffff:1.1.1.1", "2a00::1", "100.128.0.1", "8.8.8.8"] {
+            assert!(!bogon::is_bogon(&ip.parse().unwrap()), "{ip}");
+        }
+    }
+
+    #[test]
+    fn config_resolver() {
+        let mut tenant = Config::new();
+        tenant.add_trusted_ip("203.0.113.0/24").unwrap();
+        tenant.trust_forwarded();
+
+        let mut other = Config::new();
+        other.add_trusted_ip("198.51.100.0/24").unwrap();
+
+        let mut resolver = crate::ConfigResolver::new(Config::new_local());
+        resolver.add_host("tenant.com", tenant.clone());
+        resolver.add_host("*.tenant.com", tenant);
+        resolver.add_host("admin.tenant.com", other);
+
+        let ip = "203.0.113.1".parse::<IpAddr>().unwrap();
+
+        assert!(resolver.resolve(Some("Tenant.com:8080")).is_ip_trusted(&ip));
+        assert!(resolver.resolve(Some("a.b.tenant.com")).is_ip_trusted(&ip));
+        assert!(!resolver
+            .resolve(Some("admin.tenant.com"))
+            .is_ip_trusted(&ip));
+        assert!(!resolver.resolve(Some("tenant.org")).is_ip_trusted(&ip));
+        assert!(!resolver.resolve(None).is_ip_trusted(&ip));
+
+        let mut request = Request::get("http://www.tenant.com/").body(()).unwrap();
+        request.headers_mut().append(
+            header::HeaderName::from_static("forwarded"),
+            "for=1.1.1.1".parse().unwrap(),
+        );
+
+        let trusted = Trusted::from(ip, &request, resolver.resolve_request(&request));
+        assert_eq!(trusted.ip(), "1.1.1.1".parse::<IpAddr>().unwrap());
+    }
+
+    #[test]
+    fn via_group() {
+        let mut config = Config::new();
+        config.trust_x_forwarded_for();
+        config.add_trusted_ip("10.0.0.0/8").unwrap();
+        config
+            .add_trusted_ip_group("cloudflare", "173.245.48.0/20")
+            .unwrap();
+        config
+            .add_trusted_ip_group("internal", "10.0.0.0/8")
+            .unwrap();
+        config
+            .add_trusted_ip_group("internal-lb", "10.0.1.0/24")
+            .unwrap();
+
+        let request = Request::get("/").body(()).unwrap();
+
+        let trusted = Trusted::from("173.245.48.1".parse().unwrap(), &request, &config);
+        assert_eq!(trusted.via_group(), Some("cloudflare"));
+
+        let trusted = Trusted::from("10.0.1.1".parse().unwrap(), &request, &config.compile());
+        assert_eq!(trusted.into_owned().via_group(), Some("internal-lb"));
+
+        let trusted = Trusted::from("10.0.2.1".parse().unwrap(), &request, &config);
+        assert_eq!(trusted.via_group(), Some("internal"));
+
+        let trusted = Trusted::from("1.1.1.1".parse().unwrap(), &request, &config);
+        assert_eq!(trusted.via_group(), None);
+    }
+
+    #[test]
+    fn client_certificate() {
+        struct Connection(IpAddr, Vec<&'static str>);
+
+        impl ConnectionInformation for Connection {
+            fn peer_addr(&self) -> IpAddr {
+                self.0
+            }
+
+            fn client_certificate_identities(&self) -> impl Iterator<Item = &str> {
+                self.1.iter().copied()
+            }
+        }
+
+        let mut request = Request::get("/").body(()).unwrap();
+        request.headers_mut().append(
+            header::HeaderName::from_static("forwarded"),
+            "for=1.1.1.1".parse().unwrap(),
+        );
+
+        let mut config = Config::default();
+        config.require_client_certificate("spiffe://example.org/proxy/*");
+
+        let peer = "127.0.0.1".parse::<IpAddr>().unwrap();
+
+        let connection = Connection(
+            peer,
+            vec!["proxy.example.org", "spiffe://example.org/proxy/edge"],
+        );
+        let trusted = Trusted::from_connection(&connection, &request, &config);
+        assert_eq!(trusted.ip(), "1.1.1.1".parse::<IpAddr>().unwrap());
+
+        let connection = Connection(peer, vec!["spiffe://example.org/app/edge"]);
+        let trusted = Trusted::from_connection(&connection, &request, &config);
+        assert_eq!(trusted.ip(), peer);
+
+        let connection = Connection(
+            "8.8.8.8".parse().unwrap(),
+            vec!["spiffe://example.org/proxy/edge"],
+        );
+        let trusted = Trusted::from_connection(&connection, &request, &config);
+        assert_eq!(trusted.ip(), "8.8.8.8".parse::<IpAddr>().unwrap());
+
+        let trusted = Trusted::from(peer, &request, &config);
+        assert_eq!(trusted.ip(), peer);
+    }
+
+    #[test]
+    fn server_name_mismatch() {
+        struct Connection(&'static str);
+
+        impl ConnectionInformation for Connection {
+            fn peer_addr(&self) -> IpAddr {
+                "127.0.0.1".parse().unwrap()
+            }
+
+            fn server_name(&self) -> Option<&str> {
+                Some(self.0)
+            }
+        }
+
+        let mut request = Request::get("/").body(()).unwrap();
+        request.headers_mut().append(
+            header::HeaderName::from_static("forwarded"),
+            "for=1.1.1.1;host=example.com:8443".parse().unwrap(),
+        );
+
+        let mut config = Config::default();
+        config.check_server_name();
+
+        let trusted = Trusted::from_connection(&Connection("Example.com"), &request, &config);
+        assert!(trusted.warnings().is_empty());
+
+        let trusted = Trusted::from_connection(&Connection("other.com"), &request, &config);
+        assert_eq!(
+            trusted.warnings(),
+            &[Warning::ServerNameMismatch {
+                server_name: "other.com".to_string(),
+                host: "example.com".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn scheme_port_mismatch() {
+        let mut request = Request::get("/").body(()).unwrap();
+        request.headers_mut().append(
+            header::HeaderName::from_static("forwarded"),
+            "for=1.1.1.1;proto=https;host=example.com:80"
+                .parse()
+                .unwrap(),
+        );
+
+        let mut config = Config::default();
+
+        let trusted = Trusted::from("127.0.0.1".parse().unwrap(), &request, &config);
+        assert_eq!(trusted.host_with_port(), Some("example.com:80"));
+        assert_eq!(
+            trusted.warnings(),
+            &[Warning::SchemePortMismatch {
+                scheme: "https".to_string(),
+                port: 80
+            }]
+        );
+
+        config.correct_port_from_scheme();
+
+        let trusted = Trusted::from("127.0.0.1".parse().unwrap(), &request, &config);
+        assert_eq!(trusted.host_with_port(), Some("example.com"));
+        assert_eq!(trusted.port(), None);
+        assert_eq!(trusted.warnings().len(), 1);
+
+        let mut request = Request::get("/").body(()).unwrap();
+        request.headers_mut().append(
+            header::HeaderName::from_static("forwarded"),
+            "for=1.1.1.1;proto=https;host=example.com:8443"
+                .parse()
+                .unwrap(),
+        );
+
+        let trusted = Trusted::from("127.0.0.1".parse().unwrap(), &request, &config);
+        assert_eq!(trusted.port(), Some(8443));
+        assert!(trusted.warnings().is_empty());
+    }
+
+    #[cfg(feature = "hmac")]
+    #[test]
+    fn signed_client_ip() {
+        let key = b"secret";
+        let client = "1.2.3.4".parse::<IpAddr>().unwrap();
+
+        let mut config = Config::default();
+        config.trust_signed_client_ip(key);
+
+        let mut request = Request::get("/").body(()).unwrap();
+        request.headers_mut().append(
+            header::HeaderName::from_static("x-client-ip"),
+            "1.2.3.4".parse().unwrap(),
+        );
+        request.headers_mut().append(
+            header::HeaderName::from_static("x-client-ip-sig"),
+            crate::sign_client_ip(key, &client).parse().unwrap(),
+        );
+
+        // valid even if the peer is not trusted
+        let trusted = Trusted::from("8.8.8.8".parse().unwrap(), &request, &config);
+        assert_eq!(trusted.ip(), client);
+        assert!(trusted.warnings().is_empty());
+
+        // the walked chain is dropped when the signed address wins
+        request.headers_mut().insert(
+            "x-forwarded-for",
+            "6.6.6.6, 5.6.7.8, 10.0.0.2".parse().unwrap(),
+        );
+
+        let trusted = Trusted::from("127.0.0.1".parse().unwrap(), &request, &config);
+        assert_eq!(trusted.ip(), client);
+        assert_eq!(trusted.ip_source(), IpSource::Signed);
+        assert!(trusted.proxies().is_empty());
+        assert_eq!(trusted.untrusted_chain().len(), 0);
+        assert_eq!(trusted.sanitized_x_forwarded_for(), "1.2.3.4, 127.0.0.1");
+
+        // the headers can be renamed before the key is set
+        let mut renamed = Config::default();
+        renamed.set_signed_client_ip_headers("X-Edge-Client", "X-Edge-Client-Sig");
+        renamed.trust_signed_client_ip(key);
+
+        let trusted = Trusted::from("8.8.8.8".parse().unwrap(), &request, &renamed);
+        assert_eq!(trusted.ip(), "8.8.8.8".parse::<IpAddr>().unwrap());
+
+        request
+            .headers_mut()
+            .insert("x-edge-client", client.to_string().parse().unwrap());
+        request.headers_mut().insert(
+            "x-edge-client-sig",
+            crate::sign_client_ip(key, &client).parse().unwrap(),
+        );
+
+        let trusted = Trusted::from("8.8.8.8".parse().unwrap(), &request, &renamed);
+        assert_eq!(trusted.ip(), client);
+
+        let mut config = Config::default();
+        config.trust_signed_client_ip(b"other");
+
+        let trusted = Trusted::from("8.8.8.8".parse().unwrap(), &request, &config);
+        assert_eq!(trusted.ip(), "8.8.8.8".parse::<IpAddr>().unwrap());
+        assert_eq!(
+            trusted.warnings(),
+            &[Warning::InvalidSignature {
+                header: "x-client-ip".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn sanitized_x_forwarded_for() {
+        let mut request = Request::get("/").body(()).unwrap();
+        request.headers_mut().append(
+            header::HeaderName::from_static("forwarded"),
+            "for=6.6.6.6, for=1.1.1.1, for=\"[fd00::1]\", for=10.0.0.1"
+                .parse()
+                .unwrap(),
+        );
+
+        let config = Config::default();
+
+        let trusted = Trusted::from("127.0.0.1".parse().unwrap(), &request, &config);
+        assert_eq!(trusted.ip(), "1.1.1.1".parse::<IpAddr>().unwrap());
+        assert_eq!(trusted.peer(), "127.0.0.1".parse::<IpAddr>().unwrap());
+        assert_eq!(
+            trusted.proxies(),
+            &[
+                "fd00::1".parse::<IpAddr>().unwrap(),
+                "10.0.0.1".parse::<IpAddr>().unwrap()
+            ]
+        );
+        assert_eq!(
+            trusted.sanitized_x_forwarded_for(),
+            "1.1.1.1, fd00::1, 10.0.0.1, 127.0.0.1"
+        );
+
+        let trusted = Trusted::from("8.8.8.8".parse().unwrap(), &request, &config);
+        assert!(trusted.proxies().is_empty());
+        assert_eq!(trusted.sanitized_x_forwarded_for(), "8.8.8.8");
+    }
+
+    #[test]
+    fn ip_source() {
+        let config = Config::new_local();
+        let peer = "127.0.0.1".parse().unwrap();
+
+        let mut request = Request::get("/").body(()).unwrap();
+        assert_eq!(
+            Trusted::from(peer, &request, &config).ip_source(),
+            IpSource::Peer
+        );
+
+        request.headers_mut().append(
+            header::HeaderName::from_static("x-forwarded-for"),
+            "1.1.1.1".parse().unwrap(),
+        );
+        assert_eq!(
+            Trusted::from(peer, &request, &config).ip_source(),
+            IpSource::XForwardedFor
+        );
+
+        request
+            .headers_mut()
+            .append(header::FORWARDED, "for=2.2.2.2".parse().unwrap());
+        assert_eq!(
+            Trusted::from(peer, &request, &config).ip_source(),
+            IpSource::Forwarded
+        );
+        assert_eq!(
+            Trusted::from("8.8.8.8".parse().unwrap(), &request, &config).ip_source(),
+            IpSource::Peer
+        );
+    }
+
+    #[test]
+    fn varnish_preset() {
+        let config = Config::new_varnish(&["10.0.0.5"]).unwrap();
+        let mut request = Request::get("/").body(()).unwrap();
+        request.headers_mut().append(
+            header::HeaderName::from_static("x-forwarded-for"),
+            "6.6.6.6, 1.2.3.4".parse().unwrap(),
+        );
+        request
+            .headers_mut()
+            .append(header::FORWARDED, "for=5.5.5.5".parse().unwrap());
+
+        let trusted = Trusted::from("10.0.0.5".parse().unwrap(), &request, &config);
+        assert_eq!(trusted.ip(), "1.2.3.4".parse::<IpAddr>().unwrap());
+
+        let trusted = Trusted::from("10.0.0.6".parse().unwrap(), &request, &config);
+        assert_eq!(trusted.ip(), "10.0.0.6".parse::<IpAddr>().unwrap());
+    }
+
+    #[test]
+    fn tailscale_preset() {
+        let config = Config::new_tailscale();
+        let mut request = Request::get("/").body(()).unwrap();
+        request
+            .headers_mut()
+            .append("x-forwarded-for", "1.2.3.4, 100.64.0.7".parse().unwrap());
+
+        let trusted = Trusted::from("fd7a:115c:a1e0::1".parse().unwrap(), &request, &config);
+        assert_eq!(trusted.ip(), "1.2.3.4".parse::<IpAddr>().unwrap());
+
+        let trusted = Trusted::from("192.168.1.1".parse().unwrap(), &request, &config);
+        assert_eq!(trusted.ip(), "192.168.1.1".parse::<IpAddr>().unwrap());
+
+        let config = Config::new_wireguard(&["10.8.0.0/24"]).unwrap();
+        let trusted = Trusted::from("10.8.0.1".parse().unwrap(), &request, &config);
+        assert_eq!(trusted.ip(), "100.64.0.7".parse::<IpAddr>().unwrap());
+        assert!(Config::new_wireguard(&["10.8.0.0/33"]).is_err());
+    }
+
+    #[test]
+    fn traefik_preset() {
+        let config = Config::new_traefik(&["10.0.0.0/24", "173.245.48.0/20"]).unwrap();
+        let mut request = Request::get("/").body(()).unwrap();
+        request.headers_mut().append(
+            header::HeaderName::from_static("x-forwarded-for"),
+            "6.6.6.6, 1.2.3.4, 173.245.48.1".parse().unwrap(),
+        );
+        request.headers_mut().append(
+            header::HeaderName::from_static("x-forwarded-proto"),
+            "https".parse().unwrap(),
+        );
+        request.headers_mut().append(
+            header::HeaderName::from_static("x-forwarded-host"),
+            "example.com".parse().unwrap(),
+        );
+        request
+            .headers_mut()
+            .append(header::FORWARDED, "for=5.5.5.5".parse().unwrap());
+
+        let trusted = Trusted::from("10.0.0.2".parse().unwrap(), &request, &config);
+        assert_eq!(trusted.ip(), "1.2.3.4".parse::<IpAddr>().unwrap());
+        assert_eq!(trusted.scheme(), Some("https"));
+        assert_eq!(trusted.host(), Some("example.com"));
+    }
+
+    #[test]
+    fn caddy_preset() {
+        let config = Config::new_caddy(&["192.168.0.0/16"]).unwrap();
+        let mut request = Request::get("/").body(()).unwrap();
+        request.headers_mut().append(
+            header::HeaderName::from_static("x-forwarded-for"),
+            "6.6.6.6, 1.2.3.4, 192.168.1.1".parse().unwrap(),
+        );
+        request.headers_mut().append(
+            header::HeaderName::from_static("x-forwarded-proto"),
+            "https".parse().unwrap(),
+        );
+
+        let trusted = Trusted::from("192.168.1.2".parse().unwrap(), &request, &config);
+        assert_eq!(trusted.ip(), "6.6.6.6".parse::<IpAddr>().unwrap());
+        assert_eq!(trusted.scheme(), Some("https"));
+
+        let trusted = Trusted::from("8.8.8.8".parse().unwrap(), &request, &config);
+        assert_eq!(trusted.ip(), "8.8.8.8".parse::<IpAddr>().unwrap());
+
+        let strict = Config::new_caddy_strict(&["192.168.0.0/16"]).unwrap();
+        let trusted = Trusted::from("192.168.1.2".parse().unwrap(), &request, &strict);
+        assert_eq!(trusted.ip(), "1.2.3.4".parse::<IpAddr>().unwrap());
+        assert_eq!(trusted.scheme(), Some("https"));
+    }
+
+    #[test]
+    #[cfg(feature = "ingress-nginx")]
+    fn ingress_nginx() {
+        let mut request = Request::get("/").body(()).unwrap();
+        request.headers_mut().append(
+            header::HeaderName::from_static("x-forwarded-for"),
+            "6.6.6.6, 1.2.3.4, 173.245.48.1".parse().unwrap(),
+        );
+
+        let mut settings = crate::ingress_nginx::IngressNginx {
+            use_forwarded_headers: true,
+            proxy_real_ip_cidr: "173.245.48.0/20, 10.0.0.0/8".to_string(),
+            compute_full_forwarded_for: true,
+        };
+        let config = settings.to_config(&["10.244.0.0/16"]).unwrap();
+        let trusted = Trusted::from("10.244.1.1".parse().unwrap(), &request, &config);
+        assert_eq!(trusted.ip(), "1.2.3.4".parse::<IpAddr>().unwrap());
+
+        settings.compute_full_forwarded_for = false;
+        let config = settings.to_config(&["10.244.0.0/16"]).unwrap();
+        let trusted = Trusted::from("10.244.1.1".parse().unwrap(), &request, &config);
+        assert_eq!(trusted.ip(), "173.245.48.1".parse::<IpAddr>().unwrap());
+    }
+
+    #[test]
+    fn config_error_report() {
+        let mut config = Config::new();
+
+        let error = config.add_trusted_ip("10.0.0.0/33").unwrap_err();
+        assert!(matches!(
+            &error,
+            ConfigError::InvalidNetwork { input, position: None, .. } if input == "10.0.0.0/33"
+        ));
+
+        let error = config
+            .add_trusted_ips(["10.0.0.0/8", "nope", "192.168.1.1", "fd00::/129"])
+            .unwrap_err();
+        let positions = error
+            .errors()
+            .iter()
+            .map(|error| match error {
+                ConfigError::InvalidNetwork { position, .. } => *position,
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(positions, vec![Some(2), Some(4)]);
+        assert!(!config.is_ip_trusted(&IpAddr::from([10, 0, 0, 1])));
+
+        config
+            .add_trusted_ip_list("10.0.0.0/8 # internal\n\n192.168.1.1\n")
+            .unwrap();
+        assert!(config.is_ip_trusted(&IpAddr::from([10, 0, 0, 1])));
+        assert!(config.is_ip_trusted(&IpAddr::from([192, 168, 1, 1])));
+    }
+
+    #[test]
+    fn ip_extractor() {
+        struct Header(&'static str);
+
+        impl crate::IpExtractor for Header {
+            fn header(&self) -> &str {
+                self.0
+            }
+
+            fn extract(&self, values: &[&str]) -> Option<IpAddr> {
+                values.last()?.trim().parse().ok()
+            }
+        }
+
+        let mut config = Config::new_local();
+        config.add_ip_extractor(0, Header("x-low"));
+        config.add_ip_extractor(10, Header("x-high"));
+
+        let mut request = Request::get("/").body(()).unwrap();
+        request
+            .headers_mut()
+            .insert("x-forwarded-for", "1.1.1.1".parse().unwrap());
+        request
+            .headers_mut()
+            .insert("x-low", "2.2.2.2".parse().unwrap());
+        request
+            .headers_mut()
+            .insert("x-high", "invalid".parse().unwrap());
+
+        let trusted = Trusted::from(IpAddr::from([127, 0, 0, 1]), &request, &config);
+        assert_eq!(trusted.ip(), IpAddr::from([2, 2, 2, 2]));
+        assert_eq!(trusted.ip_source(), IpSource::Extractor);
+
+        request
+            .headers_mut()
+            .insert("x-high", "3.3.3.3".parse().unwrap());
+
+        let trusted = Trusted::from(IpAddr::from([127, 0, 0, 1]), &request, &config);
+        assert_eq!(trusted.ip(), IpAddr::from([3, 3, 3, 3]));
+
+        let trusted = Trusted::from(IpAddr::from([8, 8, 8, 8]), &request, &config);
+        assert_eq!(trusted.ip(), IpAddr::from([8, 8, 8, 8]));
+        assert_eq!(trusted.ip_source(), IpSource::Peer);
+    }
+
+    #[test]
+    fn host_normalizer() {
+        let mut config = Config::new_local();
+        config.trust_x_forwarded_host();
+        config.correct_port_from_scheme();
+        config.set_host_normalizer(|host: &str, scheme: Option<&str>| {
+            assert_eq!(scheme, Some("https"));
+
+            host.ends_with("example.com")
+                .then(|| host.to_ascii_lowercase())
+        });
+
+        let mut request = Request::get("https://example.com/").body(()).unwrap();
+        request
+            .headers_mut()
+            .insert("x-forwarded-host", "API.example.com:80".parse().unwrap());
+
+        let trusted = Trusted::from(IpAddr::from([127, 0, 0, 1]), &request, &config);
+        assert!(matches!(trusted.host, Some(Value::Cow(Cow::Owned(_)))));
+        assert_eq!(trusted.host(), Some("api.example.com"));
+
+        request
+            .headers_mut()
+            .insert("x-forwarded-host", "other.com".parse().unwrap());
+
+        let trusted = Trusted::from(IpAddr::from([127, 0, 0, 1]), &request, &config);
+        assert_eq!(trusted.host(), None);
+        assert_eq!(
+            trusted.warnings(),
+            &[Warning::HostRejected {
+                host: "other.com".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn untrusted_chain() {
+        let mut config = Config::new_local();
+        config.set_max_header_value_length(40);
+
+        let mut request = Request::get("/").body(()).unwrap();
+        request.headers_mut().append(
+            header::FORWARDED,
+            "for=6.6.6.6, for=7.7.7.7, for=8.8.8.8, for=9.9.9.9"
+                .parse()
+                .unwrap(),
+        );
+        request.headers_mut().append(
+            header::FORWARDED,
+            "for=unknown, for=1.2.3.4, for=10.0.0.1".parse().unwrap(),
+        );
+
+        let trusted = Trusted::from(IpAddr::from([127, 0, 0, 1]), &request, &config);
+        assert_eq!(trusted.ip(), IpAddr::from([1, 2, 3, 4]));
+        assert!(trusted.untrusted_chain().eq(["unknown"]));
+        assert!(trusted.warnings().is_empty());
+        assert!(trusted.into_owned().untrusted_chain().eq(["unknown"]));
+
+        let trusted = Trusted::from(IpAddr::from([8, 8, 8, 8]), &request, &config);
+        assert_eq!(trusted.untrusted_chain().len(), 0);
+    }
+
+    #[test]
+    fn trusted_hops() {
+        let mut config = Config::new_local();
+        config.set_trusted_hops(1);
+
+        let mut request = Request::get("/").body(()).unwrap();
+        request.headers_mut().insert(
+            "x-forwarded-for",
+            "6.6.6.6, 1.2.3.4, 10.0.0.2, 5.5.5.5".parse().unwrap(),
+        );
+
+        // the peer must be trusted by address too when trusted networks are configured
+        let trusted = Trusted::from(IpAddr::from([35, 1, 2, 3]), &request, &config);
+        assert_eq!(trusted.ip(), IpAddr::from([35, 1, 2, 3]));
+        assert!(trusted.proxies().is_empty());
+
+        let trusted = Trusted::from(IpAddr::from([10, 0, 0, 1]), &request, &config);
+        assert_eq!(trusted.ip(), IpAddr::from([1, 2, 3, 4]));
+        assert_eq!(
+            trusted.proxies(),
+            &[IpAddr::from([10, 0, 0, 2]), IpAddr::from([5, 5, 5, 5])]
+        );
+
+        request.headers_mut().insert(
+            header::FORWARDED,
+            "for=1.2.3.4, for=_hidden, for=10.0.0.2".parse().unwrap(),
+        );
+        config.set_trusted_hops(2);
+
+        let trusted = Trusted::from(IpAddr::from([10, 0, 0, 1]), &request, &config);
+        assert_eq!(trusted.ip(), IpAddr::from([1, 2, 3, 4]));
+        assert_eq!(trusted.ip_source(), IpSource::Forwarded);
+    }
+
+    #[test]
+    fn retained_proxies() {
+        let mut config = Config::new();
+        config.add_internal_proxy("10.0.0.0/8").unwrap();
+        config.add_trusted_ip("203.0.113.0/24").unwrap();
+        config.trust_x_forwarded_for();
+
+        let mut request = Request::get("/").body(()).unwrap();
+        request.headers_mut().insert(
+            "x-forwarded-for",
+            "1.2.3.4, 203.0.113.7, 10.1.1.1".parse().unwrap(),
+        );
+
+        let trusted = Trusted::from(IpAddr::from([203, 0, 113, 1]), &request, &config);
+        assert_eq!(trusted.ip(), IpAddr::from([1, 2, 3, 4]));
+        assert_eq!(
+            trusted.proxies(),
+            &[IpAddr::from([203, 0, 113, 7]), IpAddr::from([10, 1, 1, 1])]
+        );
+        assert_eq!(
+            trusted.retained_proxies(),
+            &[
+                IpAddr::from([203, 0, 113, 7]),
+                IpAddr::from([203, 0, 113, 1])
+            ]
+        );
+
+        let trusted = Trusted::from(IpAddr::from([8, 8, 8, 8]), &request, &config);
+        assert!(trusted.retained_proxies().is_empty());
+    }
+
+    #[test]
+    fn disable_recursion() {
+        let mut config = Config::new_local();
+        config.disable_recursion();
+
+        let mut request = Request::get("/").body(()).unwrap();
+        request.headers_mut().insert(
+            header::FORWARDED,
+            "for=1.2.3.4, for=10.0.0.2".parse().unwrap(),
+        );
+
+        let trusted = Trusted::from(IpAddr::from([10, 0, 0, 1]), &request, &config);
+        assert_eq!(trusted.ip(), IpAddr::from([10, 0, 0, 2]));
+        assert!(trusted.proxies().is_empty());
+        assert!(trusted.untrusted_chain().eq(["1.2.3.4"]));
+
+        config.set_trusted_hops(1);
+
+        let trusted = Trusted::from(IpAddr::from([10, 0, 0, 1]), &request, &config);
+        assert_eq!(trusted.ip(), IpAddr::from([1, 2, 3, 4]));
+    }
+
+    #[test]
+    fn client_ip_strategy() {
+        let mut config = Config::new_local();
+        config.set_client_ip_strategy(crate::ClientIpStrategy::LeftmostNonPrivate);
+
+        let mut request = Request::get("/").body(()).unwrap();
+        request.headers_mut().insert(
+            "x-forwarded-for",
+            "unknown, 10.1.1.1, 6.6.6.6, 1.2.3.4, 10.0.0.2"
+                .parse()
+                .unwrap(),
+        );
+
+        let trusted = Trusted::from(IpAddr::from([10, 0, 0, 1]), &request, &config);
+        assert_eq!(trusted.ip(), IpAddr::from([6, 6, 6, 6]));
+        assert!(trusted.untrusted_chain().eq(["unknown", "10.1.1.1"]));
+
+        let trusted = Trusted::from(IpAddr::from([8, 8, 8, 8]), &request, &config);
+        assert_eq!(trusted.ip(), IpAddr::from([8, 8, 8, 8]));
+
+        request
+            .headers_mut()
+            .insert("x-forwarded-for", "10.1.1.1, 10.0.0.2".parse().unwrap());
+
+        let trusted = Trusted::from(IpAddr::from([10, 0, 0, 1]), &request, &config);
+        assert_eq!(trusted.ip(), IpAddr::from([10, 0, 0, 1]));
+
+        config.set_client_ip_strategy(crate::ClientIpStrategy::Rightmost);
+
+        let trusted = Trusted::from(IpAddr::from([10, 0, 0, 1]), &request, &config);
+        assert_eq!(trusted.ip(), IpAddr::from([10, 0, 0, 2]));
+    }
+
+    #[test]
+    fn port_trust() {
+        use core::net::SocketAddr;
+
+        let mut config = Config::new_local();
+        config.require_peer_ports(1..=1023);
+
+        let mut request = Request::get("/").body(()).unwrap();
+        request
+            .headers_mut()
+            .insert("x-forwarded-for", "1.2.3.4".parse().unwrap());
+
+        let trusted =
+            Trusted::from_connection(&SocketAddr::from(([10, 0, 0, 1], 700)), &request, &config);
+        assert_eq!(trusted.ip(), IpAddr::from([1, 2, 3, 4]));
+
+        let trusted =
+            Trusted::from_connection(&SocketAddr::from(([10, 0, 0, 1], 50000)), &request, &config);
+        assert_eq!(trusted.ip(), IpAddr::from([10, 0, 0, 1]));
+
+        let trusted = Trusted::from(IpAddr::from([10, 0, 0, 1]), &request, &config);
+        assert_eq!(trusted.ip(), IpAddr::from([10, 0, 0, 1]));
+    }
+
+    #[test]
+    fn listener_configs() {
+        use crate::ListenerConfigs;
+        use core::net::SocketAddr;
+
+        let mut admin = Config::new();
+        admin.add_trusted_ip("192.168.0.0/16").unwrap();
+
+        let mut listeners = ListenerConfigs::new(Config::new());
+        listeners.add_listener(SocketAddr::from(([0, 0, 0, 0], 8443)), Config::new_local());
+        listeners.add_listener(SocketAddr::from(([127, 0, 0, 1], 8443)), admin);
+
+        let ip = IpAddr::from([192, 168, 1, 1]);
+        let resolve = |local: SocketAddr| listeners.resolve(Some(local)).is_ip_trusted(&ip);
+
+        assert!(resolve(SocketAddr::from(([127, 0, 0, 1], 8443))));
+        assert!(resolve(SocketAddr::from(([10, 0, 0, 2], 8443))));
+        assert!(!resolve(SocketAddr::from(([10, 0, 0, 2], 443))));
+        assert!(!resolve("[::1]:8443".parse().unwrap()));
+        assert!(!listeners.resolve(None).is_ip_trusted(&ip));
+    }
+
+    #[test]
+    fn from_async() {
+        use core::future::Future;
+        use core::task::{Context, Poll, Waker};
+
+        struct Remote(Config, Vec<IpAddr>);
+
+        impl AsyncTrustPolicy for Remote {
+            fn config(&self) -> &Config {
+                &self.0
+            }
+
+            async fn is_ip_trusted(&self, remote_addr: &IpAddr) -> bool {
+                self.1.contains(remote_addr)
+            }
+        }
+
+        let mut config = Config::new();
+        config.trust_x_forwarded_for();
+        let policy = Remote(
+            config,
+            vec![IpAddr::from([10, 0, 0, 1]), IpAddr::from([10, 0, 0, 2])],
+        );
+
+        let mut request = Request::get("/").body(()).unwrap();
+        request
+            .headers_mut()
+            .insert("x-forwarded-for", "1.2.3.4, 10.0.0.2".parse().unwrap());
+
+        let mut future = core::pin::pin!(Trusted::from_async(
+            IpAddr::from([10, 0, 0, 1]),
+            &request,
+            &policy
+        ));
+        let Poll::Ready(trusted) = future
+            .as_mut()
+            .poll(&mut Context::from_waker(Waker::noop()))
+        else {
+            panic!("policy is always ready");
+        };
+
+        assert_eq!(trusted.ip(), IpAddr::from([1, 2, 3, 4]));
+        assert_eq!(trusted.proxies(), &[IpAddr::from([10, 0, 0, 2])]);
+    }
+
+    #[test]
+    fn expiring_trusted_ips() {
+        use std::time::{Duration, Instant};
+
+        let mut config = Config::new();
+        config
+            .add_trusted_ip_for("203.0.113.0/24", Duration::from_secs(3600))
+            .unwrap();
+        config
+            .add_trusted_ip_until("198.51.100.7", Instant::now())
+            .unwrap();
+
+        let matcher = config.compile();
+
+        for policy in [&config as &dyn TrustPolicy, &matcher] {
+            assert!(policy.is_ip_trusted(&IpAddr::from([203, 0, 113, 7])));
+            assert!(!policy.is_ip_trusted(&IpAddr::from([198, 51, 100, 7])));
+        }
+
+        config.remove_expired_ips();
+        assert!(config.is_ip_trusted(&IpAddr::from([203, 0, 113, 7])));
+    }
+
+    #[test]
+    fn scheme_downgrade() {
+        let mut config = Config::new_local();
+        config.trust_x_forwarded_proto();
+
+        let mut request = Request::get("/").body(()).unwrap();
+        request
+            .headers_mut()
+            .insert("x-forwarded-proto", "https, http".parse().unwrap());
+
+        // the first value is sent by the client, not by a trusted proxy
+        let trusted = Trusted::from(IpAddr::from([127, 0, 0, 1]), &request, &config);
+        assert_eq!(trusted.scheme(), Some("http"));
+        assert!(trusted.warnings().is_empty());
+
+        request
+            .headers_mut()
+            .insert("x-forwarded-for", "1.2.3.4, 10.0.0.2".parse().unwrap());
+
+        let trusted = Trusted::from(IpAddr::from([127, 0, 0, 1]), &request, &config);
+        assert_eq!(trusted.scheme(), Some("http"));
+        assert_eq!(
+            trusted.warnings(),
+            &[Warning::SchemeDowngrade {
+                header: "x-forwarded-proto"
+            }]
+        );
+
+        request
+            .headers_mut()
+            .insert("x-forwarded-proto", "http, https".parse().unwrap());
+
+        let trusted = Trusted::from(IpAddr::from([127, 0, 0, 1]), &request, &config);
+        assert!(trusted.warnings().is_empty());
+
+        request.headers_mut().insert(
+            header::FORWARDED,
+            "for=1.2.3.4;proto=https, for=10.0.0.2;proto=http"
+                .parse()
+                .unwrap(),
+        );
+
+        let trusted = Trusted::from(IpAddr::from([127, 0, 0, 1]), &request, &config);
+        assert_eq!(trusted.ip(), IpAddr::from([1, 2, 3, 4]));
+        assert_eq!(
+            trusted.warnings(),
+            &[Warning::SchemeDowngrade {
+                header: "forwarded"
+            }]
+        );
+
+        request.headers_mut().insert(
+            header::FORWARDED,
+            "for=6.6.6.6;proto=https, for=1.2.3.4;proto=http"
+                .parse()
+                .unwrap(),
+        );
+
+        let trusted = Trusted::from(IpAddr::from([127, 0, 0, 1]), &request, &config);
+        assert_eq!(trusted.ip(), IpAddr::from([1, 2, 3, 4]));
+        assert!(trusted.warnings().is_empty());
+    }
+
+    #[test]
+    fn host_mismatch() {
+        let mut config = Config::new_local();
+        config.trust_x_forwarded_host();
+
+        let mut request = Request::get("/").body(()).unwrap();
+        request
+            .headers_mut()
+            .insert("x-forwarded-host", "evil.com, example.com".parse().unwrap());
+
+        // the first value is sent by the client, not by a trusted proxy
+        let trusted = Trusted::from(IpAddr::from([127, 0, 0, 1]), &request, &config);
+        assert!(trusted.host_chain().eq(["example.com"]));
+        assert!(trusted.warnings().is_empty());
+
+        request
+            .headers_mut()
+            .insert("x-forwarded-for", "1.2.3.4, 10.0.0.2".parse().unwrap());
+        request.headers_mut().insert(
+            "x-forwarded-host",
+            "example.com, EXAMPLE.com".parse().unwrap(),
+        );
+
+        let trusted = Trusted::from(IpAddr::from([127, 0, 0, 1]), &request, &config);
+        assert!(trusted.host_chain().eq(["example.com", "EXAMPLE.com"]));
+        assert!(trusted.warnings().is_empty());
+
+        let trusted = trusted.into_owned();
+        assert!(trusted.host_chain().eq(["example.com", "EXAMPLE.com"]));
+
+        request.headers_mut().insert(
+            header::FORWARDED,
+            "for=1.2.3.4;host=example.com, for=10.0.0.2;host=internal.local"
+                .parse()
+                .unwrap(),
+        );
+
+        let trusted = Trusted::from(IpAddr::from([127, 0, 0, 1]), &request, &config);
+        assert_eq!(trusted.host(), Some("example.com"));
+        assert!(trusted.host_chain().eq(["example.com", "internal.local"]));
+        assert_eq!(
+            trusted.warnings(),
+            &[Warning::HostMismatch {
+                header: "forwarded"
+            }]
+        );
+    }
+
+    #[test]
+    fn shadow_evaluation() {
+        let current = Config::new_local();
+        let mut candidate = Config::new_local();
+        candidate.add_trusted_ip("1.2.3.0/24").unwrap();
+
+        let mut request = Request::get("/").body(()).unwrap();
+        request
+            .headers_mut()
+            .insert("x-forwarded-for", "5.6.7.8, 1.2.3.4".parse().unwrap());
+
+        let peer = IpAddr::from([127, 0, 0, 1]);
+        let evaluation = ShadowEvaluation::evaluate(&peer, &request, &current, &candidate);
+        assert_eq!(evaluation.differences(), &[ShadowDifference::Ip]);
+        assert_eq!(evaluation.current().ip(), IpAddr::from([1, 2, 3, 4]));
+        assert_eq!(evaluation.candidate().ip(), IpAddr::from([5, 6, 7, 8]));
+
+        let evaluation = ShadowEvaluation::evaluate(&peer, &request, &current, &current);
+        assert!(evaluation.is_same());
+        assert_eq!(evaluation.into_current().ip(), IpAddr::from([1, 2, 3, 4]));
+    }
+
+    #[test]
+    fn forwarding_loop() {
+        let mut config = Config::new_local();
+        config.trust_x_forwarded_by();
+        config.add_server_address(IpAddr::from([10, 0, 0, 5]));
+        config.add_server_identifier("_gateway");
+
+        let mut request = Request::get("/").body(()).unwrap();
+        request
+            .headers_mut()
+            .insert("x-forwarded-for", "1.2.3.4, 10.0.0.2".parse().unwrap());
+
+        let trusted = Trusted::from(IpAddr::from([127, 0, 0, 1]), &request, &config);
+        assert!(trusted.warnings().is_empty());
+
+        // entries beyond the client are sent by the client
+        request.headers_mut().insert(
+            "x-forwarded-for",
+            "10.0.0.5, 1.2.3.4, 10.0.0.2".parse().unwrap(),
+        );
+        request
+            .headers_mut()
+            .insert("x-forwarded-by", "_gateway, edge, proxy".parse().unwrap());
+
+        let trusted = Trusted::from_strict(IpAddr::from([127, 0, 0, 1]), &request, &config)
+            .expect("strict extraction should succeed");
+        assert_eq!(trusted.ip(), IpAddr::from([1, 2, 3, 4]));
+
+        let trusted = Trusted::from(IpAddr::from([10, 0, 0, 5]), &request, &config);
+        assert_eq!(
+            trusted.warnings(),
+            &[Warning::ForwardingLoop {
+                header: "x-forwarded-for"
+            }]
+        );
+
+        request
+            .headers_mut()
+            .insert("x-forwarded-by", "_GATEWAY".parse().unwrap());
+
+        let trusted = Trusted::from(IpAddr::from([127, 0, 0, 1]), &request, &config);
+        assert_eq!(
+            trusted.warnings(),
+            &[Warning::ForwardingLoop {
+                header: "x-forwarded-by"
+            }]
+        );
+
+        request.headers_mut().remove("x-forwarded-by");
+        request.headers_mut().insert(
+            header::FORWARDED,
+            "for=1.2.3.4;by=\"10.0.0.5:8080\"".parse().unwrap(),
+        );
+
+        let error = Trusted::from_strict(IpAddr::from([127, 0, 0, 1]), &request, &config)
+            .expect_err("strict extraction should fail");
+        assert_eq!(
+            error,
+            Warning::ForwardingLoop {
+                header: "forwarded"
+            }
+        );
+    }
+
+    #[test]
+    fn server_identity() {
+        let mut config = Config::new_local();
+        assert!(config.server_identity().is_none());
+
+        config.set_server_identity(ServerIdentity::Ip(IpAddr::from([
+            0x2001, 0xdb8, 0, 0, 0, 0, 0, 1,
+        ])));
+        assert_eq!(
+            config.server_identity().unwrap().to_string(),
+            "\"[2001:db8::1]\""
+        );
+        assert!(config.is_server_node("2001:db8::1"));
+
+        let mut request = Request::get("/").body(()).unwrap();
+        request.headers_mut().insert(
+            header::FORWARDED,
+            "for=1.2.3.4;by=\"[2001:db8::1]\"".parse().unwrap(),
+        );
+
+        let trusted = Trusted::from(IpAddr::from([127, 0, 0, 1]), &request, &config);
+        assert_eq!(
+            trusted.warnings(),
+            &[Warning::ForwardingLoop {
+                header: "forwarded"
+            }]
+        );
+
+        config.set_server_identity(ServerIdentity::Hostname("gateway.local".to_string()));
+        assert_eq!(
+            config.server_identity().unwrap().to_string(),
+            "\"gateway.local\""
+        );
+        assert!(config.is_server_node("Gateway.Local"));
+    }
+
+    #[test]
+    fn peer_trust_override() {
+        let config = Config::new_local();
+
+        let mut request = Request::get("/").body(()).unwrap();
+        request
+            .headers_mut()
+            .insert("x-forwarded-for", "1.2.3.4".parse().unwrap());
+
+        let peer = IpAddr::from([127, 0, 0, 1]);
+        let trusted = Trusted::from_connection(&PeerTrust::untrusted(peer), &request, &config);
+        assert_eq!(trusted.ip(), peer);
+
+        let peer = IpAddr::from([203, 0, 113, 1]);
+        let trusted = Trusted::from_connection(&PeerTrust::trusted(peer), &request, &config);
+        assert_eq!(trusted.ip(), IpAddr::from([1, 2, 3, 4]));
+        assert_eq!(PeerTrust::trusted(peer).into_inner(), peer);
+    }
+
+    #[test]
+    fn dual_extraction() {
+        let mut config = Config::new_local();
+        config.trust_x_forwarded_host();
+        assert!(DualExtractor::new(&Config::new()).is_none());
+
+        let extractor = DualExtractor::new(&config).unwrap();
+
+        let mut request = Request::get("/").body(()).unwrap();
+        request
+            .headers_mut()
+            .insert(header::FORWARDED, "for=1.2.3.4".parse().unwrap());
+        request
+            .headers_mut()
+            .insert("x-forwarded-for", "1.2.3.4".parse().unwrap());
+
+        let peer = IpAddr::from([127, 0, 0, 1]);
+        let extraction = extractor.extract(&peer, &request);
+        assert!(extraction.is_same());
+        assert_eq!(extraction.forwarded().ip_source(), IpSource::Forwarded);
+        assert_eq!(
+            extraction.x_forwarded_for().ip_source(),
+            IpSource::XForwardedFor
+        );
+
+        request
+            .headers_mut()
+            .insert("x-forwarded-for", "5.6.7.8".parse().unwrap());
+
+        let extraction = extractor.extract(&peer, &request);
+        assert_eq!(extraction.differences(), &[ShadowDifference::Ip]);
+    }
+
+    #[test]
+    fn dyn_request_information() {
+        let mut config = Config::new_local();
+        config.trust_x_forwarded_host();
+
+        let mut request = Request::get("/").body(()).unwrap();
+        request
+            .headers_mut()
+            .insert(header::FORWARDED, "for=1.2.3.4".parse().unwrap());
+        request
+            .headers_mut()
+            .insert("x-forwarded-host", "example.com".parse().unwrap());
+        request
+            .headers_mut()
+            .append("x-client-ip", "5.6.7.8".parse().unwrap());
+        request
+            .headers_mut()
+            .append("x-client-ip", "9.9.9.9".parse().unwrap());
+
+        let request: Box<dyn DynRequestInformation> = Box::new(request);
+        let trusted = Trusted::from(IpAddr::from([127, 0, 0, 1]), &*request, &config);
+        assert_eq!(trusted.ip(), IpAddr::from([1, 2, 3, 4]));
+        assert_eq!(trusted.host(), Some("example.com"));
+
+        let values = RequestInformation::header(&*request, "x-client-ip")
+            .rev()
+            .collect::<Vec<_>>();
+        assert_eq!(values, &["9.9.9.9", "5.6.7.8"]);
+    }
+
+    #[test]
+    fn chain_template() {
+        use crate::template::{ChainTemplate, ChainViolation};
+
+        let mut template = ChainTemplate::new();
+        template
+            .add_layer("cdn", &["173.245.48.0/20"], &["cdn-loop"])
+            .unwrap();
+        template.add_layer("lb", &["10.1.0.0/16"], &[]).unwrap();
+        template.add_layer("nginx", &["10.2.0.0/16"], &[]).unwrap();
+        assert!(template
+            .add_layer("invalid", &["10.0.0.0/33"], &[])
+            .is_err());
+
+        let config = template.to_config();
+
+        let mut request = Request::get("/").body(()).unwrap();
+        request.headers_mut().insert(
+            "x-forwarded-for",
+            "1.2.3.4, 10.2.0.1, 10.1.0.1".parse().unwrap(),
+        );
+
+        let trusted = Trusted::from(IpAddr::from([10, 2, 0, 2]), &request, &config);
+        assert_eq!(trusted.ip(), IpAddr::from([1, 2, 3, 4]));
+
+        let chain = template.validate(&trusted, &request);
+        let layers = chain
+            .hops()
+            .iter()
+            .map(|hop| hop.layer())
+            .collect::<Vec<_>>();
+        assert_eq!(layers, &[Some("nginx"), Some("lb"), Some("nginx")]);
+        assert_eq!(
+            chain.violations(),
+            &[
+                ChainViolation::OutOfOrder {
+                    ip: IpAddr::from([10, 1, 0, 1]),
+                    layer: "lb"
+                },
+                ChainViolation::MissingLayer { layer: "cdn" },
+            ]
+        );
+
+        let trusted = Trusted::from(IpAddr::from([10, 2, 0, 2]), &request, &Config::new());
+        let chain = template.validate(&trusted, &request);
+        assert!(chain.hops().is_empty());
+        assert_eq!(chain.violations().len(), 3);
+    }
+
+    #[test]
+    fn reputation() {
+        use core::future::Future;
+        use core::task::{Context, Poll, Waker};
+
+        struct Blocklist;
+
+        impl AsyncReputationProvider for Blocklist {
+            async fn reputation(&self, ip: IpAddr) -> Reputation {
+                if ip == IpAddr::from([6, 6, 6, 6]) {
+                    Reputation::Block
+                } else {
+                    Reputation::Allow
+                }
+            }
+        }
+
+        let mut config = Config::new_local();
+
+        let mut request = Request::get("/").body(()).unwrap();
+        request
+            .headers_mut()
+            .insert("x-forwarded-for", "6.6.6.6".parse().unwrap());
+
+        let mut trusted = Trusted::from(IpAddr::from([127, 0, 0, 1]), &request, &config);
+        assert_eq!(trusted.reputation(), None);
+
+        {
+            let mut lookup = core::pin::pin!(trusted.lookup_reputation(&Blocklist));
+            let mut context = Context::from_waker(Waker::noop());
+            assert_eq!(
+                lookup.as_mut().poll(&mut context),
+                Poll::Ready(Reputation::Block)
+            );
+        }
+        assert_eq!(trusted.reputation(), Some(Reputation::Block));
+
+        config.set_reputation_provider(|ip: IpAddr| {
+            if ip.is_loopback() {
+                Reputation::Allow
+            } else {
+                Reputation::Suspicious
+            }
+        });
+
+        let trusted = Trusted::from(IpAddr::from([127, 0, 0, 1]), &request, &config);
+        assert_eq!(trusted.reputation(), Some(Reputation::Suspicious));
+        assert_eq!(
+            trusted.into_owned().reputation(),
+            Some(Reputation::Suspicious)
+        );
+    }
+
+    #[test]
+    fn cloudflare_preset() {
+        let config = Config::new_cloudflare();
+        let mut request = Request::get("/").body(()).unwrap();
+        request
+            .headers_mut()
+            .insert("x-forwarded-for", "1.2.3.4".parse().unwrap());
+        request
+            .headers_mut()
+            .insert("x-forwarded-proto", "https".parse().unwrap());
+
+        let trusted = Trusted::from("2606:4700::6810:1".parse().unwrap(), &request, &config);
+        assert_eq!(trusted.ip(), IpAddr::from([1, 2, 3, 4]));
+        assert_eq!(trusted.scheme(), Some("https"));
+
+        let trusted = Trusted::from(IpAddr::from([10, 0, 0, 1]), &request, &config);
+        assert_eq!(trusted.ip(), IpAddr::from([10, 0, 0, 1]));
+    }
+
+    #[test]
+    fn azure_front_door_preset() {
+        let mut config = Config::new_azure_front_door();
+        config.add_trusted_ip("10.1.0.0/24").unwrap();
+
+        let mut request = Request::get("/").body(()).unwrap();
+        request.headers_mut().insert(
+            "x-forwarded-for",
+            "5.6.7.8, 1.2.3.4:50123, 147.243.1.2".parse().unwrap(),
+        );
+
+        let trusted = Trusted::from(IpAddr::from([10, 1, 0, 4]), &request, &config);
+        assert_eq!(trusted.ip(), IpAddr::from([1, 2, 3, 4]));
+        assert_eq!(trusted.ip_source(), IpSource::XForwardedFor);
+
+        request
+            .headers_mut()
+            .insert("x-azure-clientip", "[2001:db8::1]:443".parse().unwrap());
+
+        let trusted = Trusted::from(IpAddr::from([10, 1, 0, 4]), &request, &config);
+        assert_eq!(trusted.ip(), "2001:db8::1".parse::<IpAddr>().unwrap());
+        assert_eq!(trusted.ip_source(), IpSource::Extractor);
+
+        let trusted = Trusted::from(IpAddr::from([8, 8, 8, 8]), &request, &config);
+        assert_eq!(trusted.ip(), IpAddr::from([8, 8, 8, 8]));
+    }
+
+    #[test]
+    fn akamai_preset() {
+        let mut config = Config::new_akamai(&["23.32.0.0/11", "2600:1400::/24"]).unwrap();
+        config.add_trusted_ip("10.0.0.0/8").unwrap();
+
+        let mut request = Request::get("/").body(()).unwrap();
+        request
+            .headers_mut()
+            .insert("true-client-ip", "1.2.3.4".parse().unwrap());
+        request
+            .headers_mut()
+            .insert("x-forwarded-for", "5.6.7.8".parse().unwrap());
+
+        let trusted = Trusted::from("2600:1400::1".parse().unwrap(), &request, &config);
+        assert_eq!(trusted.ip(), IpAddr::from([1, 2, 3, 4]));
+        assert_eq!(trusted.ip_source(), IpSource::Extractor);
+
+        let trusted = Trusted::from(IpAddr::from([10, 0, 0, 1]), &request, &config);
+        assert_eq!(trusted.ip(), IpAddr::from([5, 6, 7, 8]));
+        assert_eq!(trusted.ip_source(), IpSource::XForwardedFor);
+
+        let error = Config::new_akamai(&["23.32.0.0/11", "akamai"]).unwrap_err();
+        assert!(error.to_string().contains("at position 2"));
+    }
+
+    #[test]
+    fn trusted_hostname() {
+        let mut config = Config::new();
+        config.trust_x_forwarded_for();
+        config.add_trusted_hostname("localhost").unwrap();
+
+        let matcher = config.compile();
+        let mut request = Request::get("/").body(()).unwrap();
+        request
+            .headers_mut()
+            .insert("x-forwarded-for", "1.2.3.4".parse().unwrap());
+
+        let trusted = Trusted::from(IpAddr::from([127, 0, 0, 1]), &request, &matcher);
+        assert_eq!(trusted.ip(), IpAddr::from([1, 2, 3, 4]));
+
+        config.resolve_trusted_hostnames().unwrap();
+        drop(config.refresh_trusted_hostnames(core::time::Duration::from_secs(60)));
+
+        let trusted = Trusted::from(IpAddr::from([10, 0, 0, 1]), &request, &config);
+        assert_eq!(trusted.ip(), IpAddr::from([10, 0, 0, 1]));
+    }
+
+    #[test]
+    fn untrusted_ips() {
+        let mut config = Config::new();
+        config.trust_x_forwarded_for();
+        config.add_trusted_ip("10.0.0.0/8").unwrap();
+        config.add_trusted_ip("10.42.1.0/24").unwrap();
+        config.add_untrusted_ip("10.42.0.0/16").unwrap();
+        config.add_untrusted_ip("10.255.255.255").unwrap();
+        config.add_untrusted_ip("0.0.0.0/0").unwrap();
+
+        let matcher = config.compile();
+        let mut request = Request::get("/").body(()).unwrap();
+        request
+            .headers_mut()
+            .insert("x-forwarded-for", "1.2.3.4".parse().unwrap());
+
+        for (peer, expected) in [
+            ([10, 0, 0, 1], [1, 2, 3, 4]),
+            ([10, 42, 0, 1], [10, 42, 0, 1]),
+            ([10, 42, 1, 1], [1, 2, 3, 4]),
+            ([10, 43, 0, 0], [1, 2, 3, 4]),
+            ([10, 255, 255, 254], [1, 2, 3, 4]),
+            ([10, 255, 255, 255], [10, 255, 255, 255]),
+            ([11, 0, 0, 1], [11, 0, 0, 1]),
+        ] {
+            let peer = IpAddr::from(peer);
+
+            assert_eq!(
+                Trusted::from(peer, &request, &config).ip(),
+                IpAddr::from(expected)
+            );
+            assert_eq!(
+                Trusted::from(peer, &request, &matcher).ip(),
+                IpAddr::from(expected)
+            );
+        }
+    }
+
+    #[test]
+    fn trust_all() {
+        let config = Config::new_trust_all();
+        let mut request = Request::get("/").body(()).unwrap();
+        request.headers_mut().insert(
+            header::FORWARDED,
+            "for=\"[2001:db8::1]\";proto=https;host=example.com, for=10.0.0.1"
+                .parse()
+                .unwrap(),
+        );
+
+        let trusted = Trusted::from("2a00::1".parse().unwrap(), &request, &config);
+        assert_eq!(trusted.ip(), "2001:db8::1".parse::<IpAddr>().unwrap());
+        assert_eq!(trusted.scheme(), Some("https"));
+        assert_eq!(trusted.host(), Some("example.com"));
+        assert!(config.is_ip_trusted(&IpAddr::from([255, 255, 255, 255])));
+    }
+
+    #[test]
+    fn hop_count_preset() {
+        let config = Config::new_hop_count(2);
+        let mut request = Request::get("/").body(()).unwrap();
+        request.headers_mut().insert(
+            header::FORWARDED,
+            "for=6.6.6.6, for=1.2.3.4, for=5.6.7.8, for=9.9.9.9"
+                .parse()
+                .unwrap(),
+        );
+
+        let trusted = Trusted::from(IpAddr::from([8, 8, 8, 8]), &request, &config);
+        assert_eq!(trusted.ip(), IpAddr::from([1, 2, 3, 4]));
+        assert_eq!(trusted.ip_source(), IpSource::Forwarded);
+    }
+
+    #[test]
+    fn custom_header_names() {
+        let mut config = Config::new_local();
+        config.trust_x_forwarded_for();
+        config.trust_forwarded();
+        config.set_forwarded_for_header("X-Client-IP");
+        config.set_forwarded_header("X-Forwarded");
+
+        let mut request = Request::get("/").body(()).unwrap();
+        request
+            .headers_mut()
+            .insert("x-client-ip", "1.2.3.4, 10.0.0.1".parse().unwrap());
+        request
+            .headers_mut()
+            .insert("x-forwarded-for", "6.6.6.6".parse().unwrap());
+
+        let trusted = Trusted::from(IpAddr::from([127, 0, 0, 1]), &request, &config);
+        assert_eq!(trusted.ip(), IpAddr::from([1, 2, 3, 4]));
+        assert_eq!(trusted.ip_source(), IpSource::XForwardedFor);
+
+        request
+            .headers_mut()
+            .insert("x-forwarded", "for=5.6.7.8".parse().unwrap());
+        request
+            .headers_mut()
+            .insert(header::FORWARDED, "for=7.7.7.7".parse().unwrap());
+
+        let trusted = Trusted::from(IpAddr::from([127, 0, 0, 1]), &request, &config);
+        assert_eq!(trusted.ip(), IpAddr::from([5, 6, 7, 8]));
+        assert_eq!(trusted.ip_source(), IpSource::Forwarded);
+    }
+
+    #[test]
+    fn client_ip_header() {
+        let mut config = Config::new_local();
+        config.trust_x_forwarded_for();
+        config.set_client_ip_header("X-Real-IP", ClientIpHeaderPriority::AfterForwarded);
+
+        let mut request = Request::get("/").body(()).unwrap();
+        request
+            .headers_mut()
+            .insert("x-real-ip", "1.2.3.4:4321".parse().unwrap());
+
+        let trusted = Trusted::from(IpAddr::from([127, 0, 0, 1]), &request, &config);
+        assert_eq!(trusted.ip(), IpAddr::from([1, 2, 3, 4]));
+        assert_eq!(trusted.ip_source(), IpSource::ClientIpHeader);
+
+        request
+            .headers_mut()
+            .insert("x-forwarded-for", "5.6.7.8".parse().unwrap());
+
+        let trusted = Trusted::from(IpAddr::from([127, 0, 0, 1]), &request, &config);
+        assert_eq!(trusted.ip(), IpAddr::from([5, 6, 7, 8]));
+        assert_eq!(trusted.ip_source(), IpSource::XForwardedFor);
+
+        config.set_client_ip_header("X-Real-IP", ClientIpHeaderPriority::BeforeForwarded);
+
+        let trusted = Trusted::from(IpAddr::from([127, 0, 0, 1]), &request, &config);
+        assert_eq!(trusted.ip(), IpAddr::from([1, 2, 3, 4]));
+
+        let trusted = Trusted::from(IpAddr::from([8, 8, 8, 8]), &request, &config);
+        assert_eq!(trusted.ip(), IpAddr::from([8, 8, 8, 8]));
+    }
+
+    #[test]
+    fn allowed_schemes() {
+        let mut config = Config::new_local();
+        config.trust_x_forwarded_proto();
+        config.allow_scheme("HTTPS");
+        config.allow_scheme("wss");
+
+        let mut request = Request::get("/").body(()).unwrap();
+        request
+            .headers_mut()
+            .insert("x-forwarded-proto", "https".parse().unwrap());
+
+        let trusted = Trusted::from(IpAddr::from([127, 0, 0, 1]), &request, &config);
+        assert_eq!(trusted.scheme(), Some("https"));
+        assert_eq!(trusted.warnings(), &[]);
+
+        request
+            .headers_mut()
+            .insert(header::FORWARDED, "proto=javascript".parse().unwrap());
+
+        let trusted = Trusted::from(IpAddr::from([127, 0, 0, 1]), &request, &config);
+        assert_eq!(trusted.scheme(), None);
+        assert_eq!(
+            trusted.warnings(),
+            &[Warning::SchemeRejected {
+                scheme: "javascript".to_string()
+            }]
+        );
+
+        let error = Trusted::from_strict(IpAddr::from([127, 0, 0, 1]), &request, &config);
+        assert!(error.is_err());
+    }
+
+    #[test]
+    fn allowed_host_suffixes() {
+        let mut config = Config::new_local();
+        config.trust_x_forwarded_host();
+        config.allow_host_suffix("Example.com");
+
+        let mut request = Request::get("/").body(()).unwrap();
+
+        for host in ["example.com", "www.EXAMPLE.com:8080", "a.b.example.com."] {
+            request
+                .headers_mut()
+                .insert("x-forwarded-host", host.parse().unwrap());
+
+            let trusted = Trusted::from(IpAddr::from([127, 0, 0, 1]), &request, &config);
+            assert!(trusted
+                .host()
+                .is_some_and(|trusted| host.starts_with(trusted)));
+            assert_eq!(trusted.warnings(), &[]);
+        }
+
+        for host in ["badexample.com", "example.com.evil.com", "[::1]:80"] {
+            request
+                .headers_mut()
+                .insert("x-forwarded-host", host.parse().unwrap());
+
+            let trusted = Trusted::from(IpAddr::from([127, 0, 0, 1]), &request, &config);
+            assert_eq!(trusted.host(), None);
+            assert_eq!(
+                trusted.warnings(),
+                &[Warning::HostRejected {
+                    host: host.to_string()
+                }]
+            );
+        }
+    }
+
+    #[test]
+    fn max_chain_length() {
+        use crate::LongChain;
+
+        let mut config = Config::new_local();
+        config.set_max_chain_length(2);
+
+        let mut request = Request::get("/").body(()).unwrap();
+        request.headers_mut().insert(
+            "x-forwarded-for",
+            "1.2.3.4, 10.0.0.2, 10.0.0.1".parse().unwrap(),
+        );
+
+        let trusted = Trusted::from(IpAddr::from([127, 0, 0, 1]), &request, &config);
+        assert_eq!(trusted.ip(), IpAddr::from([127, 0, 0, 1]));
+        assert_eq!(
+            trusted.proxies(),
+            &[IpAddr::from([10, 0, 0, 2]), IpAddr::from([10, 0, 0, 1])]
+        );
+        assert_eq!(
+            trusted.warnings(),
+            &[Warning::ChainTooLong {
+                header: "x-forwarded-for",
+                length: 3,
+                max: 2
+            }]
+        );
+
+        config.set_long_chain(LongChain::Untrust);
+
+        let trusted = Trusted::from(IpAddr::from([127, 0, 0, 1]), &request, &config);
+        assert_eq!(trusted.ip(), IpAddr::from([127, 0, 0, 1]));
+        assert!(trusted.proxies().is_empty());
+        assert_eq!(
+            trusted.ignored_headers(),
+            &[IgnoredHeader {
+                name: "x-forwarded-for",
+                reason: IgnoreReason::ChainTooLong
+            }]
+        );
+
+        config.set_max_chain_length(3);
+
+        let trusted = Trusted::from(IpAddr::from([127, 0, 0, 1]), &request, &config);
+        assert_eq!(trusted.ip(), IpAddr::from([1, 2, 3, 4]));
+        assert_eq!(trusted.warnings(), &[]);
+    }
+
+    #[test]
+    fn private_extended_preset() {
+        let config = Config::new_private_extended();
+
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "100.64.0.1",
+            "169.254.169.254",
+            "::1",
+            "fc00::1",
+            "fd12::1",
+            "fe80::1",
+        ] {
+            assert!(config.is_ip_trusted(&ip.parse().unwrap()), "{ip}");
+        }
+
+        assert!(!config.is_ip_trusted(&IpAddr::from([8, 8, 8, 8])));
+        assert!(!config.is_ip_trusted(&"2001:db8::1".parse().unwrap()));
+        assert_eq!(config.validate(), []);
+
+        let mut request = Request::get("/").body(()).unwrap();
+        request
+            .headers_mut()
+            .insert("x-forwarded-for", "1.2.3.4".parse().unwrap());
+
+        let trusted = Trusted::from(IpAddr::from([169, 254, 1, 1]), &request, &config);
+        assert_eq!(trusted.ip(), IpAddr::from([1, 2, 3, 4]));
+    }
+
+    #[test]
+    fn ipv4_mapped_peer() {
+        let mut config = Config::new_local();
+        config
+            .add_trusted_ip_group("edge", "203.0.113.0/24")
+            .unwrap();
+        config.unmap_ipv4_mapped_addresses();
+
+        let mut request = Request::get("/").body(()).unwrap();
+        request.headers_mut().insert(
+            "x-forwarded-for",
+            "1.2.3.4, [::ffff:10.0.0.2]".parse().unwrap(),
+        );
+
+        let peer: IpAddr = "::ffff:203.0.113.7".parse().unwrap();
+        let trusted = Trusted::from(peer, &request, &config);
+        assert_eq!(trusted.ip(), IpAddr::from([1, 2, 3, 4]));
+        assert_eq!(trusted.via_group(), Some("edge"));
+        assert_eq!(trusted.peer(), peer);
+
+        let trusted = Trusted::from(peer, &request, &config.compile());
+        assert_eq!(trusted.ip(), IpAddr::from([1, 2, 3, 4]));
+        assert!(!Config::new_local().is_ip_trusted(&"::ffff:10.0.0.2".parse().unwrap()));
+    }
+
+    #[test]
+    fn compiled_lookup() {
+        let mut config = Config::new();
+        config.add_cloudflare_ranges();
+        config.add_trusted_ip("10.0.0.0/8").unwrap();
+        config.add_untrusted_ip("10.42.0.0/16").unwrap();
+        config.add_trusted_ip("10.42.0.1").unwrap();
+
+        assert!(config.is_ip_trusted(&IpAddr::from([173, 245, 48, 1])));
+        assert!(config.is_ip_trusted(&"2606:4700::1".parse().unwrap()));
+        assert!(config.is_ip_trusted(&IpAddr::from([10, 1, 0, 1])));
+        assert!(!config.is_ip_trusted(&IpAddr::from([10, 42, 0, 2])));
+        assert!(config.is_ip_trusted(&IpAddr::from([10, 42, 0, 1])));
+        assert!(!config.is_ip_trusted(&IpAddr::from([8, 8, 8, 8])));
+
+        // the compiled ranges are reset when the networks change
+        config.add_trusted_ip("8.8.8.0/24").unwrap();
+        assert!(config.is_ip_trusted(&IpAddr::from([8, 8, 8, 8])));
+
+        config.add_untrusted_ip("10.1.0.0/16").unwrap();
+        assert!(!config.is_ip_trusted(&IpAddr::from([10, 1, 0, 1])));
+
+        assert!(config.remove_trusted_ip("173.245.48.0/20").unwrap());
+        assert!(!config.is_ip_trusted(&IpAddr::from([173, 245, 48, 1])));
+
+        let cloned = config.clone();
+        config.clear_trusted_ips();
+        assert!(!config.is_ip_trusted(&IpAddr::from([8, 8, 8, 8])));
+        assert!(cloned.is_ip_trusted(&IpAddr::from([8, 8, 8, 8])));
+    }
 }
@@ -0,0 +1,120 @@
+//! [`tungstenite`](https://docs.rs/tungstenite) handshake integration
+//!
+//! The handshake request is read by a [`Callback`] given to `accept_hdr`, [`TrustedCallback`]
+//! stores the trusted information of the request so the connection is attributed at accept time.
+//! `tokio-tungstenite` uses the same callbacks with `accept_hdr_async`.
+//!
+//! # Example
+//! ```no_run
+//! use std::net::TcpListener;
+//! use trusted_proxies::tungstenite::TrustedCallback;
+//! use trusted_proxies::Config;
+//!
+//! let config = Config::new_local();
+//! let listener = TcpListener::bind("127.0.0.1:9001").unwrap();
+//!
+//! for stream in listener.incoming() {
+//!     let stream = stream.unwrap();
+//!     let peer = stream.peer_addr().unwrap();
+//!     let mut trusted = None;
+//!
+//!     let callback = TrustedCallback::new(peer, &config, &mut trusted);
+//!     let Ok(_websocket) = tungstenite::accept_hdr(stream, callback) else {
+//!         continue;
+//!     };
+//!
+//!     if let Some(trusted) = trusted {
+//!         println!("connection from {}", trusted.ip());
+//!     }
+//! }
+//! ```
+
+use crate::{ConnectionInformation, TrustPolicy, Trusted};
+use ::tungstenite::handshake::server::{Callback, ErrorResponse, Request, Response};
+
+/// A handshake callback storing the trusted information of the request
+#[derive(Debug)]
+pub struct TrustedCallback<'a, C, P: ?Sized> {
+    connection: C,
+    policy: &'a P,
+    trusted: &'a mut Option<Trusted<'static>>,
+}
+
+impl<'a, C, P> TrustedCallback<'a, C, P>
+where
+    C: ConnectionInformation,
+    P: TrustPolicy + ?Sized,
+{
+    /// Create a callback storing the trusted information in `trusted` once the request is read
+    pub fn new(connection: C, policy: &'a P, trusted: &'a mut Option<Trusted<'static>>) -> Self {
+        Self {
+            connection,
+            policy,
+            trusted,
+        }
+    }
+}
+
+impl<C, P> Callback for TrustedCallback<'_, C, P>
+where
+    C: ConnectionInformation,
+    P: TrustPolicy + ?Sized,
+{
+    fn on_request(self, request: &Request, response: Response) -> Result<Response, ErrorResponse> {
+        *self.trusted =
+            Some(Trusted::from_connection(&self.connection, request, self.policy).into_owned());
+
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Config;
+    use core::net::IpAddr;
+    use std::io::{self, Read, Write};
+
+    /// A stream reading a handshake request and discarding the response
+    struct HandshakeStream(io::Cursor<&'static [u8]>);
+
+    impl Read for HandshakeStream {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.0.read(buf)
+        }
+    }
+
+    impl Write for HandshakeStream {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn handshake() {
+        let stream = HandshakeStream(io::Cursor::new(
+            b"GET /chat HTTP/1.1\r\n\
+            Host: example.com\r\n\
+            Upgrade: websocket\r\n\
+            Connection: Upgrade\r\n\
+            Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+            Sec-WebSocket-Version: 13\r\n\
+            X-Forwarded-For: 1.2.3.4\r\n\r\n",
+        ));
+
+        let config = Config::new_local();
+        let mut trusted = None;
+        let peer = IpAddr::from([127, 0, 0, 1]);
+
+        ::tungstenite::accept_hdr(stream, TrustedCallback::new(peer, &config, &mut trusted))
+            .unwrap();
+
+        let trusted = trusted.unwrap();
+        assert_eq!(trusted.ip(), IpAddr::from([1, 2, 3, 4]));
+        assert_eq!(trusted.host(), Some("example.com"));
+    }
+}
@@ -0,0 +1,166 @@
+//! Forwarding headers for an upstream server, for services acting as the first proxy tier
+//!
+//! This is the inverse of the extraction: the headers sent by the client are replaced by the
+//! verified information, in the format an nginx-style upstream expects.
+//!
+//! # Example
+//! ```
+//! use trusted_proxies::{upstream, Config, Trusted};
+//!
+//! let config = Config::new_local();
+//! let mut request = http::Request::get("/").body(()).unwrap();
+//! request.headers_mut().insert("x-forwarded-for", "6.6.6.6, 1.2.3.4".parse().unwrap());
+//!
+//! let trusted = Trusted::from(core::net::IpAddr::from([10, 0, 0, 1]), &request, &config);
+//! let mut upstream_request = http::Request::get("/").body(()).unwrap();
+//! upstream::insert_headers(&trusted, upstream_request.headers_mut());
+//!
+//! assert_eq!(upstream_request.headers()["x-real-ip"], "1.2.3.4");
+//! assert_eq!(upstream_request.headers()["x-forwarded-for"], "1.2.3.4, 10.0.0.1");
+//! ```
+
+use crate::{Config, ServerIdentity, Trusted};
+use core::net::IpAddr;
+use http::header::{HeaderMap, HeaderName, HeaderValue, FORWARDED};
+
+/// Header containing the resolved client address
+pub const X_REAL_IP: HeaderName = HeaderName::from_static("x-real-ip");
+
+/// Insert `X-Real-IP`, a sanitized `X-Forwarded-For`, `X-Forwarded-Proto` and
+/// `X-Forwarded-Host` into the headers sent upstream
+///
+/// Existing forwarding headers, including `Forwarded`, are removed first so values sent by the
+/// client cannot reach the upstream server. See [`Trusted::sanitized_x_forwarded_for`].
+pub fn insert_headers(trusted: &Trusted, headers: &mut HeaderMap) {
+    let values = [
+        (X_REAL_IP, Some(trusted.ip().to_string())),
+        (
+            HeaderName::from_static("x-forwarded-for"),
+            Some(trusted.sanitized_x_forwarded_for()),
+        ),
+        (
+            HeaderName::from_static("x-forwarded-proto"),
+            trusted.scheme().map(str::to_string),
+        ),
+        (
+            HeaderName::from_static("x-forwarded-host"),
+            trusted.host_with_port().map(str::to_string),
+        ),
+    ];
+
+    headers.remove(FORWARDED);
+    headers.remove("x-forwarded-by");
+
+    for (name, value) in values {
+        headers.remove(&name);
+
+        if let Some(value) = value.and_then(|v| HeaderValue::try_from(v).ok()) {
+            headers.insert(name, value);
+        }
+    }
+}
+
+/// Insert the same headers as [`insert_headers`], and identify this server when the
+/// configuration has an identity, see [`Config::set_server_identity`]
+///
+/// With an identity, a single `Forwarded` element describing the last hop and `X-Forwarded-By`
+/// are inserted too.
+///
+/// # Example
+/// ```
+/// use trusted_proxies::{upstream, Config, ServerIdentity, Trusted};
+///
+/// let mut config = Config::new_local();
+/// config.set_server_identity(ServerIdentity::Obfuscated("_gateway".to_string()));
+///
+/// let mut request = http::Request::get("/").body(()).unwrap();
+/// request.headers_mut().insert("x-forwarded-for", "1.2.3.4".parse().unwrap());
+///
+/// let trusted = Trusted::from(core::net::IpAddr::from([10, 0, 0, 1]), &request, &config);
+/// let mut upstream_request = http::Request::get("/").body(()).unwrap();
+/// upstream::insert_headers_with(&trusted, &config, upstream_request.headers_mut());
+///
+/// assert_eq!(upstream_request.headers()["forwarded"], "for=1.2.3.4;by=_gateway");
+/// assert_eq!(upstream_request.headers()["x-forwarded-by"], "_gateway");
+/// ```
+pub fn insert_headers_with(trusted: &Trusted, config: &Config, headers: &mut HeaderMap) {
+    insert_headers(trusted, headers);
+
+    let Some(identity) = config.server_identity() else {
+        return;
+    };
+
+    let by = match identity {
+        ServerIdentity::Ip(ip) => ip.to_string(),
+        ServerIdentity::Obfuscated(name) | ServerIdentity::Hostname(name) => name.clone(),
+    };
+
+    if let Ok(value) = HeaderValue::try_from(forwarded_element(trusted, identity)) {
+        headers.insert(FORWARDED, value);
+    }
+
+    if let Ok(value) = HeaderValue::try_from(by) {
+        headers.insert(HeaderName::from_static("x-forwarded-by"), value);
+    }
+}
+
+/// Build the `Forwarded` element of the last hop, as seen by this server
+fn forwarded_element(trusted: &Trusted, identity: &ServerIdentity) -> String {
+    let mut element = match trusted.ip() {
+        IpAddr::V4(ip) => format!("for={ip}"),
+        IpAddr::V6(ip) => format!("for=\"[{ip}]\""),
+    };
+
+    element.push_str(&format!(";by={identity}"));
+
+    if let Some(host) = trusted.host_with_port() {
+        element.push_str(&format!(";host=\"{host}\""));
+    }
+
+    if let Some(scheme) = trusted.scheme() {
+        element.push_str(&format!(";proto={scheme}"));
+    }
+
+    element
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replace_client_headers() {
+        let mut config = Config::new_local();
+        let mut request = http::Request::get("/").body(()).unwrap();
+        request.headers_mut().insert(
+            FORWARDED,
+            "for=\"[2001:db8::1]\";proto=https;host=example.com"
+                .parse()
+                .unwrap(),
+        );
+
+        let trusted = Trusted::from(IpAddr::from([10, 0, 0, 1]), &request, &config);
+
+        let mut upstream_request = http::Request::get("/").body(()).unwrap();
+        let headers = upstream_request.headers_mut();
+        headers.insert(X_REAL_IP, "6.6.6.6".parse().unwrap());
+        headers.insert("x-forwarded-by", "spoofed".parse().unwrap());
+        headers.insert(FORWARDED, "for=6.6.6.6".parse().unwrap());
+
+        insert_headers(&trusted, headers);
+        assert_eq!(headers[X_REAL_IP], "2001:db8::1");
+        assert_eq!(headers["x-forwarded-for"], "2001:db8::1, 10.0.0.1");
+        assert_eq!(headers["x-forwarded-proto"], "https");
+        assert_eq!(headers["x-forwarded-host"], "example.com");
+        assert!(!headers.contains_key(FORWARDED));
+        assert!(!headers.contains_key("x-forwarded-by"));
+
+        config.set_server_identity(ServerIdentity::Ip(IpAddr::from([10, 0, 0, 2])));
+        insert_headers_with(&trusted, &config, headers);
+        assert_eq!(
+            headers[FORWARDED],
+            "for=\"[2001:db8::1]\";by=10.0.0.2;host=\"example.com\";proto=https"
+        );
+        assert_eq!(headers["x-forwarded-by"], "10.0.0.2");
+    }
+}
@@ -0,0 +1,126 @@
+use core::fmt;
+use core::net::IpAddr;
+
+/// A warning raised while extracting trusted information from a request
+///
+/// Warnings do not prevent the extraction, the offending values are ignored and the next
+/// available information is used instead. When using [`crate::Trusted::from_strict`], the first
+/// warning is returned as an error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Warning {
+    /// A header value is longer than the configured maximum length and has been ignored
+    HeaderValueTooLong {
+        header: &'static str,
+        length: usize,
+        max: usize,
+    },
+    /// The total length of the examined header values reached the configured maximum, remaining
+    /// values have not been examined
+    TotalHeaderLengthExceeded { max: usize },
+    /// A header value contains control characters, NUL bytes or non ASCII characters
+    ///
+    /// The value is still parsed in a best-effort way, unless using the strict extraction.
+    InvalidCharacters { header: &'static str },
+    /// The peer is trusted but did not send the scheme, see [`crate::Config::require_proto`]
+    MissingProto,
+    /// The peer is trusted but did not send the host, see [`crate::Config::require_host`]
+    MissingHost,
+    /// The resolved client address is private, loopback or reserved, see
+    /// [`crate::Config::set_private_client_ip`]
+    PrivateClientIp { ip: IpAddr },
+    /// The TLS server name does not match the trusted host, see
+    /// [`crate::Config::check_server_name`]
+    ServerNameMismatch { server_name: String, host: String },
+    /// The port of the trusted host is the default port of another scheme (e.g. `https` with
+    /// port 80), see [`crate::Config::correct_port_from_scheme`]
+    SchemePortMismatch { scheme: String, port: u16 },
+    /// The signature of the signed client address is missing or invalid, see
+    /// `Config::trust_signed_client_ip`
+    InvalidSignature { header: String },
+    /// The trusted host has been rejected by the host normalizer or is not an allowed host, see
+    /// [`crate::Config::set_host_normalizer`] and [`crate::Config::allow_host_suffix`]
+    HostRejected { host: String },
+    /// A proxy of the chain received the request over `https` but a closer one over `http`,
+    /// which usually means a misconfigured or malicious proxy
+    SchemeDowngrade { header: &'static str },
+    /// The proxies of the chain gave different hosts, only the farthest one is used, see
+    /// [`crate::Trusted::host_chain`]
+    HostMismatch { header: &'static str },
+    /// An address or identifier of this server appears in the chain, the request probably went
+    /// through this server already, see [`crate::Config::add_server_address`]
+    ForwardingLoop { header: &'static str },
+    /// The trusted scheme is not one of the allowed schemes and has been ignored, see
+    /// [`crate::Config::allow_scheme`]
+    SchemeRejected { scheme: String },
+    /// A forwarding header has more elements than the maximum chain length, see
+    /// [`crate::Config::set_max_chain_length`]
+    ChainTooLong {
+        header: &'static str,
+        length: usize,
+        max: usize,
+    },
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::HeaderValueTooLong {
+                header,
+                length,
+                max,
+            } => write!(
+                f,
+                "{header} header value is {length} bytes long, maximum allowed is {max}"
+            ),
+            Self::TotalHeaderLengthExceeded { max } => write!(
+                f,
+                "forwarding headers are longer than {max} bytes, remaining values were ignored"
+            ),
+            Self::InvalidCharacters { header } => {
+                write!(f, "{header} header value contains invalid characters")
+            }
+            Self::MissingProto => f.write_str("trusted proxy did not send the request scheme"),
+            Self::MissingHost => f.write_str("trusted proxy did not send the request host"),
+            Self::PrivateClientIp { ip } => {
+                write!(f, "client address {ip} is private or reserved")
+            }
+            Self::ServerNameMismatch { server_name, host } => {
+                write!(
+                    f,
+                    "TLS server name {server_name} does not match host {host}"
+                )
+            }
+            Self::SchemePortMismatch { scheme, port } => {
+                write!(f, "port {port} does not match scheme {scheme}")
+            }
+            Self::InvalidSignature { header } => {
+                write!(f, "{header} header has an invalid signature")
+            }
+            Self::HostRejected { host } => write!(f, "host {host} has been rejected"),
+            Self::SchemeDowngrade { header } => {
+                write!(f, "{header} header shows a downgrade from https to http")
+            }
+            Self::HostMismatch { header } => {
+                write!(f, "{header} header shows different hosts along the chain")
+            }
+            Self::ForwardingLoop { header } => {
+                write!(
+                    f,
+                    "{header} header shows a forwarding loop through this server"
+                )
+            }
+            Self::SchemeRejected { scheme } => write!(f, "scheme {scheme} is not allowed"),
+            Self::ChainTooLong {
+                header,
+                length,
+                max,
+            } => write!(
+                f,
+                "{header} header has {length} elements, more than the maximum of {max}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Warning {}
@@ -0,0 +1,148 @@
+//! WASI component exporting the extraction, see `wit/trusted-proxies.wit`
+//!
+//! Build the component with `cargo rustc --release --target wasm32-wasip2 --features wasi
+//! --crate-type cdylib`, so API gateways with Wasm plugin systems can run the same trust logic.
+
+use crate::extract::HeaderList;
+use crate::{ranges, Config, Trusted};
+use core::net::IpAddr;
+
+wit_bindgen::generate!({
+    world: "trusted-proxies",
+    path: "wit",
+});
+
+use exports::redirectionio::trusted_proxies::extract::{self, Guest};
+
+struct Component;
+
+impl Guest for Component {
+    fn extract(
+        config: extract::Config,
+        peer: String,
+        scheme: Option<String>,
+        headers: Vec<extract::Header>,
+    ) -> Result<extract::Trusted, String> {
+        let mut trust = Config::new();
+        trust.extend_trusted_ips(ranges::LOCAL.iter().copied());
+
+        for ip in &config.trusted_ips {
+            trust.add_trusted_ip(ip).map_err(|err| err.to_string())?;
+        }
+
+        if config.trust_forwarded {
+            trust.trust_forwarded();
+        }
+
+        if config.trust_x_forwarded_for {
+            trust.trust_x_forwarded_for();
+        }
+
+        if config.trust_x_forwarded_host {
+            trust.trust_x_forwarded_host();
+        }
+
+        if config.trust_x_forwarded_proto {
+            trust.trust_x_forwarded_proto();
+        }
+
+        if config.trust_x_forwarded_by {
+            trust.trust_x_forwarded_by();
+        }
+
+        let peer = peer
+            .parse::<IpAddr>()
+            .map_err(|err| format!("invalid peer address {peer}: {err}"))?;
+        let request = HeaderList {
+            headers: headers
+                .iter()
+                .map(|header| (header.name.as_str(), header.value.as_str()))
+                .collect(),
+            scheme: scheme.as_deref(),
+        };
+        let trusted = Trusted::from(peer, &request, &trust);
+
+        Ok(extract::Trusted {
+            ip: trusted.ip().to_string(),
+            host: trusted.host_with_port().map(str::to_string),
+            scheme: trusted.scheme().map(str::to_string),
+            by: trusted.by().map(str::to_string),
+        })
+    }
+}
+
+export!(Component);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(name: &str, value: &str) -> extract::Header {
+        extract::Header {
+            name: name.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    #[test]
+    fn extract_component() {
+        let config = extract::Config {
+            trusted_ips: vec!["203.0.113.0/24".to_string()],
+            trust_forwarded: false,
+            trust_x_forwarded_for: true,
+            trust_x_forwarded_host: true,
+            trust_x_forwarded_proto: false,
+            trust_x_forwarded_by: false,
+        };
+        let headers = vec![
+            header("host", "internal"),
+            header("x-forwarded-for", "1.2.3.4"),
+            header("x-forwarded-host", "example.com"),
+            header("x-forwarded-proto", "http"),
+            header("forwarded", "for=6.6.6.6;host=evil.com"),
+        ];
+
+        let trusted = Component::extract(
+            config.clone(),
+            "203.0.113.7".to_string(),
+            Some("https".to_string()),
+            headers.clone(),
+        )
+        .unwrap();
+
+        assert_eq!(trusted.ip, "1.2.3.4");
+        assert_eq!(trusted.host.as_deref(), Some("example.com"));
+        assert_eq!(trusted.scheme.as_deref(), Some("https"));
+        assert_eq!(trusted.by, None);
+
+        let trusted =
+            Component::extract(config.clone(), "8.8.8.8".to_string(), None, headers.clone())
+                .unwrap();
+        assert_eq!(trusted.ip, "8.8.8.8");
+        assert_eq!(trusted.host.as_deref(), Some("internal"));
+
+        assert!(
+            Component::extract(config.clone(), "nope".to_string(), None, headers.clone()).is_err()
+        );
+
+        let trusted = Component::extract(
+            extract::Config {
+                trust_x_forwarded_for: false,
+                trust_x_forwarded_host: false,
+                ..config.clone()
+            },
+            "10.0.0.1".to_string(),
+            None,
+            headers,
+        )
+        .unwrap();
+        assert_eq!(trusted.ip, "10.0.0.1");
+        assert_eq!(trusted.host.as_deref(), Some("internal"));
+
+        let config = extract::Config {
+            trusted_ips: vec!["10.0.0.0/33".to_string()],
+            ..config
+        };
+        assert!(Component::extract(config, "8.8.8.8".to_string(), None, Vec::new()).is_err());
+    }
+}
@@ -0,0 +1,195 @@
+//! Reload trusted networks from a file when it changes
+//!
+//! [`FileWatcher`] reads a list with one network per line, see [`Config::add_trusted_ip_list`],
+//! and publishes a new configuration to a [`ConfigHandle`] every time the file changes. The
+//! directory of the file is watched rather than the file itself, so the atomic symlink swap used
+//! by Kubernetes to update a mounted ConfigMap is detected.
+//!
+//! # Example
+//! ```no_run
+//! use trusted_proxies::watch::FileWatcher;
+//! use trusted_proxies::{Config, ConfigHandle};
+//!
+//! let mut base = Config::new();
+//! base.trust_x_forwarded_for();
+//!
+//! let handle = ConfigHandle::new(base.clone());
+//! // the watcher stops when dropped
+//! let watcher = FileWatcher::new("/etc/proxies/trusted.txt", base, handle.clone()).unwrap();
+//! ```
+
+use crate::{Config, ConfigError, ConfigHandle};
+use core::fmt;
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// An error raised while loading or watching a file
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum WatchError {
+    /// The file cannot be read
+    Io(std::io::Error),
+    /// The file contains invalid networks
+    Ranges(ConfigError),
+    /// The file cannot be watched
+    Watch(notify::Error),
+}
+
+impl fmt::Display for WatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "cannot read trusted networks: {err}"),
+            Self::Ranges(err) => write!(f, "invalid list of trusted networks: {err}"),
+            Self::Watch(err) => write!(f, "cannot watch trusted networks: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for WatchError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::Ranges(err) => Some(err),
+            Self::Watch(err) => Some(err),
+        }
+    }
+}
+
+/// State shared with the watcher thread
+#[derive(Debug)]
+struct Reloader {
+    path: PathBuf,
+    base: Config,
+    handle: ConfigHandle,
+    content: Option<String>,
+    error: Option<WatchError>,
+}
+
+impl Reloader {
+    fn reload(&mut self) -> Result<(), WatchError> {
+        let content = std::fs::read_to_string(&self.path).map_err(WatchError::Io)?;
+
+        if self.content.as_ref() == Some(&content) {
+            return Ok(());
+        }
+
+        let mut config = self.base.clone();
+        config
+            .add_trusted_ip_list(&content)
+            .map_err(WatchError::Ranges)?;
+
+        self.handle.store(config);
+        self.content = Some(content);
+
+        Ok(())
+    }
+}
+
+/// Watch a file listing trusted networks, publishing a new configuration when it changes
+///
+/// Watching stops when the watcher is dropped.
+#[derive(Debug)]
+pub struct FileWatcher {
+    reloader: Arc<Mutex<Reloader>>,
+    _watcher: notify::RecommendedWatcher,
+}
+
+impl FileWatcher {
+    /// Load the file and start watching it
+    ///
+    /// The networks of the file are added to the given base configuration. The file is loaded
+    /// before returning, an error is returned when it cannot be loaded.
+    pub fn new<P: AsRef<Path>>(
+        path: P,
+        base: Config,
+        handle: ConfigHandle,
+    ) -> Result<Self, WatchError> {
+        let path = std::path::absolute(path.as_ref()).map_err(WatchError::Io)?;
+        let directory = path.parent().unwrap_or(&path).to_path_buf();
+        let mut reloader = Reloader {
+            path,
+            base,
+            handle,
+            content: None,
+            error: None,
+        };
+
+        reloader.reload()?;
+
+        let reloader = Arc::new(Mutex::new(reloader));
+        let shared = reloader.clone();
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                let mut reloader = shared.lock().unwrap_or_else(|e| e.into_inner());
+
+                match event {
+                    Ok(event) if matches!(event.kind, EventKind::Access(_)) => {}
+                    Ok(_) => {
+                        if let Err(err) = reloader.reload() {
+                            reloader.error = Some(err);
+                        }
+                    }
+                    Err(err) => reloader.error = Some(WatchError::Watch(err)),
+                }
+            })
+            .map_err(WatchError::Watch)?;
+
+        watcher
+            .watch(&directory, RecursiveMode::NonRecursive)
+            .map_err(WatchError::Watch)?;
+
+        Ok(Self {
+            reloader,
+            _watcher: watcher,
+        })
+    }
+
+    /// Take the last error raised while reloading the file, if any
+    ///
+    /// When a reload fails, the previous configuration is kept.
+    pub fn take_error(&self) -> Option<WatchError> {
+        self.reloader
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .error
+            .take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::net::IpAddr;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn reload_on_change() {
+        let directory =
+            std::env::temp_dir().join(format!("trusted-proxies-{}", std::process::id()));
+        let path = directory.join("trusted.txt");
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(&path, "10.0.0.0/8\n").unwrap();
+
+        let handle = ConfigHandle::new(Config::new());
+        let watcher = FileWatcher::new(&path, Config::new(), handle.clone()).unwrap();
+        assert!(handle.load().is_ip_trusted(&IpAddr::from([10, 0, 0, 1])));
+
+        std::fs::write(&path, "192.168.0.0/16\n").unwrap();
+
+        let start = Instant::now();
+
+        while !handle.load().is_ip_trusted(&IpAddr::from([192, 168, 1, 1])) {
+            assert!(
+                start.elapsed() < Duration::from_secs(10),
+                "file was not reloaded"
+            );
+            std::thread::sleep(Duration::from_millis(20));
+        }
+
+        assert!(!handle.load().is_ip_trusted(&IpAddr::from([10, 0, 0, 1])));
+        assert!(watcher.take_error().is_none());
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}